@@ -5,13 +5,16 @@ extern crate stopwatch;
 
 use {
     cli::{
-        formatter::{self, FormatCommand, FormatMetrics},
-        logger, server, tracker,
+        formatter::{self, FormatCommand, FormatMetrics, GenerationError},
+        logger, output, server,
+        tracker::{self, TRACKER_DIR},
     },
+    padd::FormatJob,
     std::{
-        env,
+        env, fs,
         path::{Path, PathBuf},
         process::Command,
+        time::{SystemTime, UNIX_EPOCH},
     },
 };
 
@@ -22,18 +25,22 @@ use self::{
     stopwatch::Stopwatch,
 };
 
+/// The extension of the files storing the expected formatted output of snapshot inputs.
+const SNAPSHOT_EXTENSION: &str = "expected";
+
+/// The number of inputs generated when no count is given.
+const DEFAULT_GENERATE_COUNT: usize = 10;
+
+/// The maximum derivation depth of generated inputs when no depth is given.
+const DEFAULT_GENERATE_DEPTH: usize = 8;
+
 pub fn fmt(matches: &ArgMatches) {
     let mut sw = Stopwatch::start_new();
 
     let spec_path = matches.value_of("spec").unwrap();
 
-    let formatter = match formatter::generate_formatter(&spec_path) {
-        Err(err) => {
-            logger::fatal(&format!(
-                "Error loading specification {}: {}",
-                &spec_path, err
-            ));
-        }
+    let formatter = match formatter::generate_formatter(spec_path) {
+        Err(err) => spec_fatal(spec_path, &err),
         Ok(formatter) => formatter,
     };
 
@@ -82,6 +89,14 @@ pub fn fmt(matches: &ArgMatches) {
     let no_write = matches.is_present("no-write");
     let check = matches.is_present("check");
 
+    let width = match matches.value_of("width") {
+        None => None,
+        Some(width) => match str::parse::<usize>(width) {
+            Ok(width) => Some(width),
+            Err(_) => logger::fatal(&format!("Invalid line width: '{}'", width)),
+        },
+    };
+
     println!();
 
     let metrics = formatter::format(FormatCommand {
@@ -93,6 +108,7 @@ pub fn fmt(matches: &ArgMatches) {
         no_track,
         no_write,
         check,
+        width,
     });
 
     sw.stop();
@@ -133,6 +149,511 @@ pub fn forget(matches: &ArgMatches) {
     tracker::clear_tracking(target);
 }
 
+pub fn lex(matches: &ArgMatches) {
+    let spec_path = matches.value_of("spec").unwrap();
+    let file_path = matches.value_of("file").unwrap();
+
+    let fjr = match formatter::load_runner(spec_path) {
+        Err(err) => spec_fatal(spec_path, &err),
+        Ok(fjr) => fjr,
+    };
+
+    let text = match fs::read_to_string(file_path) {
+        Err(err) => logger::fatal(&format!("Could not read file {}: {}", file_path, err)),
+        Ok(text) => text,
+    };
+
+    let tokens = match fjr.lex(FormatJob::from_text(text)) {
+        Err(err) => logger::fatal(&format!("Error lexing {}: {}", file_path, err)),
+        Ok(tokens) => tokens,
+    };
+
+    match matches.value_of("format") {
+        Some("json") => println!("{}", output::token_json(&tokens)),
+        _ => print!("{}", output::token_table(&tokens)),
+    }
+}
+
+pub fn parse(matches: &ArgMatches) {
+    let spec_path = matches.value_of("spec").unwrap();
+    let file_path = matches.value_of("file").unwrap();
+
+    let fjr = match formatter::load_runner(spec_path) {
+        Err(err) => spec_fatal(spec_path, &err),
+        Ok(fjr) => fjr,
+    };
+
+    let text = match fs::read_to_string(file_path) {
+        Err(err) => logger::fatal(&format!("Could not read file {}: {}", file_path, err)),
+        Ok(text) => text,
+    };
+
+    if matches.is_present("ambiguities") {
+        let ambiguities = match fjr.ambiguities(FormatJob::from_text(text)) {
+            Err(err) => logger::fatal(&format!("Error parsing {}: {}", file_path, err)),
+            Ok(ambiguities) => ambiguities,
+        };
+
+        for ambiguity in &ambiguities {
+            println!("{}", output::ambiguity_text(ambiguity));
+        }
+        return;
+    }
+
+    let tree = match fjr.parse(FormatJob::from_text(text)) {
+        Err(err) => logger::fatal(&format!("Error parsing {}: {}", file_path, err)),
+        Ok(tree) => tree,
+    };
+
+    match matches.value_of("format") {
+        Some("json") => println!("{}", output::tree_json(&tree)),
+        Some("sexp") => println!("{}", output::tree_sexp(&tree)),
+        _ => print!("{}", output::tree_text(&tree)),
+    }
+}
+
+pub fn lint(matches: &ArgMatches) {
+    let spec_path = matches.value_of("spec").unwrap();
+
+    let mut problems: usize = 0;
+
+    match formatter::lint_spec(spec_path) {
+        Err(err) => spec_fatal(spec_path, &err),
+        Ok(lints) => {
+            for lint in &lints {
+                println!("{}:{}", spec_path, output::lint_text(lint));
+            }
+            problems += lints.len();
+        }
+    }
+
+    if let Some(corpus_path) = matches.value_of("corpus") {
+        let fjr = match formatter::load_runner(spec_path) {
+            Err(err) => spec_fatal(spec_path, &err),
+            Ok(fjr) => fjr,
+        };
+
+        let file_regex: Regex = match matches.value_of("matching") {
+            None => Regex::new(".*").unwrap(),
+            Some(regex) => match Regex::new(regex) {
+                Ok(fn_regex) => fn_regex,
+                Err(err) => logger::fatal(&format!("Failed to build file name regex: {}", err)),
+            },
+        };
+
+        let mut file_paths: Vec<PathBuf> = Vec::new();
+        find_files(Path::new(corpus_path), &file_regex, &mut file_paths);
+        file_paths.sort();
+
+        for file_path in &file_paths {
+            let file_path = file_path.to_string_lossy();
+
+            let text = match fs::read_to_string(&*file_path) {
+                Err(err) => {
+                    logger::err(&format!("Could not read file {}: {}", file_path, err));
+                    problems += 1;
+                    continue;
+                }
+                Ok(text) => text,
+            };
+
+            match fjr.ambiguities(FormatJob::from_text(text)) {
+                Err(err) => {
+                    logger::err(&format!("Error parsing {}: {}", file_path, err));
+                    problems += 1;
+                }
+                Ok(ambiguities) => {
+                    for ambiguity in &ambiguities {
+                        println!("{}:{}", file_path, output::ambiguity_text(ambiguity));
+                    }
+                    problems += ambiguities.len();
+                }
+            }
+        }
+    }
+
+    if problems > 0 {
+        logger::fatal(&format!("Found {} problems in {}", problems, spec_path));
+    }
+
+    logger::info(&format!("No problems found in {}", spec_path));
+}
+
+pub fn test(matches: &ArgMatches) {
+    let spec_path = matches.value_of("spec").unwrap();
+
+    let fjr = match formatter::load_runner(spec_path) {
+        Err(err) => spec_fatal(spec_path, &err),
+        Ok(fjr) => fjr,
+    };
+
+    let tests = match formatter::spec_tests(spec_path) {
+        Err(err) => spec_fatal(spec_path, &err),
+        Ok(tests) => tests,
+    };
+
+    let mut failures: usize = 0;
+
+    for test in &tests {
+        let result = fjr.format(FormatJob::from_text(test.input().to_string()));
+
+        match result {
+            Ok(ref formatted) if formatted == test.expect() => {
+                println!("test {} ... {}", test.name(), "ok".green());
+            }
+            Ok(formatted) => {
+                println!("test {} ... {}", test.name(), "FAILED".red());
+                print!("{}", output::text_diff(test.expect(), &formatted));
+                failures += 1;
+            }
+            Err(err) => {
+                println!("test {} ... {}", test.name(), "FAILED".red());
+                println!("Error formatting input: {}", err);
+                failures += 1;
+            }
+        }
+    }
+
+    if failures > 0 {
+        logger::fatal(&format!(
+            "{} of {} tests failed in {}",
+            failures,
+            tests.len(),
+            spec_path
+        ));
+    }
+
+    logger::info(&format!("{} tests passed in {}", tests.len(), spec_path));
+}
+
+pub fn snapshot(matches: &ArgMatches) {
+    let spec_path = matches.value_of("spec").unwrap();
+    let dir_path = matches.value_of("dir").unwrap();
+    let update = matches.is_present("update");
+
+    let fjr = match formatter::load_runner(spec_path) {
+        Err(err) => spec_fatal(spec_path, &err),
+        Ok(fjr) => fjr,
+    };
+
+    let mut file_paths: Vec<PathBuf> = Vec::new();
+    find_files(
+        Path::new(dir_path),
+        &Regex::new(".*").unwrap(),
+        &mut file_paths,
+    );
+    file_paths.retain(|path| path.extension() != Some(SNAPSHOT_EXTENSION.as_ref()));
+    file_paths.sort();
+
+    let mut failures: usize = 0;
+    let mut updates: usize = 0;
+
+    for file_path in &file_paths {
+        let expected_path = snapshot_path(file_path);
+        let file_path = file_path.to_string_lossy();
+
+        let text = match fs::read_to_string(&*file_path) {
+            Err(err) => {
+                logger::err(&format!("Could not read file {}: {}", file_path, err));
+                failures += 1;
+                continue;
+            }
+            Ok(text) => text,
+        };
+
+        let formatted = match fjr.format(FormatJob::from_text(text)) {
+            Err(err) => {
+                println!("{} ... {}", file_path, "FAILED".red());
+                println!("Error formatting input: {}", err);
+                failures += 1;
+                continue;
+            }
+            Ok(formatted) => formatted,
+        };
+
+        let expected = fs::read_to_string(&expected_path).ok();
+
+        if update {
+            if expected.as_ref() == Some(&formatted) {
+                println!("{} ... {}", file_path, "ok".green());
+            } else if let Err(err) = fs::write(&expected_path, &formatted) {
+                logger::err(&format!(
+                    "Could not write file {}: {}",
+                    expected_path.to_string_lossy(),
+                    err
+                ));
+                failures += 1;
+            } else {
+                println!("{} ... {}", file_path, "updated".yellow());
+                updates += 1;
+            }
+            continue;
+        }
+
+        match expected {
+            None => {
+                println!("{} ... {}", file_path, "FAILED".red());
+                println!(
+                    "Missing expected output {}, run with --update to create it",
+                    expected_path.to_string_lossy()
+                );
+                failures += 1;
+            }
+            Some(ref expected) if *expected != formatted => {
+                println!("{} ... {}", file_path, "FAILED".red());
+                print!("{}", output::text_diff(expected, &formatted));
+                failures += 1;
+            }
+            Some(_) => println!("{} ... {}", file_path, "ok".green()),
+        }
+    }
+
+    if failures > 0 {
+        logger::fatal(&format!(
+            "{} of {} snapshots failed in {}",
+            failures,
+            file_paths.len(),
+            dir_path
+        ));
+    }
+
+    if update {
+        logger::info(&format!(
+            "Updated {} of {} snapshots in {}",
+            updates,
+            file_paths.len(),
+            dir_path
+        ));
+    } else {
+        logger::info(&format!(
+            "{} snapshots passed in {}",
+            file_paths.len(),
+            dir_path
+        ));
+    }
+}
+
+pub fn generate(matches: &ArgMatches) {
+    let spec_path = matches.value_of("spec").unwrap();
+
+    let count = parse_number(matches, "count", "number of inputs", DEFAULT_GENERATE_COUNT);
+    let max_depth = parse_number(
+        matches,
+        "max-depth",
+        "maximum depth",
+        DEFAULT_GENERATE_DEPTH,
+    );
+    let seed = match matches.value_of("seed") {
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64),
+        Some(seed) => match str::parse::<u64>(seed) {
+            Ok(seed) => seed,
+            Err(_) => logger::fatal(&format!("Invalid seed: '{}'", seed)),
+        },
+    };
+
+    let fjr = match formatter::load_runner(spec_path) {
+        Err(err) => spec_fatal(spec_path, &err),
+        Ok(fjr) => fjr,
+    };
+
+    let inputs = fjr.generate(count, max_depth, seed);
+    if inputs.is_empty() && count > 0 {
+        logger::fatal(&format!(
+            "Could not generate any inputs from {} with seed {}",
+            spec_path, seed
+        ));
+    }
+
+    match matches.value_of("output") {
+        None => {
+            for input in &inputs {
+                println!("{}", input);
+            }
+        }
+        Some(output_path) => {
+            if let Err(err) = fs::create_dir_all(output_path) {
+                logger::fatal(&format!(
+                    "Could not create directory {}: {}",
+                    output_path, err
+                ));
+            }
+
+            for (i, input) in inputs.iter().enumerate() {
+                let file_path = Path::new(output_path).join(format!("{}", i + 1));
+                if let Err(err) = fs::write(&file_path, input) {
+                    logger::fatal(&format!(
+                        "Could not write file {}: {}",
+                        file_path.to_string_lossy(),
+                        err
+                    ));
+                }
+            }
+
+            logger::info(&format!(
+                "Generated {} inputs in {} with seed {}",
+                inputs.len(),
+                output_path,
+                seed
+            ));
+        }
+    }
+}
+
+pub fn verify(matches: &ArgMatches) {
+    let spec_path = matches.value_of("spec").unwrap();
+    let target_path = matches.value_of("target").unwrap();
+
+    let fjr = match formatter::load_runner(spec_path) {
+        Err(err) => spec_fatal(spec_path, &err),
+        Ok(fjr) => fjr,
+    };
+
+    let file_regex: Regex = match matches.value_of("matching") {
+        None => Regex::new(".*").unwrap(),
+        Some(regex) => match Regex::new(regex) {
+            Ok(fn_regex) => fn_regex,
+            Err(err) => logger::fatal(&format!("Failed to build file name regex: {}", err)),
+        },
+    };
+
+    let mut file_paths: Vec<PathBuf> = Vec::new();
+    find_files(Path::new(target_path), &file_regex, &mut file_paths);
+    file_paths.sort();
+
+    let mut violations: usize = 0;
+    let mut failures: usize = 0;
+
+    for file_path in &file_paths {
+        let file_path = file_path.to_string_lossy();
+
+        let text = match fs::read_to_string(&*file_path) {
+            Err(err) => {
+                logger::err(&format!("Could not read file {}: {}", file_path, err));
+                failures += 1;
+                continue;
+            }
+            Ok(text) => text,
+        };
+
+        match fjr.verify(FormatJob::from_text(text)) {
+            Err(err) => {
+                logger::err(&format!("Error formatting {}: {}", file_path, err));
+                failures += 1;
+            }
+            Ok(ref file_violations) if file_violations.is_empty() => {}
+            Ok(file_violations) => {
+                for violation in &file_violations {
+                    println!("{}: {}", file_path, violation);
+                }
+                violations += file_violations.len();
+                failures += 1;
+            }
+        }
+    }
+
+    if failures > 0 {
+        logger::fatal(&format!(
+            "{} of {} files failed verification under {}, with {} violations",
+            failures,
+            file_paths.len(),
+            target_path,
+            violations
+        ));
+    }
+
+    logger::info(&format!(
+        "Verified {} files under {}",
+        file_paths.len(),
+        target_path
+    ));
+}
+
+pub fn export_grammar(matches: &ArgMatches) {
+    let spec_path = matches.value_of("spec").unwrap();
+
+    let fjr = match formatter::load_runner(spec_path) {
+        Err(err) => spec_fatal(spec_path, &err),
+        Ok(fjr) => fjr,
+    };
+
+    let rules = fjr.grammar_rules();
+
+    match matches.value_of("format") {
+        Some("w3c") => print!("{}", output::grammar_w3c(&rules)),
+        Some("dot") => print!("{}", output::grammar_dot(&rules)),
+        _ => print!("{}", output::grammar_ebnf(&rules)),
+    }
+}
+
+/// Returns the value of the numeric argument `name` in `matches`, or `default` if it is not
+/// present, exiting with an error describing the `description` of the argument if it is invalid.
+fn parse_number(matches: &ArgMatches, name: &str, description: &str, default: usize) -> usize {
+    match matches.value_of(name) {
+        None => default,
+        Some(value) => match str::parse::<usize>(value) {
+            Ok(value) => value,
+            Err(_) => logger::fatal(&format!("Invalid {}: '{}'", description, value)),
+        },
+    }
+}
+
+/// Returns the path of the file storing the expected formatted output of the file at `path`.
+fn snapshot_path(path: &Path) -> PathBuf {
+    let mut file_name = path.as_os_str().to_os_string();
+    file_name.push(".");
+    file_name.push(SNAPSHOT_EXTENSION);
+    PathBuf::from(file_name)
+}
+
+/// Exits with an error describing why the specification at `spec_path` could not be loaded, along
+/// with a snippet of the specification at the location of the error, if it is known.
+fn spec_fatal(spec_path: &str, err: &GenerationError) -> ! {
+    let message = format!("Error loading specification {}: {}", spec_path, err);
+
+    let snippet = err.location().and_then(|location| {
+        fs::read_to_string(spec_path)
+            .ok()
+            .map(|spec| output::spec_snippet(spec_path, &spec, &location))
+    });
+
+    match snippet {
+        Some(snippet) => logger::fatal(&format!("{}\n{}", message, snippet)),
+        None => logger::fatal(&message),
+    }
+}
+
+/// Appends the paths of all files under `path` (or `path` itself, if it is a file) whose names
+/// match `file_regex` to `file_paths`.
+fn find_files(path: &Path, file_regex: &Regex, file_paths: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        if path.ends_with(TRACKER_DIR) {
+            return; // Don't search tracker files
+        }
+
+        match fs::read_dir(path) {
+            Err(err) => logger::err(&format!(
+                "An error occurred while searching directory {}: {}",
+                path.to_string_lossy(),
+                err
+            )),
+            Ok(dir_items) => dir_items.for_each(|res| match res {
+                Ok(dir_item) => find_files(&dir_item.path(), file_regex, file_paths),
+                Err(err) => logger::err(&format!(
+                    "An error occurred while searching directory {}: {}",
+                    path.to_string_lossy(),
+                    err
+                )),
+            }),
+        }
+    } else if let Some(file_name) = path.file_name() {
+        if file_regex.is_match(&file_name.to_string_lossy()) {
+            file_paths.push(path.to_path_buf());
+        }
+    }
+}
+
 pub fn daemon(matches: &ArgMatches, args: &[&str]) {
     if matches.subcommand_matches("start").is_some() {
         if server::running() {
@@ -10,10 +10,11 @@ use {
     padd::{self, FormatJob, FormatJobRunner},
     std::{
         collections::HashMap,
-        error, fmt,
+        env, error, fmt,
         fs::{self, File, OpenOptions},
         io::{Read, Seek, SeekFrom, Write},
         path::{Path, PathBuf},
+        process,
         sync::{Arc, Mutex},
     },
 };
@@ -25,6 +26,11 @@ use self::{
 
 const THREAD_POOL_QUEUE_LENGTH_PER_WORKER: usize = 2;
 
+/// The environment variable which overrides the compiled specification cache directory.
+pub const CACHE_DIR_VAR: &str = "PADD_CACHE_DIR";
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
 lazy_static! {
     static ref FJR_CACHE: Mutex<HashMap<String, Arc<FormatJobRunner>>> = Mutex::new(HashMap::new());
 }
@@ -44,6 +50,7 @@ pub struct FormatCommand<'path> {
     pub no_track: bool,
     pub no_write: bool,
     pub check: bool,
+    pub width: Option<usize>,
 }
 
 struct FormatInstance<'outer> {
@@ -59,6 +66,8 @@ struct FormatCriteria<'outer> {
     no_track: bool,
     no_write: bool,
     check: bool,
+    width: Option<usize>,
+    track_key: String,
 }
 
 pub struct FormatMetrics {
@@ -103,6 +112,8 @@ struct FormatPayload {
     no_track: bool,
     no_write: bool,
     check: bool,
+    width: Option<usize>,
+    track_key: String,
     metrics: Arc<Mutex<FormatMetrics>>,
 }
 
@@ -114,6 +125,8 @@ impl FormatPayload {
             no_track: instance.criteria.no_track,
             no_write: instance.criteria.no_write,
             check: instance.criteria.check,
+            width: instance.criteria.width,
+            track_key: instance.criteria.track_key.clone(),
             metrics: instance.metrics.clone(),
         }
     }
@@ -122,6 +135,47 @@ impl FormatPayload {
 pub fn generate_formatter(spec_path: &str) -> Result<Formatter, GenerationError> {
     logger::info(&format!("Loading specification {} ...", spec_path));
 
+    let spec = read_spec(spec_path)?;
+    let spec_sha = spec_sha(&spec);
+
+    if FJR_CACHE.lock().unwrap().contains_key(&spec_sha) {
+        logger::info(&format!(
+            "Loading cached specification: sha256: {}",
+            &spec_sha
+        ));
+    }
+
+    let fjr_arc = cached_runner(&spec, &spec_sha)?;
+
+    logger::info(&format!(
+        "Successfully loaded specification: sha256: {}",
+        &spec_sha
+    ));
+
+    Ok(Formatter { fjr_arc, spec_sha })
+}
+
+/// Returns the format job runner for the specification at `spec_path`, without logging any
+/// progress to stdout. This is used by commands whose output is meant to be consumed by other
+/// programs.
+pub fn load_runner(spec_path: &str) -> Result<Arc<FormatJobRunner>, GenerationError> {
+    let spec = read_spec(spec_path)?;
+    cached_runner(&spec, &spec_sha(&spec))
+}
+
+/// Returns the likely problems in the specification at `spec_path`.
+pub fn lint_spec(spec_path: &str) -> Result<Vec<padd::SpecLint>, GenerationError> {
+    let spec = read_spec(spec_path)?;
+    Ok(FormatJobRunner::lint(&spec)?)
+}
+
+/// Returns the tests declared in the specification at `spec_path`.
+pub fn spec_tests(spec_path: &str) -> Result<Vec<padd::SpecTest>, GenerationError> {
+    let spec = read_spec(spec_path)?;
+    Ok(FormatJobRunner::tests(&spec)?)
+}
+
+fn read_spec(spec_path: &str) -> Result<String, GenerationError> {
     let mut spec = String::new();
 
     match File::open(spec_path) {
@@ -141,33 +195,131 @@ pub fn generate_formatter(spec_path: &str) -> Result<Formatter, GenerationError>
         }
     }
 
+    Ok(spec)
+}
+
+fn spec_sha(spec: &str) -> String {
     let mut sha = Sha256::new();
-    sha.input_str(&spec[..]);
-    let spec_sha = sha.result_str();
+    sha.input_str(spec);
+    sha.result_str()
+}
 
-    let fjr_arc = {
-        let mut fjr_cache = FJR_CACHE.lock().unwrap();
+/// Returns the format job runner for `spec`, which has hash `spec_sha`.
+///
+/// Runners are cached in memory for the lifetime of the process, and compiled specifications are
+/// cached on disk (see `compiled_spec_path`), so that specifications are only generated once.
+fn cached_runner(spec: &str, spec_sha: &str) -> Result<Arc<FormatJobRunner>, GenerationError> {
+    let mut fjr_cache = FJR_CACHE.lock().unwrap();
+
+    if let Some(fjr_arc) = fjr_cache.get(spec_sha) {
+        return Ok(fjr_arc.clone());
+    }
 
-        if fjr_cache.contains_key(&spec_sha) {
-            logger::info(&format!(
-                "Loading cached specification: sha256: {}",
-                &spec_sha
-            ));
+    let compiled_path = compiled_spec_path(spec_sha);
+
+    let fjr = match compiled_path
+        .as_ref()
+        .and_then(|path| load_compiled_spec(path))
+    {
+        Some(fjr) => fjr,
+        None => {
+            let fjr = FormatJobRunner::build(spec)?;
+            if let Some(ref path) = compiled_path {
+                store_compiled_spec(path, &fjr);
+            }
+            fjr
         }
+    };
+
+    let fjr_arc = Arc::new(fjr);
+    fjr_cache.insert(spec_sha.to_string(), fjr_arc.clone());
+    Ok(fjr_arc)
+}
 
-        #[allow(clippy::or_fun_call)]
-        fjr_cache
-            .entry(spec_sha.clone())
-            .or_insert(Arc::new(FormatJobRunner::build(&spec)?))
-            .clone()
+/// Returns the path of the compiled specification cache file for the specification with hash
+/// `spec_sha`, or `None` if there is no cache directory (or when running tests).
+///
+/// The cache directory is `$PADD_CACHE_DIR` if set, otherwise `$XDG_CACHE_HOME/padd` or
+/// `$HOME/.cache/padd`. Cache files are keyed by the specification hash, the padd version (since
+/// specification generation may change between versions) and the serialization format version.
+fn compiled_spec_path(spec_sha: &str) -> Option<PathBuf> {
+    if cfg!(test) {
+        return None; // Tests which run in-process must not share the user's cache directory
+    }
+
+    let mut path = match env::var_os(CACHE_DIR_VAR) {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let mut path = match env::var_os("XDG_CACHE_HOME") {
+                Some(dir) => PathBuf::from(dir),
+                None => {
+                    let mut path = PathBuf::from(env::var_os("HOME")?);
+                    path.push(".cache");
+                    path
+                }
+            };
+            path.push("padd");
+            path
+        }
     };
 
-    logger::info(&format!(
-        "Successfully loaded specification: sha256: {}",
-        &spec_sha
+    path.push(format!(
+        "{}-v{}-{}",
+        spec_sha,
+        VERSION,
+        padd::SERIAL_VERSION
     ));
+    Some(path)
+}
 
-    Ok(Formatter { fjr_arc, spec_sha })
+/// Returns the runner stored in the compiled specification cache file at `path`, or `None` if
+/// the file does not exist or is invalid.
+fn load_compiled_spec(path: &Path) -> Option<FormatJobRunner> {
+    let bytes = fs::read(path).ok()?;
+
+    match FormatJobRunner::from_bytes(&bytes) {
+        Ok(fjr) => {
+            debug!("Loaded compiled specification {}", path.to_string_lossy());
+            Some(fjr)
+        }
+        Err(err) => {
+            warn!(
+                "Ignoring invalid compiled specification {}: {}",
+                path.to_string_lossy(),
+                err
+            );
+            None
+        }
+    }
+}
+
+/// Writes the compiled specification of `fjr` to the cache file at `path`.
+///
+/// The file is written to a temporary path and then renamed, so that concurrent padd processes
+/// never read a partially written file. Failures are logged but otherwise ignored, since the cache
+/// is only an optimization.
+fn store_compiled_spec(path: &Path, fjr: &FormatJobRunner) {
+    let mut temp_name = path.file_name().unwrap().to_os_string();
+    temp_name.push(format!(".tmp{}", process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let res = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&temp_path, fjr.to_bytes()))
+        .and_then(|_| fs::rename(&temp_path, path));
+
+    match res {
+        Ok(_) => debug!("Stored compiled specification {}", path.to_string_lossy()),
+        Err(err) => {
+            let _ = fs::remove_file(&temp_path);
+            warn!(
+                "Failed to store compiled specification {}: {}",
+                path.to_string_lossy(),
+                err
+            );
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -176,6 +328,17 @@ pub enum GenerationError {
     BuildErr(padd::BuildError),
 }
 
+impl GenerationError {
+    /// Returns the location in the specification of the construct which caused this error, or
+    /// `None` if it is not known.
+    pub fn location(&self) -> Option<padd::SpecLocation> {
+        match *self {
+            Self::FileErr(_) => None,
+            Self::BuildErr(ref err) => err.location(),
+        }
+    }
+}
+
 impl fmt::Display for GenerationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -211,9 +374,14 @@ pub fn format(cmd: FormatCommand) -> FormatMetrics {
             logger::fmt(&file_path_string);
 
             let result = if payload.check {
-                check_file(file_path, &payload.formatter.fjr_arc)
+                check_file(file_path, &payload.formatter.fjr_arc, payload.width)
             } else {
-                format_file(file_path, &payload.formatter.fjr_arc, payload.no_write)
+                format_file(
+                    file_path,
+                    &payload.formatter.fjr_arc,
+                    payload.width,
+                    payload.no_write,
+                )
             };
 
             match result {
@@ -232,7 +400,7 @@ pub fn format(cmd: FormatCommand) -> FormatMetrics {
             }
 
             if !payload.no_track {
-                tracker::track_file(file_path, &payload.formatter.spec_sha);
+                tracker::track_file(file_path, &payload.track_key);
             }
         },
     );
@@ -251,6 +419,8 @@ pub fn format(cmd: FormatCommand) -> FormatMetrics {
             no_track: cmd.no_track,
             no_write: cmd.no_write,
             check: cmd.check,
+            width: cmd.width,
+            track_key: track_key(&cmd.formatter, cmd.width),
         },
         metrics: Arc::new(Mutex::new(FormatMetrics::new())),
     };
@@ -263,6 +433,15 @@ pub fn format(cmd: FormatCommand) -> FormatMetrics {
     metrics.copy()
 }
 
+/// Returns the key which files formatted by `formatter` with the line width override `width` are
+/// tracked under, so that files are reformatted when either the specification or width changes.
+fn track_key(formatter: &Formatter, width: Option<usize>) -> String {
+    match width {
+        Some(width) => format!("{}-w{}", formatter.spec_sha, width),
+        None => formatter.spec_sha.clone(),
+    }
+}
+
 fn format_target(target_path: &Path, instance: &mut FormatInstance) {
     let path_string = target_path.to_string_lossy().to_string();
     let file_name = target_path.file_name().unwrap().to_str().unwrap();
@@ -285,7 +464,7 @@ fn format_target(target_path: &Path, instance: &mut FormatInstance) {
 
         if instance.criteria.no_skip
             || instance.criteria.check
-            || tracker::needs_formatting(target_path, &instance.formatter.spec_sha)
+            || tracker::needs_formatting(target_path, &instance.criteria.track_key)
         {
             let payload = FormatPayload::from(target_path, instance);
             instance.pool.enqueue(payload).unwrap();
@@ -296,6 +475,7 @@ fn format_target(target_path: &Path, instance: &mut FormatInstance) {
 fn format_file(
     target_path: &Path,
     fjr: &FormatJobRunner,
+    width: Option<usize>,
     no_write: bool,
 ) -> Result<(), FormattingError> {
     let target_file = OpenOptions::new().read(true).write(true).open(&target_path);
@@ -314,7 +494,7 @@ fn format_file(
                     )));
                 }
 
-                fjr.format(FormatJob::from_text(text))
+                fjr.format(format_job(text, width))
             };
 
             match result {
@@ -344,7 +524,10 @@ fn format_file(
                         ))),
                     }
                 }
-                Err(err) => Err(FormattingError::FormatErr(err, target_path_string)),
+                Err(err) => Err(FormattingError::FormatErr(
+                    Box::new(err),
+                    target_path_string,
+                )),
             }
         }
         Err(err) => Err(FormattingError::FileErr(format!(
@@ -354,7 +537,11 @@ fn format_file(
     }
 }
 
-fn check_file(target_path: &Path, fjr: &FormatJobRunner) -> Result<(), FormattingError> {
+fn check_file(
+    target_path: &Path,
+    fjr: &FormatJobRunner,
+    width: Option<usize>,
+) -> Result<(), FormattingError> {
     let target_file = OpenOptions::new().read(true).write(true).open(&target_path);
     let target_path_string = target_path.to_string_lossy().to_string();
     match target_file {
@@ -370,7 +557,7 @@ fn check_file(target_path: &Path, fjr: &FormatJobRunner) -> Result<(), Formattin
                 )));
             }
 
-            let result = fjr.format(FormatJob::from_text(text.clone()));
+            let result = fjr.format(format_job(text.clone(), width));
 
             match result {
                 Ok(res) => {
@@ -380,7 +567,10 @@ fn check_file(target_path: &Path, fjr: &FormatJobRunner) -> Result<(), Formattin
                         Err(FormattingError::CheckErr(target_path_string))
                     }
                 }
-                Err(err) => Err(FormattingError::FormatErr(err, target_path_string)),
+                Err(err) => Err(FormattingError::FormatErr(
+                    Box::new(err),
+                    target_path_string,
+                )),
             }
         }
         Err(err) => Err(FormattingError::FileErr(format!(
@@ -390,10 +580,17 @@ fn check_file(target_path: &Path, fjr: &FormatJobRunner) -> Result<(), Formattin
     }
 }
 
+fn format_job(text: String, width: Option<usize>) -> FormatJob {
+    match width {
+        Some(width) => FormatJob::from_text(text).with_width(width),
+        None => FormatJob::from_text(text),
+    }
+}
+
 #[derive(Debug)]
 pub enum FormattingError {
     FileErr(String),
-    FormatErr(padd::FormatError, String),
+    FormatErr(Box<padd::FormatError>, String),
     CheckErr(String),
 }
 
@@ -413,7 +610,7 @@ impl error::Error for FormattingError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             Self::FileErr(_) => None,
-            Self::FormatErr(ref err, _) => Some(err),
+            Self::FormatErr(ref err, _) => Some(err.as_ref()),
             Self::CheckErr(_) => None,
         }
     }
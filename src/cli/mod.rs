@@ -7,6 +7,7 @@ mod cmd;
 mod formatter;
 #[macro_use]
 pub mod logger;
+mod output;
 #[cfg(test)]
 pub mod server;
 //#ccstop
@@ -29,16 +30,48 @@ pub fn run(args: Vec<&str>) {
         if server::running() {
             server::send_command(command);
         } else {
-            cmd::fmt(&matches);
+            cmd::fmt(matches);
         }
     }
 
     if let Some(matches) = matches.subcommand_matches("forget") {
-        cmd::forget(&matches);
+        cmd::forget(matches);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("lex") {
+        cmd::lex(matches);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("parse") {
+        cmd::parse(matches);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("lint") {
+        cmd::lint(matches);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("test") {
+        cmd::test(matches);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("snapshot") {
+        cmd::snapshot(matches);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("generate") {
+        cmd::generate(matches);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("verify") {
+        cmd::verify(matches);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("export-grammar") {
+        cmd::export_grammar(matches);
     }
 
     if let Some(matches) = matches.subcommand_matches("daemon") {
-        cmd::daemon(&matches, &args);
+        cmd::daemon(matches, &args);
     }
 
     if matches.subcommand_matches("start-server").is_some() {
@@ -0,0 +1,642 @@
+extern crate colored;
+extern crate difference;
+
+use {
+    self::{
+        colored::Colorize,
+        difference::{Changeset, Difference},
+    },
+    padd::{
+        Ambiguity, GrammarRule, GrammarTerm, LexedToken, Production, SpecLint, SpecLocation,
+        SymbolParseMethod, SyntaxTree,
+    },
+};
+
+/// Returns a table of `tokens`, with one row per token, and columns for the token position,
+/// kind, flags, and lexeme. Ignorable tokens are flagged with `ign`, and injectable tokens with
+/// `inj`.
+pub fn token_table(tokens: &[LexedToken]) -> String {
+    let rows: Vec<(String, &str, String, String)> = tokens
+        .iter()
+        .map(|token| {
+            let position = format!(
+                "{}:{}",
+                token.span().start.line,
+                token.span().start.character
+            );
+
+            let mut flags: Vec<&str> = Vec::new();
+            if token.is_ignorable() {
+                flags.push("ign");
+            }
+            if token.is_injectable() {
+                flags.push("inj");
+            }
+
+            (
+                position,
+                token.kind(),
+                flags.join(","),
+                escape(token.lexeme()),
+            )
+        })
+        .collect();
+
+    let position_width = rows
+        .iter()
+        .map(|row| row.0.len())
+        .chain(Some("POS".len()))
+        .max()
+        .unwrap();
+    let kind_width = rows
+        .iter()
+        .map(|row| row.1.chars().count())
+        .chain(Some("KIND".len()))
+        .max()
+        .unwrap();
+    let flags_width = rows
+        .iter()
+        .map(|row| row.2.len())
+        .chain(Some("FLAGS".len()))
+        .max()
+        .unwrap();
+
+    let mut table = format!(
+        "{:pw$}  {:kw$}  {:fw$}  LEXEME\n",
+        "POS",
+        "KIND",
+        "FLAGS",
+        pw = position_width,
+        kw = kind_width,
+        fw = flags_width
+    );
+
+    for (position, kind, flags, lexeme) in &rows {
+        table.push_str(&format!(
+            "{:pw$}  {:kw$}  {:fw$}  '{}'\n",
+            position,
+            kind,
+            flags,
+            lexeme,
+            pw = position_width,
+            kw = kind_width,
+            fw = flags_width
+        ));
+    }
+
+    table
+}
+
+/// Returns a JSON array of `tokens`, where each token is an object containing its kind, lexeme,
+/// span, and flags.
+pub fn token_json(tokens: &[LexedToken]) -> String {
+    let objects: Vec<String> = tokens
+        .iter()
+        .map(|token| {
+            format!(
+                "{{\"kind\":{},\"lexeme\":{},\"start\":{},\"end\":{},\
+                 \"ignorable\":{},\"injectable\":{}}}",
+                json_string(token.kind()),
+                json_string(token.lexeme()),
+                json_position(&token.span().start),
+                json_position(&token.span().end),
+                token.is_ignorable(),
+                token.is_injectable()
+            )
+        })
+        .collect();
+
+    format!("[{}]", objects.join(","))
+}
+
+/// Returns a drawing of `tree`, where each node is annotated with the production used to derive
+/// its children, and its parse method if it was not parsed normally.
+pub fn tree_text(tree: &SyntaxTree) -> String {
+    let mut res = String::new();
+
+    // The prefix of each line is shared, with one margin for each open ancestor.
+    let mut prefix = String::new();
+    let mut prefix_lens: Vec<usize> = Vec::new();
+
+    walk_tree(tree, &mut |step| match step {
+        TreeStep::Enter(node, position) => {
+            let is_tail = position.is_none_or(|(_, is_tail)| is_tail);
+
+            res.push_str(&prefix);
+            res.push_str(if is_tail { "└── " } else { "├── " });
+
+            if node.is_leaf() {
+                if *node.parse_method() == SymbolParseMethod::Injected {
+                    res.push_str("<< ");
+                }
+
+                match node.kind() {
+                    Some(kind) => res.push_str(&format!("{} <- '{}'", kind, escape(node.lexeme()))),
+                    None => res.push_str("NULL"),
+                }
+            } else {
+                res.push_str(node.kind().unwrap_or("?"));
+            }
+
+            if let Some(production) = node.production() {
+                res.push_str(&format!(" [{}]", production_text(production)));
+            }
+
+            if *node.parse_method() != SymbolParseMethod::Standard {
+                res.push_str(&format!(" ({:?})", node.parse_method()));
+            }
+
+            res.push('\n');
+
+            prefix_lens.push(prefix.len());
+            prefix.push_str(if is_tail { "    " } else { "│   " });
+        }
+        TreeStep::Exit(_) => prefix.truncate(prefix_lens.pop().unwrap()),
+    });
+
+    res
+}
+
+/// Returns `tree` as a JSON object, containing the kind, lexeme, span, parse method, production,
+/// and children of each node.
+pub fn tree_json(tree: &SyntaxTree) -> String {
+    let mut res = String::new();
+
+    walk_tree(tree, &mut |step| match step {
+        TreeStep::Enter(node, position) => {
+            if let Some((index, _)) = position {
+                if index > 0 {
+                    res.push(',');
+                }
+            }
+
+            let kind = match node.kind() {
+                Some(kind) => json_string(kind),
+                None => "null".to_string(),
+            };
+
+            let production = match node.production() {
+                Some(production) => {
+                    let rhs: Vec<String> = production
+                        .rhs()
+                        .iter()
+                        .map(|symbol| match symbol.separator() {
+                            Some(separator) => format!(
+                                "{{\"symbol\":{},\"list\":{},\"separator\":{}}}",
+                                json_string(symbol.symbol()),
+                                symbol.is_list(),
+                                json_string(separator)
+                            ),
+                            None => format!(
+                                "{{\"symbol\":{},\"list\":{}}}",
+                                json_string(symbol.symbol()),
+                                symbol.is_list()
+                            ),
+                        })
+                        .collect();
+
+                    format!(
+                        "{{\"lhs\":{},\"rhs\":[{}]}}",
+                        json_string(production.lhs()),
+                        rhs.join(",")
+                    )
+                }
+                None => "null".to_string(),
+            };
+
+            res.push_str(&format!(
+                "{{\"kind\":{},\"lexeme\":{},\"start\":{},\"end\":{},\"method\":{},\
+                 \"production\":{},\"children\":[",
+                kind,
+                json_string(node.lexeme()),
+                json_position(&node.span().start),
+                json_position(&node.span().end),
+                json_string(&format!("{:?}", node.parse_method())),
+                production,
+            ));
+        }
+        TreeStep::Exit(_) => res.push_str("]}"),
+    });
+
+    res
+}
+
+/// Returns `tree` as an S-expression, where leaves are written as `(KIND "lexeme")`, interior
+/// nodes as `(kind children...)`, and nodes which were not parsed normally are tagged with their
+/// parse method (e.g. `:injected`).
+pub fn tree_sexp(tree: &SyntaxTree) -> String {
+    let mut res = String::new();
+
+    walk_tree(tree, &mut |step| match step {
+        TreeStep::Enter(node, position) => {
+            if position.is_some() {
+                res.push(' ');
+            }
+            res.push('(');
+
+            if !node.is_leaf() {
+                res.push_str(node.kind().unwrap_or("?"));
+            } else if let Some(kind) = node.kind() {
+                res.push_str(&format!("{} {}", kind, json_string(node.lexeme())));
+            }
+        }
+        TreeStep::Exit(node) => {
+            if *node.parse_method() != SymbolParseMethod::Standard {
+                // Only empty leaves have no kind before their parse method.
+                if !node.is_leaf() || node.kind().is_some() {
+                    res.push(' ');
+                }
+                res.push_str(&format!(":{:?}", node.parse_method()).to_lowercase());
+            }
+            res.push(')');
+        }
+    });
+
+    res
+}
+
+/// Tree Step: A step of a depth-first walk over the nodes of a syntax tree.
+///
+/// # Types
+///
+/// * `Enter` - indicates that a node is reached, before any of its children, along with its index
+///   among the children of its parent and whether it is the last such child, or `None` if it is
+///   the root.
+/// * `Exit` - indicates that a node is left, after all of its children.
+enum TreeStep<'tree> {
+    Enter(&'tree SyntaxTree, Option<(usize, bool)>),
+    Exit(&'tree SyntaxTree),
+}
+
+/// Walks the nodes of `tree` depth-first, in order, calling `step` as each node is entered and
+/// exited. The walk is iterative, so that walking a very deep tree cannot overflow the stack.
+fn walk_tree<'tree>(tree: &'tree SyntaxTree, step: &mut dyn FnMut(TreeStep<'tree>)) {
+    step(TreeStep::Enter(tree, None));
+
+    // The entered nodes which have not yet been exited, with the index of their next child.
+    let mut stack: Vec<(&SyntaxTree, usize)> = vec![(tree, 0)];
+
+    while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+        let children = node.children();
+        if *next == children.len() {
+            stack.pop();
+            step(TreeStep::Exit(node));
+            continue;
+        }
+
+        let index = *next;
+        *next += 1;
+
+        step(TreeStep::Enter(
+            &children[index],
+            Some((index, index == children.len() - 1)),
+        ));
+        stack.push((&children[index], 0));
+    }
+}
+
+/// Returns a single line describing `ambiguity`, starting with the span of the ambiguous region,
+/// followed by the chosen production and any competing productions.
+pub fn ambiguity_text(ambiguity: &Ambiguity) -> String {
+    let span = format!(
+        "{}:{}-{}:{}",
+        ambiguity.span().start.line,
+        ambiguity.span().start.character,
+        ambiguity.span().end.line,
+        ambiguity.span().end.character
+    );
+
+    let chosen = production_text(ambiguity.chosen());
+
+    if ambiguity.productions().len() > 1 {
+        let competing: Vec<String> = ambiguity
+            .productions()
+            .iter()
+            .filter(|prod| *prod != ambiguity.chosen())
+            .map(|prod| format!("`{}`", production_text(prod)))
+            .collect();
+
+        format!(
+            "{}: ambiguous {}, chose `{}` over {}",
+            span,
+            ambiguity.chosen().lhs(),
+            chosen,
+            competing.join(", ")
+        )
+    } else {
+        format!(
+            "{}: ambiguous {}, `{}` matches in more than one way",
+            span,
+            ambiguity.chosen().lhs(),
+            chosen
+        )
+    }
+}
+
+/// Returns a single line describing `lint`, starting with the span of the declaration it refers
+/// to.
+pub fn lint_text(lint: &SpecLint) -> String {
+    format!(
+        "{}:{}-{}:{}: {}",
+        lint.span().start.line,
+        lint.span().start.character,
+        lint.span().end.line,
+        lint.span().end.character,
+        lint
+    )
+}
+
+/// Returns `rules` in ISO EBNF, where optional terms are written as `[x]`, and inline lists of
+/// one or more elements as `x, {x}` (or `x, {s, x}` if separated by `s`).
+pub fn grammar_ebnf(rules: &[GrammarRule]) -> String {
+    fn term_text(term: &GrammarTerm) -> String {
+        let text = match term.separator {
+            Some(ref separator) => format!("{}, {{{}, {}}}", term.symbol, separator, term.symbol),
+            None if term.is_list => format!("{}, {{{}}}", term.symbol, term.symbol),
+            None => term.symbol.clone(),
+        };
+
+        if term.is_optional {
+            format!("[{}]", text)
+        } else {
+            text
+        }
+    }
+
+    grammar_text(rules, "=", ", ", "(* empty *)", ";", term_text)
+}
+
+/// Returns `rules` in the EBNF notation of the W3C XML specification, as used by railroad diagram
+/// generators, where optional terms are written as `x?`, and inline lists of one or more elements
+/// as `x+` (or `x (s x)*` if separated by `s`).
+pub fn grammar_w3c(rules: &[GrammarRule]) -> String {
+    fn term_text(term: &GrammarTerm) -> String {
+        match term.separator {
+            Some(ref separator) if term.is_optional => {
+                format!("({} ({} {})*)?", term.symbol, separator, term.symbol)
+            }
+            Some(ref separator) => format!("{} ({} {})*", term.symbol, separator, term.symbol),
+            None if term.is_list && term.is_optional => format!("{}*", term.symbol),
+            None if term.is_list => format!("{}+", term.symbol),
+            None if term.is_optional => format!("{}?", term.symbol),
+            None => term.symbol.clone(),
+        }
+    }
+
+    grammar_text(rules, "::=", " ", "/* empty */", "", term_text)
+}
+
+/// Returns `rules` as a Graphviz graph, with an edge from each non-terminal to each symbol used
+/// by its productions. Non-terminals are drawn as boxes and terminals as ellipses, edges to
+/// optional terms are dashed, edges to lists are labelled `+`, and edges to list separators are
+/// dotted.
+pub fn grammar_dot(rules: &[GrammarRule]) -> String {
+    fn push_unique(lines: &mut Vec<String>, line: String) {
+        if !lines.contains(&line) {
+            lines.push(line);
+        }
+    }
+
+    let mut nodes: Vec<String> = rules
+        .iter()
+        .map(|rule| format!("    {} [shape=box];", json_string(&rule.lhs)))
+        .collect();
+    let mut edges: Vec<String> = Vec::new();
+
+    for rule in rules {
+        for term in rule.alternatives.iter().flatten() {
+            let mut attributes: Vec<&str> = Vec::new();
+            if term.is_optional {
+                attributes.push("style=dashed");
+            }
+            if term.is_list {
+                attributes.push("label=\"+\"");
+            }
+
+            let attributes = if attributes.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", attributes.join(", "))
+            };
+
+            push_unique(
+                &mut edges,
+                format!(
+                    "    {} -> {}{};",
+                    json_string(&rule.lhs),
+                    json_string(&term.symbol),
+                    attributes
+                ),
+            );
+            if term.is_terminal {
+                push_unique(
+                    &mut nodes,
+                    format!("    {} [shape=ellipse];", json_string(&term.symbol)),
+                );
+            }
+
+            if let Some(ref separator) = term.separator {
+                push_unique(
+                    &mut edges,
+                    format!(
+                        "    {} -> {} [style=dotted];",
+                        json_string(&rule.lhs),
+                        json_string(separator)
+                    ),
+                );
+                if !rules.iter().any(|rule| rule.lhs == *separator) {
+                    push_unique(
+                        &mut nodes,
+                        format!("    {} [shape=ellipse];", json_string(separator)),
+                    );
+                }
+            }
+        }
+    }
+
+    format!(
+        "digraph grammar {{\n{}\n\n{}\n}}\n",
+        nodes.join("\n"),
+        edges.join("\n")
+    )
+}
+
+/// Returns `rules` as text, with a blank line between rules, and each alternative of a rule on
+/// its own line.
+///
+/// # Parameters
+///
+/// * `rules` - the grammar rules to write.
+/// * `define` - the operator between the left and right-hand-sides of a rule.
+/// * `concat` - the separator between the terms of an alternative.
+/// * `empty` - the text of an empty alternative.
+/// * `terminator` - the text ending each rule.
+/// * `term_text` - a function returning the text of a term.
+fn grammar_text(
+    rules: &[GrammarRule],
+    define: &str,
+    concat: &str,
+    empty: &str,
+    terminator: &str,
+    term_text: fn(&GrammarTerm) -> String,
+) -> String {
+    let rule_texts: Vec<String> = rules
+        .iter()
+        .map(|rule| {
+            let alternatives: Vec<String> = rule
+                .alternatives
+                .iter()
+                .map(|alternative| {
+                    if alternative.is_empty() {
+                        empty.to_string()
+                    } else {
+                        let terms: Vec<String> = alternative.iter().map(term_text).collect();
+                        terms.join(concat)
+                    }
+                })
+                .collect();
+
+            // Align each alternative under the first, after the definition operator.
+            let indent = " ".repeat(rule.lhs.chars().count() + define.len());
+            let mut text = format!(
+                "{} {} {}",
+                rule.lhs,
+                define,
+                alternatives.join(&format!("\n{}| ", indent))
+            );
+
+            if !terminator.is_empty() {
+                text.push(' ');
+                text.push_str(terminator);
+            }
+            text
+        })
+        .collect();
+
+    format!("{}\n", rule_texts.join("\n\n"))
+}
+
+/// Returns a snippet of the specification `spec` read from `spec_path`, showing the line at
+/// `location` with the located construct underlined by carets.
+pub fn spec_snippet(spec_path: &str, spec: &str, location: &SpecLocation) -> String {
+    let start = &location.span().start;
+    let end = &location.span().end;
+
+    let line = spec.lines().nth(start.line.saturating_sub(1)).unwrap_or("");
+    let column = start.character.saturating_sub(1);
+
+    // Underline to the end of the span, or the end of the line if the span covers multiple lines.
+    let underline_end = if end.line == start.line {
+        end.character.saturating_sub(1)
+    } else {
+        line.chars().count()
+    };
+    let carets = "^".repeat(underline_end.saturating_sub(column).max(1));
+
+    // Preserve tabs before the located construct, so that the carets line up with it.
+    let indent: String = line
+        .chars()
+        .take(column)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    let line_number = start.line.to_string();
+    let gutter = " ".repeat(line_number.len());
+
+    format!(
+        "{gutter}--> {path}:{location}\n{gutter} |\n{number} | {line}\n{gutter} | {indent}{carets}",
+        gutter = gutter,
+        path = spec_path,
+        location = location,
+        number = line_number,
+        line = line,
+        indent = indent,
+        carets = carets,
+    )
+}
+
+/// Returns a line-by-line diff from `expected` to `actual`, where lines only in `expected` are
+/// prefixed by `-` and coloured red, lines only in `actual` are prefixed by `+` and coloured
+/// green, and common lines are prefixed by a space.
+pub fn text_diff(expected: &str, actual: &str) -> String {
+    let changeset = Changeset::new(expected, actual, "\n");
+
+    let mut res = String::new();
+    for diff in &changeset.diffs {
+        match *diff {
+            Difference::Same(ref lines) => {
+                for line in lines.split('\n') {
+                    res.push_str(&format!(" {}\n", line));
+                }
+            }
+            Difference::Rem(ref lines) => {
+                for line in lines.split('\n') {
+                    res.push_str(&format!("{}\n", format!("-{}", line).bright_red()));
+                }
+            }
+            Difference::Add(ref lines) => {
+                for line in lines.split('\n') {
+                    res.push_str(&format!("{}\n", format!("+{}", line).bright_green()));
+                }
+            }
+        }
+    }
+    res
+}
+
+/// Returns `production` in the form `lhs -> rhs`, where list symbols are wrapped in braces, along
+/// with their separators (if any).
+fn production_text(production: &Production) -> String {
+    let rhs: Vec<String> = production
+        .rhs()
+        .iter()
+        .map(|symbol| match symbol.separator() {
+            Some(separator) => format!("{{{} / {}}}", symbol.symbol(), separator),
+            None if symbol.is_list() => format!("{{{}}}", symbol.symbol()),
+            None => symbol.symbol().to_string(),
+        })
+        .collect();
+
+    if rhs.is_empty() {
+        format!("{} ->", production.lhs())
+    } else {
+        format!("{} -> {}", production.lhs(), rhs.join(" "))
+    }
+}
+
+/// Returns `position` as a JSON object.
+pub fn json_position(position: &padd::Position) -> String {
+    format!(
+        "{{\"offset\":{},\"line\":{},\"character\":{}}}",
+        position.offset, position.line, position.character
+    )
+}
+
+/// Returns `string` as a quoted JSON string literal.
+pub fn json_string(string: &str) -> String {
+    let mut res = String::from("\"");
+
+    for c in string.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+
+    res.push('"');
+    res
+}
+
+/// Returns `string` with newlines, tabs, and carriage returns escaped, so that it fits on a
+/// single line.
+fn escape(string: &str) -> String {
+    string
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
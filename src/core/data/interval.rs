@@ -1,6 +1,9 @@
-use std::{
-    cmp, error, fmt,
-    ops::{Range, RangeInclusive},
+use {
+    core::util::serial::{self, Reader, Serial, Writer},
+    std::{
+        cmp, error, fmt,
+        ops::{Range, RangeInclusive},
+    },
 };
 
 /// Bound: Trait representing an inclusive bound.
@@ -104,6 +107,34 @@ impl<Key: Bound, Value> IntervalMap<Key, Value> {
         Ok(())
     }
 
+    /// Returns references to the values of all intervals in the map, in no particular order.
+    pub fn values(&self) -> Vec<&Value> {
+        let mut values: Vec<&Value> = Vec::new();
+        let mut stack: Vec<&Node<Key, Value>> = self.root.iter().map(|root| &**root).collect();
+
+        while let Some(node) = stack.pop() {
+            values.push(&node.value);
+            stack.extend(node.left.iter().chain(&node.right).map(|child| &**child));
+        }
+
+        values
+    }
+
+    /// Returns all intervals in the map along with references to their values, in no particular
+    /// order.
+    pub fn entries(&self) -> Vec<(RangeInclusive<Key>, &Value)> {
+        let mut entries: Vec<(RangeInclusive<Key>, &Value)> = Vec::new();
+        let mut stack: Vec<&Node<Key, Value>> = self.root.iter().map(|root| &**root).collect();
+
+        while let Some(node) = stack.pop() {
+            let keys = node.keys.start.clone()..=node.keys.end.clone();
+            entries.push((keys, &node.value));
+            stack.extend(node.left.iter().chain(&node.right).map(|child| &**child));
+        }
+
+        entries
+    }
+
     /// Inserts `value` into the map whose root node is `node` for all keys in interval `keys`.
     /// Returns the new root of the tree initially rooted at `node`, after insertion and balancing.
     ///
@@ -363,6 +394,42 @@ impl<Key: Bound, Value> Node<Key, Value> {
     }
 }
 
+impl<Key: Bound + Serial, Value: Serial> Serial for IntervalMap<Key, Value> {
+    fn write(&self, writer: &mut Writer) {
+        self.root.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            root: Option::read(reader)?,
+        })
+    }
+}
+
+impl<Key: Bound + Serial, Value: Serial> Serial for Node<Key, Value> {
+    /// Writes the subtree rooted at this node in pre-order, so that its shape (and balance) is
+    /// preserved when read.
+    fn write(&self, writer: &mut Writer) {
+        self.keys.start.write(writer);
+        self.keys.end.write(writer);
+        self.value.write(writer);
+        self.left.write(writer);
+        self.right.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        let start = Key::read(reader)?;
+        let end = Key::read(reader)?;
+
+        let mut node = Self::new(Interval { start, end }, Value::read(reader)?);
+        node.left = Option::read(reader)?;
+        node.right = Option::read(reader)?;
+        node.update_from_children();
+
+        Ok(node)
+    }
+}
+
 /// Error: Represents an error encountered while using an `IntervalMap`.
 ///
 /// # Types
@@ -596,6 +663,42 @@ mod tests {
         assert_balance(&map, 2);
     }
 
+    #[test]
+    fn values() {
+        //setup
+        let mut map: IntervalMap<u32, u32> = IntervalMap::new();
+        map.insert(Interval::from(1..=3), 32).unwrap();
+        map.insert(Interval::from(6..14), 16).unwrap();
+        map.insert(Interval::from(5..=5), 1).unwrap();
+
+        //exercise
+        let mut values = map.values();
+        values.sort();
+
+        //verify
+        assert_eq!(values, vec![&1, &16, &32]);
+    }
+
+    #[test]
+    fn entries() {
+        //setup
+        let mut map: IntervalMap<u32, u32> = IntervalMap::new();
+        map.insert(Interval::from(1..=3), 32).unwrap();
+        map.insert(Interval::from(6..14), 16).unwrap();
+        map.insert(Interval::from(5..=5), 1).unwrap();
+
+        //exercise
+        let mut entries: Vec<(u32, u32, &u32)> = map
+            .entries()
+            .into_iter()
+            .map(|(keys, value)| (*keys.start(), *keys.end(), value))
+            .collect();
+        entries.sort();
+
+        //verify
+        assert_eq!(entries, vec![(1, 3, &32), (5, 5, &1), (6, 13, &16)]);
+    }
+
     fn assert_balance<K: Bound, V>(map: &IntervalMap<K, V>, size: u32) {
         let actual_height = map.root.as_ref().map_or(0, |root| root.height) as i64;
         let expected_height = f64::from(size).log2().ceil() as i64;
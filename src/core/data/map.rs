@@ -1,3 +1,5 @@
+use core::util::serial::{self, Reader, Serial, Writer};
+
 /// Continuously Encoded Hash Map: Keys are integers which index values directly in a vector.
 /// Efficient when the key space is very dense (e.g. when storing half a bijection).
 ///
@@ -95,6 +97,18 @@ impl<V: Default> CEHashMap<V> {
     }
 }
 
+impl<V: Default + Serial> Serial for CEHashMap<V> {
+    fn write(&self, writer: &mut Writer) {
+        self.vector.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            vector: Vec::read(reader)?,
+        })
+    }
+}
+
 /// Continuously Encoded Hash Map Iterator: An iterator over the values of a CEHashMap.
 ///
 /// # Type Parameters
@@ -1,4 +1,7 @@
-use std::{error, fmt};
+use {
+    core::util::serial::{self, Reader, Serial, Writer},
+    std::{error, fmt},
+};
 
 /// Trie: A simple trie data structure, supporting generic value types and byte-slice keys.
 pub struct Trie<Value> {
@@ -36,6 +39,46 @@ impl<Value> Trie<Value> {
     pub fn longest_match(&self, key: &[u8]) -> Option<(&Value, usize)> {
         self.root.longest_match(KeySeq::from(key), None)
     }
+
+    /// Returns references to all values in the trie, in no particular order.
+    pub fn values(&self) -> Vec<&Value> {
+        let mut values: Vec<&Value> = Vec::new();
+        let mut stack: Vec<&Node<Value>> = vec![&self.root];
+
+        while let Some(node) = stack.pop() {
+            values.extend(node.value.as_ref());
+            stack.extend(node.children.iter().flatten().map(|child| &**child));
+        }
+
+        values
+    }
+
+    /// Returns all keys in the trie along with references to their values, in no particular order.
+    pub fn entries(&self) -> Vec<(Vec<u8>, &Value)> {
+        let mut entries: Vec<(Vec<u8>, &Value)> = Vec::new();
+        let mut stack: Vec<(&Node<Value>, Vec<u8>)> = vec![(&self.root, Vec::new())];
+
+        while let Some((node, muxes)) = stack.pop() {
+            if let Some(value) = node.value.as_ref() {
+                // Values are only stored once a whole number of key bytes has been consumed.
+                let key: Vec<u8> = muxes
+                    .chunks(2)
+                    .map(|pair| (pair[0] << MUX_WIDTH) | pair[1])
+                    .collect();
+                entries.push((key, value));
+            }
+
+            for (index, child) in node.children.iter().enumerate() {
+                if let Some(child) = child {
+                    let mut child_muxes = muxes.clone();
+                    child_muxes.push(index as u8);
+                    stack.push((&**child, child_muxes));
+                }
+            }
+        }
+
+        entries
+    }
 }
 
 /// The number of key bits to multiplex on during traversal of each trie node.
@@ -139,6 +182,54 @@ impl<Value> Node<Value> {
     }
 }
 
+impl<Value: Serial> Serial for Trie<Value> {
+    fn write(&self, writer: &mut Writer) {
+        self.root.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            root: HeapNode::read(reader)?,
+        })
+    }
+}
+
+impl<Value: Serial> Serial for Node<Value> {
+    /// Writes this node in pre-order, where each present child is preceded by its index.
+    fn write(&self, writer: &mut Writer) {
+        self.value.write(writer);
+
+        let children: Vec<(usize, &HeapNode<Value>)> = self
+            .children
+            .iter()
+            .enumerate()
+            .filter_map(|(index, child)| child.as_ref().map(|child| (index, child)))
+            .collect();
+
+        writer.write_u8(children.len() as u8);
+        for (index, child) in children {
+            writer.write_u8(index as u8);
+            child.write(writer);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        let mut node = Self::new();
+        node.value = Option::read(reader)?;
+
+        for _ in 0..reader.read_u8()? {
+            let index = reader.read_u8()? as usize;
+            if index >= TREE_WIDTH {
+                return Err(serial::Error::InvalidErr(format!("trie child {}", index)));
+            }
+
+            node.children[index] = Some(HeapNode::read(reader)?);
+        }
+
+        Ok(node)
+    }
+}
+
 /// Key Sequence: Represents a suffix of a trie key.
 ///
 /// # Fields
@@ -450,4 +541,43 @@ mod tests {
         trie.remove("".as_bytes());
         assert_eq!(trie.longest_match("z".as_bytes()), None);
     }
+
+    #[test]
+    fn values() {
+        let mut trie: Trie<u32> = Trie::new();
+
+        trie.insert("abc".as_bytes(), 1).unwrap();
+        trie.insert("abcd".as_bytes(), 2).unwrap();
+        trie.insert("z".as_bytes(), 3).unwrap();
+        trie.insert("".as_bytes(), 4).unwrap();
+        trie.remove("abc".as_bytes());
+
+        let mut values = trie.values();
+        values.sort();
+
+        assert_eq!(values, vec![&2, &3, &4]);
+    }
+
+    #[test]
+    fn entries() {
+        let mut trie: Trie<u32> = Trie::new();
+
+        trie.insert("abc".as_bytes(), 1).unwrap();
+        trie.insert("abcd".as_bytes(), 2).unwrap();
+        trie.insert("ü".as_bytes(), 3).unwrap();
+        trie.insert("".as_bytes(), 4).unwrap();
+        trie.remove("abc".as_bytes());
+
+        let mut entries = trie.entries();
+        entries.sort();
+
+        assert_eq!(
+            entries,
+            vec![
+                (Vec::new(), &4),
+                ("abcd".as_bytes().to_vec(), &2),
+                ("ü".as_bytes().to_vec(), &3),
+            ]
+        );
+    }
 }
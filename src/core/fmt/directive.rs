@@ -0,0 +1,187 @@
+use {
+    core::{
+        lex::Token,
+        parse::{
+            grammar::{Grammar, GrammarSymbol},
+            SymbolParseMethod, Tree,
+        },
+    },
+    std::ops::Range,
+};
+
+/// The text which, when found in a directive token, disables formatting until the next `ON`.
+pub const OFF: &str = "padd:off";
+
+/// The text which, when found in a directive token, re-enables formatting after an `OFF`.
+pub const ON: &str = "padd:on";
+
+/// Returns the byte ranges of the input in which formatting is disabled, given the lexed
+/// `tokens` of the input.
+///
+/// Each range starts at a directive token containing `padd:off`, and ends after the next
+/// directive token containing `padd:on` (or the last token, if formatting is never re-enabled).
+pub fn disabled_ranges<Symbol: GrammarSymbol>(
+    tokens: &[Token<Symbol>],
+    grammar: &dyn Grammar<Symbol>,
+) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for token in tokens {
+        if !grammar.is_directive(token.kind()) {
+            continue;
+        }
+
+        match start {
+            None if token.lexeme().contains(OFF) => start = Some(token.span().start.offset),
+            Some(offset) if token.lexeme().contains(ON) => {
+                ranges.push(offset..token.span().end.offset);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(offset) = start {
+        ranges.push(offset..tokens.last().unwrap().span().end.offset);
+    }
+
+    ranges
+}
+
+/// Disabled Region: A region of the input which must be reproduced exactly when formatted.
+///
+/// # Fields
+///
+/// * `range` - the byte range of the input which is reproduced.
+/// * `first` - the start offset of the first formatted token (or recovered region) in `range`.
+/// * `last` - the end offset of the last formatted token (or recovered region) in `range`.
+pub struct DisabledRegion {
+    range: Range<usize>,
+    first: usize,
+    last: usize,
+}
+
+/// Boundary: The start or end of the formatted text of a disabled region, which is replaced by
+/// the original text of the region once laid out.
+///
+/// # Types
+///
+/// * `Start` - indicates the start of the formatted text of the region with the given index.
+/// * `End` - indicates the end of the formatted text of the region with the given index.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Boundary {
+    Start(usize),
+    End(usize),
+}
+
+/// Returns the disabled regions of `parse` for the disabled byte `ranges` of the input, which are
+/// sorted and disjoint.
+///
+/// Ranges which do not contain any formatted tokens are dropped.
+pub fn disabled_regions<Symbol: GrammarSymbol>(
+    parse: &Tree<Symbol>,
+    ranges: &[Range<usize>],
+) -> Vec<DisabledRegion> {
+    if ranges.is_empty() {
+        return Vec::new();
+    }
+
+    let mut anchors: Vec<Option<(usize, usize)>> = vec![None; ranges.len()];
+    let mut stack: Vec<&Tree<Symbol>> = vec![parse];
+
+    while let Some(node) = stack.pop() {
+        let span = node.span();
+        if span.is_empty() {
+            continue;
+        }
+
+        if !node.is_leaf() && node.spm != SymbolParseMethod::Recovered {
+            stack.extend(node.children.iter().rev());
+            continue;
+        }
+
+        let (start, end) = (span.start.offset, span.end.offset);
+        let i = ranges.partition_point(|range| range.end < end);
+        if i < ranges.len() && ranges[i].start <= start {
+            let first = anchors[i].map_or(start, |(first, _)| first);
+            anchors[i] = Some((first, end));
+        }
+    }
+
+    ranges
+        .iter()
+        .zip(anchors)
+        .filter_map(|(range, anchor)| {
+            anchor.map(|(first, last)| DisabledRegion {
+                range: range.clone(),
+                first,
+                last,
+            })
+        })
+        .collect()
+}
+
+/// Returns the boundaries of any disabled regions which the token (or recovered region) `node`
+/// starts and ends, respectively, which surround the formatted text of `node`.
+pub fn boundaries<Symbol: GrammarSymbol>(
+    node: &Tree<Symbol>,
+    regions: &[DisabledRegion],
+) -> (Option<Boundary>, Option<Boundary>) {
+    let span = node.span();
+    let start = regions
+        .binary_search_by_key(&span.start.offset, |region| region.first)
+        .ok()
+        .map(Boundary::Start);
+    let end = regions
+        .binary_search_by_key(&span.end.offset, |region| region.last)
+        .ok()
+        .map(Boundary::End);
+
+    (start, end)
+}
+
+/// Returns the laid out `text` with the text between the boundaries of each disabled region
+/// replaced by the original text of the region in `input`, given the byte offsets of the
+/// `boundaries` in `text`, in order.
+///
+/// Regions whose boundaries were not both laid out (in order) are left formatted.
+pub fn resolve(
+    text: String,
+    boundaries: &[(usize, Boundary)],
+    input: &str,
+    regions: &[DisabledRegion],
+) -> String {
+    if boundaries.is_empty() {
+        return text;
+    }
+
+    // The offsets of the start and end boundaries of each region, in order.
+    let mut offsets: Vec<Vec<(usize, bool)>> = vec![Vec::new(); regions.len()];
+    for &(offset, boundary) in boundaries {
+        match boundary {
+            Boundary::Start(i) => offsets[i].push((offset, true)),
+            Boundary::End(i) => offsets[i].push((offset, false)),
+        }
+    }
+
+    let mut res = String::new();
+    let mut cursor: usize = 0;
+
+    for (region, offsets) in regions.iter().zip(offsets) {
+        let mut offsets = offsets
+            .into_iter()
+            .skip_while(|&(offset, is_start)| offset < cursor || !is_start);
+
+        if let Some((start, _)) = offsets.next() {
+            if let Some((end, _)) = offsets.find(|&(_, is_start)| !is_start) {
+                res.push_str(&text[cursor..start]);
+                res.push_str(&input[region.range.clone()]);
+                cursor = end;
+            }
+        }
+    }
+
+    res.push_str(&text[cursor..]);
+    res
+}
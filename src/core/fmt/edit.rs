@@ -0,0 +1,314 @@
+use {
+    core::util::diff::{self, Difference},
+    std::ops::Range,
+};
+
+/// Text Edit: A replacement of a range of some original text.
+///
+/// # Fields
+///
+/// * `range` - the range of byte offsets in the original text being replaced.
+/// * `replacement` - the text to replace the range with.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    /// Returns `text` with `edits` applied, where `edits` are sorted and non-overlapping.
+    pub fn apply(text: &str, edits: &[TextEdit]) -> String {
+        let mut res = String::new();
+        let mut cursor: usize = 0;
+
+        for edit in edits {
+            res.push_str(&text[cursor..edit.range.start]);
+            res.push_str(&edit.replacement);
+            cursor = edit.range.end;
+        }
+
+        res.push_str(&text[cursor..]);
+        res
+    }
+}
+
+/// Returns a sorted list of non-overlapping edits which transform `original` into `formatted`.
+///
+/// The lines of the texts are first aligned ignoring whitespace, since formatting typically only
+/// changes whitespace. Each pair of aligned lines which differ, and each block of unaligned
+/// lines, is then replaced by an edit which excludes any characters common to the start or end
+/// of both versions.
+pub fn diff(original: &str, formatted: &str) -> Vec<TextEdit> {
+    if original == formatted {
+        return Vec::new();
+    }
+
+    let original_lines = line_starts(original);
+    let formatted_lines = line_starts(formatted);
+
+    // Compare the texts line by line, where lines with equal non-whitespace characters are equal.
+    let differences = diff::diff(
+        &line_keys(original, &original_lines),
+        &line_keys(formatted, &formatted_lines),
+    );
+
+    let mut edits: Vec<TextEdit> = Vec::new();
+    let mut original_line: usize = 0;
+    let mut formatted_line: usize = 0;
+    let mut block: Option<(usize, usize)> = None;
+
+    {
+        let mut push_edit = |original_start: usize,
+                             formatted_start: usize,
+                             original_end: usize,
+                             formatted_end: usize| {
+            let original_range = original_lines[original_start]..original_lines[original_end];
+            let formatted_range = formatted_lines[formatted_start]..formatted_lines[formatted_end];
+
+            if original[original_range.clone()] == formatted[formatted_range.clone()] {
+                return;
+            }
+
+            let edit = block_edit(original, formatted, original_range, formatted_range);
+
+            // Merge adjacent edits, so that each contiguous change is a single edit.
+            if let Some(last) = edits.last_mut() {
+                if last.range.end == edit.range.start {
+                    last.range.end = edit.range.end;
+                    last.replacement.push_str(&edit.replacement);
+                    return;
+                }
+            }
+
+            edits.push(edit);
+        };
+
+        for difference in differences {
+            match difference {
+                Difference::Same(count) => {
+                    if let Some((original_start, formatted_start)) = block.take() {
+                        push_edit(
+                            original_start,
+                            formatted_start,
+                            original_line,
+                            formatted_line,
+                        );
+                    }
+
+                    for _ in 0..count {
+                        push_edit(
+                            original_line,
+                            formatted_line,
+                            original_line + 1,
+                            formatted_line + 1,
+                        );
+                        original_line += 1;
+                        formatted_line += 1;
+                    }
+                }
+                Difference::Removed(count) => {
+                    block.get_or_insert((original_line, formatted_line));
+                    original_line += count;
+                }
+                Difference::Added(count) => {
+                    block.get_or_insert((original_line, formatted_line));
+                    formatted_line += count;
+                }
+            }
+        }
+
+        if let Some((original_start, formatted_start)) = block {
+            push_edit(
+                original_start,
+                formatted_start,
+                original_line,
+                formatted_line,
+            );
+        }
+    }
+
+    edits
+}
+
+/// Returns the byte offsets of the start of each (non-empty) line in `text`, followed by the
+/// length of `text`.
+fn line_starts(text: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    for (offset, c) in text.char_indices() {
+        if c == '\n' && offset + 1 < text.len() {
+            starts.push(offset + 1);
+        }
+    }
+
+    if !text.is_empty() {
+        starts.push(text.len());
+    }
+    starts
+}
+
+/// Returns the non-whitespace characters of each line of `text` (delimited by `starts`).
+fn line_keys(text: &str, starts: &[usize]) -> Vec<String> {
+    (1..starts.len())
+        .map(|i| {
+            text[starts[i - 1]..starts[i]]
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the edit which replaces the `original_range` block of `original` with the
+/// `formatted_range` block of `formatted`, trimmed of any common suffix and prefix.
+fn block_edit(
+    original: &str,
+    formatted: &str,
+    original_range: Range<usize>,
+    formatted_range: Range<usize>,
+) -> TextEdit {
+    let original_block = &original[original_range.clone()];
+    let formatted_block = &formatted[formatted_range];
+
+    let suffix: usize = original_block
+        .chars()
+        .rev()
+        .zip(formatted_block.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+
+    let original_block = &original_block[..original_block.len() - suffix];
+    let formatted_block = &formatted_block[..formatted_block.len() - suffix];
+
+    let prefix: usize = original_block
+        .chars()
+        .zip(formatted_block.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+
+    TextEdit {
+        range: original_range.start + prefix..original_range.start + original_block.len(),
+        replacement: formatted_block[prefix..].to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_identical() {
+        //exercise
+        let edits = diff("a\nb\n", "a\nb\n");
+
+        //verify
+        assert!(edits.is_empty());
+    }
+
+    #[test]
+    fn diff_minimal_edits() {
+        //setup
+        let original = "a=b;\nc  =d;\ne=f;\ng=h;\n  i=j;";
+        let formatted = "a=b;\nc = d;\ne=f;\ng=h;\ni = j;";
+
+        //exercise
+        let edits = diff(original, formatted);
+
+        //verify
+        assert_eq!(
+            edits,
+            vec![
+                TextEdit {
+                    range: 7..9,
+                    replacement: "= ".to_string(),
+                },
+                TextEdit {
+                    range: 22..26,
+                    replacement: "i = ".to_string(),
+                },
+            ]
+        );
+        assert_eq!(TextEdit::apply(original, &edits), formatted);
+    }
+
+    #[test]
+    fn diff_added_and_removed_lines() {
+        //setup
+        let original = "a\nb\nc\n";
+        let formatted = "a\nx\ny\nc\nd\n";
+
+        //exercise
+        let edits = diff(original, formatted);
+
+        //verify
+        assert_eq!(
+            edits,
+            vec![
+                TextEdit {
+                    range: 2..3,
+                    replacement: "x\ny".to_string(),
+                },
+                TextEdit {
+                    range: 6..6,
+                    replacement: "d\n".to_string(),
+                },
+            ]
+        );
+        assert_eq!(TextEdit::apply(original, &edits), formatted);
+    }
+
+    #[test]
+    fn diff_trailing_lines() {
+        //setup
+        let original = "a\nb\n\n\n";
+        let formatted = "a\nb";
+
+        //exercise
+        let edits = diff(original, formatted);
+
+        //verify
+        assert_eq!(
+            edits,
+            vec![TextEdit {
+                range: 3..6,
+                replacement: String::new(),
+            }]
+        );
+        assert_eq!(TextEdit::apply(original, &edits), formatted);
+    }
+
+    #[test]
+    fn diff_multibyte_characters() {
+        //setup
+        let original = "é  ü\nö";
+        let formatted = "é ü\nö";
+
+        //exercise
+        let edits = diff(original, formatted);
+
+        //verify
+        assert_eq!(TextEdit::apply(original, &edits), formatted);
+        assert_eq!(edits[0].range, 2..3);
+    }
+
+    #[test]
+    fn diff_large() {
+        //setup
+        let lines: Vec<String> = (0..100_000).map(|i| format!("x={};", i)).collect();
+        let original = lines.join("\n");
+
+        let mut formatted_lines = lines.clone();
+        formatted_lines[10] = "x = 10;".to_string();
+        formatted_lines.remove(50_000);
+        formatted_lines.insert(90_000, "y=0;".to_string());
+        let formatted = formatted_lines.join("\n");
+
+        //exercise
+        let edits = diff(&original, &formatted);
+
+        //verify
+        assert_eq!(edits.len(), 3);
+        assert_eq!(TextEdit::apply(&original, &edits), formatted);
+    }
+}
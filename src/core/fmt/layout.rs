@@ -0,0 +1,329 @@
+use {core::fmt::directive::Boundary, std::rc::Rc};
+
+/// The maximum line width used when neither the specification nor the format job sets one.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// Doc: A node of a layout document, which is built by the formatter and laid out once the whole
+/// document is known.
+///
+/// The documents inside groups and line breaks are shared, so cloning a document is shallow.
+///
+/// # Types
+///
+/// * `Text` - stores text which is laid out as-is.
+/// * `Group` - stores the documents of a group, which are laid out flat if they fit on the
+///   current line.
+/// * `Line` - stores the flat and broken documents of a line break.
+/// * `Boundary` - stores a boundary of a disabled region, which takes up no columns.
+#[derive(Clone)]
+pub enum Doc {
+    Text(String),
+    Group(Rc<Vec<Doc>>),
+    Line(Rc<Vec<Doc>>, Rc<Vec<Doc>>),
+    Boundary(Boundary),
+}
+
+impl Drop for Doc {
+    // Documents are dropped iteratively, so that deeply nested groups cannot overflow the call
+    // stack.
+    fn drop(&mut self) {
+        let mut stack: Vec<Doc> = Vec::new();
+        self.take_children(&mut stack);
+        while let Some(mut doc) = stack.pop() {
+            doc.take_children(&mut stack);
+        }
+    }
+}
+
+impl Doc {
+    /// Moves the child documents of this document onto `stack`, unless they are shared with
+    /// another document.
+    fn take_children(&mut self, stack: &mut Vec<Doc>) {
+        match self {
+            Doc::Text(_) | Doc::Boundary(_) => {}
+            Doc::Group(docs) => {
+                if let Some(docs) = Rc::get_mut(docs) {
+                    stack.append(docs);
+                }
+            }
+            Doc::Line(flat_docs, broken_docs) => {
+                for docs in [flat_docs, broken_docs] {
+                    if let Some(docs) = Rc::get_mut(docs) {
+                        stack.append(docs);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Appends `text` to the documents `docs`, extending the last document if it is text.
+pub fn push_text(docs: &mut Vec<Doc>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+
+    match docs.last_mut() {
+        Some(Doc::Text(last)) => last.push_str(text),
+        _ => docs.push(Doc::Text(text.to_string())),
+    }
+}
+
+/// Appends (shallow) clones of the documents `other` to the documents `docs`.
+pub fn push_docs(docs: &mut Vec<Doc>, other: &[Doc]) {
+    for doc in other {
+        match doc {
+            Doc::Text(text) => push_text(docs, text),
+            _ => docs.push(doc.clone()),
+        }
+    }
+}
+
+/// Returns true if the documents `docs` consist only of text, which is equal to `text`.
+pub fn is_text(docs: &[Doc], text: &str) -> bool {
+    let mut rest = text;
+    for doc in docs {
+        match doc {
+            Doc::Text(doc_text) if rest.starts_with(&doc_text[..]) => {
+                rest = &rest[doc_text.len()..];
+            }
+            _ => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+/// Returns the text of the documents `docs` laid out to fit within `width` columns where possible,
+/// given that the text starts at column `column`, along with the byte offsets of the boundaries of
+/// disabled regions in the text, in order.
+///
+/// Groups are laid out from the outside in. Each group is laid out flat if it contains no
+/// newlines when flat, and it (along with the text following it, up to the next line break) fits
+/// on the current line. Otherwise the group is broken, and each of its line breaks is laid out in
+/// its broken form. Line breaks outside of any group are always broken.
+///
+pub fn layout(docs: &[Doc], width: usize, column: usize) -> (String, Vec<(usize, Boundary)>) {
+    let mut res = String::new();
+    let mut boundaries: Vec<(usize, Boundary)> = Vec::new();
+    let mut column = column;
+    let mut stack: Vec<(bool, &Doc)> = docs.iter().rev().map(|doc| (false, doc)).collect();
+
+    while let Some((flat, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                res.push_str(text);
+                column = match text.rfind('\n') {
+                    Some(index) => text[index + 1..].chars().count(),
+                    None => column + text.chars().count(),
+                };
+            }
+            Doc::Group(docs) => {
+                let flat = flat || fits(docs, &stack, width as isize - column as isize);
+                stack.extend(docs.iter().rev().map(|doc| (flat, doc)));
+            }
+            Doc::Line(flat_docs, broken_docs) => {
+                let docs = if flat { flat_docs } else { broken_docs };
+                stack.extend(docs.iter().rev().map(|doc| (flat, doc)));
+            }
+            Doc::Boundary(boundary) => boundaries.push((res.len(), *boundary)),
+        }
+    }
+
+    (res, boundaries)
+}
+
+/// Returns true if the group containing `docs` fits in the `remaining` columns of the current
+/// line when laid out flat, followed by the layout stack `rest` up to its next newline.
+fn fits(docs: &[Doc], rest: &[(bool, &Doc)], remaining: isize) -> bool {
+    let mut remaining = remaining;
+    let mut rest_index = rest.len();
+    let mut stack: Vec<(bool, bool, &Doc)> =
+        docs.iter().rev().map(|doc| (true, true, doc)).collect();
+
+    loop {
+        let (flat, in_group, doc) = match stack.pop() {
+            Some(entry) => entry,
+            None => {
+                if rest_index == 0 {
+                    return remaining >= 0;
+                }
+                rest_index -= 1;
+                let (flat, doc) = rest[rest_index];
+                (flat, false, doc)
+            }
+        };
+
+        match doc {
+            Doc::Text(text) => {
+                let line_end = text.find('\n');
+                remaining -= text[..line_end.unwrap_or(text.len())].chars().count() as isize;
+                if remaining < 0 {
+                    return false;
+                }
+
+                if line_end.is_some() {
+                    // A newline in the group forces it to break, but a newline after the group
+                    // ends the line that the group must fit on.
+                    return !in_group;
+                }
+            }
+            Doc::Group(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (flat, in_group, doc)));
+            }
+            Doc::Line(flat_docs, broken_docs) => {
+                let docs = if flat { flat_docs } else { broken_docs };
+                stack.extend(docs.iter().rev().map(|doc| (flat, in_group, doc)));
+            }
+            Doc::Boundary(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> Doc {
+        Doc::Text(text.to_string())
+    }
+
+    fn line(flat: &str, broken: &str) -> Doc {
+        Doc::Line(Rc::new(vec![text(flat)]), Rc::new(vec![text(broken)]))
+    }
+
+    fn group(docs: Vec<Doc>) -> Doc {
+        Doc::Group(Rc::new(docs))
+    }
+
+    fn call(name: &str, args: Vec<Vec<Doc>>) -> Vec<Doc> {
+        let mut inner = vec![line("", "\n    ")];
+        for (i, arg) in args.into_iter().enumerate() {
+            if i > 0 {
+                inner.push(text(","));
+                inner.push(line(" ", "\n    "));
+            }
+            inner.extend(arg);
+        }
+        inner.push(line("", "\n"));
+        vec![text(&format!("{}(", name)), group(inner), text(")")]
+    }
+
+    #[test]
+    fn layout_text() {
+        //exercise
+        let (res, _) = layout(&[text("a\nb")], 1, 0);
+
+        //verify
+        assert_eq!(res, "a\nb");
+    }
+
+    #[test]
+    fn layout_group_fits() {
+        //setup
+        let docs = call("f", vec![vec![text("a")], vec![text("b")], vec![text("c")]]);
+
+        //exercise
+        let (res, _) = layout(&docs, 11, 0);
+
+        //verify
+        assert_eq!(res, "f(a, b, c)");
+    }
+
+    #[test]
+    fn layout_group_broken() {
+        //setup
+        let mut docs = call("f", vec![vec![text("a")], vec![text("b")], vec![text("c")]]);
+        docs.push(text(";"));
+
+        //exercise
+        let (res, _) = layout(&docs, 10, 0);
+
+        //verify
+        assert_eq!(res, "f(\n    a,\n    b,\n    c\n);");
+    }
+
+    #[test]
+    fn layout_group_start_column() {
+        //setup
+        let docs = call("f", vec![vec![text("a")], vec![text("b")]]);
+
+        //exercise
+        let (res, _) = layout(&docs, 10, 4);
+
+        //verify
+        assert_eq!(res, "f(\n    a,\n    b\n)");
+    }
+
+    #[test]
+    fn layout_nested_groups() {
+        //setup
+        let inner = call("g", vec![vec![text("b")], vec![text("c")]]);
+        let docs = call("f", vec![vec![text("a")], inner]);
+
+        //exercise
+        let (res, _) = layout(&docs, 12, 0);
+
+        //verify
+        assert_eq!(res, "f(\n    a,\n    g(b, c)\n)");
+    }
+
+    #[test]
+    fn layout_newline_breaks_group() {
+        //setup
+        let docs = call("f", vec![vec![text("a")], vec![text("{\n}")]]);
+
+        //exercise
+        let (res, _) = layout(&docs, 80, 0);
+
+        //verify
+        assert_eq!(res, "f(\n    a,\n    {\n}\n)");
+    }
+
+    #[test]
+    fn layout_line_outside_group() {
+        //setup
+        let docs = vec![text("a"), line(" ", "\n"), text("b")];
+
+        //exercise
+        let (res, _) = layout(&docs, 80, 0);
+
+        //verify
+        assert_eq!(res, "a\nb");
+    }
+
+    #[test]
+    fn layout_deeply_nested_groups() {
+        //setup
+        let depth = 100_000;
+        let mut docs: Vec<Doc> = Vec::new();
+        for _ in 0..depth {
+            docs = vec![group(vec![line("(", "[\n"), group(docs)])];
+        }
+
+        //exercise
+        let (res, _) = layout(&docs, 80, 0);
+
+        //verify
+        assert_eq!(res.matches('[').count(), depth - 80);
+        assert_eq!(res.matches('(').count(), 80);
+    }
+
+    #[test]
+    fn push_text_merges() {
+        //setup
+        let mut docs: Vec<Doc> = Vec::new();
+
+        //exercise
+        push_text(&mut docs, "a");
+        push_text(&mut docs, "");
+        push_docs(&mut docs, &[text("b"), line(" ", "\n"), text("c")]);
+        push_text(&mut docs, "d");
+
+        //verify
+        assert_eq!(docs.len(), 3);
+        assert!(is_text(&docs[..1], "ab"));
+        assert!(is_text(&docs[2..], "cd"));
+        assert!(!is_text(&docs, "ab cd"));
+        assert!(!is_text(&docs[..1], "abc"));
+    }
+}
@@ -1,13 +1,28 @@
 use {
     core::{
         data::map::CEHashMap,
-        fmt::pattern::{Capture, Pattern, Segment},
+        fmt::{
+            layout::Doc,
+            pattern::{Capture, Condition, Conditional, Pattern, Segment},
+        },
         parse::{grammar::GrammarSymbol, Production, ProductionSymbol, SymbolParseMethod, Tree},
+        util::serial::{self, Reader, Serial, Writer},
     },
-    std::{collections::HashMap, error, fmt},
+    std::{borrow::Cow, cell::RefCell, collections::HashMap, error, fmt, ops::Range, ptr, rc::Rc},
 };
 
+/// The variable which, when set in the scope of an inline list, replaces the list's separators.
+const LIST_SEPARATOR: &str = "separator";
+
+/// The variables which are set in the scope of the first and last elements of an inline list.
+const LIST_FIRST: &str = "first";
+const LIST_LAST: &str = "last";
+
+pub mod directive;
+pub mod edit;
+pub mod layout;
 mod pattern;
+pub mod verify;
 
 /// Formatter: A utility struct used to format parse trees based on a set of `Pattern` objects.
 ///
@@ -20,21 +35,231 @@ mod pattern;
 /// * `pattern_map` - a map from productions to their respective patterns.
 /// * `injection_map` - a map from grammar symbols to their respective injectables, used to format
 /// injected symbols.
+/// * `width` - the maximum line width declared by the specification, if any.
 pub struct Formatter<Symbol: GrammarSymbol> {
     pattern_map: HashMap<Production<Symbol>, Pattern>,
     injection_map: HashMap<Symbol, Injectable>,
+    width: Option<usize>,
 }
 
 impl<Symbol: GrammarSymbol> Formatter<Symbol> {
-    /// Returns the formatted string for the given parse tree.
-    pub fn format(&self, parse: &Tree<Symbol>) -> String {
+    /// Returns the formatted string for the given parse tree of `input`.
+    ///
+    /// Regions of `input` which were recovered from parse errors, or which lie within the
+    /// `disabled` byte ranges, are reproduced verbatim. Groups are laid out to fit within `width`
+    /// columns if set, or the width declared by the specification otherwise.
+    pub fn format(
+        &self,
+        parse: &Tree<Symbol>,
+        input: &str,
+        disabled: &[Range<usize>],
+        width: Option<usize>,
+    ) -> String {
         let format_job = FormatJob {
             parse,
+            input,
             pattern_map: &self.pattern_map,
             injection_map: &self.injection_map,
+            targets: Vec::new(),
+            target_docs: RefCell::new(Vec::new()),
+            disabled: directive::disabled_regions(parse, disabled),
+            width: self.width(width),
         };
         format_job.run()
     }
+
+    /// Returns `input` with only the smallest subtrees of `parse` which enclose the byte range
+    /// `start..end` formatted, and all other input left unchanged.
+    ///
+    /// Subtrees are formatted using the variable scope they would have in a full format, so that
+    /// (for example) indentation is preserved. Any leading or trailing whitespace which a subtree's
+    /// own pattern emits replaces the corresponding whitespace around the subtree in `input`.
+    /// Enclosed subtrees which are never captured by the patterns of their ancestors are left
+    /// unchanged. As in `format`, regions within the `disabled` byte ranges are reproduced
+    /// verbatim, and groups are laid out to fit `width`.
+    pub fn format_range(
+        &self,
+        parse: &Tree<Symbol>,
+        input: &str,
+        disabled: &[Range<usize>],
+        width: Option<usize>,
+        start: usize,
+        end: usize,
+    ) -> String {
+        let targets = range_targets(parse, start, end);
+        let format_job = FormatJob {
+            parse,
+            input,
+            pattern_map: &self.pattern_map,
+            injection_map: &self.injection_map,
+            target_docs: RefCell::new(vec![None; targets.len()]),
+            targets,
+            disabled: directive::disabled_regions(parse, disabled),
+            width: self.width(width),
+        };
+        format_job.run();
+
+        let mut res = String::new();
+        let mut cursor: usize = 0;
+
+        for (i, target) in format_job.targets.iter().enumerate() {
+            let span = target.span();
+            let (start, end) = (span.start.offset, span.end.offset);
+
+            match format_job.target_docs.borrow_mut()[i].take() {
+                Some(target_docs) => {
+                    let limit = format_job
+                        .targets
+                        .get(i + 1)
+                        .map_or(input.len(), |next| next.span().start.offset);
+
+                    // The leading whitespace of the formatted text can move its start column, in
+                    // which case it is laid out again from the new column.
+                    let column = column_of(input, start);
+                    let mut text = format_job.lay_out(&target_docs, column);
+                    let mut text_start = leading_start(input, cursor, start, &text);
+                    if column_of(input, text_start) != column {
+                        text = format_job.lay_out(&target_docs, column_of(input, text_start));
+                        text_start = leading_start(input, cursor, start, &text);
+                    }
+
+                    res.push_str(&input[cursor..text_start]);
+                    res.push_str(&text);
+                    cursor = trailing_end(input, end, limit, &text);
+                }
+                None => {
+                    res.push_str(&input[cursor..end]);
+                    cursor = end;
+                }
+            }
+        }
+
+        res.push_str(&input[cursor..]);
+        res
+    }
+
+    /// Returns the maximum line width to format with, given an optional `width` override.
+    fn width(&self, width: Option<usize>) -> usize {
+        width.or(self.width).unwrap_or(layout::DEFAULT_WIDTH)
+    }
+}
+
+impl<Symbol: GrammarSymbol + Serial> Serial for Formatter<Symbol> {
+    fn write(&self, writer: &mut Writer) {
+        self.pattern_map.write(writer);
+        self.injection_map.write(writer);
+        self.width.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            pattern_map: HashMap::read(reader)?,
+            injection_map: HashMap::read(reader)?,
+            width: Option::read(reader)?,
+        })
+    }
+}
+
+/// Returns the column (in characters) of the byte `offset` in `input`.
+fn column_of(input: &str, offset: usize) -> usize {
+    let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+    input[line_start..offset].chars().count()
+}
+
+/// Returns the start of the input replaced by `text`, the formatted text of a range target which
+/// starts at byte `offset` of `input`, where no input before `min` is replaced.
+///
+/// If `text` starts with whitespace containing N newlines, it replaces the whitespace preceding
+/// the target from its Nth last newline, or just the indentation of the target if N is zero.
+fn leading_start(input: &str, min: usize, offset: usize, text: &str) -> usize {
+    let leading = &text[..text.len() - text.trim_start().len()];
+    if leading.is_empty() {
+        return offset;
+    }
+
+    let preceding = &input[min..offset];
+    let whitespace_start = offset - (preceding.len() - preceding.trim_end().len());
+    let whitespace = &input[whitespace_start..offset];
+
+    whitespace_start
+        + match leading.matches('\n').count() {
+            0 => whitespace.rfind('\n').map_or(0, |i| i + 1),
+            newlines => whitespace
+                .rmatch_indices('\n')
+                .nth(newlines - 1)
+                .map_or(0, |(i, _)| i),
+        }
+}
+
+/// Returns the end of the input replaced by `text`, the formatted text of a range target which
+/// ends at byte `offset` of `input`, where no input after `max` is replaced.
+///
+/// If `text` ends with whitespace containing N newlines, it replaces the whitespace following the
+/// target up to and including its Nth newline, or up to its first newline if N is zero.
+fn trailing_end(input: &str, offset: usize, max: usize, text: &str) -> usize {
+    let trailing = &text[text.trim_end().len()..];
+    if trailing.is_empty() {
+        return offset;
+    }
+
+    let following = &input[offset..max];
+    let whitespace = &following[..following.len() - following.trim_start().len()];
+
+    offset
+        + match trailing.matches('\n').count() {
+            0 => whitespace.find('\n').unwrap_or(whitespace.len()),
+            newlines => whitespace
+                .match_indices('\n')
+                .nth(newlines - 1)
+                .map_or(whitespace.len(), |(i, _)| i + 1),
+        }
+}
+
+/// Returns the smallest set of sibling subtrees of `parse` which enclose the byte range
+/// `start..end`, in order. An empty range encloses the character starting at `start`.
+///
+/// Descends from the root while a single interior child encloses the entire range, stopping at
+/// recovered regions (which cannot be partially formatted). Leaves are never returned, since the
+/// whitespace around a token is determined by the pattern of its parent. If the range overlaps
+/// multiple interior children of the current node (and no leaves), those children are returned.
+/// Otherwise, the current node is returned.
+fn range_targets<Symbol: GrammarSymbol>(
+    parse: &Tree<Symbol>,
+    start: usize,
+    end: usize,
+) -> Vec<&Tree<Symbol>> {
+    let end = if end > start { end } else { start + 1 };
+    let mut node = parse;
+
+    loop {
+        if node.spm == SymbolParseMethod::Recovered {
+            return vec![node];
+        }
+
+        let overlapping: Vec<&Tree<Symbol>> = node
+            .children
+            .iter()
+            .filter(|child| {
+                let span = child.span();
+                !span.is_empty() && span.start.offset < end && start < span.end.offset
+            })
+            .collect();
+
+        if overlapping.is_empty() || overlapping.iter().any(|child| child.is_leaf()) {
+            return vec![node];
+        }
+
+        if overlapping.len() > 1 {
+            return overlapping;
+        }
+
+        let span = overlapping[0].span();
+        if span.start.offset > start || span.end.offset < end {
+            return overlapping;
+        }
+
+        node = overlapping[0];
+    }
 }
 
 /// Formatter Builder: A builder for efficiently constructing `Formatter` structs.
@@ -50,10 +275,12 @@ impl<Symbol: GrammarSymbol> Formatter<Symbol> {
 /// injected symbols.
 /// * `memory` - a map from pattern strings to `Pattern` objects, so that parsing can be skipped for
 /// pattern strings that have already been seen elsewhere in the specification.
+/// * `width` - the maximum line width declared by the specification, if any.
 pub struct FormatterBuilder<Symbol: GrammarSymbol> {
     pattern_map: HashMap<Production<Symbol>, Pattern>,
     injection_map: HashMap<Symbol, Injectable>,
     memory: HashMap<String, Pattern>,
+    width: Option<usize>,
 }
 
 impl<Symbol: GrammarSymbol> FormatterBuilder<Symbol> {
@@ -63,6 +290,7 @@ impl<Symbol: GrammarSymbol> FormatterBuilder<Symbol> {
             pattern_map: HashMap::new(),
             injection_map: HashMap::new(),
             memory: HashMap::new(),
+            width: None,
         }
     }
 
@@ -71,9 +299,16 @@ impl<Symbol: GrammarSymbol> FormatterBuilder<Symbol> {
         Formatter {
             pattern_map: self.pattern_map,
             injection_map: self.injection_map,
+            width: self.width,
         }
     }
 
+    /// Sets the maximum line width that groups are laid out to fit within, replacing any
+    /// previously set width.
+    pub fn set_width(&mut self, width: usize) {
+        self.width = Some(width);
+    }
+
     /// Adds a pattern to the formatter for a specific production.
     ///
     /// Returns an error if the passed pattern string cannot be built into a `Pattern`.
@@ -207,13 +442,24 @@ struct Injection<'scope, Symbol: GrammarSymbol> {
 /// # Fields
 ///
 /// * `parse` - the parse tree to be formatted.
+/// * `input` - the input string which was parsed into `parse`.
 /// * `pattern_map` - a map from productions to their respective patterns.
 /// * `injection_map` - a map from grammar symbols to their respective injectables, used to format
 /// injected symbols.
+/// * `targets` - the subtrees being formatted when formatting a range of the input, or empty if
+///   the entire parse tree is being formatted.
+/// * `target_docs` - the formatted documents of each subtree in `targets`, once reached.
+/// * `disabled` - the regions of the input in which formatting is disabled.
+/// * `width` - the maximum line width that groups are laid out to fit within.
 struct FormatJob<'parse, Symbol: GrammarSymbol + 'parse> {
     parse: &'parse Tree<Symbol>,
+    input: &'parse str,
     pattern_map: &'parse HashMap<Production<Symbol>, Pattern>,
     injection_map: &'parse HashMap<Symbol, Injectable>,
+    targets: Vec<&'parse Tree<Symbol>>,
+    target_docs: RefCell<Vec<Option<Vec<Doc>>>>,
+    disabled: Vec<directive::DisabledRegion>,
+    width: usize,
 }
 
 impl<'parse, Symbol: GrammarSymbol + 'parse> FormatJob<'parse, Symbol> {
@@ -221,37 +467,288 @@ impl<'parse, Symbol: GrammarSymbol + 'parse> FormatJob<'parse, Symbol> {
     ///
     /// Returns the formatted string.
     fn run(&self) -> String {
-        self.recur(self.parse, &HashMap::new())
+        self.lay_out(&self.format_tree(), 0)
+    }
+
+    /// Returns the formatted documents `docs`, which start at column `column`, laid out as text.
+    fn lay_out(&self, docs: &[Doc], column: usize) -> String {
+        let (res, boundaries) = layout::layout(docs, self.width, column);
+        directive::resolve(res, &boundaries, self.input, &self.disabled)
+    }
+
+    /// Returns the formatted documents of the parse tree being formatted.
+    ///
+    /// The tree is formatted using an explicit stack of tasks rather than recursion, so that very
+    /// deep parse trees (e.g. from left-recursive productions over long inputs) cannot overflow the
+    /// call stack. Formatted documents are written in order to the top of a stack of output
+    /// buffers, where new buffers are started to evaluate declaration values, groups, and line
+    /// breaks.
+    fn format_tree(&self) -> Vec<Doc> {
+        let mut tasks: Vec<Task<'parse, Symbol>> =
+            vec![Task::Node(self.parse, Rc::new(HashMap::new()))];
+        let mut buffers: Vec<Vec<Doc>> = vec![Vec::new()];
+
+        while let Some(task) = tasks.pop() {
+            // Tasks are pushed in execution order, then reversed in place once the task is done.
+            let pushed = tasks.len();
+
+            match task {
+                Task::Node(node, scope) => {
+                    if self.is_disjoint(node) {
+                        // The node is disjoint from all targets, so its formatted string is never
+                        // used.
+                        continue;
+                    }
+
+                    self.push_node(node, &scope, &mut tasks);
+
+                    if let Some(i) = self.targets.iter().position(|t| ptr::eq(*t, node)) {
+                        // Start a new text document, so that the target's documents can be
+                        // separated from those preceding it.
+                        let buffer = buffers.last_mut().unwrap();
+                        buffer.push(Doc::Text(String::new()));
+                        tasks.push(Task::Target(i, buffer.len() - 1));
+                    }
+                }
+                Task::Production(node, scope) => self.push_production(node, &scope, &mut tasks),
+                Task::Text(text) => layout::push_text(buffers.last_mut().unwrap(), &text),
+                Task::Value(value) => layout::push_docs(buffers.last_mut().unwrap(), &value),
+                Task::Capture(capture) => {
+                    let values = capture
+                        .capture
+                        .declarations
+                        .iter()
+                        .filter(|decl| decl.value.is_some())
+                        .count();
+                    let values_start = buffers.len() - values;
+                    let mut values = buffers.split_off(values_start).into_iter();
+
+                    let inner_scope = if capture.capture.declarations.is_empty() {
+                        capture.scope.clone()
+                    } else {
+                        let mut inner_scope = (*capture.scope).clone();
+                        for decl in &capture.capture.declarations {
+                            match decl.value {
+                                Some(_) => {
+                                    let value = Rc::new(values.next().unwrap());
+                                    inner_scope.insert(decl.key.clone(), value);
+                                }
+                                None => {
+                                    inner_scope.remove(&decl.key);
+                                }
+                            }
+                        }
+                        Rc::new(inner_scope)
+                    };
+
+                    for injection in capture
+                        .injections
+                        .iter()
+                        .filter(|injection| injection.direction == InjectionAffinity::Right)
+                    {
+                        self.push_injection(injection, &capture.scope, &mut tasks);
+                    }
+
+                    tasks.push(Task::Node(capture.child, inner_scope));
+
+                    for injection in capture
+                        .injections
+                        .iter()
+                        .filter(|injection| injection.direction == InjectionAffinity::Left)
+                    {
+                        self.push_injection(injection, &capture.scope, &mut tasks);
+                    }
+                }
+                Task::Boundary(boundary) => {
+                    buffers.last_mut().unwrap().push(Doc::Boundary(boundary))
+                }
+                Task::Buffer => buffers.push(Vec::new()),
+                Task::Group => {
+                    let inner = buffers.pop().unwrap();
+                    let group = Doc::Group(Rc::new(inner));
+                    buffers.last_mut().unwrap().push(group);
+                }
+                Task::Line => {
+                    let broken = buffers.pop().unwrap();
+                    let flat = buffers.pop().unwrap();
+                    let line = Doc::Line(Rc::new(flat), Rc::new(broken));
+                    buffers.last_mut().unwrap().push(line);
+                }
+                Task::Target(i, start) => {
+                    let mut target_docs = self.target_docs.borrow_mut();
+                    if target_docs[i].is_none() {
+                        target_docs[i] = Some(buffers.last().unwrap()[start..].to_vec());
+                    }
+                }
+            }
+
+            tasks[pushed..].reverse();
+        }
+
+        buffers.pop().unwrap()
+    }
+
+    /// Returns true if range targets are being formatted, and `node` is disjoint from all of them.
+    fn is_disjoint(&self, node: &Tree<Symbol>) -> bool {
+        if self.targets.is_empty() {
+            return false;
+        }
+
+        let first = self.targets[0].span().start.offset;
+        let last = self.targets[self.targets.len() - 1].span().end.offset;
+        let span = node.span();
+
+        !span.is_empty() && (span.end.offset <= first || span.start.offset >= last)
     }
 
-    /// Returns the formatted string of the passed parse tree node.
+    /// Pushes the tasks to format the passed parse tree node, in order, onto `out`.
     ///
     /// # Parameters
     ///
     /// * `node` - the current parse tree node.
-    /// * `scope` - a hashmap storing the values of variables in the scope of the current node,
-    /// indexed by the variable names.
-    #[inline(always)]
-    fn recur(&self, node: &Tree<Symbol>, scope: &HashMap<String, String>) -> String {
-        if node.is_leaf() {
-            if node.is_null() {
-                return String::new();
+    /// * `scope` - the variable scope of the current node.
+    /// * `out` - the tasks accumulated so far, in the order they will be run.
+    fn push_node(
+        &self,
+        node: &'parse Tree<Symbol>,
+        scope: &Scope,
+        out: &mut Vec<Task<'parse, Symbol>>,
+    ) {
+        if node.spm == SymbolParseMethod::Recovered {
+            // Unparsable regions cannot be formatted, so reproduce them exactly.
+            let span = node.span();
+            let text = &self.input[span.start.offset..span.end.offset];
+            self.push_token(node, Task::Text(Cow::Borrowed(text)), out);
+        } else if node.is_leaf() {
+            if !node.is_null() {
+                self.push_token(node, Task::Text(Cow::Borrowed(node.lhs.lexeme())), out);
+            }
+        } else if node.spm == SymbolParseMethod::Repeated {
+            self.push_list(node, scope, out);
+        } else {
+            self.push_production(node, scope, out);
+        }
+    }
+
+    /// Pushes the tasks to format the passed inline list node, or single inline list element, in
+    /// order, onto `out`.
+    ///
+    /// Each element of the list is formatted with the `first` and `last` variables set if it is the
+    /// first or last element, respectively. If the `separator` variable is set, its value replaces
+    /// each separator of a separated list, or is inserted between the elements of an unseparated
+    /// list. The `separator` variable is not visible to the list elements.
+    ///
+    /// # Parameters
+    ///
+    /// * `node` - the current parse tree node, either a list node or a single element.
+    /// * `scope` - the variable scope of the current node.
+    /// * `out` - the tasks accumulated so far, in the order they will be run.
+    fn push_list(
+        &self,
+        node: &'parse Tree<Symbol>,
+        scope: &Scope,
+        out: &mut Vec<Task<'parse, Symbol>>,
+    ) {
+        let items: Vec<&'parse Tree<Symbol>> = if node.is_null() {
+            node.children.iter().collect()
+        } else {
+            vec![node]
+        };
+
+        let is_element = |item: &Tree<Symbol>, kind: &Option<&Symbol>| {
+            item.spm != SymbolParseMethod::Injected && Some(item.lhs.kind()) == *kind
+        };
+
+        let kind = items
+            .iter()
+            .find(|item| item.spm != SymbolParseMethod::Injected)
+            .map(|item| item.lhs.kind());
+        let elements = items.iter().filter(|item| is_element(item, &kind)).count();
+        let separated = items
+            .iter()
+            .any(|item| item.spm != SymbolParseMethod::Injected && !is_element(item, &kind));
+
+        let separator_opt = scope.get(LIST_SEPARATOR);
+        let mut inner_scope = (**scope).clone();
+        inner_scope.remove(LIST_SEPARATOR);
+        let inner_scope = Rc::new(inner_scope);
+
+        let mut index = 0;
+
+        for item in items {
+            if item.spm == SymbolParseMethod::Injected {
+                let injectable = &self.injection_map[item.lhs.kind()];
+                let injection = Injection {
+                    tree: item,
+                    pattern: &injectable.pattern,
+                    direction: injectable.affinity.clone(),
+                };
+
+                self.push_injection(&injection, scope, out);
+            } else if !is_element(item, &kind) {
+                match separator_opt {
+                    Some(separator) => self.push_token(item, Task::Value(separator.clone()), out),
+                    None => out.push(Task::Node(item, inner_scope.clone())),
+                }
+            } else {
+                if index > 0 && !separated {
+                    if let Some(separator) = separator_opt {
+                        out.push(Task::Value(separator.clone()));
+                    }
+                }
+
+                let mut element_scope = (*inner_scope).clone();
+                for (name, set) in &[(LIST_FIRST, index == 0), (LIST_LAST, index + 1 == elements)] {
+                    if *set {
+                        let value = Rc::new(vec![Doc::Text("true".to_string())]);
+                        element_scope.insert(name.to_string(), value);
+                    } else {
+                        element_scope.remove(*name);
+                    }
+                }
+                let element_scope = Rc::new(element_scope);
+
+                if ptr::eq(item, node) {
+                    out.push(Task::Production(node, element_scope));
+                } else {
+                    out.push(Task::Node(item, element_scope));
+                }
+
+                index += 1;
             }
-            return node.lhs.lexeme().clone();
         }
+    }
 
+    /// Pushes the tasks to format the passed interior parse tree node, in order, onto `out`, using
+    /// the pattern of its production if there is one, or by concatenating its children otherwise.
+    ///
+    /// Injections are filtered and directed at this stage of formatting. Injections are paired
+    /// with their neighbours based on their preferred affinity, or their non-preferred neighbour
+    /// if their preferred neighbour is not captured by the pattern (or doesn't exist).
+    ///
+    /// # Parameters
+    ///
+    /// * `node` - the current parse tree node.
+    /// * `scope` - the variable scope of the current node.
+    /// * `out` - the tasks accumulated so far, in the order they will be run.
+    fn push_production(
+        &self,
+        node: &'parse Tree<Symbol>,
+        scope: &Scope,
+        out: &mut Vec<Task<'parse, Symbol>>,
+    ) {
+        let pattern_map: &'parse HashMap<Production<Symbol>, Pattern> = self.pattern_map;
         let pattern = match node.production {
-            Some(ref production) => self.pattern_map.get(production),
+            Some(ref production) => pattern_map.get(production),
             None => None,
         };
 
-        match pattern {
-            Some(ref p) => self.fill_pattern_outer(p, &node.children, scope),
+        let pattern = match pattern {
+            Some(pattern) => pattern,
             None => {
                 // Reconstruct one after the other
-                let mut res = String::new();
                 for child in &node.children {
-                    res = if child.spm == SymbolParseMethod::Injected {
+                    if child.spm == SymbolParseMethod::Injected {
                         let injectable = &self.injection_map[child.lhs.kind()];
                         let injection = Injection {
                             tree: child,
@@ -259,53 +756,45 @@ impl<'parse, Symbol: GrammarSymbol + 'parse> FormatJob<'parse, Symbol> {
                             direction: injectable.affinity.clone(),
                         };
 
-                        format!("{}{}", res, self.injection_string(&injection, scope))
+                        self.push_injection(&injection, scope, out);
                     } else {
-                        format!("{}{}", res, self.recur(child, scope))
-                    };
+                        out.push(Task::Node(child, scope.clone()));
+                    }
                 }
-                res
+                return;
             }
-        }
-    }
+        };
 
-    /// Returns the formatted string for a pattern given a set of child nodes.
-    ///
-    /// Injections are filtered and directed at this stage of formatting. Injections are paired
-    /// with their neighbours based on their preferred affinity, or their non-preferred neighbour
-    /// if their preferred neighbour is not captured by the pattern (or doesn't exist).
-    ///
-    /// # Parameters
-    ///
-    /// * `pattern` - the pattern to be used when formatting the children.
-    /// * `children` - a slice of parse tree nodes storing the children of the current (pattern)
-    /// node.
-    /// * `scope` - a hashmap storing the values of variables in the scope of the current node,
-    /// indexed by the variable names.
-    #[inline(always)]
-    fn fill_pattern_outer(
-        &self,
-        pattern: &Pattern,
-        children: &[Tree<Symbol>],
-        scope: &HashMap<String, String>,
-    ) -> String {
+        let expected_children: Vec<&'parse Tree<Symbol>> = node
+            .children
+            .iter()
+            .filter(|child| child.spm != SymbolParseMethod::Injected)
+            .collect();
+
+        // Only the branches of conditionals which will be formatted can capture children.
         let mut captured_children: CEHashMap<()> = CEHashMap::new();
-        for seg in &pattern.segments {
-            if let Segment::Capture(ref c) = seg {
-                captured_children.insert(c.child_index, ());
+        let mut segments: Vec<&Segment> = pattern.segments.iter().collect();
+        while let Some(seg) = segments.pop() {
+            match seg {
+                Segment::Capture(ref c) => captured_children.insert(c.child_index, ()),
+                Segment::Group(ref inner) => segments.extend(inner.iter()),
+                Segment::Conditional(ref conditional) => {
+                    segments.extend(self.branch(conditional, &expected_children, scope).iter())
+                }
+                _ => {}
             }
         }
 
         let mut injections_by_node: CEHashMap<Vec<Injection<Symbol>>> = CEHashMap::new();
-        let mut expected_children: Vec<&Tree<Symbol>> = Vec::new();
+        let mut expected_count: usize = 0;
 
-        for child in children {
+        for child in &node.children {
             if child.spm == SymbolParseMethod::Injected {
                 let injectable = &self.injection_map[child.lhs.kind()];
 
                 let mut target: i64 = match &injectable.affinity {
-                    InjectionAffinity::Left => expected_children.len() as i64 - 1,
-                    InjectionAffinity::Right => expected_children.len() as i64,
+                    InjectionAffinity::Left => expected_count as i64 - 1,
+                    InjectionAffinity::Right => expected_count as i64,
                 };
 
                 let direction = if target < 0 || !captured_children.contains(target as usize) {
@@ -338,189 +827,228 @@ impl<'parse, Symbol: GrammarSymbol + 'parse> FormatJob<'parse, Symbol> {
                     injections_by_node.get_mut(target).unwrap().push(injection);
                 }
             } else {
-                expected_children.push(child)
+                expected_count += 1;
             }
         }
 
-        self.fill_pattern_inner(
-            pattern,
+        self.push_segments(
+            &pattern.segments,
             &expected_children[..],
             scope,
-            Some(injections_by_node),
-        )
+            &mut Some(injections_by_node),
+            out,
+        );
     }
 
-    /// Returns the formatted string for a pattern given a set of child nodes.
+    /// Pushes the tasks to fill a sequence of pattern segments given a set of child nodes, in
+    /// order, onto `out`.
     ///
-    /// Injections can optionally be provided, in which case they will be consumed and passed to
-    /// capture evaluation to be injected.
+    /// Injections can optionally be provided, in which case they will be consumed by the captures
+    /// of their target child nodes. Groups and line breaks are emitted as layout documents, which
+    /// are laid out once the whole formatted document is known.
     ///
     /// # Parameters
     ///
-    /// * `pattern` - the pattern to be used when formatting the children.
+    /// * `segments` - the pattern segments to be filled.
     /// * `children` - a slice of parse tree nodes storing the children of the current (pattern)
-    /// node.
-    /// * `scope` - a hashmap storing the values of variables in the scope of the current node,
-    /// indexed by the variable names.
+    ///   node.
+    /// * `scope` - the variable scope of the current node.
     /// * `injections_by_node_opt` - an optional map from child index (in `children`) to a vector
-    /// of injections that should be performed for that child node.
-    #[inline(always)]
-    fn fill_pattern_inner(
+    ///   of injections that should be performed for that child node.
+    /// * `out` - the tasks accumulated so far, in the order they will be run.
+    fn push_segments(
         &self,
-        pattern: &Pattern,
-        children: &[&Tree<Symbol>],
-        scope: &HashMap<String, String>,
-        mut injections_by_node_opt: Option<CEHashMap<Vec<Injection<Symbol>>>>,
-    ) -> String {
-        let mut res: String = String::new();
-        for seg in &pattern.segments {
+        segments: &'parse [Segment],
+        children: &[&'parse Tree<Symbol>],
+        scope: &Scope,
+        injections_by_node_opt: &mut Option<CEHashMap<Vec<Injection<'parse, Symbol>>>>,
+        out: &mut Vec<Task<'parse, Symbol>>,
+    ) {
+        for seg in segments {
             match *seg {
-                Segment::Filler(ref s) => res = format!("{}{}", res, s),
+                Segment::Filler(ref s) => out.push(Task::Text(Cow::Borrowed(s))),
                 Segment::Substitution(ref s) => {
                     if let Some(value) = scope.get(s) {
-                        res = format!("{}{}", res, value);
+                        out.push(Task::Value(value.clone()));
                     }
                 }
                 Segment::Capture(ref c) => {
-                    let injections_opt = match &mut injections_by_node_opt {
+                    let injections = match injections_by_node_opt {
                         Some(injections_by_node) => injections_by_node.remove(c.child_index),
                         None => None,
                     };
 
-                    res = format!(
-                        "{}{}",
-                        res,
-                        self.evaluate_capture(c, children, scope, &injections_opt)
-                    );
+                    // Declaration values are evaluated in the outer scope, each into its own
+                    // buffer, before the captured child is formatted.
+                    for decl in &c.declarations {
+                        if let Some(ref pattern) = decl.value {
+                            out.push(Task::Buffer);
+                            self.push_segments(&pattern.segments, children, scope, &mut None, out);
+                        }
+                    }
+
+                    out.push(Task::Capture(CaptureTask {
+                        capture: c,
+                        child: children[c.child_index],
+                        scope: scope.clone(),
+                        injections: injections.unwrap_or_default(),
+                    }));
+                }
+                Segment::Group(ref segments) => {
+                    out.push(Task::Buffer);
+                    self.push_segments(segments, children, scope, injections_by_node_opt, out);
+                    out.push(Task::Group);
+                }
+                Segment::Line(ref line) => {
+                    out.push(Task::Buffer);
+                    self.push_segments(&line.flat, children, scope, &mut None, out);
+                    out.push(Task::Buffer);
+                    self.push_segments(&line.broken, children, scope, &mut None, out);
+                    out.push(Task::Line);
+                }
+                Segment::Conditional(ref conditional) => {
+                    let segments = self.branch(conditional, children, scope);
+                    self.push_segments(segments, children, scope, injections_by_node_opt, out);
                 }
             };
         }
-
-        res
     }
 
-    /// Returns the formatted string for a pattern capture given a set of child nodes.
-    ///
-    /// This method determines the inner scope of the capture, and then calls
-    /// `evaluate_capture_internal` to perform the actual evaluation.
+    /// Returns the segments of the branch of `conditional` to be formatted, depending on whether
+    /// its condition holds for the passed child nodes and scope.
     ///
     /// # Parameters
     ///
-    /// * `capture` - the pattern capture to be "filled".
+    /// * `conditional` - the conditional segment being formatted.
     /// * `children` - a slice of parse tree nodes storing the children of the current (pattern)
-    /// node.
-    /// * `outer_scope` - a hashmap storing the values of variables in the scope of the current
-    /// node, indexed by the variable names.
-    /// * `injections_opt` - An optional vector of injections to perform during this capture
-    /// evaluation.
-    #[inline(always)]
-    fn evaluate_capture(
+    ///   node.
+    /// * `scope` - a hashmap storing the values of variables in the scope of the current node,
+    ///   indexed by the variable names.
+    fn branch<'segment>(
         &self,
-        capture: &Capture,
+        conditional: &'segment Conditional,
         children: &[&Tree<Symbol>],
-        outer_scope: &HashMap<String, String>,
-        injections_opt: &Option<Vec<Injection<Symbol>>>,
-    ) -> String {
-        if !capture.declarations.is_empty() {
-            let mut inner_scope = outer_scope.clone();
-            for decl in &capture.declarations {
-                match decl.value {
-                    Some(ref pattern) => {
-                        inner_scope.insert(
-                            decl.key.clone(),
-                            self.fill_pattern_inner(pattern, children, outer_scope, None),
-                        );
-                    }
-                    None => {
-                        inner_scope.remove(&decl.key);
-                    }
-                }
-            }
+        scope: &HashMap<String, Value>,
+    ) -> &'segment [Segment] {
+        let holds = match conditional.condition {
+            Condition::Set(ref name) => scope.contains_key(name),
+            Condition::Equals(ref name, ref value) => scope
+                .get(name)
+                .is_some_and(|docs| layout::is_text(docs, value)),
+            Condition::Captured(child_index) => !children[child_index].span().is_empty(),
+        };
 
-            self.evaluate_capture_internal(
-                children[capture.child_index],
-                outer_scope,
-                &inner_scope,
-                injections_opt,
-            )
+        if holds {
+            &conditional.then
         } else {
-            self.evaluate_capture_internal(
-                children[capture.child_index],
-                outer_scope,
-                outer_scope,
-                injections_opt,
-            )
+            &conditional.otherwise
         }
     }
 
-    /// Returns the formatted string for a pattern capture given the associated child node.
-    ///
-    /// This method calls `recur` with the inner scope of the capture to build the formatted string
-    /// of the child node, then prefixes and postfixes the string with the injection strings of all
-    /// passed injections.
+    /// Pushes the tasks to format an injection, in order, onto `out`.
     ///
     /// # Parameters
     ///
-    /// * `child` - the child node to being captured.
-    /// * `outer_scope` - a hashmap storing the values of variables in the scope of the parent
-    /// node, indexed by the variable names.
-    /// * `inner_scope` - a hashmap storing the values of variables in the scope of the child
-    /// node, indexed by the variable names.
-    /// * `injections_opt` - An optional vector of injections to perform during this capture
-    /// evaluation.
-    #[inline(always)]
-    fn evaluate_capture_internal(
+    /// * `injection` - the injection to be formatted.
+    /// * `scope` - the variable scope in which to format the injection.
+    /// * `out` - the tasks accumulated so far, in the order they will be run.
+    fn push_injection(
         &self,
-        child: &Tree<Symbol>,
-        outer_scope: &HashMap<String, String>,
-        inner_scope: &HashMap<String, String>,
-        injections_opt: &Option<Vec<Injection<Symbol>>>,
-    ) -> String {
-        let child_string = self.recur(child, &inner_scope);
-        let mut prefix = String::new();
-        let mut postfix = String::new();
-
-        if let Some(injections) = injections_opt {
-            injections
-                .iter()
-                .filter(|injection| injection.direction == InjectionAffinity::Left)
-                .for_each(|injection| {
-                    let injection_string = self.injection_string(injection, outer_scope);
-                    postfix = format!("{}{}", postfix, injection_string);
-                });
-
-            injections
-                .iter()
-                .filter(|injection| injection.direction == InjectionAffinity::Right)
-                .rev()
-                .for_each(|injection| {
-                    let injection_string = self.injection_string(injection, outer_scope);
-                    prefix = format!("{}{}", injection_string, prefix);
-                });
+        injection: &Injection<'parse, Symbol>,
+        scope: &Scope,
+        out: &mut Vec<Task<'parse, Symbol>>,
+    ) {
+        match injection.pattern {
+            Some(ref pattern) => {
+                self.push_segments(&pattern.segments, &[injection.tree], scope, &mut None, out)
+            }
+            None => {
+                let lexeme = injection.tree.lhs.lexeme();
+                self.push_token(injection.tree, Task::Text(Cow::Borrowed(lexeme)), out)
+            }
         }
-
-        format!("{}{}{}", prefix, child_string, postfix)
     }
 
-    /// Returns the formatted string for an injection.
-    ///
-    /// # Parameters
-    ///
-    /// * `injection` - the injection to be formatted.
-    /// * `scope` - the variable scope in which to format the injection, indexed by variable names.
-    #[inline(always)]
-    fn injection_string(
+    /// Pushes the task `task`, which formats the token (or recovered region) `node`, onto `out`,
+    /// surrounded by the boundaries of any disabled region which `node` starts or ends.
+    fn push_token(
         &self,
-        injection: &Injection<Symbol>,
-        scope: &HashMap<String, String>,
-    ) -> String {
-        match injection.pattern {
-            Some(ref pattern) => self.fill_pattern_inner(pattern, &[injection.tree], scope, None),
-            None => injection.tree.lhs.lexeme().clone(),
-        }
+        node: &'parse Tree<Symbol>,
+        task: Task<'parse, Symbol>,
+        out: &mut Vec<Task<'parse, Symbol>>,
+    ) {
+        let (start, end) = directive::boundaries(node, &self.disabled);
+        out.extend(start.map(Task::Boundary));
+        out.push(task);
+        out.extend(end.map(Task::Boundary));
     }
 }
 
+/// Value: The formatted documents of a variable's value.
+type Value = Rc<Vec<Doc>>;
+
+/// Scope: The values of the variables in scope at a parse tree node, indexed by variable name.
+/// Scopes are shared between the tasks of a `FormatJob` until they are modified.
+type Scope = Rc<HashMap<String, Value>>;
+
+/// Task: A unit of work on the explicit work stack of a `FormatJob`.
+///
+/// Formatting a parse tree amounts to writing a sequence of layout documents, in order, to an
+/// output buffer. Each task either writes to the output buffers directly, or expands into further tasks,
+/// so that the depth of the parse tree never affects the depth of the call stack.
+///
+/// # Type Parameters
+///
+/// * `Symbol` - the symbol type of the grammar used to construct the parse tree.
+///
+/// # Types
+///
+/// * `Node` - formats a parse tree node in a scope.
+/// * `Production` - formats an interior parse tree node in a scope via its production, even if the
+///   node is an inline list element.
+/// * `Text` - writes a string to the current buffer.
+/// * `Value` - writes the documents of a variable's value to the current buffer.
+/// * `Boundary` - writes a boundary of a disabled region to the current buffer.
+/// * `Capture` - formats a captured child node along with its injections, once the values of the
+///   capture's declarations have been written to buffers of their own.
+/// * `Buffer` - starts a new buffer.
+/// * `Group` - ends the current buffer, writing it to the previous buffer as a layout group.
+/// * `Line` - ends the current two buffers, writing them to the previous buffer as the flat and
+///   broken text of a line break.
+/// * `Target` - records the formatted documents of a range target (by index), which were written to
+///   the current buffer since the given index.
+enum Task<'parse, Symbol: GrammarSymbol + 'parse> {
+    Node(&'parse Tree<Symbol>, Scope),
+    Production(&'parse Tree<Symbol>, Scope),
+    Text(Cow<'parse, str>),
+    Value(Value),
+    Boundary(directive::Boundary),
+    Capture(CaptureTask<'parse, Symbol>),
+    Buffer,
+    Group,
+    Line,
+    Target(usize, usize),
+}
+
+/// Capture Task: The data needed to format a captured child node.
+///
+/// # Type Parameters
+///
+/// * `Symbol` - the symbol type of the grammar used to construct the parse tree.
+///
+/// # Fields
+///
+/// * `capture` - the pattern capture being formatted.
+/// * `child` - the captured child node.
+/// * `scope` - the variable scope of the parent node.
+/// * `injections` - the injections to perform around the captured child node.
+struct CaptureTask<'parse, Symbol: GrammarSymbol + 'parse> {
+    capture: &'parse Capture,
+    child: &'parse Tree<Symbol>,
+    scope: Scope,
+    injections: Vec<Injection<'parse, Symbol>>,
+}
+
 /// Pattern Pair: A pair representing an un-parsed pattern.
 ///
 /// # Type Parameters
@@ -562,6 +1090,26 @@ impl InjectionAffinity {
     }
 }
 
+impl Serial for InjectionAffinity {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u8(match self {
+            Self::Left => 0,
+            Self::Right => 1,
+        });
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        match reader.read_u8()? {
+            0 => Ok(Self::Left),
+            1 => Ok(Self::Right),
+            byte => Err(serial::Error::InvalidErr(format!(
+                "injection affinity {}",
+                byte
+            ))),
+        }
+    }
+}
+
 /// Injectable: Internal representation of an injection specification.
 ///
 /// Note: the symbol itself is not stored in an `Injectable`, since it will already be stored as a
@@ -576,6 +1124,20 @@ struct Injectable {
     affinity: InjectionAffinity,
 }
 
+impl Serial for Injectable {
+    fn write(&self, writer: &mut Writer) {
+        self.pattern.write(writer);
+        self.affinity.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            pattern: Option::read(reader)?,
+            affinity: InjectionAffinity::read(reader)?,
+        })
+    }
+}
+
 /// Injectable String: External representation of an injection specification.
 ///
 /// # Type Parameters
@@ -594,3 +1156,76 @@ pub struct InjectableString<Symbol: GrammarSymbol> {
     pub pattern_string: Option<String>,
     pub affinity: InjectionAffinity,
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        core::{lex::Token, parse::ProductionSymbol},
+        std::thread,
+    };
+
+    #[test]
+    fn format_deep_tree() {
+        //setup
+        let depth = 100_000;
+
+        let base = Production::from(
+            "s".to_string(),
+            vec![ProductionSymbol::symbol("A".to_string())],
+        );
+        let rec = Production::from(
+            "s".to_string(),
+            vec![
+                ProductionSymbol::symbol("s".to_string()),
+                ProductionSymbol::symbol("A".to_string()),
+            ],
+        );
+
+        let mut builder: FormatterBuilder<String> = FormatterBuilder::new();
+        builder
+            .add_pattern(PatternPair {
+                production: rec.clone(),
+                string_production: rec.clone(),
+                pattern: "{}{},".to_string(),
+            })
+            .unwrap();
+        let formatter = builder.build();
+
+        let leaf = || Tree {
+            lhs: Token::leaf("A".to_string(), "a".to_string()),
+            children: Vec::new(),
+            production: None,
+            spm: SymbolParseMethod::Standard,
+        };
+
+        //exercise
+        let res = thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(move || {
+                let mut tree = Tree {
+                    lhs: Token::interior("s".to_string()),
+                    children: vec![leaf()],
+                    production: Some(base),
+                    spm: SymbolParseMethod::Standard,
+                };
+
+                for _ in 1..depth {
+                    tree = Tree {
+                        lhs: Token::interior("s".to_string()),
+                        children: vec![tree, leaf()],
+                        production: Some(rec.clone()),
+                        spm: SymbolParseMethod::Standard,
+                    };
+                }
+
+                formatter.format(&tree, "", &[], None)
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        //verify
+        assert_eq!(res, format!("a{}", "a,".repeat(depth - 1)));
+    }
+}
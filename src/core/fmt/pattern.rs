@@ -11,7 +11,10 @@ use {
             grammar::{self, GrammarBuilder, GrammarSymbol, SimpleGrammar, SimpleGrammarBuilder},
             Production, Tree,
         },
-        util::string_utils,
+        util::{
+            serial::{self, Reader, Serial, Writer},
+            string_utils,
+        },
     },
     std::{error, fmt},
 };
@@ -21,6 +24,11 @@ use {
 enum S {
     Start,
     LeftBrace,
+    GroupOpen,
+    LineOpen,
+    ConditionalOpen,
+    Colon,
+    Or,
     RightBrace,
     LeftBracket,
     RightBracket,
@@ -59,6 +67,8 @@ fn build_pattern_ecdfa() -> Result<EncodedCDFA<PatternSymbol>, lex::CDFAError> {
         .mark_trans(Transit::to(S::RightBracket), ']')?
         .mark_trans(Transit::to(S::Semi), ';')?
         .mark_trans(Transit::to(S::Equals), '=')?
+        .mark_trans(Transit::to(S::Colon), ':')?
+        .mark_trans(Transit::to(S::Or), '|')?
         .mark_trans(Transit::to(S::Escape), '\\')?
         .mark_trans(Transit::to(S::Zero), '0')?
         .mark_range(Transit::to(S::Number), '1', '9')?
@@ -66,6 +76,12 @@ fn build_pattern_ecdfa() -> Result<EncodedCDFA<PatternSymbol>, lex::CDFAError> {
         .mark_range(Transit::to(S::Alpha), 'A', 'Z')?
         .default_to(Transit::to(S::Filler))?;
 
+    builder
+        .state(&S::LeftBrace)
+        .mark_trans(Transit::to(S::GroupOpen), '^')?
+        .mark_trans(Transit::to(S::LineOpen), '/')?
+        .mark_trans(Transit::to(S::ConditionalOpen), '?')?;
+
     builder
         .state(&S::Filler)
         .mark_trans(Transit::to(S::Escape), '\\')?
@@ -74,6 +90,8 @@ fn build_pattern_ecdfa() -> Result<EncodedCDFA<PatternSymbol>, lex::CDFAError> {
         .mark_trans(Transit::to(S::Fail), '[')?
         .mark_trans(Transit::to(S::Fail), ';')?
         .mark_trans(Transit::to(S::Fail), '=')?
+        .mark_trans(Transit::to(S::Fail), ':')?
+        .mark_trans(Transit::to(S::Fail), '|')?
         .default_to(Transit::to(S::Filler))?
         .accept()
         .tokenize(&PatternSymbol::TFiller);
@@ -97,6 +115,11 @@ fn build_pattern_ecdfa() -> Result<EncodedCDFA<PatternSymbol>, lex::CDFAError> {
         .accept(&S::Semi)
         .accept(&S::Equals)
         .accept(&S::LeftBrace)
+        .accept(&S::GroupOpen)
+        .accept(&S::LineOpen)
+        .accept(&S::ConditionalOpen)
+        .accept(&S::Colon)
+        .accept(&S::Or)
         .accept(&S::RightBrace)
         .accept(&S::LeftBracket)
         .accept(&S::RightBracket)
@@ -106,6 +129,11 @@ fn build_pattern_ecdfa() -> Result<EncodedCDFA<PatternSymbol>, lex::CDFAError> {
         .tokenize(&S::Semi, &PatternSymbol::TSemi)
         .tokenize(&S::Equals, &PatternSymbol::TEquals)
         .tokenize(&S::LeftBrace, &PatternSymbol::TLeftBrace)
+        .tokenize(&S::GroupOpen, &PatternSymbol::TGroupOpen)
+        .tokenize(&S::LineOpen, &PatternSymbol::TLineOpen)
+        .tokenize(&S::ConditionalOpen, &PatternSymbol::TConditionalOpen)
+        .tokenize(&S::Colon, &PatternSymbol::TColon)
+        .tokenize(&S::Or, &PatternSymbol::TOr)
         .tokenize(&S::RightBrace, &PatternSymbol::TRightBrace)
         .tokenize(&S::LeftBracket, &PatternSymbol::TLeftBracket)
         .tokenize(&S::RightBracket, &PatternSymbol::TRightBracket)
@@ -116,14 +144,27 @@ fn build_pattern_ecdfa() -> Result<EncodedCDFA<PatternSymbol>, lex::CDFAError> {
 
 /// Pattern Symbol: An enum whose elements are the symbols in the grammar of a pattern.
 /// Symbols prefixed with `T` represent terminal symbols in the grammar.
-#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Default)]
 pub enum PatternSymbol {
+    #[default]
     Pattern,
     Segments,
     Segment,
     Filler,
     Substitution,
     Capture,
+    Group,
+    Line,
+    LineSegments,
+    LineSegment,
+    Conditional,
+    Condition,
+    ConditionValue,
+    ValueFiller,
+    Alternative,
+    BranchSegments,
+    BranchSegment,
+    BranchFiller,
     CaptureDescriptor,
     CaptureIndex,
     Declarations,
@@ -136,16 +177,15 @@ pub enum PatternSymbol {
     TRightBracket,
     TLeftBrace,
     TRightBrace,
+    TGroupOpen,
+    TLineOpen,
+    TConditionalOpen,
+    TColon,
+    TOr,
     TSemi,
     TEquals,
 }
 
-impl Default for PatternSymbol {
-    fn default() -> Self {
-        Self::Pattern
-    }
-}
-
 impl Data for PatternSymbol {
     fn to_string(&self) -> String {
         format!("{:?}", self)
@@ -178,13 +218,18 @@ fn build_pattern_grammar() -> Result<SimpleGrammar<PatternSymbol>, grammar::Buil
         .from(PatternSymbol::Segment)
         .to(vec![PatternSymbol::Filler])
         .to(vec![PatternSymbol::Substitution])
-        .to(vec![PatternSymbol::Capture]);
+        .to(vec![PatternSymbol::Capture])
+        .to(vec![PatternSymbol::Group])
+        .to(vec![PatternSymbol::Line])
+        .to(vec![PatternSymbol::Conditional]);
 
     builder
         .from(PatternSymbol::Filler)
         .to(vec![PatternSymbol::TFiller])
         .to(vec![PatternSymbol::TAlpha])
-        .to(vec![PatternSymbol::TNumber]);
+        .to(vec![PatternSymbol::TNumber])
+        .to(vec![PatternSymbol::TColon])
+        .to(vec![PatternSymbol::TOr]);
 
     builder.from(PatternSymbol::Substitution).to(vec![
         PatternSymbol::TLeftBracket,
@@ -192,6 +237,95 @@ fn build_pattern_grammar() -> Result<SimpleGrammar<PatternSymbol>, grammar::Buil
         PatternSymbol::TRightBracket,
     ]);
 
+    builder.from(PatternSymbol::Group).to(vec![
+        PatternSymbol::TGroupOpen,
+        PatternSymbol::Segments,
+        PatternSymbol::TRightBrace,
+    ]);
+
+    builder.from(PatternSymbol::Line).to(vec![
+        PatternSymbol::TLineOpen,
+        PatternSymbol::LineSegments,
+        PatternSymbol::TSemi,
+        PatternSymbol::LineSegments,
+        PatternSymbol::TRightBrace,
+    ]);
+
+    builder
+        .from(PatternSymbol::LineSegments)
+        .to(vec![
+            PatternSymbol::LineSegment,
+            PatternSymbol::LineSegments,
+        ])
+        .epsilon();
+
+    builder
+        .from(PatternSymbol::LineSegment)
+        .to(vec![PatternSymbol::Filler])
+        .to(vec![PatternSymbol::Substitution]);
+
+    builder.from(PatternSymbol::Conditional).to(vec![
+        PatternSymbol::TConditionalOpen,
+        PatternSymbol::Condition,
+        PatternSymbol::TColon,
+        PatternSymbol::BranchSegments,
+        PatternSymbol::Alternative,
+        PatternSymbol::TRightBrace,
+    ]);
+
+    builder
+        .from(PatternSymbol::Condition)
+        .to(vec![PatternSymbol::TAlpha])
+        .to(vec![
+            PatternSymbol::TAlpha,
+            PatternSymbol::TEquals,
+            PatternSymbol::ConditionValue,
+        ])
+        .to(vec![PatternSymbol::TNumber]);
+
+    builder
+        .from(PatternSymbol::ConditionValue)
+        .to(vec![
+            PatternSymbol::ValueFiller,
+            PatternSymbol::ConditionValue,
+        ])
+        .epsilon();
+
+    builder
+        .from(PatternSymbol::ValueFiller)
+        .to(vec![PatternSymbol::TFiller])
+        .to(vec![PatternSymbol::TAlpha])
+        .to(vec![PatternSymbol::TNumber]);
+
+    builder
+        .from(PatternSymbol::Alternative)
+        .to(vec![PatternSymbol::TOr, PatternSymbol::BranchSegments])
+        .epsilon();
+
+    builder
+        .from(PatternSymbol::BranchSegments)
+        .to(vec![
+            PatternSymbol::BranchSegment,
+            PatternSymbol::BranchSegments,
+        ])
+        .epsilon();
+
+    builder
+        .from(PatternSymbol::BranchSegment)
+        .to(vec![PatternSymbol::BranchFiller])
+        .to(vec![PatternSymbol::Substitution])
+        .to(vec![PatternSymbol::Capture])
+        .to(vec![PatternSymbol::Group])
+        .to(vec![PatternSymbol::Line])
+        .to(vec![PatternSymbol::Conditional]);
+
+    builder
+        .from(PatternSymbol::BranchFiller)
+        .to(vec![PatternSymbol::TFiller])
+        .to(vec![PatternSymbol::TAlpha])
+        .to(vec![PatternSymbol::TNumber])
+        .to(vec![PatternSymbol::TColon]);
+
     builder.from(PatternSymbol::Capture).to(vec![
         PatternSymbol::TLeftBrace,
         PatternSymbol::CaptureDescriptor,
@@ -256,11 +390,20 @@ pub struct Pattern {
 /// during formatting.
 /// * `Capture` - stores a `Capture`, indicating that a child (in the parse tree) should be
 /// formatted and inserted at this position during formatting.
+/// * `Group` - stores the segments of a layout group, whose line breaks are only taken if the
+///   group does not fit on the current line.
+/// * `Line` - stores a `Line`, indicating a line break which is taken if its enclosing group is
+///   broken.
+/// * `Conditional` - stores a `Conditional`, indicating that one of two sequences of segments
+///   should be formatted depending on a condition.
 #[derive(Clone)]
 pub enum Segment {
     Filler(String),
     Substitution(String),
     Capture(Capture),
+    Group(Vec<Segment>),
+    Line(Line),
+    Conditional(Conditional),
 }
 
 /// Capture: Stores information about the capture of a child's formatting, to be inserted into the
@@ -278,6 +421,48 @@ pub struct Capture {
     pub declarations: Vec<Declaration>,
 }
 
+/// Line: Stores the alternative forms of a line break in a pattern.
+///
+/// # Fields
+///
+/// * `flat` - the segments inserted if the enclosing group fits on the current line.
+/// * `broken` - the segments inserted if the enclosing group is broken, typically a newline
+///   followed by indentation.
+#[derive(Clone)]
+pub struct Line {
+    pub flat: Vec<Segment>,
+    pub broken: Vec<Segment>,
+}
+
+/// Conditional: Stores the branches of a conditional segment in a pattern.
+///
+/// # Fields
+///
+/// * `condition` - the condition which determines the branch to format.
+/// * `then` - the segments formatted if `condition` holds.
+/// * `otherwise` - the segments formatted if `condition` does not hold.
+#[derive(Clone)]
+pub struct Conditional {
+    pub condition: Condition,
+    pub then: Vec<Segment>,
+    pub otherwise: Vec<Segment>,
+}
+
+/// Condition: Represents the condition of a `Conditional` segment.
+///
+/// # Types
+///
+/// * `Set` - holds if the variable with the stored name is set in the current scope.
+/// * `Equals` - holds if the variable with the first stored name is set to the second stored
+///   value in the current scope.
+/// * `Captured` - holds if the child at the stored index is non-empty, i.e. it covers some input.
+#[derive(Clone)]
+pub enum Condition {
+    Set(String),
+    Equals(String, String),
+    Captured(usize),
+}
+
 /// Declaration: Represents a variable declaration in a `Capture`.
 ///
 /// # Fields
@@ -291,6 +476,151 @@ pub struct Declaration {
     pub value: Option<Pattern>,
 }
 
+impl Serial for Pattern {
+    fn write(&self, writer: &mut Writer) {
+        self.segments.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            segments: Vec::read(reader)?,
+        })
+    }
+}
+
+impl Serial for Segment {
+    fn write(&self, writer: &mut Writer) {
+        match self {
+            Segment::Filler(filler) => {
+                writer.write_u8(0);
+                filler.write(writer);
+            }
+            Segment::Substitution(variable) => {
+                writer.write_u8(1);
+                variable.write(writer);
+            }
+            Segment::Capture(capture) => {
+                writer.write_u8(2);
+                capture.write(writer);
+            }
+            Segment::Group(segments) => {
+                writer.write_u8(3);
+                segments.write(writer);
+            }
+            Segment::Line(line) => {
+                writer.write_u8(4);
+                line.write(writer);
+            }
+            Segment::Conditional(conditional) => {
+                writer.write_u8(5);
+                conditional.write(writer);
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        match reader.read_u8()? {
+            0 => Ok(Segment::Filler(String::read(reader)?)),
+            1 => Ok(Segment::Substitution(String::read(reader)?)),
+            2 => Ok(Segment::Capture(Capture::read(reader)?)),
+            3 => Ok(Segment::Group(Vec::read(reader)?)),
+            4 => Ok(Segment::Line(Line::read(reader)?)),
+            5 => Ok(Segment::Conditional(Conditional::read(reader)?)),
+            byte => Err(serial::Error::InvalidErr(format!("segment tag {}", byte))),
+        }
+    }
+}
+
+impl Serial for Capture {
+    fn write(&self, writer: &mut Writer) {
+        self.child_index.write(writer);
+        self.declarations.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            child_index: usize::read(reader)?,
+            declarations: Vec::read(reader)?,
+        })
+    }
+}
+
+impl Serial for Line {
+    fn write(&self, writer: &mut Writer) {
+        self.flat.write(writer);
+        self.broken.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            flat: Vec::read(reader)?,
+            broken: Vec::read(reader)?,
+        })
+    }
+}
+
+impl Serial for Conditional {
+    fn write(&self, writer: &mut Writer) {
+        self.condition.write(writer);
+        self.then.write(writer);
+        self.otherwise.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            condition: Condition::read(reader)?,
+            then: Vec::read(reader)?,
+            otherwise: Vec::read(reader)?,
+        })
+    }
+}
+
+impl Serial for Condition {
+    fn write(&self, writer: &mut Writer) {
+        match self {
+            Condition::Set(name) => {
+                writer.write_u8(0);
+                name.write(writer);
+            }
+            Condition::Equals(name, value) => {
+                writer.write_u8(1);
+                name.write(writer);
+                value.write(writer);
+            }
+            Condition::Captured(child_index) => {
+                writer.write_u8(2);
+                child_index.write(writer);
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        match reader.read_u8()? {
+            0 => Ok(Condition::Set(String::read(reader)?)),
+            1 => Ok(Condition::Equals(
+                String::read(reader)?,
+                String::read(reader)?,
+            )),
+            2 => Ok(Condition::Captured(usize::read(reader)?)),
+            byte => Err(serial::Error::InvalidErr(format!("condition tag {}", byte))),
+        }
+    }
+}
+
+impl Serial for Declaration {
+    fn write(&self, writer: &mut Writer) {
+        self.key.write(writer);
+        self.value.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            key: String::read(reader)?,
+            value: Option::read(reader)?,
+        })
+    }
+}
+
 /// Returns a `Pattern` object given an input string storing the pattern (from the specification),
 /// or an error if the input string does not represent a valid pattern.
 ///
@@ -362,8 +692,24 @@ fn generate_pattern_recursive<'scope, Symbol: GrammarSymbol>(
     }
 
     match node.lhs.kind() {
-        PatternSymbol::TFiller | PatternSymbol::TAlpha | PatternSymbol::TNumber => {
+        PatternSymbol::TFiller
+        | PatternSymbol::TAlpha
+        | PatternSymbol::TNumber
+        | PatternSymbol::TColon
+        | PatternSymbol::TOr => {
             let name = string_utils::replace_escapes(&node.lhs.lexeme()[..]);
+
+            // Colons and bars are lexed separately from other filler (to delimit conditionals),
+            // so rejoin them with any adjacent filler.
+            let kind = node.lhs.kind();
+            let delimiter = *kind == PatternSymbol::TColon || *kind == PatternSymbol::TOr;
+            if let Some(Segment::Filler(ref mut last)) = accumulator.last_mut() {
+                if delimiter || last.ends_with(':') || last.ends_with('|') {
+                    last.push_str(&name);
+                    return Ok(captures);
+                }
+            }
+
             accumulator.push(Segment::Filler(name));
         }
         PatternSymbol::Substitution => {
@@ -404,6 +750,57 @@ fn generate_pattern_recursive<'scope, Symbol: GrammarSymbol>(
             }));
             return Ok(captures + 1);
         }
+        PatternSymbol::Group => {
+            let mut segments: Vec<Segment> = Vec::new();
+            let captures = generate_pattern_recursive(
+                node.get_child(1),
+                &mut segments,
+                prod,
+                string_prod,
+                captures,
+            )?;
+
+            accumulator.push(Segment::Group(segments));
+            return Ok(captures);
+        }
+        PatternSymbol::Line => {
+            let mut flat: Vec<Segment> = Vec::new();
+            let mut broken: Vec<Segment> = Vec::new();
+            generate_pattern_recursive(node.get_child(1), &mut flat, prod, string_prod, 0)?;
+            generate_pattern_recursive(node.get_child(3), &mut broken, prod, string_prod, 0)?;
+
+            accumulator.push(Segment::Line(Line { flat, broken }));
+        }
+        PatternSymbol::Conditional => {
+            let condition = generate_condition(node.get_child(1), prod, string_prod)?;
+
+            let mut then: Vec<Segment> = Vec::new();
+            let mut otherwise: Vec<Segment> = Vec::new();
+            let captures = generate_pattern_recursive(
+                node.get_child(3),
+                &mut then,
+                prod,
+                string_prod,
+                captures,
+            )?;
+            let captures = match node.get_opt(4) {
+                Some(alternative) => generate_pattern_recursive(
+                    alternative.get_child(1),
+                    &mut otherwise,
+                    prod,
+                    string_prod,
+                    captures,
+                )?,
+                None => captures,
+            };
+
+            accumulator.push(Segment::Conditional(Conditional {
+                condition,
+                then,
+                otherwise,
+            }));
+            return Ok(captures);
+        }
         _ => {
             let mut new_captures = captures;
             for child in &node.children {
@@ -421,6 +818,55 @@ fn generate_pattern_recursive<'scope, Symbol: GrammarSymbol>(
     Ok(captures)
 }
 
+/// Builds a `Condition` from the parse tree of the condition of a conditional segment.
+///
+/// Returns a `Condition`, or an error if the condition refers to a child which is out of bounds.
+///
+/// # Type Parameters
+///
+/// * `Symbol` - the symbol type of the `Grammar` associated with this pattern.
+///
+/// # Parameters
+///
+/// * `condition_node` - the condition node in the parse tree.
+/// * `prod` - the production associated with the pattern being built.
+/// * `string_prod` - the string representation of `prod`, useful for building errors.
+fn generate_condition<Symbol: GrammarSymbol>(
+    condition_node: &Tree<PatternSymbol>,
+    prod: &Production<Symbol>,
+    string_prod: &Production<String>,
+) -> Result<Condition, BuildError> {
+    let head = condition_node.get_child(0);
+
+    if *head.lhs.kind() == PatternSymbol::TNumber {
+        let child_index = head.lhs.lexeme().parse::<usize>().unwrap();
+        if child_index >= prod.rhs.len() {
+            return Err(BuildError::CaptureErr(format!(
+                "Condition index {} out of bounds for production '{}' with {} children",
+                child_index,
+                string_prod.to_string(),
+                prod.rhs.len()
+            )));
+        }
+        return Ok(Condition::Captured(child_index));
+    }
+
+    let name = head.lhs.lexeme().clone();
+    if condition_node.children.len() == 1 {
+        return Ok(Condition::Set(name));
+    }
+
+    let mut value = String::new();
+    let mut value_node = condition_node.get_child(2);
+    while !value_node.is_empty() && !value_node.is_leaf() {
+        let filler = value_node.get_child(0).get_child(0);
+        value.push_str(&string_utils::replace_escapes(filler.lhs.lexeme()));
+        value_node = value_node.get_child(1);
+    }
+
+    Ok(Condition::Equals(name, value))
+}
+
 /// Recursively builds the declarations of a capture segment, placing them in an accumulator.
 ///
 /// Returns an error if the pattern cannot be built.
@@ -662,41 +1108,49 @@ mod tests {
             &Segment::Filler(ref s) => "\t \n\n\n\n" == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(1).unwrap() {
             &Segment::Filler(_) => false,
             &Segment::Substitution(_) => false,
             &Segment::Capture(ref c) => c.child_index == 1 && c.declarations.len() == 0,
+            _ => false,
         });
         assert!(match pattern.segments.get(2).unwrap() {
             &Segment::Filler(ref s) => "  " == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(3).unwrap() {
             &Segment::Filler(_) => false,
             &Segment::Substitution(_) => false,
             &Segment::Capture(ref c) => c.child_index == 2 && c.declarations.len() == 0,
+            _ => false,
         });
         assert!(match pattern.segments.get(4).unwrap() {
             &Segment::Filler(ref s) => "  " == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(5).unwrap() {
             &Segment::Filler(_) => false,
             &Segment::Substitution(_) => false,
             &Segment::Capture(ref c) => c.child_index == 4 && c.declarations.len() == 1,
+            _ => false,
         });
         assert!(match pattern.segments.get(4).unwrap() {
             &Segment::Filler(ref s) => "  " == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(7).unwrap() {
             &Segment::Filler(_) => false,
             &Segment::Substitution(_) => false,
             &Segment::Capture(ref c) => c.child_index == 3 && c.declarations.len() == 2,
+            _ => false,
         });
     }
 
@@ -724,41 +1178,49 @@ mod tests {
             &Segment::Filler(ref s) => "\t \n\n\n\n" == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(1).unwrap() {
             &Segment::Filler(_) => false,
             &Segment::Substitution(_) => false,
             &Segment::Capture(ref c) => c.child_index == 1 && c.declarations.len() == 0,
+            _ => false,
         });
         assert!(match pattern.segments.get(2).unwrap() {
             &Segment::Filler(ref s) => "  " == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(3).unwrap() {
             &Segment::Filler(_) => false,
             &Segment::Substitution(_) => false,
             &Segment::Capture(ref c) => c.child_index == 1 && c.declarations.len() == 0,
+            _ => false,
         });
         assert!(match pattern.segments.get(4).unwrap() {
             &Segment::Filler(ref s) => "  " == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(5).unwrap() {
             &Segment::Filler(_) => false,
             &Segment::Substitution(_) => false,
             &Segment::Capture(ref c) => c.child_index == 2 && c.declarations.len() == 1,
+            _ => false,
         });
         assert!(match pattern.segments.get(4).unwrap() {
             &Segment::Filler(ref s) => "  " == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(7).unwrap() {
             &Segment::Filler(_) => false,
             &Segment::Substitution(_) => false,
             &Segment::Capture(ref c) => c.child_index == 3 && c.declarations.len() == 2,
+            _ => false,
         });
     }
 
@@ -783,41 +1245,49 @@ mod tests {
             &Segment::Filler(ref s) => "\t \n\r" == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(1).unwrap() {
             &Segment::Filler(_) => false,
             &Segment::Substitution(ref s) => "a" == *s,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(2).unwrap() {
             &Segment::Filler(_) => false,
             &Segment::Substitution(_) => false,
             &Segment::Capture(ref c) => c.child_index == 1 && c.declarations.len() == 0,
+            _ => false,
         });
         assert!(match pattern.segments.get(3).unwrap() {
             &Segment::Filler(ref s) => "  " == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(4).unwrap() {
             &Segment::Filler(_) => false,
             &Segment::Substitution(_) => false,
             &Segment::Capture(ref c) => c.child_index == 1 && c.declarations.len() == 0,
+            _ => false,
         });
         assert!(match pattern.segments.get(5).unwrap() {
             &Segment::Filler(ref s) => " " == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(6).unwrap() {
             &Segment::Filler(_) => false,
             &Segment::Substitution(ref s) => "prefix" == *s,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(7).unwrap() {
             &Segment::Filler(ref s) => " " == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
     }
 
@@ -839,29 +1309,160 @@ mod tests {
             &Segment::Filler(ref s) => "1234567890" == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(1).unwrap() {
             &Segment::Filler(ref s) => "abcdefghijklmnopqrstuvwxyz" == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(2).unwrap() {
             &Segment::Filler(ref s) => " \n\t`~!@#$%^&*()_-+:'\"<>,.?/|" == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
         assert!(match pattern.segments.get(3).unwrap() {
             &Segment::Filler(_) => false,
             &Segment::Substitution(_) => false,
             &Segment::Capture(ref c) => c.child_index == 0 && c.declarations.len() == 0,
+            _ => false,
         });
         assert!(match pattern.segments.get(4).unwrap() {
             &Segment::Filler(ref s) => "{}[];=\\" == *s,
             &Segment::Substitution(_) => false,
             &Segment::Capture(_) => false,
+            _ => false,
         });
     }
 
+    #[test]
+    fn generate_pattern_group_and_line() {
+        //setup
+        let input = "({^{/;\n[prefix]  }{}{/ ;}{}})";
+        let prod = Production {
+            lhs: PatternSymbol::Pattern,
+            rhs: vec![
+                ProductionSymbol::symbol(PatternSymbol::Filler),
+                ProductionSymbol::symbol(PatternSymbol::Filler),
+            ],
+        };
+
+        //exercise
+        let pattern = generate_pattern(input, &prod, &prod.string_production()).unwrap();
+
+        //verify
+        assert_eq!(pattern.segments.len(), 3);
+        let group = match pattern.segments.get(1).unwrap() {
+            Segment::Group(segments) => segments,
+            _ => panic!("Expected group segment"),
+        };
+        assert_eq!(group.len(), 4);
+        assert!(match group.first().unwrap() {
+            Segment::Line(line) => {
+                line.flat.is_empty()
+                    && line.broken.len() == 3
+                    && match line.broken.get(1).unwrap() {
+                        Segment::Substitution(s) => "prefix" == *s,
+                        _ => false,
+                    }
+            }
+            _ => false,
+        });
+        assert!(match group.get(1).unwrap() {
+            Segment::Capture(c) => c.child_index == 0,
+            _ => false,
+        });
+        assert!(match group.get(2).unwrap() {
+            Segment::Line(line) => line.flat.len() == 1 && line.broken.is_empty(),
+            _ => false,
+        });
+        assert!(match group.get(3).unwrap() {
+            Segment::Capture(c) => c.child_index == 1,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn generate_pattern_conditionals() {
+        //setup
+        let input = "{?1: {}|:}{?kind=a b:x\\|y|{?kind:z}}{}";
+        let prod = Production {
+            lhs: PatternSymbol::Pattern,
+            rhs: vec![
+                ProductionSymbol::symbol(PatternSymbol::Filler),
+                ProductionSymbol::symbol(PatternSymbol::Filler),
+            ],
+        };
+
+        //exercise
+        let pattern = generate_pattern(input, &prod, &prod.string_production()).unwrap();
+
+        assert_eq!(pattern.segments.len(), 3);
+        assert_eq!(pattern.segments.len(), 3);
+        assert!(match pattern.segments.first().unwrap() {
+            Segment::Conditional(conditional) => {
+                (match conditional.condition {
+                    Condition::Captured(child_index) => child_index == 1,
+                    _ => false,
+                }) && conditional.then.len() == 2
+                    && (match conditional.then.get(1).unwrap() {
+                        Segment::Capture(c) => c.child_index == 0,
+                        _ => false,
+                    })
+                    && (match conditional.otherwise.first().unwrap() {
+                        Segment::Filler(s) => ":" == *s,
+                        _ => false,
+                    })
+            }
+            _ => false,
+        });
+        assert!(match pattern.segments.get(1).unwrap() {
+            Segment::Conditional(conditional) => {
+                (match conditional.condition {
+                    Condition::Equals(ref name, ref value) => "kind" == *name && "a b" == *value,
+                    _ => false,
+                }) && (match conditional.then.get(1).unwrap() {
+                    Segment::Filler(s) => "|y" == *s,
+                    _ => false,
+                }) && (match conditional.otherwise.first().unwrap() {
+                    Segment::Conditional(inner) => match inner.condition {
+                        Condition::Set(ref name) => "kind" == *name,
+                        _ => false,
+                    },
+                    _ => false,
+                })
+            }
+            _ => false,
+        });
+        assert!(match pattern.segments.get(2).unwrap() {
+            Segment::Capture(c) => c.child_index == 1,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn pattern_condition_error() {
+        //setup
+        let input = "{?1:{}}";
+        let prod = Production {
+            lhs: PatternSymbol::Pattern,
+            rhs: vec![ProductionSymbol::symbol(PatternSymbol::TSemi)],
+        };
+
+        //exercise
+        let res = generate_pattern(input, &prod, &prod.string_production());
+
+        //verify
+        assert!(res.is_err());
+        assert_eq!(
+            format!("{}", res.err().unwrap()),
+            "Pattern capture error: \
+             Condition index 1 out of bounds for production 'Pattern TSemi' with 1 children"
+        );
+    }
+
     #[test]
     fn pattern_lex_error() {
         //setup
@@ -898,7 +1499,8 @@ mod tests {
         assert!(res.is_err());
         assert_eq!(
             format!("{}", res.err().unwrap()),
-            "Pattern parse error: Recognition failed after consuming all tokens"
+            "Pattern parse error: Recognition failed after consuming all tokens at 1:2, \
+             expected `TNumber` or `TRightBrace` or `TSemi` in Capture, CaptureDescriptor"
         );
     }
 
@@ -0,0 +1,165 @@
+use {
+    core::{
+        lex::Position,
+        util::diff::{self, Difference},
+    },
+    std::hash::Hash,
+};
+
+/// Token Change: A difference between the token stream of some input and that of its formatted
+/// output.
+///
+/// # Types
+///
+/// * `Removed` - indicates that the token at the given index of the input stream has no
+///   counterpart in the output stream.
+/// * `Added` - indicates that the token at the given index of the output stream has no
+///   counterpart in the input stream.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum TokenChange {
+    Removed(usize),
+    Added(usize),
+}
+
+/// Returns the changes which transform the token stream `input` into `output`, in stream order,
+/// where tokens are identified by their keys.
+///
+/// The streams are aligned by a diff, so a dropped or inserted token is reported on its own,
+/// rather than causing every later token to mismatch.
+pub fn token_changes<Key: Eq + Hash>(input: &[Key], output: &[Key]) -> Vec<TokenChange> {
+    let mut changes: Vec<TokenChange> = Vec::new();
+    let mut input_index: usize = 0;
+    let mut output_index: usize = 0;
+
+    for difference in diff::diff(input, output) {
+        match difference {
+            Difference::Same(count) => {
+                input_index += count;
+                output_index += count;
+            }
+            Difference::Removed(count) => {
+                for _ in 0..count {
+                    changes.push(TokenChange::Removed(input_index));
+                    input_index += 1;
+                }
+            }
+            Difference::Added(count) => {
+                for _ in 0..count {
+                    changes.push(TokenChange::Added(output_index));
+                    output_index += 1;
+                }
+            }
+        }
+    }
+
+    changes
+}
+
+/// Returns the position in `original` of the first character which differs from `changed`, or
+/// `None` if the texts are equal.
+pub fn first_difference(original: &str, changed: &str) -> Option<Position> {
+    if original == changed {
+        return None;
+    }
+
+    let mut position = Position::default();
+    let mut changed_chars = changed.chars();
+
+    for c in original.chars() {
+        if changed_chars.next() != Some(c) {
+            break;
+        }
+
+        position.offset += c.len_utf8();
+        if c == '\n' {
+            position.line += 1;
+            position.character = 1;
+        } else {
+            position.character += 1;
+        }
+    }
+
+    Some(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_changes_equal() {
+        //setup
+        let tokens = vec!["a", "b", "c"];
+
+        //exercise
+        let changes = token_changes(&tokens, &tokens);
+
+        //verify
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn token_changes_aligned() {
+        //setup
+        let input = vec!["a", "b", "c", "d", "e"];
+        let output = vec!["a", "c", "d", "x", "e"];
+
+        //exercise
+        let changes = token_changes(&input, &output);
+
+        //verify
+        assert_eq!(
+            changes,
+            vec![TokenChange::Removed(1), TokenChange::Added(3)]
+        );
+    }
+
+    #[test]
+    fn token_changes_empty() {
+        //setup
+        let input = vec!["a", "b"];
+        let output: Vec<&str> = Vec::new();
+
+        //exercise
+        let removed = token_changes(&input, &output);
+        let added = token_changes(&output, &input);
+
+        //verify
+        assert_eq!(
+            removed,
+            vec![TokenChange::Removed(0), TokenChange::Removed(1)]
+        );
+        assert_eq!(added, vec![TokenChange::Added(0), TokenChange::Added(1)]);
+    }
+
+    #[test]
+    fn first_difference_position() {
+        //setup
+        let original = "ab\ncde\nf";
+        let changed = "ab\ncdx\nf";
+
+        //exercise
+        let same = first_difference(original, original);
+        let position = first_difference(original, changed);
+        let truncated = first_difference(original, "ab\n");
+
+        //verify
+        assert_eq!(same, None);
+        assert_eq!(
+            position,
+            Some(Position {
+                offset: 5,
+                line: 2,
+                character: 3,
+            })
+        );
+        assert_eq!(
+            truncated,
+            Some(Position {
+                offset: 3,
+                line: 2,
+                character: 1,
+            })
+        );
+    }
+}
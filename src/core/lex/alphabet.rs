@@ -1,4 +1,7 @@
-use std::collections::HashSet;
+use {
+    core::util::serial::{self, Reader, Serial, Writer},
+    std::collections::HashSet,
+};
 
 /// Alphabet: Trait representing a lexing alphabet.
 pub trait Alphabet {
@@ -27,6 +30,13 @@ impl HashedAlphabet {
     pub fn insert(&mut self, c: char) {
         self.alphabet.insert(c);
     }
+
+    /// Returns the characters in the alphabet, in ascending order.
+    pub fn chars(&self) -> Vec<char> {
+        let mut chars: Vec<char> = self.alphabet.iter().cloned().collect();
+        chars.sort();
+        chars
+    }
 }
 
 impl Alphabet for HashedAlphabet {
@@ -35,6 +45,18 @@ impl Alphabet for HashedAlphabet {
     }
 }
 
+impl Serial for HashedAlphabet {
+    fn write(&self, writer: &mut Writer) {
+        self.alphabet.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            alphabet: HashSet::read(reader)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
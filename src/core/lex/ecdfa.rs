@@ -11,9 +11,15 @@ use {
             CDFABuilder, CDFAError, Transit, TransitBuilder, TransitionResult, CDFA,
         },
         parse::grammar::GrammarSymbol,
-        util::encoder::Encoder,
+        util::{
+            encoder::Encoder,
+            serial::{self, Reader, Serial, Writer},
+        },
+    },
+    std::{
+        collections::{HashMap, HashSet, VecDeque},
+        ops::RangeInclusive,
     },
-    std::{collections::HashMap, ops::RangeInclusive, usize},
 };
 
 pub struct EncodedCDFABuilder<State: Data, Symbol: GrammarSymbol> {
@@ -43,6 +49,25 @@ impl<State: Data, Symbol: GrammarSymbol> EncodedCDFABuilder<State, Symbol> {
         }
     }
 
+    /// Returns the CDFA along with the encoder of its states, which maps the states passed to
+    /// this builder to the states of the CDFA, or an error if the CDFA cannot be built.
+    pub fn build_encoded(self) -> Result<(EncodedCDFA<Symbol>, Encoder<State>), CDFAError> {
+        if self.start == usize::MAX {
+            Err(CDFAError::BuildErr("No start state was set".to_string()))
+        } else if self.start > self.t_delta.size() {
+            Err(CDFAError::BuildErr("Invalid start state".to_string()))
+        } else {
+            let cdfa = EncodedCDFA {
+                alphabet: self.alphabet,
+                accepting: self.accepting,
+                t_delta: self.t_delta,
+                tokenizer: self.tokenizer,
+                start: self.start,
+            };
+            Ok((cdfa, self.encoder))
+        }
+    }
+
     fn encode_transit(&mut self, transit: Transit<State>) -> Transit<usize> {
         let mut builder = TransitBuilder::to(self.encoder.encode(&transit.dest));
 
@@ -64,24 +89,12 @@ impl<State: Data, Symbol: GrammarSymbol> CDFABuilder<State, Symbol, EncodedCDFA<
             accepting: HashMap::new(),
             t_delta: CEHashMap::new(),
             tokenizer: CEHashMap::new(),
-            start: usize::max_value(),
+            start: usize::MAX,
         }
     }
 
     fn build(self) -> Result<EncodedCDFA<Symbol>, CDFAError> {
-        if self.start == usize::max_value() {
-            Err(CDFAError::BuildErr("No start state was set".to_string()))
-        } else if self.start > self.t_delta.size() {
-            Err(CDFAError::BuildErr("Invalid start state".to_string()))
-        } else {
-            Ok(EncodedCDFA {
-                alphabet: self.alphabet,
-                accepting: self.accepting,
-                t_delta: self.t_delta,
-                tokenizer: self.tokenizer,
-                start: self.start,
-            })
-        }
+        self.build_encoded().map(|(cdfa, _)| cdfa)
     }
 
     fn set_alphabet(&mut self, chars: impl Iterator<Item = char>) -> &mut Self {
@@ -107,7 +120,7 @@ impl<State: Data, Symbol: GrammarSymbol> CDFABuilder<State, Symbol, EncodedCDFA<
     }
 
     fn mark_start(&mut self, state: &State) -> &mut Self {
-        if self.start == usize::max_value() {
+        if self.start == usize::MAX {
             self.start = self.encoder.encode(state);
         }
         self
@@ -283,6 +296,156 @@ impl<Symbol: GrammarSymbol> EncodedCDFA<Symbol> {
     pub fn produces(&self) -> CEHashMapIterator<Symbol> {
         self.tokenizer.iter()
     }
+
+    /// Returns the states which can be reached from the start state, either by a transition or as
+    /// the destination of an accepting state or transition.
+    pub fn reachable_states(&self) -> HashSet<usize> {
+        let mut reachable: HashSet<usize> = HashSet::new();
+        let mut stack: Vec<usize> = vec![self.start];
+        reachable.insert(self.start);
+
+        while let Some(state) = stack.pop() {
+            let mut successors: Vec<usize> = Vec::new();
+
+            if let Some(Some(destination)) = self.accepting.get(&state) {
+                successors.push(*destination);
+            }
+
+            if let Some(t_trie) = self.t_delta.get(state) {
+                for transit in t_trie.transits() {
+                    successors.push(transit.dest);
+                    successors.extend(transit.acceptor_destination);
+                }
+            }
+
+            for successor in successors {
+                if reachable.insert(successor) {
+                    stack.push(successor);
+                }
+            }
+        }
+
+        reachable
+    }
+
+    /// Returns sample lexemes which can be lexed starting from state `start`, with one sample for
+    /// each pair of token kind and next start state.
+    ///
+    /// Samples are found by a breadth-first search of the CDFA, and are returned in the order they
+    /// are found, so each sample takes among the fewest transitions to an accepting state of its
+    /// kind. Since lexing is greedy, a sample is not guaranteed to lex as a single token when
+    /// followed by other input.
+    pub fn sample_lexemes(&self, start: usize) -> Vec<SampleLexeme<Symbol>> {
+        let mut samples: Vec<SampleLexeme<Symbol>> = Vec::new();
+        let mut sampled: HashSet<(Option<Symbol>, usize)> = HashSet::new();
+        let mut visited: HashSet<usize> = HashSet::new();
+        let mut queue: VecDeque<(usize, String, Option<usize>)> = VecDeque::new();
+
+        visited.insert(start);
+        queue.push_back((start, String::new(), None));
+
+        let mut fallback_chars = match self.alphabet {
+            Some(ref alphabet) => alphabet.chars(),
+            None => SAMPLE_CHARS.chars().collect(),
+        };
+        fallback_chars.sort_by_key(|c| sample_rank(*c));
+
+        while let Some((state, lexeme, acceptor_destination)) = queue.pop_front() {
+            if state != start && self.accepts(&state) {
+                let kind = self.tokenize(&state);
+                let next_start = acceptor_destination
+                    .or_else(|| self.default_acceptor_destination(&state))
+                    .unwrap_or(start);
+
+                if sampled.insert((kind.clone(), next_start)) {
+                    samples.push(SampleLexeme {
+                        kind,
+                        lexeme: lexeme.clone(),
+                        next_start,
+                    });
+                }
+            }
+
+            let t_trie = match self.t_delta.get(state) {
+                None => continue,
+                Some(t_trie) => t_trie,
+            };
+
+            for input in t_trie.sample_inputs(&fallback_chars) {
+                if !input.chars().all(|c| self.alphabet_contains(c)) {
+                    continue;
+                }
+
+                if let TransitionResult::Ok(dest) = t_trie.transition(&input) {
+                    // Non-consuming transitions leave the lexeme unchanged, and partially consumed
+                    // inputs are sampled by shorter inputs.
+                    let next_lexeme = if dest.consumed == input.len() {
+                        format!("{}{}", lexeme, input)
+                    } else if dest.consumed == 0 {
+                        lexeme.clone()
+                    } else {
+                        continue;
+                    };
+
+                    if visited.insert(dest.state) {
+                        queue.push_back((dest.state, next_lexeme, dest.acceptor_destination));
+                    }
+                }
+            }
+        }
+
+        samples
+    }
+}
+
+/// Sample Lexeme: A sample of input which the CDFA lexes as a single token.
+///
+/// # Type Parameters
+///
+/// * `Symbol` - the type of grammar symbol the token is tokenized into.
+///
+/// # Fields
+///
+/// * `kind` - the kind of the token, or `None` if the input is accepted without being tokenized
+///   (e.g. whitespace).
+/// * `lexeme` - the sampled input.
+/// * `next_start` - the CDFA state from which lexing continues after the token.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SampleLexeme<Symbol: GrammarSymbol> {
+    pub kind: Option<Symbol>,
+    pub lexeme: String,
+    pub next_start: usize,
+}
+
+/// Returns the rank of `c` in the order in which characters are preferred when sampling lexemes,
+/// where lower ranks are preferred.
+fn sample_rank(c: char) -> usize {
+    SAMPLE_CHARS.find(c).unwrap_or(SAMPLE_CHARS.len())
+}
+
+/// The characters tried when sampling the default transitions of a CDFA without an alphabet, in
+/// order of preference.
+const SAMPLE_CHARS: &str = " \n\tabcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789\
+                            !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+impl<Symbol: GrammarSymbol + Serial> Serial for EncodedCDFA<Symbol> {
+    fn write(&self, writer: &mut Writer) {
+        self.alphabet.write(writer);
+        self.accepting.write(writer);
+        self.t_delta.write(writer);
+        self.tokenizer.write(writer);
+        self.start.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            alphabet: Option::read(reader)?,
+            accepting: HashMap::read(reader)?,
+            t_delta: CEHashMap::read(reader)?,
+            tokenizer: CEHashMap::read(reader)?,
+            start: usize::read(reader)?,
+        })
+    }
 }
 
 impl<Symbol: GrammarSymbol> CDFA<usize, Symbol> for EncodedCDFA<Symbol> {
@@ -366,6 +529,48 @@ impl TransitionTrie {
         }
     }
 
+    /// Returns all transits out of this trie, in no particular order.
+    fn transits(&self) -> Vec<&Transit<usize>> {
+        let mut transits = self.trie.values();
+        transits.extend(self.ranges.values());
+        transits.extend(self.default.as_ref());
+        transits
+    }
+
+    /// Returns inputs which may take transitions out of this trie, in a deterministic order:
+    /// the explicit matchers, then the first character of each range, then `fallback_chars` to
+    /// take the default transition. Matchers and ranges starting with preferred sample
+    /// characters are ordered first.
+    fn sample_inputs(&self, fallback_chars: &[char]) -> Vec<String> {
+        let mut chains: Vec<String> = self
+            .trie
+            .entries()
+            .into_iter()
+            .filter_map(|(key, _)| String::from_utf8(key).ok())
+            .collect();
+        chains.sort_by_key(|chain| (chain.chars().next().map(sample_rank), chain.clone()));
+
+        let mut range_starts: Vec<u32> = self
+            .ranges
+            .entries()
+            .into_iter()
+            .map(|(keys, _)| *keys.start())
+            .collect();
+        range_starts.sort_by_key(|start| (std::char::from_u32(*start).map(sample_rank), *start));
+
+        let mut inputs = chains;
+        inputs.extend(
+            range_starts
+                .into_iter()
+                .filter_map(std::char::from_u32)
+                .map(|c| c.to_string()),
+        );
+        if self.default.is_some() {
+            inputs.extend(fallback_chars.iter().map(|c| c.to_string()));
+        }
+        inputs
+    }
+
     fn transition_explicit(&self, input: &str) -> TransitionResult<usize> {
         match self.trie.longest_match(input.as_bytes()) {
             None => TransitionResult::Fail,
@@ -425,6 +630,22 @@ impl TransitionTrie {
     }
 }
 
+impl Serial for TransitionTrie {
+    fn write(&self, writer: &mut Writer) {
+        self.trie.write(writer);
+        self.ranges.write(writer);
+        self.default.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            trie: Trie::read(reader)?,
+            ranges: IntervalMap::read(reader)?,
+            default: Option::read(reader)?,
+        })
+    }
+}
+
 impl Default for TransitionTrie {
     fn default() -> Self {
         Self::new()
@@ -553,6 +774,70 @@ RBR <- '}'
         );
     }
 
+    #[test]
+    fn lex_spans() {
+        //setup
+        let mut builder: EncodedCDFABuilder<String, String> = EncodedCDFABuilder::new();
+        builder
+            .set_alphabet("{} \n".chars())
+            .mark_start(&"start".to_string());
+        builder
+            .state(&"start".to_string())
+            .mark_trans(Transit::to("ws".to_string()), ' ')
+            .unwrap()
+            .mark_trans(Transit::to("ws".to_string()), '\n')
+            .unwrap()
+            .mark_trans(Transit::to("lbr".to_string()), '{')
+            .unwrap()
+            .mark_trans(Transit::to("rbr".to_string()), '}')
+            .unwrap();
+        builder
+            .state(&"ws".to_string())
+            .mark_trans(Transit::to("ws".to_string()), ' ')
+            .unwrap()
+            .mark_trans(Transit::to("ws".to_string()), '\n')
+            .unwrap()
+            .accept()
+            .tokenize(&"WS".to_string());
+        builder
+            .accept(&"lbr".to_string())
+            .tokenize(&"lbr".to_string(), &"LBR".to_string())
+            .accept(&"rbr".to_string())
+            .tokenize(&"rbr".to_string(), &"RBR".to_string());
+
+        let cdfa: EncodedCDFA<String> = builder.build().unwrap();
+
+        let input = "  {\n }{";
+
+        let lexer = lex::def_lexer();
+
+        //exercise
+        let tokens = lexer.lex(input, &cdfa).unwrap();
+
+        //verify
+        let spans: Vec<(String, usize, usize)> = tokens
+            .iter()
+            .map(|token| {
+                (
+                    token.span().to_string(),
+                    token.span().start.offset,
+                    token.span().end.offset,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                ("1:1-1:3".to_string(), 0, 2),
+                ("1:3-1:4".to_string(), 2, 3),
+                ("1:4-2:2".to_string(), 3, 5),
+                ("2:2-2:3".to_string(), 5, 6),
+                ("2:3-2:4".to_string(), 6, 7),
+            ]
+        );
+    }
+
     #[test]
     fn lex_ignore() {
         //setup
@@ -1289,6 +1574,62 @@ A <- 'a'
         );
     }
 
+    #[test]
+    fn sample_lexemes() {
+        //setup
+        let mut builder: EncodedCDFABuilder<String, String> = EncodedCDFABuilder::new();
+        builder.mark_start(&"start".to_string());
+        builder
+            .state(&"start".to_string())
+            .mark_chain(Transit::to("four".to_string()), "four")
+            .unwrap()
+            .mark_range(Transit::to("id".to_string()), 'a', 'z')
+            .unwrap()
+            .mark_trans(Transit::to("ws".to_string()), ' ')
+            .unwrap()
+            .default_to(Transit::to("other".to_string()))
+            .unwrap();
+        builder
+            .state(&"id".to_string())
+            .mark_range(Transit::to("id".to_string()), 'a', 'z')
+            .unwrap()
+            .tokenize(&"ID".to_string())
+            .accept();
+        builder
+            .accept(&"four".to_string())
+            .tokenize(&"four".to_string(), &"FOUR".to_string())
+            .accept(&"ws".to_string())
+            .accept(&"other".to_string())
+            .tokenize(&"other".to_string(), &"OTHER".to_string());
+
+        let (cdfa, mut states) = builder.build_encoded().unwrap();
+        let start = states.encode(&"start".to_string());
+
+        //exercise
+        let samples = cdfa.sample_lexemes(start);
+
+        //verify
+        let sample_pairs: Vec<(Option<&str>, &str)> = samples
+            .iter()
+            .map(|sample| {
+                (
+                    sample.kind.as_ref().map(|kind| &kind[..]),
+                    &sample.lexeme[..],
+                )
+            })
+            .collect();
+        assert_eq!(
+            sample_pairs,
+            vec![
+                (None, " "),
+                (Some("FOUR"), "four"),
+                (Some("ID"), "a"),
+                (Some("OTHER"), "\n"),
+            ]
+        );
+        assert!(samples.iter().all(|sample| sample.next_start == start));
+    }
+
     fn tokens_string<Kind: Data>(tokens: &Vec<Token<Kind>>) -> String {
         let mut result = String::new();
         for token in tokens {
@@ -1,6 +1,6 @@
 use core::{
     data::Data,
-    lex::{self, Lexer, Token, TransitionResult, CDFA, FAIL_SEQUENCE_LENGTH},
+    lex::{self, Lexer, Position, Span, Token, TransitionResult, CDFA, FAIL_SEQUENCE_LENGTH},
     parse::grammar::GrammarSymbol,
 };
 
@@ -157,6 +157,13 @@ impl<State: Data, Symbol: GrammarSymbol> Lexer<State, Symbol> for LongestMatchLe
                 Some(state) => state,
             };
 
+            let offset = input.len() - remaining.len();
+            let start = Position {
+                offset,
+                line,
+                character,
+            };
+
             line = res.line;
             character = res.character;
 
@@ -169,6 +176,7 @@ impl<State: Data, Symbol: GrammarSymbol> Lexer<State, Symbol> for LongestMatchLe
 
                         return Err(lex::Error::from(lex::UnacceptedError {
                             sequence,
+                            offset,
                             line,
                             character,
                         }));
@@ -179,10 +187,16 @@ impl<State: Data, Symbol: GrammarSymbol> Lexer<State, Symbol> for LongestMatchLe
                 Some(state) => {
                     // Scanning succeeded, tokenize the consumed input and continue.
                     if let Some(kind) = cdfa.tokenize(&state) {
-                        tokens.push(Token::leaf(
-                            kind,
-                            remaining[..res.consumed].chars().collect(),
-                        ));
+                        let end = Position {
+                            offset: offset + res.consumed,
+                            line,
+                            character,
+                        };
+
+                        tokens.push(
+                            Token::leaf(kind, remaining[..res.consumed].chars().collect())
+                                .with_span(Span::new(start, end)),
+                        );
                     }
                 }
             }
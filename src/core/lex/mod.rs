@@ -2,6 +2,7 @@ use {
     core::{
         data::{interval, Data},
         parse::grammar::GrammarSymbol,
+        util::serial::{self, Reader, Serial, Writer},
     },
     std::{error, fmt},
 };
@@ -9,6 +10,7 @@ use {
 pub mod alphabet;
 pub mod ecdfa;
 pub mod longest_match;
+pub mod sample;
 
 /// The character sequence length to generate when lexing fails.
 static FAIL_SEQUENCE_LENGTH: usize = 10;
@@ -174,6 +176,22 @@ impl<State: Data> Transit<State> {
     }
 }
 
+impl<State: Data + Serial> Serial for Transit<State> {
+    fn write(&self, writer: &mut Writer) {
+        self.dest.write(writer);
+        self.consumer.write(writer);
+        self.acceptor_destination.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            dest: State::read(reader)?,
+            consumer: ConsumerStrategy::read(reader)?,
+            acceptor_destination: Option::read(reader)?,
+        })
+    }
+}
+
 /// Transit Builder: Simple builder for `Transit` structs.
 /// Fields and type parameters correspond exactly with those of the target type.
 #[derive(Clone)]
@@ -228,6 +246,26 @@ pub enum ConsumerStrategy {
     None,
 }
 
+impl Serial for ConsumerStrategy {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u8(match self {
+            ConsumerStrategy::All => 0,
+            ConsumerStrategy::None => 1,
+        });
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        match reader.read_u8()? {
+            0 => Ok(ConsumerStrategy::All),
+            1 => Ok(ConsumerStrategy::None),
+            byte => Err(serial::Error::InvalidErr(format!(
+                "consumer strategy {}",
+                byte
+            ))),
+        }
+    }
+}
+
 /// Transition Result: Represents the result of a transition attempt.
 ///
 /// # Types
@@ -327,10 +365,12 @@ impl From<interval::Error> for CDFAError {
 ///
 /// * `kind` - the kind of token, or `None` if the token represents an epsilon value (null).
 /// * `lexeme` - the scanned characters which produced this token.
+/// * `span` - the region of the original input covered by this token.
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Token<Symbol: fmt::Debug> {
     kind: Option<Symbol>,
     lexeme: String,
+    span: Span,
 }
 
 // TODO(shane) try to achieve better separation between parsing and lexing logic here.
@@ -339,6 +379,7 @@ impl<Symbol: Data> Token<Symbol> {
         Self {
             kind: Some(kind),
             lexeme,
+            span: Span::default(),
         }
     }
 
@@ -346,6 +387,7 @@ impl<Symbol: Data> Token<Symbol> {
         Self {
             kind: Some(kind),
             lexeme: String::new(),
+            span: Span::default(),
         }
     }
 
@@ -353,9 +395,16 @@ impl<Symbol: Data> Token<Symbol> {
         Self {
             kind: None,
             lexeme: String::from("NULL"),
+            span: Span::default(),
         }
     }
 
+    /// Returns this token with its span replaced by `span`.
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
     pub fn is_null(&self) -> bool {
         self.kind.is_none()
     }
@@ -372,6 +421,10 @@ impl<Symbol: Data> Token<Symbol> {
         &self.lexeme
     }
 
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
     pub fn lexeme_escaped(&self) -> String {
         self.lexeme
             .replace('\n', "\\n")
@@ -391,12 +444,116 @@ impl<Symbol: Data> Data for Token<Symbol> {
     }
 }
 
+/// Position: A location in the original input.
+///
+/// # Fields
+///
+/// * `offset` - the byte offset from the start of the input.
+/// * `line` - the line number, starting at 1.
+/// * `character` - the character number on the current line, starting at 1.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            character: 1,
+        }
+    }
+}
+
+impl Data for Position {
+    fn to_string(&self) -> String {
+        format!("{}:{}", self.line, self.character)
+    }
+}
+
+/// Span: A contiguous region of the original input, from `start` (inclusive) to `end`
+/// (exclusive).
+///
+/// Empty spans (where `start == end`) are used for tokens and trees which do not consume any
+/// input, such as epsilon productions.
+///
+/// # Fields
+///
+/// * `start` - the position of the first character in the span.
+/// * `end` - the position immediately after the last character in the span.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// Returns a new span from `start` to `end`.
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns a new empty span located at `position`.
+    pub fn empty_at(position: Position) -> Self {
+        Self::new(position, position)
+    }
+
+    /// Returns the smallest span covering both this span and `other`.
+    pub fn cover(&self, other: &Span) -> Self {
+        let start = if other.start.offset < self.start.offset {
+            other.start
+        } else {
+            self.start
+        };
+        let end = if other.end.offset > self.end.offset {
+            other.end
+        } else {
+            self.end
+        };
+
+        Self::new(start, end)
+    }
+
+    /// Returns the number of input bytes covered by the span.
+    pub fn len(&self) -> usize {
+        self.end.offset - self.start.offset
+    }
+
+    /// Returns true if the span does not cover any input, otherwise false.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Data for Span {
+    fn to_string(&self) -> String {
+        format!("{}-{}", self.start.to_string(), self.end.to_string())
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     UnacceptedErr(UnacceptedError),
     AlphabetErr(char),
 }
 
+impl Error {
+    /// Returns the position in the input at which lexing failed, if known.
+    pub fn position(&self) -> Option<Position> {
+        match *self {
+            Self::UnacceptedErr(ref err) => Some(Position {
+                offset: err.offset,
+                line: err.line,
+                character: err.character,
+            }),
+            Self::AlphabetErr(_) => None,
+        }
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -427,6 +584,7 @@ impl From<UnacceptedError> for Error {
 #[derive(Debug)]
 pub struct UnacceptedError {
     sequence: String,
+    offset: usize,
     character: usize,
     line: usize,
 }
@@ -0,0 +1,151 @@
+use {
+    core::{
+        lex::ecdfa::{EncodedCDFA, SampleLexeme},
+        parse::grammar::GrammarSymbol,
+    },
+    std::collections::{HashMap, HashSet, VecDeque},
+};
+
+/// Lexeme Sampler: Builds sample input for sequences of tokens, following the changes of start
+/// state made by context-sensitive CDFAs.
+///
+/// # Fields
+///
+/// * `cdfa` - the CDFA to sample input from.
+/// * `samples` - the cached sample lexemes of each start state.
+pub struct LexemeSampler<'cdfa, Symbol: 'cdfa + GrammarSymbol> {
+    cdfa: &'cdfa EncodedCDFA<Symbol>,
+    samples: HashMap<usize, Vec<SampleLexeme<Symbol>>>,
+}
+
+impl<'cdfa, Symbol: 'cdfa + GrammarSymbol> LexemeSampler<'cdfa, Symbol> {
+    /// Returns a new sampler of input for `cdfa`.
+    pub fn new(cdfa: &'cdfa EncodedCDFA<Symbol>) -> Self {
+        Self {
+            cdfa,
+            samples: HashMap::new(),
+        }
+    }
+
+    /// Returns sample input which lexes as a token of kind `kind` when lexing from state `start`,
+    /// along with the state from which lexing continues, or `None` if no such input was found.
+    ///
+    /// The token may be preceded by untokenized input, if it can only be lexed after the start
+    /// state is changed.
+    pub fn token(&mut self, start: usize, kind: &Symbol) -> Option<(String, usize)> {
+        let mut visited: HashSet<usize> = HashSet::new();
+        let mut queue: VecDeque<(usize, String)> = VecDeque::new();
+
+        visited.insert(start);
+        queue.push_back((start, String::new()));
+
+        while let Some((state, prefix)) = queue.pop_front() {
+            let samples = self.samples(state);
+
+            if let Some(sample) = samples
+                .iter()
+                .find(|sample| sample.kind.as_ref() == Some(kind))
+            {
+                return Some((format!("{}{}", prefix, sample.lexeme), sample.next_start));
+            }
+
+            for sample in samples.iter().filter(|sample| sample.kind.is_none()) {
+                if visited.insert(sample.next_start) {
+                    queue.push_back((sample.next_start, format!("{}{}", prefix, sample.lexeme)));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns the shortest non-empty sample input lexed from state `start` which satisfies
+    /// `predicate`, along with the state from which lexing continues, or `None` if there is no
+    /// such input.
+    pub fn separator(
+        &mut self,
+        start: usize,
+        predicate: &dyn Fn(&SampleLexeme<Symbol>) -> bool,
+    ) -> Option<(String, usize)> {
+        self.samples(start)
+            .iter()
+            .find(|sample| !sample.lexeme.is_empty() && predicate(sample))
+            .map(|sample| (sample.lexeme.clone(), sample.next_start))
+    }
+
+    /// Returns the sample lexemes of start state `start`, sampling them if they are not cached.
+    fn samples(&mut self, start: usize) -> &[SampleLexeme<Symbol>] {
+        let cdfa = self.cdfa;
+        self.samples
+            .entry(start)
+            .or_insert_with(|| cdfa.sample_lexemes(start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{
+        data::Data,
+        lex::{ecdfa::EncodedCDFABuilder, CDFABuilder, Transit},
+    };
+
+    use super::*;
+
+    #[test]
+    fn token_after_start_change() {
+        //setup
+        #[derive(PartialEq, Eq, Hash, Clone, Debug)]
+        enum S {
+            Start,
+            A,
+            Hop,
+            Hidden,
+            Num,
+        }
+
+        impl Data for S {
+            fn to_string(&self) -> String {
+                format!("{:?}", self)
+            }
+        }
+
+        let mut builder: EncodedCDFABuilder<S, String> = EncodedCDFABuilder::new();
+        builder.mark_start(&S::Start);
+        builder
+            .state(&S::Start)
+            .mark_trans(Transit::to(S::A), 'a')
+            .unwrap()
+            .mark_trans(Transit::to(S::Hop), '!')
+            .unwrap();
+        builder.state(&S::A).tokenize(&"A".to_string()).accept();
+        builder.state(&S::Hop).accept_to(&S::Hidden);
+        builder
+            .state(&S::Hidden)
+            .mark_range(Transit::to(S::Num), '1', '9')
+            .unwrap()
+            .mark_trans(Transit::to(S::Hop), ' ')
+            .unwrap();
+        builder
+            .state(&S::Num)
+            .tokenize(&"NUM".to_string())
+            .accept_to(&S::Start);
+
+        let (cdfa, mut states) = builder.build_encoded().unwrap();
+        let start = states.encode(&S::Start);
+        let hidden = states.encode(&S::Hidden);
+
+        let mut sampler = LexemeSampler::new(&cdfa);
+
+        //exercise
+        let a = sampler.token(start, &"A".to_string());
+        let num = sampler.token(start, &"NUM".to_string());
+        let missing = sampler.token(hidden, &"A".to_string());
+        let separator = sampler.separator(hidden, &|sample| sample.kind.is_none());
+
+        //verify
+        assert_eq!(a, Some(("a".to_string(), start)));
+        assert_eq!(num, Some(("!1".to_string(), start)));
+        assert_eq!(missing, None);
+        assert_eq!(separator, Some((" ".to_string(), hidden)));
+    }
+}
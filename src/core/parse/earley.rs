@@ -2,17 +2,19 @@ use {
     core::{
         data::Data,
         fmt::InjectionAffinity,
-        lex::Token,
+        lex::{Span, Token},
         parse::{
             self,
-            grammar::{Grammar, GrammarSymbol},
-            Parser, Production, ProductionSymbol, SymbolParseMethod, Tree,
+            grammar::{Associativity, Grammar, GrammarSymbol},
+            Ambiguity, Parser, Production, ProductionSymbol, SymbolParseMethod, Tree,
         },
     },
     std::{
-        cmp::Ordering,
-        collections::{HashMap, HashSet, LinkedList},
-        usize,
+        borrow::Cow,
+        cmp::{Ordering, Reverse},
+        collections::{HashMap, HashSet},
+        ops::Add,
+        ptr, usize,
     },
 };
 
@@ -24,6 +26,61 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
         lex: Vec<Token<Symbol>>,
         grammar: &dyn Grammar<Symbol>,
     ) -> Result<Tree<Symbol>, parse::Error> {
+        self.parse_recovering(&lex, grammar, false)
+            .map(|(tree, _)| tree)
+    }
+
+    fn parse_with_ambiguities(
+        &self,
+        lex: Vec<Token<Symbol>>,
+        grammar: &dyn Grammar<Symbol>,
+    ) -> Result<(Tree<Symbol>, Vec<Ambiguity<Symbol>>), parse::Error> {
+        self.parse_recovering(&lex, grammar, true)
+    }
+}
+
+impl EarleyParser {
+    /// Parses `lex` using `grammar`, retrying with error recovery enabled if the grammar has an
+    /// error symbol and the input cannot be parsed without it.
+    ///
+    /// If `ambiguous` is true, the ambiguities resolved while choosing the parse tree are also
+    /// returned, otherwise no ambiguities are returned.
+    fn parse_recovering<Symbol: GrammarSymbol>(
+        &self,
+        lex: &[Token<Symbol>],
+        grammar: &dyn Grammar<Symbol>,
+        ambiguous: bool,
+    ) -> Result<(Tree<Symbol>, Vec<Ambiguity<Symbol>>), parse::Error> {
+        match self.parse_lex(lex, grammar, false, ambiguous) {
+            Err(err) => {
+                if grammar.error_symbol().is_none() {
+                    return Err(err);
+                }
+
+                // Retry with error recovery enabled, reporting the original error if recovery
+                // is also unsuccessful.
+                self.parse_lex(lex, grammar, true, ambiguous)
+                    .map_err(|_| err)
+            }
+            res => res,
+        }
+    }
+
+    /// Parses `lex` using `grammar`.
+    ///
+    /// If `recover` is true, then the error symbol of the grammar (if any) can be used to match
+    /// any non-empty sequence of non-sync tokens, allowing unparsable regions of the input to be
+    /// captured in `SymbolParseMethod::Recovered` nodes of the parse tree.
+    ///
+    /// Along with the parse tree, returns the ambiguities resolved while choosing the tree if
+    /// `ambiguous` is true.
+    fn parse_lex<Symbol: GrammarSymbol>(
+        &self,
+        lex: &[Token<Symbol>],
+        grammar: &dyn Grammar<Symbol>,
+        recover: bool,
+        ambiguous: bool,
+    ) -> Result<(Tree<Symbol>, Vec<Ambiguity<Symbol>>), parse::Error> {
         let mut chart: RChart<Symbol> = RChart::new();
         let mut parse_chart: PChart<Symbol> = PChart::new();
 
@@ -52,8 +109,9 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
             scan_full(
                 cursor,
                 final_required_token,
-                &lex,
+                lex,
                 grammar,
+                recover,
                 &mut chart,
                 &mut parse_chart,
             );
@@ -152,6 +210,7 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
             final_required_token: usize,
             lex: &[Token<Symbol>],
             grammar: &'grammar dyn Grammar<Symbol>,
+            recover: bool,
             chart: &'inner mut RChart<'grammar, Symbol>,
             parse_chart: &mut PChart<'grammar, Symbol>,
         ) {
@@ -181,7 +240,20 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
                     grammar,
                 )
             } else {
-                cross(chart.row(cursor).incomplete().items.iter(), symbol, grammar)
+                let mut next_row =
+                    cross(chart.row(cursor).incomplete().items.iter(), symbol, grammar);
+
+                if recover && !grammar.is_sync(symbol) {
+                    if let Some(error) = grammar.error_symbol() {
+                        next_row.append(&mut cross(
+                            chart.row(cursor).incomplete().items.iter(),
+                            error,
+                            grammar,
+                        ));
+                    }
+                }
+
+                next_row
             };
 
             if next_row.is_empty() {
@@ -231,10 +303,9 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
             let mut dest: Vec<Item<Symbol>> = Vec::new();
 
             for item in src {
-                if let Some(sym) = item.next_prod_symbol() {
-                    if sym.symbol == *symbol {
-                        advance_on_matching_symbol(item, sym, &mut dest, grammar);
-                    }
+                if item.next_symbol() == Some(symbol) {
+                    let sym = item.next_prod_symbol().unwrap();
+                    advance_on_matching_symbol(item, sym, symbol, &mut dest, grammar);
                 }
             }
 
@@ -252,9 +323,9 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
             let mut dest: Vec<Item<Symbol>> = Vec::new();
 
             for item in &src.incomplete.items {
-                let sym = item.next_prod_symbol().unwrap();
-                if sym.symbol == *symbol {
-                    advance_on_matching_symbol(item, sym, &mut dest, grammar);
+                if item.next_symbol() == Some(symbol) {
+                    let sym = item.next_prod_symbol().unwrap();
+                    advance_on_matching_symbol(item, sym, symbol, &mut dest, grammar);
                 } else {
                     advance_via_shadow(
                         item,
@@ -286,11 +357,14 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
         fn advance_on_matching_symbol<'inner, 'grammar: 'inner, Symbol: GrammarSymbol>(
             item: &'inner Item<'grammar, Symbol>,
             sym: &ProductionSymbol<Symbol>,
+            symbol: &Symbol,
             dest: &mut Vec<Item<'grammar, Symbol>>,
             grammar: &'grammar dyn Grammar<Symbol>,
         ) {
             if sym.is_list {
-                advance_list_via_shadow(item, &sym.symbol, dest, grammar);
+                // A separator can never end a list, so the item cannot advance past it.
+                let can_advance = sym.separator.as_ref() != Some(symbol);
+                advance_list_via_shadow(item, symbol, can_advance, dest, grammar);
             } else {
                 advance_past_symbol(item, dest, grammar);
             }
@@ -384,6 +458,7 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
         fn advance_list_via_shadow<'inner, 'grammar: 'inner, Symbol: GrammarSymbol>(
             item: &'inner Item<'grammar, Symbol>,
             symbol: &Symbol,
+            can_advance: bool,
             dest: &mut Vec<Item<'grammar, Symbol>>,
             grammar: &'grammar dyn Grammar<Symbol>,
         ) {
@@ -412,9 +487,16 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
                 weight: item.weight,
             };
 
+            if grammar.error_symbol() == Some(symbol) {
+                // Penalize each recovered token, so that the smallest recovery is preferred.
+                new_item.weight += 1;
+            }
+
             dest.push(new_item.clone());
-            new_item.advance();
-            advance_over_nullable_symbols(new_item, dest, grammar);
+            if can_advance {
+                new_item.advance();
+                advance_over_nullable_symbols(new_item, dest, grammar);
+            }
         }
 
         fn advance_over_nullable_symbols<'grammar, Symbol: GrammarSymbol>(
@@ -470,55 +552,163 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
                 .any(|item| item.rule.lhs == *grammar.start() && item.start == 0)
         }
 
+        /// Returns a parse error with message `message`, located at the token which could not be
+        /// scanned (or the end of the input if all tokens were scanned), and annotated with the
+        /// terminals and non-terminals which were expected by the final row of `chart`.
+        fn failure<Symbol: GrammarSymbol>(
+            message: String,
+            cursor: usize,
+            lex: &[Token<Symbol>],
+            grammar: &dyn Grammar<Symbol>,
+            chart: &RChart<Symbol>,
+        ) -> parse::Error {
+            let position = if cursor < lex.len() {
+                Some(lex[cursor].span().start)
+            } else {
+                lex.last().map(|token| token.span().end)
+            };
+
+            let mut expected: Vec<String> = Vec::new();
+            let mut in_progress: Vec<String> = Vec::new();
+            let mut predicted: Vec<String> = Vec::new();
+
+            for item in &chart.row(chart.len() - 1).incomplete().items {
+                let symbol = item.next_symbol().unwrap();
+                if grammar.is_non_terminal(symbol) || grammar.error_symbol() == Some(symbol) {
+                    continue;
+                }
+
+                expected.push(grammar.symbol_string(symbol));
+
+                let lhs = grammar.symbol_string(&item.rule.lhs);
+                if item.next > 0 {
+                    in_progress.push(lhs);
+                } else {
+                    predicted.push(lhs);
+                }
+            }
+
+            if in_progress.is_empty() {
+                in_progress = predicted;
+            }
+
+            expected.sort();
+            expected.dedup();
+            in_progress.sort();
+            in_progress.dedup();
+
+            parse::Error {
+                message,
+                position,
+                expected,
+                in_progress,
+            }
+        }
+
         return if recognized(grammar, &chart) {
             if cursor - 1 == lex.len() {
-                Ok(parse_tree(grammar, &lex, parse_chart))
+                Ok(parse_tree(grammar, lex, recover, ambiguous, parse_chart))
             } else {
-                Err(parse::Error {
-                    message: format!(
+                Err(failure(
+                    format!(
                         "Largest parse did not consume all tokens: {} of {}",
                         cursor - 1,
                         lex.len()
                     ),
-                })
+                    cursor - 1,
+                    lex,
+                    grammar,
+                    &chart,
+                ))
             }
         } else if lex.is_empty() {
-            Err(parse::Error {
-                message: "No symbols tokenized".to_string(),
-            })
+            Err(parse::Error::from("No symbols tokenized".to_string()))
         } else if cursor - 1 == lex.len() {
-            Err(parse::Error {
-                message: "Recognition failed after consuming all tokens".to_string(),
-            })
+            Err(failure(
+                "Recognition failed after consuming all tokens".to_string(),
+                cursor - 1,
+                lex,
+                grammar,
+                &chart,
+            ))
         } else {
             let token = &lex[cursor - 1];
-            Err(parse::Error {
-                message: format!(
+            Err(failure(
+                format!(
                     "Recognition failed at token {}: {} <- '{}'",
                     cursor,
                     grammar.symbol_string(token.kind()),
                     token.lexeme_escaped(),
                 ),
-            })
+                cursor - 1,
+                lex,
+                grammar,
+                &chart,
+            ))
         };
 
         fn parse_tree<'scope, Symbol: GrammarSymbol>(
             grammar: &'scope dyn Grammar<Symbol>,
             lex: &'scope [Token<Symbol>],
+            recover: bool,
+            ambiguous: bool,
             chart: PChart<'scope, Symbol>,
-        ) -> Tree<Symbol> {
-            let tree = if grammar.weighted_parse() {
-                parse_bottom_up(grammar, lex, chart)
+        ) -> (Tree<Symbol>, Vec<Ambiguity<Symbol>>) {
+            let mut ambiguities: Vec<Ambiguity<Symbol>> = Vec::new();
+
+            // Ambiguities are only found on request, since doing so slows down parsing.
+            let collected = if ambiguous {
+                Some(&mut ambiguities)
+            } else {
+                None
+            };
+
+            // Recovered parses are always weighted, to minimize the number of recovered tokens.
+            let tree = if grammar.weighted_parse() || recover {
+                parse_bottom_up(grammar, lex, chart, collected)
             } else {
-                parse_top_down(grammar, lex, chart)
+                parse_top_down(grammar, lex, chart, collected)
             };
 
-            push_down_inline_lists(tree)
+            (push_down_inline_lists(tree, grammar), ambiguities)
+        }
+
+        fn recovered_spm<Symbol: GrammarSymbol>(
+            symbol: &Symbol,
+            spm: SymbolParseMethod,
+            grammar: &dyn Grammar<Symbol>,
+        ) -> SymbolParseMethod {
+            if grammar.error_symbol() == Some(symbol) {
+                SymbolParseMethod::Recovered
+            } else {
+                spm
+            }
+        }
+
+        fn push_down_inline_lists<Symbol: GrammarSymbol>(
+            mut root: Tree<Symbol>,
+            grammar: &dyn Grammar<Symbol>,
+        ) -> Tree<Symbol> {
+            {
+                // Visit nodes iteratively, to avoid stack overflow on deep trees.
+                let mut stack: Vec<&mut Tree<Symbol>> = vec![&mut root];
+                while let Some(node) = stack.pop() {
+                    push_down_child_lists(node, grammar);
+                    stack.extend(node.children.iter_mut());
+                }
+            }
+
+            root
         }
 
-        fn push_down_inline_lists<Symbol: GrammarSymbol>(mut root: Tree<Symbol>) -> Tree<Symbol> {
+        /// Groups the runs of inline list elements among the children of `root` into list nodes,
+        /// without visiting any other descendants of `root`.
+        fn push_down_child_lists<Symbol: GrammarSymbol>(
+            root: &mut Tree<Symbol>,
+            grammar: &dyn Grammar<Symbol>,
+        ) {
             if root.is_leaf() {
-                return root;
+                return;
             }
 
             let mut children: Vec<Tree<Symbol>> = Vec::with_capacity(root.children.len());
@@ -526,13 +716,50 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
             while !root.children.is_empty() {
                 let child = &root.children[root.children.len() - 1];
 
-                if child.spm == SymbolParseMethod::Repeated && root.children.len() > 1 {
+                if child.spm == SymbolParseMethod::Recovered {
+                    // Always push down recovered tokens, so that each recovered region is a
+                    // single node labelled with the error symbol.
+                    let mut top = root.children.len() - 1;
+                    let mut end = top;
+
+                    while end > 0 {
+                        end -= 1;
+
+                        let other_child = &root.children[end];
+                        if other_child.spm == SymbolParseMethod::Recovered {
+                            top = end;
+                        } else if other_child.spm != SymbolParseMethod::Injected {
+                            break;
+                        }
+                    }
+
+                    let sub_children: Vec<Tree<Symbol>> = root
+                        .children
+                        .drain(top..)
+                        .map(|mut t| {
+                            t.spm = SymbolParseMethod::Standard;
+                            t
+                        })
+                        .collect();
+
+                    let span = covering_span(&sub_children).unwrap_or_default();
+                    let error = grammar.error_symbol().unwrap().clone();
+
+                    children.push(Tree {
+                        lhs: Token::interior(error).with_span(span),
+                        children: sub_children,
+                        production: None,
+                        spm: SymbolParseMethod::Recovered,
+                    });
+                } else if child.spm == SymbolParseMethod::Repeated && root.children.len() > 1 {
+                    let separator = list_partner(root, child.lhs.kind());
                     let mut top = root.children.len() - 1;
                     let mut end = top - 1;
 
                     loop {
                         let other_child = &root.children[end];
-                        if *other_child.lhs.kind() == *child.lhs.kind()
+                        if (*other_child.lhs.kind() == *child.lhs.kind()
+                            || separator.as_ref() == Some(other_child.lhs.kind()))
                             && other_child.spm == SymbolParseMethod::Repeated
                         {
                             top = end;
@@ -549,41 +776,59 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
 
                     if top == root.children.len() - 1 {
                         // Do not push down single nodes
-                        children.push(push_down_inline_lists(root.children.pop().unwrap()))
+                        children.push(root.children.pop().unwrap())
                     } else {
-                        let sub_children = root
+                        let sub_children: Vec<Tree<Symbol>> = root
                             .children
                             .drain(top..)
                             .map(|mut t| {
                                 t.spm = SymbolParseMethod::Standard;
-                                push_down_inline_lists(t)
+                                t
                             })
                             .collect();
 
+                        let span = covering_span(&sub_children).unwrap_or_default();
+
                         children.push(Tree {
-                            lhs: Token::null(),
+                            lhs: Token::null().with_span(span),
                             children: sub_children,
                             production: None,
                             spm: SymbolParseMethod::Repeated,
                         });
                     }
                 } else {
-                    children.push(push_down_inline_lists(root.children.pop().unwrap()))
+                    children.push(root.children.pop().unwrap())
                 }
             }
 
             children.reverse();
             root.children = children;
-            root
+        }
+
+        /// Returns the symbol which alternates with `symbol` in a separated inline list of the
+        /// production of `node`, i.e. the separator of a list of `symbol`s, or the elements of a
+        /// list separated by `symbol`s.
+        fn list_partner<Symbol: GrammarSymbol>(
+            node: &Tree<Symbol>,
+            symbol: &Symbol,
+        ) -> Option<Symbol> {
+            node.production.as_ref().and_then(|production| {
+                production.rhs.iter().find_map(|sym| match sym.separator {
+                    Some(ref separator) if sym.symbol == *symbol => Some(separator.clone()),
+                    Some(ref separator) if *separator == *symbol => Some(sym.symbol.clone()),
+                    _ => None,
+                })
+            })
         }
 
         fn parse_bottom_up<'scope, Symbol: GrammarSymbol>(
             grammar: &'scope dyn Grammar<Symbol>,
             lex: &'scope [Token<Symbol>],
             chart: PChart<'scope, Symbol>,
+            mut ambiguities: Option<&mut Vec<Ambiguity<Symbol>>>,
         ) -> Tree<Symbol> {
-            let mut weight_map: HashMap<&Edge<Symbol>, usize> = HashMap::new();
-            let mut nlp_map: HashMap<&Edge<Symbol>, ParsePath<Symbol>> = HashMap::new();
+            let mut weight_map: HashMap<&Edge<Symbol>, Weight> = HashMap::new();
+            let mut nlp_map: HashMap<&Edge<Symbol>, WeightedParsePath<Symbol>> = HashMap::new();
 
             let mut ordered_edges: Vec<&Edge<Symbol>> = Vec::new();
             for row in 0..chart.len() {
@@ -598,7 +843,7 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
                     })
             }
 
-            ordered_edges.sort_unstable_by(|ref e1, ref e2| {
+            ordered_edges.sort_unstable_by(|e1, e2| {
                 // Order by increasing width, then decreasing depth
                 match (e1.finish - e1.start).partial_cmp(&(e2.finish - e2.start)) {
                     Some(Ordering::Equal) => e2.depth.partial_cmp(&e1.depth),
@@ -607,11 +852,19 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
                 .unwrap()
             });
 
+            let index = index_by_finish(&chart);
+
             for edge in &ordered_edges {
-                let weighted_path = optimal_next_level_path(&edge, &weight_map, grammar, &chart);
+                let weighted_path = match decompose(edge, Some(&weight_map), grammar, lex, &index) {
+                    None => panic!("Failed to decompose parse edge of recognized lex"),
+                    Some(mut path) => {
+                        path.weight.injections += edge.weight;
+                        path
+                    }
+                };
 
                 weight_map.insert(edge, weighted_path.weight);
-                nlp_map.insert(edge, weighted_path.path);
+                nlp_map.insert(edge, weighted_path);
             }
 
             let mut best_root_edge: &Edge<Symbol> = if ordered_edges.is_empty() {
@@ -625,9 +878,10 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
                 ordered_edges.last().unwrap()
             };
 
-            let mut best_root_weight = *weight_map
-                .get(best_root_edge)
-                .unwrap_or(&best_root_edge.weight);
+            let mut best_root_weight = (
+                edge_weight(best_root_edge, &weight_map),
+                Reverse(edge_priority(best_root_edge, grammar)),
+            );
 
             let finish: Node = chart.len() - 1;
 
@@ -636,7 +890,10 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
                     break;
                 }
 
-                let minimal_tree_weight = *weight_map.get(edge).unwrap_or(&edge.weight);
+                let minimal_tree_weight = (
+                    edge_weight(edge, &weight_map),
+                    Reverse(edge_priority(edge, grammar)),
+                );
 
                 if minimal_tree_weight < best_root_weight {
                     best_root_edge = edge;
@@ -644,422 +901,528 @@ impl<Symbol: GrammarSymbol> Parser<Symbol> for EarleyParser {
                 }
             }
 
-            fn link_shallow_paths<'scope, Symbol: GrammarSymbol>(
-                edge: &Edge<Symbol>,
-                spm: SymbolParseMethod,
-                grammar: &'scope dyn Grammar<Symbol>,
-                lex: &'scope [Token<Symbol>],
-                nlp_map: &HashMap<&Edge<Symbol>, ParsePath<Symbol>>,
-            ) -> Tree<Symbol> {
-                match edge.rule {
-                    None => Tree {
-                        lhs: lex[edge.start].clone(),
-                        children: Vec::new(),
-                        production: None,
-                        spm,
-                    },
-                    Some(rule) => Tree {
-                        lhs: Token::interior(rule.lhs.clone()),
-                        children: {
-                            if edge.is_empty() {
-                                vec![Tree::null()]
-                            } else if edge.is_terminal(grammar) {
-                                vec![Tree {
-                                    lhs: lex[edge.start].clone(),
-                                    children: Vec::new(),
-                                    production: None,
-                                    spm: spm.clone(),
-                                }]
-                            } else {
-                                let path = nlp_map.get(edge).unwrap();
-                                let edges = path.len();
-                                path.iter()
-                                    .enumerate()
-                                    .filter(|(_, ref inner_edge)| !inner_edge.ignored)
-                                    .rev()
-                                    .map(|(i, ref inner_edge)| {
-                                        let (_, spm, _) = edge.symbol_at(edges - i - 1);
-                                        link_shallow_paths(inner_edge, spm, grammar, lex, nlp_map)
-                                    })
-                                    .collect()
-                            }
-                        },
-                        production: match edge.rule {
-                            Some(production) => Some(production.clone()),
-                            None => None,
-                        },
-                        spm,
-                    },
+            build_tree(best_root_edge, lex, |edge, spm| {
+                let mut record = |split: bool| {
+                    if let Some(ref mut ambiguities) = ambiguities {
+                        ambiguities.extend(edge_ambiguity(
+                            edge,
+                            split,
+                            grammar,
+                            Some(&weight_map),
+                            &index,
+                            lex,
+                        ));
+                    }
+                };
+
+                if edge.is_empty() {
+                    record(false);
+                    Vec::new()
+                } else if edge.is_terminal(grammar) {
+                    record(false);
+                    vec![(
+                        Cow::Owned(Edge::terminal(edge.start, spm.clone())),
+                        spm.clone(),
+                    )]
+                } else {
+                    let path = nlp_map.get(edge).unwrap();
+                    record(path.split);
+
+                    let edges = path.path.len();
+                    path.path
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, inner_edge)| !inner_edge.ignored)
+                        .rev()
+                        .map(|(i, inner_edge)| {
+                            let (symbol, spm, _) = edge.symbol_at(edges - i - 1);
+                            (
+                                Cow::Borrowed(inner_edge),
+                                recovered_spm(symbol, spm, grammar),
+                            )
+                        })
+                        .collect()
                 }
+            })
+        }
+
+        fn parse_top_down<'scope, Symbol: GrammarSymbol>(
+            grammar: &'scope dyn Grammar<Symbol>,
+            lex: &'scope [Token<Symbol>],
+            chart: PChart<'scope, Symbol>,
+            mut ambiguities: Option<&mut Vec<Ambiguity<Symbol>>>,
+        ) -> Tree<Symbol> {
+            let finish: Node = chart.len() - 1;
+
+            // Prefer the root edge of highest priority, where ties are broken by chart order.
+            let root_edge = chart
+                .row(0)
+                .edges
+                .iter()
+                .filter(|edge| edge.finish == finish && edge.rule.unwrap().lhs == *grammar.start())
+                .fold(None, |best: Option<&Edge<Symbol>>, edge| match best {
+                    Some(best) if edge_priority(best, grammar) >= edge_priority(edge, grammar) => {
+                        Some(best)
+                    }
+                    _ => Some(edge),
+                });
+
+            let index = index_by_finish(&chart);
+
+            match root_edge {
+                None => panic!("Failed to find start item to begin parse"),
+                Some(edge) => build_tree(edge, lex, |edge, _| {
+                    let path = decompose(edge, None, grammar, lex, &index)
+                        .expect("Failed to decompose parse edge of recognized lex");
+                    if let Some(ref mut ambiguities) = ambiguities {
+                        ambiguities
+                            .extend(edge_ambiguity(edge, path.split, grammar, None, &index, lex));
+                    }
+
+                    let path = path.path;
+                    let edges = path.len();
+                    path.into_iter()
+                        .enumerate()
+                        .rev()
+                        .map(|(i, inner_edge)| {
+                            let (symbol, spm, _) = edge.symbol_at(edges - i - 1);
+                            (Cow::Owned(inner_edge), recovered_spm(symbol, spm, grammar))
+                        })
+                        .collect()
+                }),
             }
+        }
 
-            fn optimal_next_level_path<'scope, Symbol: GrammarSymbol>(
-                edge: &Edge<'scope, Symbol>,
-                weight_map: &HashMap<&'scope Edge<Symbol>, usize>,
-                grammar: &'scope dyn Grammar<Symbol>,
-                chart: &'scope PChart<'scope, Symbol>,
-            ) -> WeightedParsePath<'scope, Symbol> {
-                fn df_search<'scope, Symbol: GrammarSymbol>(
-                    depth: usize,
-                    root: Node,
-                    bottom: usize,
-                    root_edge: &Edge<'scope, Symbol>,
-                    weight_map: &HashMap<&'scope Edge<Symbol>, usize>,
-                    grammar: &'scope dyn Grammar<Symbol>,
-                    chart: &'scope PChart<'scope, Symbol>,
-                ) -> Option<WeightedParsePath<'scope, Symbol>> {
-                    if depth == bottom {
-                        if root == root_edge.finish {
-                            Some(WeightedParsePath::empty())
+        /// Returns the parse tree of the parse edge `root`, where `children_of` returns the child
+        /// edges of an interior edge (along with their parse methods), given its own parse method.
+        ///
+        /// The tree is built iteratively, to avoid stack overflow on deep trees.
+        fn build_tree<'edge, 'scope: 'edge, Symbol: GrammarSymbol>(
+            root: &'edge Edge<'scope, Symbol>,
+            lex: &[Token<Symbol>],
+            mut children_of: impl FnMut(
+                &Edge<'scope, Symbol>,
+                &SymbolParseMethod,
+            )
+                -> Vec<(Cow<'edge, Edge<'scope, Symbol>>, SymbolParseMethod)>,
+        ) -> Tree<Symbol> {
+            struct Frame<'edge, 'scope: 'edge, Symbol: GrammarSymbol + 'scope> {
+                edge: Cow<'edge, Edge<'scope, Symbol>>,
+                spm: SymbolParseMethod,
+                pending: Vec<(Cow<'edge, Edge<'scope, Symbol>>, SymbolParseMethod)>,
+                children: Vec<Tree<Symbol>>,
+            }
+
+            let leaf = |edge: &Edge<Symbol>, spm: SymbolParseMethod| Tree {
+                lhs: lex[edge.start].clone(),
+                children: Vec::new(),
+                production: None,
+                spm,
+            };
+
+            if root.rule.is_none() {
+                return leaf(root, SymbolParseMethod::Standard);
+            }
+
+            let mut pending = children_of(root, &SymbolParseMethod::Standard);
+            pending.reverse();
+
+            let mut stack: Vec<Frame<Symbol>> = vec![Frame {
+                edge: Cow::Borrowed(root),
+                spm: SymbolParseMethod::Standard,
+                pending,
+                children: Vec::new(),
+            }];
+
+            loop {
+                let next = stack.last_mut().unwrap().pending.pop();
+
+                match next {
+                    Some((edge, spm)) => {
+                        if edge.rule.is_none() {
+                            let tree = leaf(&edge, spm);
+                            stack.last_mut().unwrap().children.push(tree);
                         } else {
-                            None
+                            let mut pending = children_of(&edge, &spm);
+                            pending.reverse();
+                            let children = Vec::with_capacity(pending.len());
+
+                            stack.push(Frame {
+                                edge,
+                                spm,
+                                pending,
+                                children,
+                            });
                         }
-                    } else {
-                        let (symbol, spm, reps) = root_edge.symbol_at(depth);
-
-                        if reps > 1 {
-                            // For repeated symbols, perform the DFS iteratively to avoid stack
-                            // overflow on long lists.
-                            if !grammar.is_non_terminal(symbol) {
-                                let mut edge = Edge::terminal(root + reps, spm);
-
-                                if let Some(mut path) = df_search(
-                                    depth + reps,
-                                    root + reps,
-                                    bottom,
-                                    root_edge,
-                                    weight_map,
-                                    grammar,
-                                    chart,
-                                ) {
-                                    for _ in 0..reps {
-                                        edge.start -= 1;
-                                        edge.finish -= 1;
-                                        path.append(edge.clone(), 0);
-                                    }
-                                    return Some(path);
-                                }
-                            } else if root < chart.len() {
-                                let mut work_stack: LinkedList<(
-                                    usize,
-                                    usize,
-                                    Option<&Edge<Symbol>>,
-                                )> = LinkedList::new();
-                                let mut edge_stack: Vec<&Edge<Symbol>> = Vec::with_capacity(reps);
-
-                                work_stack.push_front((0, root, None));
-
-                                let mut best_path: Option<WeightedParsePath<'scope, Symbol>> = None;
-
-                                while !work_stack.is_empty() {
-                                    let (count, finish, edge_opt) = work_stack.pop_front().unwrap();
-                                    if count <= edge_stack.len() && count > 0 {
-                                        edge_stack.drain(count - 1..);
-                                    }
-                                    if let Some(edge) = edge_opt {
-                                        edge_stack.push(edge);
-                                    }
-
-                                    if count == reps {
-                                        if let Some(mut path) = df_search(
-                                            depth + reps,
-                                            finish,
-                                            bottom,
-                                            root_edge,
-                                            weight_map,
-                                            grammar,
-                                            chart,
-                                        ) {
-                                            let tree_list_weight: usize = edge_stack
-                                                .iter()
-                                                .map(|edge| {
-                                                    *weight_map.get(edge).unwrap_or(&edge.weight)
-                                                })
-                                                .sum();
-
-                                            if best_path.is_none()
-                                                || path.weight + tree_list_weight
-                                                    < best_path.as_ref().unwrap().weight
-                                            {
-                                                edge_stack.reverse();
-                                                for edge in &edge_stack {
-                                                    path.append((*edge).clone(), 0)
-                                                }
-                                                edge_stack.clear();
-                                                path.weight += tree_list_weight;
-                                                best_path = Some(path);
-                                            }
-                                        }
-                                    } else {
-                                        for edge in &chart.row(finish).edges {
-                                            if edge.rule.unwrap().lhs == *symbol {
-                                                work_stack.push_front((
-                                                    count + 1,
-                                                    edge.finish,
-                                                    Some(edge),
-                                                ));
-                                            }
-                                        }
-                                    }
-                                }
-
-                                return best_path;
-                            }
-                        } else if !grammar.is_non_terminal(symbol) {
-                            let edge = Edge::terminal(root, spm);
-
-                            if let Some(mut path) = df_search(
-                                depth + 1,
-                                edge.finish,
-                                bottom,
-                                root_edge,
-                                weight_map,
-                                grammar,
-                                chart,
-                            ) {
-                                path.append(edge, 0);
-                                return Some(path);
-                            }
-                        } else if root < chart.len() {
-                            let mut best_path: Option<WeightedParsePath<'scope, Symbol>> = None;
-
-                            chart
-                                .row(root)
-                                .edges
-                                .iter()
-                                .filter(|edge| edge.rule.unwrap().lhs == *symbol)
-                                .for_each(|edge| {
-                                    if let Some(mut path) = df_search(
-                                        depth + 1,
-                                        edge.finish,
-                                        bottom,
-                                        root_edge,
-                                        weight_map,
-                                        grammar,
-                                        chart,
-                                    ) {
-                                        let tree_weight =
-                                            *weight_map.get(&edge).unwrap_or(&edge.weight);
-
-                                        if best_path.is_none()
-                                            || path.weight + tree_weight
-                                                < best_path.as_ref().unwrap().weight
-                                        {
-                                            path.append(edge.clone(), tree_weight);
-                                            best_path = Some(path);
-                                        }
-                                    }
-                                });
-
-                            return best_path;
+                    }
+                    None => {
+                        let frame = stack.pop().unwrap();
+                        let rule = frame.edge.rule.unwrap();
+
+                        let mut children = frame.children;
+                        if children.is_empty() {
+                            //Empty rhs
+                            children.push(Tree::null());
                         }
 
-                        None
-                    }
-                }
+                        let span = interior_span(&mut children, frame.edge.start, lex);
 
-                let bottom = edge.symbols_len();
-                match df_search(0, edge.start, bottom, edge, weight_map, grammar, chart) {
-                    None => panic!("Failed to decompose parse edge of recognized lex"),
-                    Some(mut path) => {
-                        path.weight += edge.weight;
-                        path
+                        let tree = Tree {
+                            lhs: Token::interior(rule.lhs.clone()).with_span(span),
+                            children,
+                            production: Some(rule.clone()),
+                            spm: frame.spm,
+                        };
+
+                        match stack.last_mut() {
+                            Some(parent) => parent.children.push(tree),
+                            None => return tree,
+                        }
                     }
                 }
             }
+        }
 
-            link_shallow_paths(
-                best_root_edge,
-                SymbolParseMethod::Standard,
-                grammar,
-                lex,
-                &nlp_map,
-            )
+        /// Returns the non-empty edges of `chart`, indexed by their finish node and left-hand
+        /// side symbol, along with their index in the row of their start node.
+        fn index_by_finish<'scope, Symbol: GrammarSymbol>(
+            chart: &'scope PChart<'scope, Symbol>,
+        ) -> FinishIndex<'scope, Symbol> {
+            let mut index: FinishIndex<Symbol> = HashMap::new();
+            for row in 0..chart.len() {
+                for (i, edge) in chart.row(row).edges.iter().enumerate() {
+                    index
+                        .entry((edge.finish, &edge.rule.unwrap().lhs))
+                        .or_default()
+                        .push((i, edge));
+                }
+            }
+            index
         }
 
-        fn parse_top_down<'scope, Symbol: GrammarSymbol>(
+        /// Returns a path of edges which decomposes `root_edge` into an edge for each of its
+        /// symbols, in reverse order, or `None` if no such path exists.
+        ///
+        /// If `weight_map` is passed, the path with the minimum total weight (given the weights of
+        /// the edges in the map) is returned, otherwise the first path found is returned. Paths
+        /// are ordered by the chart order of their edges, where repeated non-terminal symbols are
+        /// ordered in reverse. The returned path is marked as split if another path divides the
+        /// tokens of `root_edge` between its symbols differently.
+        ///
+        /// The search works backwards from the finish of `root_edge`, one symbol at a time, so
+        /// only nodes from which the remaining symbols can be parsed are considered. This avoids
+        /// both deep recursion and scanning whole chart rows on left-recursive productions.
+        fn decompose<'scope, Symbol: GrammarSymbol>(
+            root_edge: &Edge<'scope, Symbol>,
+            weight_map: Option<&HashMap<&'scope Edge<Symbol>, Weight>>,
             grammar: &'scope dyn Grammar<Symbol>,
-            lex: &'scope [Token<Symbol>],
-            chart: PChart<'scope, Symbol>,
-        ) -> Tree<Symbol> {
-            fn recur<'scope, Symbol: GrammarSymbol>(
-                edge: &Edge<Symbol>,
-                spm: SymbolParseMethod,
-                grammar: &'scope dyn Grammar<Symbol>,
-                lex: &'scope [Token<Symbol>],
-                chart: &PChart<Symbol>,
-            ) -> Tree<Symbol> {
-                match edge.rule {
-                    None => Tree {
-                        //Non-empty rhs
-                        lhs: lex[edge.start].clone(),
-                        children: Vec::new(),
-                        production: None,
-                        spm,
-                    },
-                    Some(rule) => Tree {
-                        lhs: Token::interior(rule.lhs.clone()),
-                        children: {
-                            let path = top_list(edge, grammar, chart);
-                            let edges = path.len();
-                            let mut children: Vec<Tree<Symbol>> = path
-                                .into_iter()
-                                .enumerate()
-                                .rev()
-                                .map(|(i, ref inner_edge)| {
-                                    let (_, spm, _) = edge.symbol_at(edges - i - 1);
-                                    recur(&inner_edge, spm, grammar, lex, chart)
-                                })
-                                .collect();
-                            if children.is_empty() {
-                                //Empty rhs
-                                children.push(Tree::null());
-                            }
-                            children
-                        },
-                        production: match edge.rule {
-                            Some(production) => Some(production.clone()),
-                            None => None,
-                        },
-                        spm,
-                    },
-                }
+            lex: &[Token<Symbol>],
+            index: &FinishIndex<'scope, Symbol>,
+        ) -> Option<WeightedParsePath<'scope, Symbol>> {
+            let bottom = root_edge.symbols_len();
+            let start = root_edge.start;
+            let finish = root_edge.finish;
+
+            if bottom == 0 {
+                return if start == finish {
+                    Some(WeightedParsePath::empty())
+                } else {
+                    None
+                };
             }
 
-            fn top_list<'scope, Symbol: GrammarSymbol>(
-                edge: &'scope Edge<Symbol>,
-                grammar: &'scope dyn Grammar<Symbol>,
-                chart: &'scope PChart<'scope, Symbol>,
-            ) -> ParsePath<'scope, Symbol> {
-                fn df_search<'scope, Symbol: GrammarSymbol>(
-                    depth: usize,
-                    root: Node,
-                    bottom: usize,
-                    root_edge: &Edge<'scope, Symbol>,
-                    grammar: &'scope dyn Grammar<Symbol>,
-                    chart: &'scope PChart<'scope, Symbol>,
-                ) -> Option<ParsePath<'scope, Symbol>> {
-                    if depth == bottom {
-                        if root == root_edge.finish {
-                            Some(ParsePath::new())
-                        } else {
-                            None
+            // The best (edge, weight) choice from each node which can parse the symbols at and
+            // after each depth, with the deepest symbol first, and whether the choices from the
+            // node had different finishes.
+            let mut layers: Vec<HashMap<Node, (WeightedEdge<Symbol>, bool)>> =
+                Vec::with_capacity(bottom);
+
+            for depth in (0..bottom).rev() {
+                let (symbol, spm, reps) = root_edge.symbol_at(depth);
+
+                let rest: Vec<(Node, Weight)> = match layers.last() {
+                    None => vec![(finish, Weight::default())],
+                    Some(layer) => layer
+                        .iter()
+                        .map(|(node, &((_, weight), _))| (*node, weight))
+                        .collect(),
+                };
+
+                // The candidate choices from each node, along with their order in the chart.
+                let mut choices: HashMap<Node, Vec<(usize, WeightedEdge<Symbol>)>> = HashMap::new();
+
+                for (node, rest_weight) in rest {
+                    if !grammar.is_non_terminal(symbol) {
+                        let matches = |token: &Token<Symbol>| {
+                            token.kind() == symbol || grammar.error_symbol() == Some(symbol)
+                        };
+
+                        if node > start && matches(&lex[node - 1]) {
+                            choices.entry(node - 1).or_default().push((
+                                0,
+                                (
+                                    Cow::Owned(Edge::terminal(node - 1, spm.clone())),
+                                    rest_weight,
+                                ),
+                            ));
                         }
-                    } else {
-                        let (symbol, spm, reps) = root_edge.symbol_at(depth);
+                    } else if let Some(edges) = index.get(&(node, symbol)) {
+                        for &(i, edge) in edges.iter().filter(|(_, edge)| edge.start >= start) {
+                            let mut weight = match weight_map {
+                                Some(weight_map) => edge_weight(edge, weight_map),
+                                None => Weight::default(),
+                            };
+                            if violates_precedence(root_edge, depth, edge, grammar) {
+                                weight.violations += 1;
+                            }
+                            choices
+                                .entry(edge.start)
+                                .or_default()
+                                .push((i, (Cow::Borrowed(edge), weight + rest_weight)));
+                        }
+                    }
+                }
 
+                let layer = choices
+                    .into_iter()
+                    .filter(|(node, _)| depth > 0 || *node == start)
+                    .map(|(node, mut choices)| {
+                        choices.sort_by_key(|&(i, _)| i);
                         if reps > 1 {
-                            // For repeated symbols, perform the DFS iteratively to avoid stack
-                            // overflow on long lists.
-                            if !grammar.is_non_terminal(symbol) {
-                                let mut edge = Edge::terminal(root + reps, spm);
-
-                                if let Some(mut path) = df_search(
-                                    depth + reps,
-                                    root + reps,
-                                    bottom,
-                                    root_edge,
-                                    grammar,
-                                    chart,
-                                ) {
-                                    for _ in 0..reps {
-                                        edge.start -= 1;
-                                        edge.finish -= 1;
-                                        path.push(edge.clone());
-                                    }
-                                    return Some(path);
-                                }
-                            } else if root < chart.len() {
-                                let mut work_stack: LinkedList<(
-                                    usize,
-                                    usize,
-                                    Option<&Edge<Symbol>>,
-                                )> = LinkedList::new();
-                                let mut edge_stack: Vec<&Edge<Symbol>> = Vec::with_capacity(reps);
-
-                                work_stack.push_front((0, root, None));
-
-                                while !work_stack.is_empty() {
-                                    let (count, finish, edge_opt) = work_stack.pop_front().unwrap();
-                                    if count <= edge_stack.len() && count > 0 {
-                                        edge_stack.drain(count - 1..);
-                                    }
-                                    if let Some(edge) = edge_opt {
-                                        edge_stack.push(edge);
-                                    }
-
-                                    if count == reps {
-                                        if let Some(mut path) = df_search(
-                                            depth + reps,
-                                            finish,
-                                            bottom,
-                                            root_edge,
-                                            grammar,
-                                            chart,
-                                        ) {
-                                            edge_stack.reverse();
-                                            for edge in edge_stack {
-                                                path.push(edge.clone())
-                                            }
-                                            return Some(path);
-                                        }
-                                    } else {
-                                        for edge in &chart.row(finish).edges {
-                                            if edge.rule.unwrap().lhs == *symbol {
-                                                work_stack.push_front((
-                                                    count + 1,
-                                                    edge.finish,
-                                                    Some(edge),
-                                                ));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        } else if !grammar.is_non_terminal(symbol) {
-                            if let Some(mut path) =
-                                df_search(depth + 1, root + 1, bottom, root_edge, grammar, chart)
-                            {
-                                path.push(Edge::terminal(root, spm));
-                                return Some(path);
-                            }
-                        } else if root < chart.len() {
-                            for edge in &chart.row(root).edges {
-                                if edge.rule.unwrap().lhs == *symbol {
-                                    if let Some(mut path) = df_search(
-                                        depth + 1,
-                                        edge.finish,
-                                        bottom,
-                                        root_edge,
-                                        grammar,
-                                        chart,
-                                    ) {
-                                        path.push(edge.clone());
-                                        return Some(path);
-                                    }
-                                }
-                            }
+                            choices.reverse();
                         }
 
-                        None
+                        // Ties in weight are broken by the highest production priority, then by
+                        // the first choice in order.
+                        let best = choices
+                            .iter()
+                            .enumerate()
+                            .min_by_key(|(_, (_, (edge, weight)))| {
+                                (*weight, Reverse(edge_priority(edge, grammar)))
+                            })
+                            .map(|(best, _)| best)
+                            .unwrap();
+
+                        // Choices with more precedence violations are not competing parses.
+                        let (best_finish, best_weight) = {
+                            let (_, (ref edge, weight)) = choices[best];
+                            (edge.finish, weight)
+                        };
+                        let split = choices.iter().any(|(_, (edge, weight))| {
+                            edge.finish != best_finish
+                                && weight.violations <= best_weight.violations
+                        });
+
+                        let (_, choice) = choices.swap_remove(best);
+                        (node, (choice, split))
+                    })
+                    .collect();
+
+                layers.push(layer);
+            }
+
+            let mut path = WeightedParsePath::empty();
+            let mut node = start;
+
+            for mut layer in layers.into_iter().rev() {
+                let ((edge, weight), split) = layer.remove(&node)?;
+                if path.path.is_empty() {
+                    path.weight = weight;
+                }
+                path.split |= split;
+                node = edge.finish;
+                path.path.push(edge.into_owned());
+            }
+
+            path.path.reverse();
+            Some(path)
+        }
+
+        /// Returns the priority of the production of `edge` in `grammar`, or zero for terminal
+        /// edges.
+        fn edge_priority<Symbol: GrammarSymbol>(
+            edge: &Edge<Symbol>,
+            grammar: &dyn Grammar<Symbol>,
+        ) -> i32 {
+            match edge.rule {
+                Some(rule) => grammar.priority(rule),
+                None => 0,
+            }
+        }
+
+        /// Returns the minimum weight of the parse trees of `edge`, given the weights of the edges
+        /// in `weight_map`.
+        fn edge_weight<'scope, Symbol: GrammarSymbol>(
+            edge: &Edge<'scope, Symbol>,
+            weight_map: &HashMap<&'scope Edge<Symbol>, Weight>,
+        ) -> Weight {
+            match weight_map.get(edge) {
+                Some(weight) => *weight,
+                None => Weight {
+                    violations: 0,
+                    injections: edge.weight,
+                },
+            }
+        }
+
+        /// Returns true if deriving the symbol at `depth` of `parent` using `child` violates the
+        /// operator precedence of the production of `parent`.
+        ///
+        /// Only the outermost symbols of `parent` are constrained, and only if `child` is open
+        /// towards `parent` (e.g. a left-most `child` ending with a non-terminal), in which case
+        /// `child` must bind more tightly than `parent`, or equally tightly if the associativity
+        /// of `parent` groups from the side of `child`.
+        fn violates_precedence<Symbol: GrammarSymbol>(
+            parent: &Edge<Symbol>,
+            depth: usize,
+            child: &Edge<Symbol>,
+            grammar: &dyn Grammar<Symbol>,
+        ) -> bool {
+            let (parent_rule, child_rule) = match (parent.rule, child.rule) {
+                (Some(parent_rule), Some(child_rule)) => (parent_rule, child_rule),
+                _ => return false,
+            };
+
+            let outer = match grammar.precedence(parent_rule) {
+                Some(precedence) => precedence,
+                None => return false,
+            };
+            let inner = match grammar.precedence(child_rule) {
+                Some(precedence) => precedence,
+                None => return false,
+            };
+
+            let open = |sym: Option<&ProductionSymbol<Symbol>>| match sym {
+                Some(sym) => grammar.is_non_terminal(&sym.symbol),
+                None => false,
+            };
+
+            let bottom = parent.symbols_len();
+            let grouping = if bottom < 2 {
+                return false;
+            } else if depth == 0 && open(child_rule.rhs.last()) {
+                Associativity::Left
+            } else if depth == bottom - 1 && open(child_rule.rhs.first()) {
+                Associativity::Right
+            } else {
+                return false;
+            };
+
+            inner.level < outer.level
+                || (inner.level == outer.level && outer.associativity != grouping)
+        }
+
+        /// Returns the ambiguity resolved by choosing `edge` to derive its tokens of `lex`, or
+        /// `None` if the tokens have only one derivation from the left-hand side of `edge`.
+        ///
+        /// If `split` is true, the production of `edge` derives the tokens in more than one way.
+        /// If `weight_map` is passed, derivations with more precedence violations than `edge` are
+        /// not considered to compete with it. Ambiguities resolved by the production of `edge`
+        /// having a strictly higher priority than every competing production are not reported.
+        fn edge_ambiguity<'scope, Symbol: GrammarSymbol>(
+            edge: &Edge<'scope, Symbol>,
+            split: bool,
+            grammar: &dyn Grammar<Symbol>,
+            weight_map: Option<&HashMap<&'scope Edge<Symbol>, Weight>>,
+            index: &FinishIndex<'scope, Symbol>,
+            lex: &[Token<Symbol>],
+        ) -> Option<Ambiguity<Symbol>> {
+            let chosen = edge.rule.unwrap();
+
+            // Derivations with more precedence violations than the chosen one do not compete.
+            let violations = |edge: &Edge<'scope, Symbol>| match weight_map {
+                Some(weight_map) => edge_weight(edge, weight_map).violations,
+                None => 0,
+            };
+            let chosen_violations = violations(edge);
+
+            let mut productions: Vec<&Production<Symbol>> = vec![chosen];
+            if let Some(edges) = index.get(&(edge.finish, &chosen.lhs)) {
+                for &(_, other) in edges.iter().filter(|(_, other)| {
+                    other.start == edge.start && violations(other) <= chosen_violations
+                }) {
+                    let rule = other.rule.unwrap();
+                    if !productions.contains(&rule) {
+                        productions.push(rule);
                     }
                 }
+            }
 
-                let bottom = edge.symbols_len();
-                df_search(0, edge.start, bottom, edge, grammar, chart)
-                    .expect("Failed to decompose parse edge of recognized lex")
+            if productions.len() == 1 && !split {
+                return None;
             }
 
-            let finish: Node = chart.len() - 1;
+            let priority = grammar.priority(chosen);
+            if !split
+                && productions[1..]
+                    .iter()
+                    .all(|other| grammar.priority(other) < priority)
+            {
+                return None;
+            }
 
-            let root_edge =
-                chart.row(0).edges.iter().find(|edge| {
-                    edge.finish == finish && edge.rule.unwrap().lhs == *grammar.start()
-                });
+            let span = lex[edge.start..edge.finish]
+                .iter()
+                .map(|token| *token.span())
+                .filter(|span| !span.is_empty())
+                .fold(None, |span: Option<Span>, other| match span {
+                    None => Some(other),
+                    Some(span) => Some(span.cover(&other)),
+                })
+                .unwrap_or_else(|| empty_span_at(edge.start, lex));
 
-            match root_edge {
-                None => panic!("Failed to find start item to begin parse"),
-                Some(edge) => recur(edge, SymbolParseMethod::Standard, grammar, lex, &chart),
+            Some(Ambiguity {
+                span,
+                productions: productions.into_iter().cloned().collect(),
+                chosen: chosen.clone(),
+            })
+        }
+
+        /// Returns the smallest span covering all non-empty trees in `children`, or `None` if no
+        /// such trees exist.
+        fn covering_span<Symbol: GrammarSymbol>(children: &[Tree<Symbol>]) -> Option<Span> {
+            children
+                .iter()
+                .filter(|child| !child.is_null() && !child.span().is_empty())
+                .fold(None, |span: Option<Span>, child| match span {
+                    None => Some(*child.span()),
+                    Some(span) => Some(span.cover(child.span())),
+                })
+        }
+
+        /// Returns the span of an interior tree node with `children`, whose parse edge starts at
+        /// token index `start` in `lex`.
+        ///
+        /// If no children cover any input, the span is empty and located at the start of the
+        /// edge, in which case any null children are assigned the same empty span.
+        fn interior_span<Symbol: GrammarSymbol>(
+            children: &mut [Tree<Symbol>],
+            start: usize,
+            lex: &[Token<Symbol>],
+        ) -> Span {
+            if let Some(span) = covering_span(children) {
+                return span;
+            }
+
+            let span = empty_span_at(start, lex);
+
+            for child in children.iter_mut().filter(|child| child.is_null()) {
+                child.lhs = Token::null().with_span(span);
+            }
+
+            span
+        }
+
+        /// Returns the empty span located at the start of token index `start` in `lex`, or at the
+        /// end of the input if `start` is past the last token.
+        fn empty_span_at<Symbol: GrammarSymbol>(start: usize, lex: &[Token<Symbol>]) -> Span {
+            if start < lex.len() {
+                Span::empty_at(lex[start].span().start)
+            } else {
+                match lex.last() {
+                    Some(token) => Span::empty_at(token.span().end),
+                    None => Span::default(),
+                }
             }
         }
     }
@@ -1183,7 +1546,9 @@ impl<'item, Symbol: GrammarSymbol + 'item> Items<'item, Symbol> {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+// Item equality is the same as the derived equality, so is consistent with the derived hash.
+#[allow(clippy::derived_hash_with_manual_eq)]
+#[derive(Eq, Hash, Clone, Debug)]
 struct Item<'rule, Symbol: GrammarSymbol + 'rule> {
     rule: &'rule Production<Symbol>,
     shadow: Option<Vec<ShadowSymbol<Symbol>>>,
@@ -1195,6 +1560,21 @@ struct Item<'rule, Symbol: GrammarSymbol + 'rule> {
     weight: usize,
 }
 
+impl<'rule, Symbol: GrammarSymbol + 'rule> PartialEq for Item<'rule, Symbol> {
+    fn eq(&self, other: &Self) -> bool {
+        // Chart rows are searched linearly for duplicate items, so the cheapest fields are
+        // compared first, and rules are only compared by value if they are distinct productions.
+        self.start == other.start
+            && self.next == other.next
+            && self.shadow_top == other.shadow_top
+            && self.depth == other.depth
+            && self.ignore_next == other.ignore_next
+            && self.weight == other.weight
+            && (ptr::eq(self.rule, other.rule) || self.rule == other.rule)
+            && self.shadow == other.shadow
+    }
+}
+
 impl<'rule, Symbol: GrammarSymbol + 'rule> Item<'rule, Symbol> {
     fn start(rule: &'rule Production<Symbol>) -> Self {
         Self {
@@ -1229,7 +1609,13 @@ impl<'rule, Symbol: GrammarSymbol + 'rule> Item<'rule, Symbol> {
 
     fn next_symbol<'scope>(&'scope self) -> Option<&'rule Symbol> {
         if self.next < self.rule.rhs.len() {
-            Some(&self.rule.rhs[self.next].symbol)
+            let sym = &self.rule.rhs[self.next];
+            match sym.separator {
+                // Separated list elements must be followed by a separator before the list can
+                // be extended.
+                Some(ref separator) if self.can_separate_list(&sym.symbol) => Some(separator),
+                _ => Some(&sym.symbol),
+            }
         } else {
             None
         }
@@ -1284,6 +1670,25 @@ impl<'rule, Symbol: GrammarSymbol + 'rule> Item<'rule, Symbol> {
 
         false
     }
+
+    fn can_separate_list(&self, symbol: &Symbol) -> bool {
+        if self.shadow_top >= self.next && self.shadow.is_some() {
+            if let Some(shadow_vec) = &self.shadow {
+                // Ignored and injected symbols may lie between a list element and its separator.
+                let previous = shadow_vec.iter().rev().find(|shadow_symbol| {
+                    shadow_symbol.spm != SymbolParseMethod::Ignored
+                        && shadow_symbol.spm != SymbolParseMethod::Injected
+                });
+                if let Some(previous) = previous {
+                    if previous.symbol == *symbol && previous.spm == SymbolParseMethod::Repeated {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        false
+    }
 }
 
 impl<'rule, Symbol: GrammarSymbol> Data for Item<'rule, Symbol> {
@@ -1505,21 +1910,49 @@ struct ShadowSymbol<Symbol: GrammarSymbol> {
 type Node = usize;
 type ParsePath<'rule, Symbol> = Vec<Edge<'rule, Symbol>>;
 
+type WeightedEdge<'rule, Symbol> = (Cow<'rule, Edge<'rule, Symbol>>, Weight);
+
+type FinishIndex<'rule, Symbol> =
+    HashMap<(Node, &'rule Symbol), Vec<(usize, &'rule Edge<'rule, Symbol>)>>;
+
+/// Weight: The cost of a parse tree, where parse trees of lower weight are preferred.
+///
+/// Weights are ordered by their fields in declaration order, so that operator precedence takes
+/// priority over the placement of injections.
+///
+/// # Fields
+///
+/// * `violations` - the number of operator precedence violations in the parse tree.
+/// * `injections` - the number of injected symbols in the parse tree.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Debug)]
+struct Weight {
+    violations: usize,
+    injections: usize,
+}
+
+impl Add for Weight {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            violations: self.violations + other.violations,
+            injections: self.injections + other.injections,
+        }
+    }
+}
+
 struct WeightedParsePath<'rule, Symbol: GrammarSymbol> {
     path: ParsePath<'rule, Symbol>,
-    weight: usize,
+    weight: Weight,
+    split: bool,
 }
 
 impl<'rule, Symbol: GrammarSymbol> WeightedParsePath<'rule, Symbol> {
     fn empty() -> Self {
         Self {
             path: Vec::new(),
-            weight: 0,
+            weight: Weight::default(),
+            split: false,
         }
     }
-
-    fn append(&mut self, edge: Edge<'rule, Symbol>, weight: usize) {
-        self.weight += weight;
-        self.path.push(edge);
-    }
 }
@@ -3,7 +3,10 @@ use {
         data::Data,
         fmt::InjectionAffinity,
         parse::{Production, ProductionSymbol},
-        util::encoder::Encoder,
+        util::{
+            encoder::Encoder,
+            serial::{self, Reader, Serial, Writer},
+        },
     },
     std::{
         collections::{HashMap, HashSet},
@@ -23,11 +26,24 @@ pub trait Grammar<Symbol: GrammarSymbol>: Send + Sync {
     fn is_injectable(&self, symbol: &Symbol) -> bool;
     fn injection_affinity(&self, symbol: &Symbol) -> Option<&InjectionAffinity>;
     fn is_ignorable(&self, symbol: &Symbol) -> bool;
+    fn is_sync(&self, symbol: &Symbol) -> bool;
+    fn is_directive(&self, symbol: &Symbol) -> bool;
+    fn error_symbol(&self) -> Option<&Symbol>;
     fn terminals(&self) -> &HashSet<Symbol>;
+    fn non_terminals(&self) -> &HashSet<Symbol>;
     fn start(&self) -> &Symbol;
     fn productions_for_lhs(&self, lhs: &Symbol) -> Option<&Vec<Production<Symbol>>>;
     fn weighted_parse(&self) -> bool;
     fn symbol_string(&self, symbol: &Symbol) -> String;
+
+    /// Returns the priority of `production`, where derivations using productions of higher
+    /// priority are preferred when parsing ambiguous input. Productions without a declared
+    /// priority have priority zero.
+    fn priority(&self, production: &Production<Symbol>) -> i32;
+
+    /// Returns the operator precedence of `production`, taken from the right-most symbol of the
+    /// production with a declared precedence, or `None` if no such symbol exists.
+    fn precedence(&self, production: &Production<Symbol>) -> Option<&Precedence>;
 }
 
 pub trait GrammarBuilder<SymbolIn: GrammarSymbol, SymbolOut: GrammarSymbol, GrammarType> {
@@ -36,10 +52,76 @@ pub trait GrammarBuilder<SymbolIn: GrammarSymbol, SymbolOut: GrammarSymbol, Gram
     fn try_mark_start(&mut self, start: &SymbolIn);
     fn mark_injectable(&mut self, symbol: &SymbolIn, affinity: InjectionAffinity);
     fn mark_ignorable(&mut self, symbol: &SymbolIn);
+    fn mark_sync(&mut self, symbol: &SymbolIn);
+    fn mark_directive(&mut self, symbol: &SymbolIn);
+    fn mark_error(&mut self, symbol: &SymbolIn);
+    fn mark_priority(&mut self, production: &Production<SymbolIn>, priority: i32);
+    fn mark_precedence(&mut self, symbol: &SymbolIn, precedence: Precedence);
     fn kind_for(&mut self, token: &SymbolIn) -> SymbolOut;
     fn build(self) -> Result<GrammarType, BuildError>;
 }
 
+/// Associativity: Represents how an operator groups with other operators of the same precedence.
+///
+/// # Types
+///
+/// * `Left` - indicates that operators group from the left, e.g. `a - b - c` is `(a - b) - c`.
+/// * `Right` - indicates that operators group from the right, e.g. `a ^ b ^ c` is `a ^ (b ^ c)`.
+/// * `NonAssoc` - indicates that operators do not group, so chained operators are ambiguous.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+    NonAssoc,
+}
+
+impl Serial for Associativity {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u8(match self {
+            Self::Left => 0,
+            Self::Right => 1,
+            Self::NonAssoc => 2,
+        });
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        match reader.read_u8()? {
+            0 => Ok(Self::Left),
+            1 => Ok(Self::Right),
+            2 => Ok(Self::NonAssoc),
+            byte => Err(serial::Error::InvalidErr(format!("associativity {}", byte))),
+        }
+    }
+}
+
+/// Precedence: The declared precedence of an operator symbol.
+///
+/// # Fields
+///
+/// * `level` - the precedence level of the operator, where operators with higher levels bind more
+///   tightly.
+/// * `associativity` - the associativity of the operator, relative to other operators of the same
+///   level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Precedence {
+    pub level: usize,
+    pub associativity: Associativity,
+}
+
+impl Serial for Precedence {
+    fn write(&self, writer: &mut Writer) {
+        self.level.write(writer);
+        self.associativity.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            level: usize::read(reader)?,
+            associativity: Associativity::read(reader)?,
+        })
+    }
+}
+
 pub struct SimpleGrammar<Symbol: GrammarSymbol> {
     prods_by_lhs: HashMap<Symbol, Vec<Production<Symbol>>>,
     nss: HashSet<Symbol>,
@@ -47,6 +129,12 @@ pub struct SimpleGrammar<Symbol: GrammarSymbol> {
     terminals: HashSet<Symbol>,
     injectable: HashMap<Symbol, InjectionAffinity>,
     ignorable: HashSet<Symbol>,
+    sync: HashSet<Symbol>,
+    directive: HashSet<Symbol>,
+    error: Option<Symbol>,
+    priorities: HashMap<Production<Symbol>, i32>,
+    operators: HashMap<Symbol, Precedence>,
+    precedences: HashMap<Production<Symbol>, Precedence>,
     start: Symbol,
 }
 
@@ -71,10 +159,26 @@ impl<Symbol: GrammarSymbol> Grammar<Symbol> for SimpleGrammar<Symbol> {
         self.ignorable.contains(symbol)
     }
 
+    fn is_sync(&self, symbol: &Symbol) -> bool {
+        self.sync.contains(symbol)
+    }
+
+    fn is_directive(&self, symbol: &Symbol) -> bool {
+        self.directive.contains(symbol)
+    }
+
+    fn error_symbol(&self) -> Option<&Symbol> {
+        self.error.as_ref()
+    }
+
     fn terminals(&self) -> &HashSet<Symbol> {
         &self.terminals
     }
 
+    fn non_terminals(&self) -> &HashSet<Symbol> {
+        &self.non_terminals
+    }
+
     fn start(&self) -> &Symbol {
         &self.start
     }
@@ -84,18 +188,39 @@ impl<Symbol: GrammarSymbol> Grammar<Symbol> for SimpleGrammar<Symbol> {
     }
 
     fn weighted_parse(&self) -> bool {
-        !self.ignorable.is_empty() || !self.injectable.is_empty()
+        !self.ignorable.is_empty() || !self.injectable.is_empty() || !self.precedences.is_empty()
     }
 
     fn symbol_string(&self, symbol: &Symbol) -> String {
         symbol.to_string()
     }
+
+    fn priority(&self, production: &Production<Symbol>) -> i32 {
+        if self.priorities.is_empty() {
+            return 0;
+        }
+
+        *self.priorities.get(production).unwrap_or(&0)
+    }
+
+    fn precedence(&self, production: &Production<Symbol>) -> Option<&Precedence> {
+        if self.precedences.is_empty() {
+            return None;
+        }
+
+        self.precedences.get(production)
+    }
 }
 
 pub struct SimpleGrammarBuilder<Symbol: GrammarSymbol> {
     prods_by_lhs: HashMap<Symbol, Vec<Production<Symbol>>>,
     injectable: HashMap<Symbol, InjectionAffinity>,
     ignorable: HashSet<Symbol>,
+    sync: HashSet<Symbol>,
+    directive: HashSet<Symbol>,
+    error: Option<Symbol>,
+    priorities: HashMap<Production<Symbol>, i32>,
+    operators: HashMap<Symbol, Precedence>,
     start: Option<Symbol>,
 }
 
@@ -105,6 +230,11 @@ impl<Symbol: GrammarSymbol> SimpleGrammarBuilder<Symbol> {
             prods_by_lhs: HashMap::new(),
             injectable: HashMap::new(),
             ignorable: HashSet::new(),
+            sync: HashSet::new(),
+            directive: HashSet::new(),
+            error: None,
+            priorities: HashMap::new(),
+            operators: HashMap::new(),
             start: None,
         }
     }
@@ -122,13 +252,19 @@ impl<Symbol: GrammarSymbol> SimpleGrammarBuilder<Symbol> {
         }
         let start = self.start.unwrap();
 
-        if self.prods_by_lhs.get(&start).is_none() {
+        if !self.prods_by_lhs.contains_key(&start) {
             panic!("Start state has no productions");
         }
 
         let nss = build_nss(&self.prods_by_lhs);
         let non_terminals = build_non_terminals(&self.prods_by_lhs);
-        let terminals = build_terminals(&self.prods_by_lhs, &non_terminals);
+        let mut terminals = build_terminals(&self.prods_by_lhs, &non_terminals);
+
+        if let Some(ref error) = self.error {
+            terminals.remove(error);
+        }
+
+        let precedences = build_precedences(&self.prods_by_lhs, &self.operators);
 
         SimpleGrammar {
             prods_by_lhs: self.prods_by_lhs,
@@ -137,6 +273,12 @@ impl<Symbol: GrammarSymbol> SimpleGrammarBuilder<Symbol> {
             terminals,
             injectable: self.injectable,
             ignorable: self.ignorable,
+            sync: self.sync,
+            directive: self.directive,
+            error: self.error,
+            priorities: self.priorities,
+            operators: self.operators,
+            precedences,
             start,
         }
     }
@@ -188,6 +330,26 @@ impl<Symbol: GrammarSymbol> GrammarBuilder<Symbol, Symbol, SimpleGrammar<Symbol>
         self.ignorable.insert(symbol.clone());
     }
 
+    fn mark_sync(&mut self, symbol: &Symbol) {
+        self.sync.insert(symbol.clone());
+    }
+
+    fn mark_directive(&mut self, symbol: &Symbol) {
+        self.directive.insert(symbol.clone());
+    }
+
+    fn mark_error(&mut self, symbol: &Symbol) {
+        self.error = Some(symbol.clone());
+    }
+
+    fn mark_priority(&mut self, production: &Production<Symbol>, priority: i32) {
+        self.priorities.insert(production.clone(), priority);
+    }
+
+    fn mark_precedence(&mut self, symbol: &Symbol, precedence: Precedence) {
+        self.operators.insert(symbol.clone(), precedence);
+    }
+
     fn kind_for(&mut self, token: &Symbol) -> Symbol {
         token.clone()
     }
@@ -225,10 +387,26 @@ impl<SymbolIn: GrammarSymbol> Grammar<usize> for EncodedGrammar<SymbolIn> {
         self.grammar.is_ignorable(symbol)
     }
 
+    fn is_sync(&self, symbol: &usize) -> bool {
+        self.grammar.is_sync(symbol)
+    }
+
+    fn is_directive(&self, symbol: &usize) -> bool {
+        self.grammar.is_directive(symbol)
+    }
+
+    fn error_symbol(&self) -> Option<&usize> {
+        self.grammar.error_symbol()
+    }
+
     fn terminals(&self) -> &HashSet<usize> {
         self.grammar.terminals()
     }
 
+    fn non_terminals(&self) -> &HashSet<usize> {
+        self.grammar.non_terminals()
+    }
+
     fn start(&self) -> &usize {
         self.grammar.start()
     }
@@ -244,6 +422,64 @@ impl<SymbolIn: GrammarSymbol> Grammar<usize> for EncodedGrammar<SymbolIn> {
     fn symbol_string(&self, symbol: &usize) -> String {
         self.encoder.decode(*symbol).unwrap().to_string()
     }
+
+    fn priority(&self, production: &Production<usize>) -> i32 {
+        self.grammar.priority(production)
+    }
+
+    fn precedence(&self, production: &Production<usize>) -> Option<&Precedence> {
+        self.grammar.precedence(production)
+    }
+}
+
+impl<Symbol: GrammarSymbol + Serial> Serial for SimpleGrammar<Symbol> {
+    fn write(&self, writer: &mut Writer) {
+        self.prods_by_lhs.write(writer);
+        self.nss.write(writer);
+        self.non_terminals.write(writer);
+        self.terminals.write(writer);
+        self.injectable.write(writer);
+        self.ignorable.write(writer);
+        self.sync.write(writer);
+        self.directive.write(writer);
+        self.error.write(writer);
+        self.priorities.write(writer);
+        self.operators.write(writer);
+        self.precedences.write(writer);
+        self.start.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            prods_by_lhs: HashMap::read(reader)?,
+            nss: HashSet::read(reader)?,
+            non_terminals: HashSet::read(reader)?,
+            terminals: HashSet::read(reader)?,
+            injectable: HashMap::read(reader)?,
+            ignorable: HashSet::read(reader)?,
+            sync: HashSet::read(reader)?,
+            directive: HashSet::read(reader)?,
+            error: Option::read(reader)?,
+            priorities: HashMap::read(reader)?,
+            operators: HashMap::read(reader)?,
+            precedences: HashMap::read(reader)?,
+            start: Symbol::read(reader)?,
+        })
+    }
+}
+
+impl<SymbolIn: GrammarSymbol + Serial> Serial for EncodedGrammar<SymbolIn> {
+    fn write(&self, writer: &mut Writer) {
+        self.grammar.write(writer);
+        self.encoder.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            grammar: SimpleGrammar::read(reader)?,
+            encoder: Encoder::read(reader)?,
+        })
+    }
 }
 
 pub struct EncodedGrammarBuilder<SymbolIn: GrammarSymbol> {
@@ -258,6 +494,20 @@ impl<SymbolIn: GrammarSymbol> EncodedGrammarBuilder<SymbolIn> {
             encoder: Encoder::new(),
         }
     }
+
+    fn encode_production(&mut self, production: &Production<SymbolIn>) -> Production<usize> {
+        let rhs: Vec<ProductionSymbol<usize>> = production
+            .rhs
+            .iter()
+            .map(|sym| ProductionSymbol {
+                symbol: self.encoder.encode(&sym.symbol),
+                is_list: sym.is_list,
+                separator: sym.separator.as_ref().map(|sep| self.encoder.encode(sep)),
+            })
+            .collect();
+
+        Production::from(self.encoder.encode(&production.lhs), rhs)
+    }
 }
 
 impl<SymbolIn: GrammarSymbol> GrammarBuilder<SymbolIn, usize, EncodedGrammar<SymbolIn>>
@@ -272,17 +522,7 @@ impl<SymbolIn: GrammarSymbol> GrammarBuilder<SymbolIn, usize, EncodedGrammar<Sym
     }
 
     fn add_production(&mut self, production: Production<SymbolIn>) -> Production<usize> {
-        let rhs: Vec<ProductionSymbol<usize>> = production
-            .rhs
-            .iter()
-            .map(|sym| ProductionSymbol {
-                symbol: self.encoder.encode(&sym.symbol),
-                is_list: sym.is_list,
-            })
-            .collect();
-
-        let encoded_production = Production::from(self.encoder.encode(&production.lhs), rhs);
-
+        let encoded_production = self.encode_production(&production);
         self.builder.add_production(encoded_production)
     }
 
@@ -299,6 +539,28 @@ impl<SymbolIn: GrammarSymbol> GrammarBuilder<SymbolIn, usize, EncodedGrammar<Sym
         self.builder.mark_ignorable(&self.encoder.encode(symbol));
     }
 
+    fn mark_sync(&mut self, symbol: &SymbolIn) {
+        self.builder.mark_sync(&self.encoder.encode(symbol));
+    }
+
+    fn mark_directive(&mut self, symbol: &SymbolIn) {
+        self.builder.mark_directive(&self.encoder.encode(symbol));
+    }
+
+    fn mark_error(&mut self, symbol: &SymbolIn) {
+        self.builder.mark_error(&self.encoder.encode(symbol));
+    }
+
+    fn mark_priority(&mut self, production: &Production<SymbolIn>, priority: i32) {
+        let encoded_production = self.encode_production(production);
+        self.builder.mark_priority(&encoded_production, priority);
+    }
+
+    fn mark_precedence(&mut self, symbol: &SymbolIn, precedence: Precedence) {
+        self.builder
+            .mark_precedence(&self.encoder.encode(symbol), precedence);
+    }
+
     fn kind_for(&mut self, token: &SymbolIn) -> usize {
         self.encoder.encode(token)
     }
@@ -323,6 +585,26 @@ fn check_grammar<Symbol: GrammarSymbol>(
         }
     }
 
+    for sync in &grammar.sync {
+        if grammar.non_terminals.contains(sync) {
+            return Err(BuildError::NonTerminalSyncErr(symbol_decoder(sync)));
+        }
+    }
+
+    for directive in &grammar.directive {
+        if grammar.non_terminals.contains(directive) {
+            return Err(BuildError::NonTerminalDirectiveErr(symbol_decoder(
+                directive,
+            )));
+        }
+    }
+
+    if let Some(ref error) = grammar.error {
+        if grammar.non_terminals.contains(error) {
+            return Err(BuildError::NonTerminalErrorErr(symbol_decoder(error)));
+        }
+    }
+
     for injected in grammar.injectable.keys() {
         if grammar.non_terminals.contains(injected) {
             return Err(BuildError::NonTerminalInjectedErr(symbol_decoder(injected)));
@@ -332,13 +614,44 @@ fn check_grammar<Symbol: GrammarSymbol>(
         }
     }
 
+    for operator in grammar.operators.keys() {
+        if grammar.non_terminals.contains(operator) {
+            return Err(BuildError::NonTerminalPrecedenceErr(symbol_decoder(
+                operator,
+            )));
+        }
+    }
+
     Ok(())
 }
 
+/// Returns the precedence of each production in `prods_by_lhs` which contains an operator with a
+/// declared precedence in `operators`, taken from the right-most such operator.
+fn build_precedences<Symbol: GrammarSymbol>(
+    prods_by_lhs: &HashMap<Symbol, Vec<Production<Symbol>>>,
+    operators: &HashMap<Symbol, Precedence>,
+) -> HashMap<Production<Symbol>, Precedence> {
+    if operators.is_empty() {
+        return HashMap::new();
+    }
+
+    prods_by_lhs
+        .values()
+        .flatten()
+        .filter_map(|prod| {
+            prod.rhs
+                .iter()
+                .rev()
+                .find_map(|sym| operators.get(&sym.symbol))
+                .map(|precedence| (prod.clone(), *precedence))
+        })
+        .collect()
+}
+
 fn build_non_terminals<Symbol: GrammarSymbol>(
     prods_by_lhs: &HashMap<Symbol, Vec<Production<Symbol>>>,
 ) -> HashSet<Symbol> {
-    prods_by_lhs.iter().map(|(lhs, _)| lhs).cloned().collect()
+    prods_by_lhs.keys().cloned().collect()
 }
 
 fn build_terminals<Symbol: GrammarSymbol>(
@@ -350,9 +663,10 @@ fn build_terminals<Symbol: GrammarSymbol>(
     for prods in prods_by_lhs.values() {
         for prod in prods {
             for sym in &prod.rhs {
-                let symbol = &sym.symbol;
-                if !non_terminals.contains(symbol) {
-                    terminals.insert(symbol.clone());
+                for symbol in Some(&sym.symbol).into_iter().chain(&sym.separator) {
+                    if !non_terminals.contains(symbol) {
+                        terminals.insert(symbol.clone());
+                    }
                 }
             }
         }
@@ -368,22 +682,16 @@ fn build_nss<Symbol: GrammarSymbol>(
     let mut prods_by_rhs: HashMap<&Symbol, Vec<&Production<Symbol>>> = HashMap::new();
     let mut work_stack: Vec<&Symbol> = Vec::new();
 
-    prods_by_lhs
-        .iter()
-        .flat_map(|(_, prods)| prods)
-        .for_each(|prod| {
-            for sym in &prod.rhs {
-                prods_by_rhs
-                    .entry(&sym.symbol)
-                    .or_insert_with(Vec::new)
-                    .push(prod);
-            }
+    prods_by_lhs.values().flatten().for_each(|prod| {
+        for sym in &prod.rhs {
+            prods_by_rhs.entry(&sym.symbol).or_default().push(prod);
+        }
 
-            if prod.rhs.is_empty() {
-                nss.insert(prod.lhs.clone());
-                work_stack.push(&prod.lhs);
-            }
-        });
+        if prod.rhs.is_empty() {
+            nss.insert(prod.lhs.clone());
+            work_stack.push(&prod.lhs);
+        }
+    });
 
     loop {
         match work_stack.pop() {
@@ -452,6 +760,10 @@ pub enum BuildError {
     NonTerminalIgnoredErr(String),
     NonTerminalInjectedErr(String),
     IgnoredAndInjectedErr(String),
+    NonTerminalSyncErr(String),
+    NonTerminalDirectiveErr(String),
+    NonTerminalErrorErr(String),
+    NonTerminalPrecedenceErr(String),
 }
 
 impl fmt::Display for BuildError {
@@ -466,6 +778,18 @@ impl fmt::Display for BuildError {
             Self::IgnoredAndInjectedErr(ref symbol) => {
                 write!(f, "Symbol '{}' is both ignored and injected", symbol)
             }
+            Self::NonTerminalSyncErr(ref symbol) => {
+                write!(f, "Sync symbol '{}' is non-terminal", symbol)
+            }
+            Self::NonTerminalDirectiveErr(ref symbol) => {
+                write!(f, "Directive symbol '{}' is non-terminal", symbol)
+            }
+            Self::NonTerminalErrorErr(ref symbol) => {
+                write!(f, "Error symbol '{}' is non-terminal", symbol)
+            }
+            Self::NonTerminalPrecedenceErr(ref symbol) => {
+                write!(f, "Operator symbol '{}' is non-terminal", symbol)
+            }
         }
     }
 }
@@ -476,6 +800,10 @@ impl error::Error for BuildError {
             Self::NonTerminalIgnoredErr(_) => None,
             Self::NonTerminalInjectedErr(_) => None,
             Self::IgnoredAndInjectedErr(_) => None,
+            Self::NonTerminalSyncErr(_) => None,
+            Self::NonTerminalDirectiveErr(_) => None,
+            Self::NonTerminalErrorErr(_) => None,
+            Self::NonTerminalPrecedenceErr(_) => None,
         }
     }
 }
@@ -1,14 +1,16 @@
 use {
     core::{
         data::Data,
-        lex::Token,
+        lex::{Position, Span, Token},
         parse::grammar::{Grammar, GrammarSymbol},
+        util::serial::{self, Reader, Serial, Writer},
     },
-    std::{error, fmt},
+    std::{error, fmt, mem},
 };
 
 mod earley;
 pub mod grammar;
+pub mod sentence;
 
 pub trait Parser<Symbol: GrammarSymbol>: 'static + Send + Sync {
     fn parse(
@@ -16,6 +18,14 @@ pub trait Parser<Symbol: GrammarSymbol>: 'static + Send + Sync {
         lex: Vec<Token<Symbol>>,
         grammar: &dyn Grammar<Symbol>,
     ) -> Result<Tree<Symbol>, Error>;
+
+    /// Parses `lex` using `grammar` in the same way as `parse`, also returning each ambiguity
+    /// resolved while choosing the parse tree, in pre-order of the nodes of the tree.
+    fn parse_with_ambiguities(
+        &self,
+        lex: Vec<Token<Symbol>>,
+        grammar: &dyn Grammar<Symbol>,
+    ) -> Result<(Tree<Symbol>, Vec<Ambiguity<Symbol>>), Error>;
 }
 
 pub fn def_parser<Symbol: GrammarSymbol>() -> Box<dyn Parser<Symbol>> {
@@ -28,6 +38,7 @@ pub enum SymbolParseMethod {
     Ignored,
     Injected,
     Repeated,
+    Recovered,
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
@@ -64,6 +75,11 @@ impl<Symbol: GrammarSymbol> Tree<Symbol> {
         self.lhs.is_null()
     }
 
+    /// Returns the region of the original input covered by this tree.
+    pub fn span(&self) -> &Span {
+        self.lhs.span()
+    }
+
     pub fn null() -> Self {
         Self {
             lhs: Token::null(),
@@ -73,32 +89,37 @@ impl<Symbol: GrammarSymbol> Tree<Symbol> {
         }
     }
 
-    #[allow(dead_code)]
     pub fn decode(&self, grammar: &dyn Grammar<Symbol>) -> Tree<String> {
-        let lhs = match self.lhs.kind_opt() {
-            Some(ref symbol) => {
-                Token::leaf(grammar.symbol_string(symbol), self.lhs.lexeme().clone())
+        // Decode nodes iteratively, so that decoding a very deep tree cannot overflow the stack.
+        // Each node is decoded once its children have been.
+        let mut stack: Vec<(&Tree<Symbol>, bool)> = vec![(self, false)];
+        let mut decoded: Vec<Tree<String>> = Vec::new();
+
+        while let Some((node, expanded)) = stack.pop() {
+            if !expanded {
+                stack.push((node, true));
+                stack.extend(node.children.iter().rev().map(|child| (child, false)));
+                continue;
             }
-            None => Token::null(),
-        };
 
-        let children: Vec<Tree<String>> = self
-            .children
-            .iter()
-            .map(|tree| tree.decode(grammar))
-            .collect();
-
-        let production = match self.production {
-            Some(ref prod) => Some(prod.decode(grammar)),
-            None => None,
-        };
-
-        Tree {
-            lhs,
-            children,
-            production,
-            spm: self.spm.clone(),
+            let lhs = match node.lhs.kind_opt() {
+                Some(ref symbol) => {
+                    Token::leaf(grammar.symbol_string(symbol), node.lhs.lexeme().clone())
+                }
+                None => Token::null(),
+            }
+            .with_span(*node.span());
+
+            let children = decoded.split_off(decoded.len() - node.children.len());
+            decoded.push(Tree {
+                lhs,
+                children,
+                production: node.production.as_ref().map(|prod| prod.decode(grammar)),
+                spm: node.spm.clone(),
+            });
         }
+
+        decoded.pop().unwrap()
     }
 
     fn to_string_internal(&self, prefix: String, is_tail: bool) -> String {
@@ -137,20 +158,107 @@ impl<Symbol: GrammarSymbol> Tree<Symbol> {
     }
 }
 
+impl<Symbol: GrammarSymbol> Drop for Tree<Symbol> {
+    fn drop(&mut self) {
+        // Drop descendants iteratively, so that dropping a very deep tree cannot overflow the
+        // stack. Each node is dropped only once its children have been moved onto the stack.
+        let mut stack: Vec<Tree<Symbol>> = mem::take(&mut self.children);
+        while let Some(mut node) = stack.pop() {
+            stack.append(&mut node.children);
+        }
+    }
+}
+
 impl<Symbol: GrammarSymbol> Data for Tree<Symbol> {
     fn to_string(&self) -> String {
         self.to_string_internal("".to_string(), true)
     }
 }
 
+/// Ambiguity: A region of the input which can be derived from the same symbol in more than one
+/// way, of which the parser chose one.
+///
+/// # Fields
+///
+/// * `span` - the ambiguous region of the input.
+/// * `productions` - the competing productions which derive the region, starting with the chosen
+///   production. If the chosen production alone derives the region in more than one way (i.e. its
+///   right-hand side can be split over the region differently), this only contains the chosen
+///   production.
+/// * `chosen` - the production used to derive the region in the parse tree.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Ambiguity<Symbol: GrammarSymbol> {
+    pub span: Span,
+    pub productions: Vec<Production<Symbol>>,
+    pub chosen: Production<Symbol>,
+}
+
+impl<Symbol: GrammarSymbol> Ambiguity<Symbol> {
+    pub fn decode(&self, grammar: &dyn Grammar<Symbol>) -> Ambiguity<String> {
+        Ambiguity {
+            span: self.span,
+            productions: self
+                .productions
+                .iter()
+                .map(|prod| prod.decode(grammar))
+                .collect(),
+            chosen: self.chosen.decode(grammar),
+        }
+    }
+}
+
+/// Error: Represents an error encountered while parsing a lex.
+///
+/// # Fields
+///
+/// * `message` - a description of the failure.
+/// * `position` - the start of the token at which parsing failed, or the end of the input if
+///   parsing failed after consuming all tokens. `None` if no tokens were available.
+/// * `expected` - the names of the terminal symbols which would have allowed parsing to continue.
+/// * `in_progress` - the names of the non-terminal symbols which were being parsed at the point of
+///   failure.
 #[derive(Debug)]
 pub struct Error {
     pub message: String,
+    pub position: Option<Position>,
+    pub expected: Vec<String>,
+    pub in_progress: Vec<String>,
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.message)
+        write!(f, "{}", self.message)?;
+
+        if let Some(ref position) = self.position {
+            write!(f, " at {}", position.to_string())?;
+        }
+
+        if !self.expected.is_empty() {
+            let expected: Vec<String> = self
+                .expected
+                .iter()
+                .map(|symbol| format!("`{}`", symbol))
+                .collect();
+
+            write!(f, ", expected {}", expected.join(" or "))?;
+
+            if !self.in_progress.is_empty() {
+                write!(f, " in {}", self.in_progress.join(", "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            position: None,
+            expected: Vec::new(),
+            in_progress: Vec::new(),
+        }
     }
 }
 
@@ -164,6 +272,7 @@ impl error::Error for Error {
 pub struct ProductionSymbol<Symbol: GrammarSymbol> {
     pub symbol: Symbol,
     pub is_list: bool,
+    pub separator: Option<Symbol>,
 }
 
 impl<Symbol: GrammarSymbol> ProductionSymbol<Symbol> {
@@ -171,6 +280,7 @@ impl<Symbol: GrammarSymbol> ProductionSymbol<Symbol> {
         Self {
             symbol,
             is_list: false,
+            separator: None,
         }
     }
 
@@ -178,10 +288,35 @@ impl<Symbol: GrammarSymbol> ProductionSymbol<Symbol> {
         Self {
             symbol,
             is_list: true,
+            separator: None,
+        }
+    }
+
+    pub fn symbol_separated_list(symbol: Symbol, separator: Symbol) -> Self {
+        Self {
+            symbol,
+            is_list: true,
+            separator: Some(separator),
         }
     }
 }
 
+impl<Symbol: GrammarSymbol + Serial> Serial for ProductionSymbol<Symbol> {
+    fn write(&self, writer: &mut Writer) {
+        self.symbol.write(writer);
+        self.is_list.write(writer);
+        self.separator.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            symbol: Symbol::read(reader)?,
+            is_list: bool::read(reader)?,
+            separator: Option::read(reader)?,
+        })
+    }
+}
+
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Production<Symbol: GrammarSymbol> {
     pub lhs: Symbol,
@@ -206,6 +341,7 @@ impl<Symbol: GrammarSymbol> Production<Symbol> {
                 .map(|sym| ProductionSymbol {
                     symbol: sym.symbol.to_string(),
                     is_list: sym.is_list,
+                    separator: sym.separator.as_ref().map(|sep| sep.to_string()),
                 })
                 .collect(),
         }
@@ -213,19 +349,34 @@ impl<Symbol: GrammarSymbol> Production<Symbol> {
 
     pub fn decode(&self, grammar: &dyn Grammar<Symbol>) -> Production<String> {
         Production {
-            lhs: self.lhs.to_string(),
+            lhs: grammar.symbol_string(&self.lhs),
             rhs: self
                 .rhs
                 .iter()
                 .map(|sym| ProductionSymbol {
                     symbol: grammar.symbol_string(&sym.symbol),
                     is_list: sym.is_list,
+                    separator: sym.separator.as_ref().map(|sep| grammar.symbol_string(sep)),
                 })
                 .collect(),
         }
     }
 }
 
+impl<Symbol: GrammarSymbol + Serial> Serial for Production<Symbol> {
+    fn write(&self, writer: &mut Writer) {
+        self.lhs.write(writer);
+        self.rhs.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        Ok(Self {
+            lhs: Symbol::read(reader)?,
+            rhs: Vec::read(reader)?,
+        })
+    }
+}
+
 impl<Symbol: GrammarSymbol> Data for Production<Symbol> {
     fn to_string(&self) -> String {
         let mut res_string = self.lhs.to_string();
@@ -615,7 +766,7 @@ mod tests {
         assert!(res.is_err());
         assert_eq!(
             format!("{}", res.err().unwrap()),
-            "Largest parse did not consume all tokens: 0 of 1"
+            "Largest parse did not consume all tokens: 0 of 1 at 1:1"
         )
     }
 
@@ -1126,7 +1277,7 @@ mod tests {
         assert!(res.is_err());
         assert_eq!(
             format!("{}", res.err().unwrap()),
-            "Recognition failed at token 1: Y <- '2'"
+            "Recognition failed at token 1: Y <- '2' at 1:1, expected `X` in s"
         );
 
         let res = parser.parse(
@@ -1140,7 +1291,7 @@ mod tests {
         assert!(res.is_err());
         assert_eq!(
             format!("{}", res.err().unwrap()),
-            "Recognition failed at token 2: Z <- '3'"
+            "Recognition failed at token 2: Z <- '3' at 1:1, expected `X` or `Y` in s"
         );
 
         let res = parser.parse(
@@ -1154,7 +1305,7 @@ mod tests {
         assert!(res.is_err());
         assert_eq!(
             format!("{}", res.err().unwrap()),
-            "Recognition failed after consuming all tokens"
+            "Recognition failed after consuming all tokens at 1:1, expected `Y` or `Z` in s"
         );
     }
 
@@ -1524,6 +1675,259 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_spans() {
+        //setup
+        let mut grammar_builder = SimpleGrammarBuilder::new();
+        add_productions(&["S A opt B", "opt C", "opt"], &mut grammar_builder);
+        grammar_builder.try_mark_start(&"S".to_string());
+        let grammar = grammar_builder.build().unwrap();
+
+        let lex = vec![
+            spanned_leaf("A", "aa", (0, 1, 1), (2, 1, 3)),
+            spanned_leaf("B", "b", (3, 2, 1), (4, 2, 2)),
+        ];
+
+        let parser = def_parser();
+
+        //exercise
+        let tree = parser.parse(lex, &grammar).unwrap();
+
+        //verify
+        assert_eq!(tree.span().to_string(), "1:1-2:2");
+        assert_eq!(tree.span().start.offset, 0);
+        assert_eq!(tree.span().end.offset, 4);
+        assert_eq!(tree.get_child(0).span().to_string(), "1:1-1:3");
+        assert_eq!(tree.get_child(2).span().to_string(), "2:1-2:2");
+
+        let opt = tree.get_child(1);
+        assert!(opt.is_empty());
+        assert!(opt.span().is_empty());
+        assert_eq!(opt.span().start.offset, 3);
+        assert_eq!(opt.get_child(0).span(), opt.span());
+    }
+
+    #[test]
+    fn parse_error_expected_symbols() {
+        //setup
+        let mut grammar_builder = SimpleGrammarBuilder::new();
+        add_productions(
+            &["S A body B", "body C D", "body C E", "body"],
+            &mut grammar_builder,
+        );
+        grammar_builder.try_mark_start(&"S".to_string());
+        let grammar = grammar_builder.build().unwrap();
+
+        let lex = vec![
+            spanned_leaf("A", "a", (0, 1, 1), (1, 1, 2)),
+            spanned_leaf("C", "c", (2, 2, 1), (3, 2, 2)),
+            spanned_leaf("B", "b", (4, 2, 3), (5, 2, 4)),
+        ];
+
+        let parser = def_parser();
+
+        //exercise
+        let res = parser.parse(lex, &grammar);
+
+        //verify
+        let err = res.err().unwrap();
+        assert_eq!(err.message, "Recognition failed at token 3: B <- 'b'");
+        assert_eq!(err.position.unwrap().to_string(), "2:3");
+        assert_eq!(err.position.unwrap().offset, 4);
+        assert_eq!(err.expected, vec!["D".to_string(), "E".to_string()]);
+        assert_eq!(err.in_progress, vec!["body".to_string()]);
+        assert_eq!(
+            format!("{}", err),
+            "Recognition failed at token 3: B <- 'b' at 2:3, expected `D` or `E` in body"
+        );
+    }
+
+    #[test]
+    fn parse_error_recovery() {
+        //setup
+        let mut grammar_builder = SimpleGrammarBuilder::new();
+        add_productions(&["S S stmt", "S stmt", "stmt A SEMI"], &mut grammar_builder);
+        grammar_builder.add_production(Production::from(
+            "stmt".to_string(),
+            vec![
+                ProductionSymbol::symbol_list("error".to_string()),
+                ProductionSymbol::symbol("SEMI".to_string()),
+            ],
+        ));
+        grammar_builder.try_mark_start(&"S".to_string());
+        grammar_builder.mark_error(&"error".to_string());
+        grammar_builder.mark_sync(&"SEMI".to_string());
+        let grammar = grammar_builder.build().unwrap();
+
+        let lex = vec![
+            spanned_leaf("B", "b", (0, 1, 1), (1, 1, 2)),
+            spanned_leaf("A", "a", (2, 1, 3), (3, 1, 4)),
+            spanned_leaf("SEMI", ";", (3, 1, 4), (4, 1, 5)),
+            spanned_leaf("A", "a", (4, 1, 5), (5, 1, 6)),
+            spanned_leaf("SEMI", ";", (5, 1, 6), (6, 1, 7)),
+        ];
+
+        let parser = def_parser();
+
+        //exercise
+        let tree = parser.parse(lex, &grammar).unwrap();
+
+        //verify
+        assert_eq!(
+            tree.to_string(),
+            "└── S
+    ├── S
+    │   └── stmt
+    │       ├── error
+    │       │   ├── B <- 'b'
+    │       │   └── A <- 'a'
+    │       └── SEMI <- ';'
+    └── stmt
+        ├── A <- 'a'
+        └── SEMI <- ';'"
+        );
+
+        let recovered = tree.get_child(0).get_child(0).get_child(0);
+        assert_eq!(recovered.spm, SymbolParseMethod::Recovered);
+        assert_eq!(recovered.span().start.offset, 0);
+        assert_eq!(recovered.span().end.offset, 3);
+    }
+
+    #[test]
+    fn parse_error_recovery_reports_original_error() {
+        //setup
+        let mut grammar_builder = SimpleGrammarBuilder::new();
+        add_productions(&["S A SEMI", "S A error"], &mut grammar_builder);
+        grammar_builder.try_mark_start(&"S".to_string());
+        grammar_builder.mark_error(&"error".to_string());
+        grammar_builder.mark_sync(&"SEMI".to_string());
+        let grammar = grammar_builder.build().unwrap();
+
+        let lex = vec![
+            spanned_leaf("A", "a", (0, 1, 1), (1, 1, 2)),
+            spanned_leaf("SEMI", ";", (1, 1, 2), (2, 1, 3)),
+            spanned_leaf("SEMI", ";", (2, 1, 3), (3, 1, 4)),
+        ];
+
+        let parser = def_parser();
+
+        //exercise
+        let res = parser.parse(lex, &grammar);
+
+        //verify
+        assert_eq!(
+            format!("{}", res.err().unwrap()),
+            "Largest parse did not consume all tokens: 2 of 3 at 1:3"
+        );
+    }
+
+    #[test]
+    fn parse_ambiguities_split() {
+        //setup
+        let mut grammar_builder = SimpleGrammarBuilder::new();
+        add_productions(&["S S OP S", "S ID"], &mut grammar_builder);
+        grammar_builder.try_mark_start(&"S".to_string());
+        let grammar = grammar_builder.build().unwrap();
+
+        let lex = vec![
+            spanned_leaf("ID", "a", (0, 1, 1), (1, 1, 2)),
+            spanned_leaf("OP", "+", (1, 1, 2), (2, 1, 3)),
+            spanned_leaf("ID", "b", (2, 1, 3), (3, 1, 4)),
+            spanned_leaf("OP", "+", (3, 1, 4), (4, 1, 5)),
+            spanned_leaf("ID", "c", (4, 1, 5), (5, 1, 6)),
+        ];
+
+        let parser = def_parser();
+
+        //exercise
+        let (tree, ambiguities) = parser.parse_with_ambiguities(lex, &grammar).unwrap();
+
+        //verify
+        assert_eq!(tree.children.len(), 3);
+        assert_eq!(ambiguities.len(), 1);
+        assert_eq!(ambiguities[0].span.to_string(), "1:1-1:6");
+        assert_eq!(
+            ambiguities[0].productions,
+            vec![production_from_string("S S OP S")]
+        );
+        assert_eq!(ambiguities[0].chosen, production_from_string("S S OP S"));
+    }
+
+    #[test]
+    fn parse_ambiguities_competing() {
+        //setup
+        let mut grammar_builder = SimpleGrammarBuilder::new();
+        add_productions(
+            &["S A body B", "body x", "body y", "y x"],
+            &mut grammar_builder,
+        );
+        grammar_builder.try_mark_start(&"S".to_string());
+        let grammar = grammar_builder.build().unwrap();
+
+        let lex = vec![
+            spanned_leaf("A", "a", (0, 1, 1), (1, 1, 2)),
+            spanned_leaf("x", "x", (2, 1, 3), (3, 1, 4)),
+            spanned_leaf("B", "b", (4, 1, 5), (5, 1, 6)),
+        ];
+
+        let parser = def_parser();
+
+        //exercise
+        let (tree, ambiguities) = parser.parse_with_ambiguities(lex, &grammar).unwrap();
+
+        //verify
+        let chosen = tree.get_child(1).production.clone().unwrap();
+        assert_eq!(ambiguities.len(), 1);
+        assert_eq!(ambiguities[0].span.to_string(), "1:3-1:4");
+        assert_eq!(ambiguities[0].chosen, chosen);
+        assert_eq!(ambiguities[0].productions.len(), 2);
+        assert_eq!(ambiguities[0].productions[0], chosen);
+        assert!(ambiguities[0]
+            .productions
+            .contains(&production_from_string("body x")));
+        assert!(ambiguities[0]
+            .productions
+            .contains(&production_from_string("body y")));
+    }
+
+    #[test]
+    fn parse_ambiguities_none() {
+        //setup
+        let mut grammar_builder = SimpleGrammarBuilder::new();
+        add_productions(&["S S OP ID", "S ID"], &mut grammar_builder);
+        grammar_builder.try_mark_start(&"S".to_string());
+        let grammar = grammar_builder.build().unwrap();
+
+        let lex = "ID OP ID OP ID"
+            .split_whitespace()
+            .map(|kind| Token::leaf(kind.to_string(), "xy".to_string()))
+            .collect();
+
+        let parser = def_parser();
+
+        //exercise
+        let (_, ambiguities) = parser.parse_with_ambiguities(lex, &grammar).unwrap();
+
+        //verify
+        assert!(ambiguities.is_empty());
+    }
+
+    fn spanned_leaf(
+        kind: &str,
+        lexeme: &str,
+        start: (usize, usize, usize),
+        end: (usize, usize, usize),
+    ) -> Token<String> {
+        let position = |(offset, line, character)| Position {
+            offset,
+            line,
+            character,
+        };
+
+        Token::leaf(kind.to_string(), lexeme.to_string())
+            .with_span(Span::new(position(start), position(end)))
+    }
+
     pub fn add_productions<'scope>(
         strings: &'scope [&'scope str],
         grammar_builder: &mut SimpleGrammarBuilder<String>,
@@ -1545,6 +1949,7 @@ mod tests {
                 rhs.push(ProductionSymbol {
                     symbol: s.to_string(),
                     is_list: false,
+                    separator: None,
                 });
             }
             i += 1;
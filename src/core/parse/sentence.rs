@@ -0,0 +1,244 @@
+use {
+    core::{
+        parse::{
+            grammar::{Grammar, GrammarSymbol},
+            Production,
+        },
+        util::random::Random,
+    },
+    std::collections::HashMap,
+};
+
+/// The maximum number of elements generated for an inline list.
+const MAX_LIST_LENGTH: usize = 3;
+
+/// Sentence Generator: Generates random sentences (sequences of terminal symbols) of a grammar.
+///
+/// # Fields
+///
+/// * `grammar` - the grammar to generate sentences of.
+/// * `heights` - the minimum height of a derivation tree for each productive non-terminal, used
+///   to end derivations once they reach the maximum depth.
+pub struct SentenceGenerator<'grammar, Symbol: 'grammar + GrammarSymbol> {
+    grammar: &'grammar dyn Grammar<Symbol>,
+    heights: HashMap<&'grammar Symbol, usize>,
+}
+
+impl<'grammar, Symbol: 'grammar + GrammarSymbol> SentenceGenerator<'grammar, Symbol> {
+    /// Returns a new generator of the sentences of `grammar`.
+    pub fn new(grammar: &'grammar dyn Grammar<Symbol>) -> Self {
+        let mut generator = Self {
+            grammar,
+            heights: HashMap::new(),
+        };
+
+        // Find the minimum heights by iterating until no height decreases.
+        loop {
+            let mut changed = false;
+
+            for lhs in grammar.non_terminals() {
+                let height = grammar
+                    .productions_for_lhs(lhs)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|production| generator.production_height(production))
+                    .min();
+
+                match (height, generator.heights.get(lhs)) {
+                    (None, _) => {}
+                    (Some(height), Some(old)) if height >= *old => {}
+                    (Some(height), _) => {
+                        generator.heights.insert(lhs, height);
+                        changed = true;
+                    }
+                }
+            }
+
+            if !changed {
+                return generator;
+            }
+        }
+    }
+
+    /// Returns a random sentence derived from the start symbol of the grammar, or `None` if the
+    /// start symbol cannot derive any sentence.
+    ///
+    /// Productions are chosen uniformly at random until a derivation reaches `max_depth` nested
+    /// non-terminals, after which the productions with the shortest derivations are chosen.
+    pub fn generate(&self, max_depth: usize, random: &mut Random) -> Option<Vec<Symbol>> {
+        let start = self.grammar.start();
+        if !self.heights.contains_key(start) {
+            return None;
+        }
+
+        let mut sentence: Vec<Symbol> = Vec::new();
+        self.derive(start, 0, max_depth, random, &mut sentence);
+        Some(sentence)
+    }
+
+    /// Appends a random derivation of `symbol` at depth `depth` to `sentence`.
+    fn derive(
+        &self,
+        symbol: &Symbol,
+        depth: usize,
+        max_depth: usize,
+        random: &mut Random,
+        sentence: &mut Vec<Symbol>,
+    ) {
+        if !self.grammar.is_non_terminal(symbol) {
+            sentence.push(symbol.clone());
+            return;
+        }
+
+        let productions: Vec<(&Production<Symbol>, usize)> = self
+            .grammar
+            .productions_for_lhs(symbol)
+            .into_iter()
+            .flatten()
+            .filter_map(|production| {
+                self.production_height(production)
+                    .map(|height| (production, height))
+            })
+            .collect();
+
+        let candidates: Vec<&Production<Symbol>> = if depth < max_depth {
+            productions
+                .iter()
+                .map(|&(production, _)| production)
+                .collect()
+        } else {
+            let min_height = productions.iter().map(|&(_, height)| height).min().unwrap();
+            productions
+                .iter()
+                .filter(|&&(_, height)| height == min_height)
+                .map(|&(production, _)| production)
+                .collect()
+        };
+
+        let production = candidates[random.below(candidates.len())];
+
+        for sym in &production.rhs {
+            let length = if sym.is_list && depth < max_depth {
+                1 + random.below(MAX_LIST_LENGTH)
+            } else {
+                1
+            };
+
+            for i in 0..length {
+                if i > 0 {
+                    if let Some(ref separator) = sym.separator {
+                        self.derive(separator, depth + 1, max_depth, random, sentence);
+                    }
+                }
+                self.derive(&sym.symbol, depth + 1, max_depth, random, sentence);
+            }
+        }
+    }
+
+    /// Returns the minimum height of a derivation tree rooted at `production`, or `None` if a
+    /// symbol of the production has no known derivation.
+    fn production_height(&self, production: &Production<Symbol>) -> Option<usize> {
+        let mut height: usize = 1;
+
+        for sym in &production.rhs {
+            for symbol in Some(&sym.symbol).into_iter().chain(&sym.separator) {
+                height = height.max(1 + self.symbol_height(symbol)?);
+            }
+        }
+
+        Some(height)
+    }
+
+    /// Returns the minimum height of a derivation tree rooted at `symbol`, or `None` if `symbol`
+    /// has no known derivation. The error symbol has no derivation, since it only matches input
+    /// during error recovery.
+    fn symbol_height(&self, symbol: &Symbol) -> Option<usize> {
+        if self.grammar.is_non_terminal(symbol) {
+            self.heights.get(symbol).cloned()
+        } else if self.grammar.error_symbol() == Some(symbol) {
+            None
+        } else {
+            Some(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::parse::{
+        grammar::{GrammarBuilder, SimpleGrammarBuilder},
+        ProductionSymbol,
+    };
+
+    use super::*;
+
+    #[test]
+    fn generate_respects_grammar() {
+        //setup
+        let mut grammar_builder = SimpleGrammarBuilder::new();
+        grammar_builder.try_mark_start(&"s".to_string());
+        grammar_builder.add_production(Production::from(
+            "s".to_string(),
+            vec![
+                ProductionSymbol::symbol("LP".to_string()),
+                ProductionSymbol::symbol("s".to_string()),
+                ProductionSymbol::symbol("RP".to_string()),
+            ],
+        ));
+        grammar_builder.add_production(Production::from(
+            "s".to_string(),
+            vec![ProductionSymbol::symbol_separated_list(
+                "X".to_string(),
+                "COMMA".to_string(),
+            )],
+        ));
+        let grammar = grammar_builder.build().unwrap();
+
+        let generator = SentenceGenerator::new(&grammar);
+        let mut random = Random::from_seed(7);
+
+        for _ in 0..50 {
+            //exercise
+            let sentence = generator.generate(4, &mut random).unwrap();
+
+            //verify
+            let opens = sentence.iter().take_while(|symbol| *symbol == "LP").count();
+            let closes = sentence
+                .iter()
+                .rev()
+                .take_while(|symbol| *symbol == "RP")
+                .count();
+            assert_eq!(opens, closes);
+            assert!(opens <= 4);
+
+            let list: Vec<&String> = sentence[opens..sentence.len() - closes].iter().collect();
+            assert_eq!(list.len() % 2, 1);
+            for (i, symbol) in list.iter().enumerate() {
+                assert_eq!(*symbol, if i % 2 == 0 { "X" } else { "COMMA" });
+            }
+        }
+    }
+
+    #[test]
+    fn generate_unproductive_start() {
+        //setup
+        let mut grammar_builder = SimpleGrammarBuilder::new();
+        grammar_builder.try_mark_start(&"s".to_string());
+        grammar_builder.add_production(Production::from(
+            "s".to_string(),
+            vec![
+                ProductionSymbol::symbol("X".to_string()),
+                ProductionSymbol::symbol("s".to_string()),
+            ],
+        ));
+        let grammar = grammar_builder.build().unwrap();
+
+        let generator = SentenceGenerator::new(&grammar);
+
+        //exercise
+        let sentence = generator.generate(4, &mut Random::from_seed(7));
+
+        //verify
+        assert_eq!(sentence, None);
+    }
+}
@@ -1,21 +1,23 @@
 use {
     core::{
+        data::Data,
         fmt::{FormatterBuilder, InjectableString, InjectionAffinity, PatternPair},
         lex::{
             ecdfa::{EncodedCDFA, EncodedCDFABuilder},
             CDFABuilder, ConsumerStrategy, TransitBuilder, CDFA,
         },
         parse::{
-            grammar::{Grammar, GrammarBuilder, GrammarSymbol},
+            grammar::{self, Associativity, Grammar, GrammarBuilder, GrammarSymbol, Precedence},
             Production, ProductionSymbol, Tree,
         },
         spec::{
             self,
             lang::SpecSymbol,
+            lint::{self, SpecLint},
             region::{self, RegionType},
-            SpecGenResult,
+            SpecGenResult, SpecTest,
         },
-        util::string_utils,
+        util::{encoder::Encoder, string_utils},
     },
     std::collections::HashSet,
 };
@@ -29,9 +31,93 @@ use {
 /// * `parse` - the parse tree generated for the specification.
 /// * `grammar_builder` - a grammar builder with which to construct the specification's grammar.
 pub fn generate_spec<Symbol: 'static + GrammarSymbol, GrammarType, GrammarBuilderType>(
+    parse: &Tree<SpecSymbol>,
+    grammar_builder: GrammarBuilderType,
+) -> Result<SpecGenResult<Symbol, GrammarType>, spec::GenError>
+where
+    GrammarType: 'static + Grammar<Symbol>,
+    GrammarBuilderType: GrammarBuilder<String, Symbol, GrammarType>,
+{
+    let ((ecdfa, grammar, formatter), _) = generate(parse, grammar_builder)?;
+    orphan_check(&ecdfa, &grammar, parse.get_child(0))?;
+    Ok((ecdfa, grammar, formatter))
+}
+
+/// Builds a specification from a parse of the specification grammar, and returns the problems
+/// found in its grammar and CDFA.
+///
+/// Orphaned terminals are reported as problems rather than errors, so that the rest of the
+/// specification is still linted. Returns an error if the specification cannot be built.
+///
+/// # Parameters
+///
+/// * `parse` - the parse tree generated for the specification.
+/// * `grammar_builder` - a grammar builder with which to construct the specification's grammar.
+pub fn lint_spec<Symbol: 'static + GrammarSymbol, GrammarType, GrammarBuilderType>(
+    parse: &Tree<SpecSymbol>,
+    grammar_builder: GrammarBuilderType,
+) -> Result<Vec<SpecLint>, spec::GenError>
+where
+    GrammarType: 'static + Grammar<Symbol>,
+    GrammarBuilderType: GrammarBuilder<String, Symbol, GrammarType>,
+{
+    let ((ecdfa, grammar, _), states) = generate(parse, grammar_builder)?;
+    Ok(lint::lint(parse, &ecdfa, &states, &grammar))
+}
+
+/// Returns the tests declared in the `test` regions of a specification parse, in the order they
+/// are declared, or an error if a required specification region is missing.
+///
+/// # Parameters
+///
+/// * `parse` - the parse tree generated for the specification.
+pub fn generate_tests(parse: &Tree<SpecSymbol>) -> Result<Vec<SpecTest>, spec::GenError> {
+    let mut tests: Vec<SpecTest> = Vec::new();
+
+    let mut region_handler = |inner_node: &Tree<SpecSymbol>, region_type: &RegionType| {
+        if *region_type == RegionType::Test {
+            tests.push(generate_test(inner_node));
+        }
+        Ok(())
+    };
+
+    region::traverse(parse.get_child(0), &mut region_handler)?;
+    Ok(tests)
+}
+
+/// Returns the test declared by the `SpecSymbol::Test` node `test_node`.
+fn generate_test(test_node: &Tree<SpecSymbol>) -> SpecTest {
+    let name = test_node.get_child(1).lhs.lexeme();
+    let name = string_utils::replace_escapes(&name[1..name.len() - 1]);
+
+    SpecTest {
+        name,
+        input: text_content(test_node.get_child(4)),
+        expect: text_content(test_node.get_child(6)),
+        span: *test_node.span(),
+    }
+}
+
+/// Returns the content of the `SpecSymbol::TText` leaf `text_node`, without its surrounding
+/// double backticks.
+fn text_content(text_node: &Tree<SpecSymbol>) -> String {
+    let text = text_node.lhs.lexeme();
+    String::from(&text[2..text.len() - 2])
+}
+
+/// Builds a specification from a parse of the specification grammar.
+///
+/// Returns the specification along with the encoder of its CDFA states if successful, otherwise
+/// an error. Orphaned terminals are not checked, see `orphan_check`.
+///
+/// # Parameters
+///
+/// * `parse` - the parse tree generated for the specification.
+/// * `grammar_builder` - a grammar builder with which to construct the specification's grammar.
+fn generate<Symbol: 'static + GrammarSymbol, GrammarType, GrammarBuilderType>(
     parse: &Tree<SpecSymbol>,
     mut grammar_builder: GrammarBuilderType,
-) -> Result<SpecGenResult<Symbol>, spec::GenError>
+) -> Result<(SpecGenResult<Symbol, GrammarType>, Encoder<String>), spec::GenError>
 where
     GrammarType: 'static + Grammar<Symbol>,
     GrammarBuilderType: GrammarBuilder<String, Symbol, GrammarType>,
@@ -46,22 +132,21 @@ where
         &mut formatter_builder,
     )?;
 
-    let ecdfa = ecdfa_builder.build()?;
-    let grammar = grammar_builder.build()?;
+    let (ecdfa, states) = ecdfa_builder.build_encoded()?;
+    let grammar = grammar_builder
+        .build()
+        .map_err(|err| locate_grammar_err(err, parse.get_child(0)))?;
 
-    orphan_check(&ecdfa, &grammar)?;
+    error_symbol_check(&ecdfa, &grammar, parse.get_child(0))?;
 
-    Ok((
-        Box::new(ecdfa),
-        Box::new(grammar),
-        formatter_builder.build(),
-    ))
+    Ok(((ecdfa, grammar, formatter_builder.build()), states))
 }
 
 /// Recursively traverses the different regions of a specification parse, and calls the associated
 /// region-specific handlers to traverse further.
 ///
-/// An error is returned if the traversal of any specification region results in an error.
+/// An error is returned if the traversal of any specification region results in an error, located
+/// within that region.
 ///
 /// # Parameters
 ///
@@ -80,20 +165,53 @@ where
     CDFABuilderType: CDFABuilder<String, Symbol, CDFAType>,
     GrammarType: Grammar<Symbol>,
 {
+    // Operator precedences are shared between grammar regions.
+    let mut precedence_level: usize = 0;
+    let mut operators: HashSet<String> = HashSet::new();
+
+    // Error recovery is only enabled for specifications which declare sync symbols, and otherwise
+    // the error symbol is an ordinary grammar symbol.
+    let mut recovery = false;
+    region::traverse(regions_node, &mut |_, region_type| {
+        recovery |= *region_type == RegionType::Sync;
+        Ok(())
+    })?;
+
     let mut region_handler = |inner_node: &Tree<SpecSymbol>, region_type: &RegionType| {
-        match region_type {
+        let result = match region_type {
             RegionType::Injectable => {
-                traverse_injectable_region(inner_node, grammar_builder, formatter_builder)?
+                traverse_injectable_region(inner_node, grammar_builder, formatter_builder)
             }
-            RegionType::Ignorable => traverse_ignorable_region(inner_node, grammar_builder),
-            RegionType::Alphabet => traverse_alphabet_region(inner_node, cdfa_builder),
-            RegionType::CDFA => traverse_cdfa_region(inner_node, cdfa_builder, grammar_builder)?,
-            RegionType::Grammar => {
-                traverse_grammar_region(inner_node, grammar_builder, formatter_builder)?
+            RegionType::Ignorable => {
+                traverse_ignorable_region(inner_node, grammar_builder);
+                Ok(())
             }
-        }
-
-        Ok(())
+            RegionType::Sync => {
+                traverse_sync_region(inner_node, grammar_builder);
+                Ok(())
+            }
+            RegionType::Directive => {
+                traverse_directive_region(inner_node, grammar_builder);
+                Ok(())
+            }
+            RegionType::Width => traverse_width_region(inner_node, formatter_builder),
+            RegionType::Alphabet => {
+                traverse_alphabet_region(inner_node, cdfa_builder);
+                Ok(())
+            }
+            RegionType::CDFA => traverse_cdfa_region(inner_node, cdfa_builder, grammar_builder),
+            RegionType::Grammar => traverse_grammar_region(
+                inner_node,
+                &mut precedence_level,
+                &mut operators,
+                recovery,
+                grammar_builder,
+                formatter_builder,
+            ),
+            RegionType::Test => Ok(()),
+        };
+
+        result.map_err(|err| err.in_region(region_type.clone(), *inner_node.span()))
     };
 
     region::traverse(regions_node, &mut region_handler)
@@ -137,12 +255,14 @@ where
         None => None,
     };
 
-    formatter_builder.add_injection(InjectableString {
-        terminal: grammar_builder.kind_for(terminal_string),
-        terminal_string: terminal_string.clone(),
-        pattern_string,
-        affinity,
-    })?;
+    formatter_builder
+        .add_injection(InjectableString {
+            terminal: grammar_builder.kind_for(terminal_string),
+            terminal_string: terminal_string.clone(),
+            pattern_string,
+            affinity,
+        })
+        .map_err(|err| spec::GenError::from(err).at(*injectable_node.span()))?;
 
     Ok(())
 }
@@ -164,6 +284,63 @@ fn traverse_ignorable_region<Symbol: GrammarSymbol, GrammarType>(
     grammar_builder.mark_ignorable(terminal);
 }
 
+/// Traverses a sync symbol specification region, marking the associated terminal symbol as a
+/// synchronization point for error recovery in the grammar.
+///
+/// # Parameters
+///
+/// * `sync_node` - the `SpecSymbol::Sync` being traversed.
+/// * `grammar_builder` - the grammar builder for the specification.
+fn traverse_sync_region<Symbol: GrammarSymbol, GrammarType>(
+    sync_node: &Tree<SpecSymbol>,
+    grammar_builder: &mut dyn GrammarBuilder<String, Symbol, GrammarType>,
+) where
+    GrammarType: Grammar<Symbol>,
+{
+    let terminal = sync_node.get_child(1).lhs.lexeme();
+    grammar_builder.mark_sync(terminal);
+}
+
+/// Traverses a directive symbol specification region, marking the associated terminal symbol as
+/// able to carry formatting directives (e.g. `padd:off`) in the grammar.
+///
+/// # Parameters
+///
+/// * `directive_node` - the `SpecSymbol::Directive` being traversed.
+/// * `grammar_builder` - the grammar builder for the specification.
+fn traverse_directive_region<Symbol: GrammarSymbol, GrammarType>(
+    directive_node: &Tree<SpecSymbol>,
+    grammar_builder: &mut dyn GrammarBuilder<String, Symbol, GrammarType>,
+) where
+    GrammarType: Grammar<Symbol>,
+{
+    let terminal = directive_node.get_child(1).lhs.lexeme();
+    grammar_builder.mark_directive(terminal);
+}
+
+/// Traverses a width specification region, setting the maximum line width of the formatter.
+///
+/// An error is returned if the declared width is too large to be represented.
+///
+/// # Parameters
+///
+/// * `width_node` - the `SpecSymbol::Width` being traversed.
+/// * `formatter_builder` - the formatter builder for the specification.
+fn traverse_width_region<Symbol: GrammarSymbol>(
+    width_node: &Tree<SpecSymbol>,
+    formatter_builder: &mut FormatterBuilder<Symbol>,
+) -> Result<(), spec::GenError> {
+    let width_node = width_node.get_child(1);
+    let width_string = width_node.lhs.lexeme();
+    match width_string.parse::<usize>() {
+        Ok(width) => {
+            formatter_builder.set_width(width);
+            Ok(())
+        }
+        Err(_) => Err(spec::GenError::WidthErr(width_string.clone()).at(*width_node.span())),
+    }
+}
+
 /// Traverses an alphabet region of a specification parse and extracts the alphabet into the CDFA
 /// being built.
 ///
@@ -208,28 +385,105 @@ where
 
 /// Traverses a grammar specification region.
 ///
-/// An error is returned if the grammar or formatter cannot be built for the region.
+/// An error is returned if the operator precedences, grammar or formatter cannot be built for the
+/// region.
 ///
 /// # Parameters
 ///
 /// * `grammar_node` - the root `SpecSymbol::Grammar` node of the parse tree.
+/// * `precedence_level` - the next operator precedence level to declare.
+/// * `operators` - the set of operators declared by previous grammar regions.
+/// * `recovery` - whether error recovery is enabled for the specification.
 /// * `grammar_builder` - the grammar builder for the specification.
 /// * `formatter_builder` - the formatter builder for the specification.
 fn traverse_grammar_region<Symbol: GrammarSymbol, GrammarType>(
     grammar_node: &Tree<SpecSymbol>,
+    precedence_level: &mut usize,
+    operators: &mut HashSet<String>,
+    recovery: bool,
     grammar_builder: &mut dyn GrammarBuilder<String, Symbol, GrammarType>,
     formatter_builder: &mut FormatterBuilder<Symbol>,
 ) -> Result<(), spec::GenError>
 where
     GrammarType: Grammar<Symbol>,
 {
-    generate_grammar_prods(
+    generate_grammar_precedences(
         grammar_node.get_child(2),
+        precedence_level,
+        operators,
+        grammar_builder,
+    )?;
+
+    generate_grammar_prods(
+        grammar_node.get_child(3),
+        recovery,
         grammar_builder,
         formatter_builder,
     )
 }
 
+/// Recursively traverses `SpecSymbol::Precedences` nodes to declare the precedence and
+/// associativity of operator symbols, where later declarations bind more tightly.
+///
+/// Returns an error if an operator is declared more than once.
+///
+/// # Parameters
+///
+/// * `precs_node` - the `SpecSymbol::Precedences` node of the parse tree to traverse.
+/// * `level` - the next precedence level to declare, which is advanced for each declaration.
+/// * `operators` - the set of operators already declared.
+/// * `grammar_builder` - the grammar builder for the specification.
+fn generate_grammar_precedences<Symbol: GrammarSymbol, GrammarType>(
+    precs_node: &Tree<SpecSymbol>,
+    level: &mut usize,
+    operators: &mut HashSet<String>,
+    grammar_builder: &mut dyn GrammarBuilder<String, Symbol, GrammarType>,
+) -> Result<(), spec::GenError>
+where
+    GrammarType: Grammar<Symbol>,
+{
+    if precs_node.is_empty() {
+        return Ok(());
+    }
+
+    generate_grammar_precedences(precs_node.get_child(0), level, operators, grammar_builder)?;
+
+    let prec_node = precs_node.get_child(1);
+    let associativity = match &prec_node.get_child(0).lhs.lexeme()[..] {
+        "%left" => Associativity::Left,
+        "%right" => Associativity::Right,
+        "%nonassoc" => Associativity::NonAssoc,
+        _ => panic!("Unexpected associativity"),
+    };
+
+    let precedence = Precedence {
+        level: *level,
+        associativity,
+    };
+    *level += 1;
+
+    let mut ops_node = prec_node.get_child(1);
+    loop {
+        let operator_node = ops_node.get_child(ops_node.children.len() - 1);
+        let operator = operator_node.lhs.lexeme();
+        if !operators.insert(operator.clone()) {
+            return Err(spec::GenError::PrecedenceErr(format!(
+                "Operator '{}' has multiple precedence declarations",
+                operator
+            ))
+            .at(*operator_node.span()));
+        }
+        grammar_builder.mark_precedence(operator, precedence);
+
+        if ops_node.children.len() == 1 {
+            break;
+        }
+        ops_node = ops_node.get_child(0);
+    }
+
+    Ok(())
+}
+
 /// Recursively traverses `SpecSymbol::States` nodes to build CDFA state definitions.
 ///
 /// Returns an error if a state definition cannot be built.
@@ -381,7 +635,9 @@ where
         }
         SpecSymbol::TDef => {
             for source in sources {
-                builder.default_to(source, transit_builder.build())?;
+                builder
+                    .default_to(source, transit_builder.build())
+                    .map_err(|err| spec::GenError::from(err).at(*matcher.span()))?;
             }
         }
         _ => panic!("Transition map input is neither Matchers nor TDef"),
@@ -434,15 +690,16 @@ where
 
         let is_simple = matcher_cleaned.len() == 1;
         for source in sources {
-            if is_simple {
+            let res = if is_simple {
                 builder.mark_trans(
                     source,
                     transit_builder.build(),
                     matcher_cleaned.chars().next().unwrap(),
-                )?;
+                )
             } else {
-                builder.mark_chain(source, transit_builder.build(), &matcher_cleaned)?;
-            }
+                builder.mark_chain(source, transit_builder.build(), &matcher_cleaned)
+            };
+            res.map_err(|err| spec::GenError::from(err).at(*matcher.span()))?;
         }
     } else {
         // This is a range matcher.
@@ -463,7 +720,8 @@ where
             return Err(spec::GenError::MatcherErr(format!(
                 "Range start must be one character, but was '{}'",
                 range_start_string
-            )));
+            ))
+            .at(*range_start_node.span()));
         }
 
         let escaped_range_end_string: String = range_end_node
@@ -479,18 +737,21 @@ where
             return Err(spec::GenError::MatcherErr(format!(
                 "Range end must be one character, but was '{}'",
                 range_end_string
-            )));
+            ))
+            .at(*range_end_node.span()));
         }
 
         let range_start = range_start_string.chars().next().unwrap();
         let range_end = range_end_string.chars().next().unwrap();
 
-        builder.mark_range_for_all(
-            sources.iter(),
-            transit_builder.build(),
-            range_start,
-            range_end,
-        )?;
+        builder
+            .mark_range_for_all(
+                sources.iter(),
+                transit_builder.build(),
+                range_start,
+                range_end,
+            )
+            .map_err(|err| spec::GenError::from(err).at(*mtc_node.span()))?;
     }
 
     // Recurse if there are more matchers for this transition.
@@ -548,10 +809,12 @@ fn add_cdfa_state_tokenizer<CDFABuilderType, CDFAType, Symbol: GrammarSymbol, Gr
 /// # Parameters
 ///
 /// * `prods_node` - the `SpecSymbol::Productions` node of the parse tree to traverse.
+/// * `recovery` - whether error recovery is enabled for the specification.
 /// * `grammar_builder` - the grammar builder for the specification.
 /// * `formatter_builder` - the formatter builder for the specification.
 fn generate_grammar_prods<Symbol: GrammarSymbol, GrammarType>(
     prods_node: &Tree<SpecSymbol>,
+    recovery: bool,
     grammar_builder: &mut dyn GrammarBuilder<String, Symbol, GrammarType>,
     formatter_builder: &mut FormatterBuilder<Symbol>,
 ) -> Result<(), spec::GenError>
@@ -560,7 +823,12 @@ where
 {
     // Recurse if there are more productions in this grammar region.
     if prods_node.children.len() == 2 {
-        generate_grammar_prods(prods_node.get_child(0), grammar_builder, formatter_builder)?;
+        generate_grammar_prods(
+            prods_node.get_child(0),
+            recovery,
+            grammar_builder,
+            formatter_builder,
+        )?;
     }
 
     let prod_node = prods_node.get_child(prods_node.children.len() - 1);
@@ -573,6 +841,7 @@ where
         prod_node.get_child(2),
         id,
         def_pattern_node,
+        recovery,
         grammar_builder,
         formatter_builder,
     )
@@ -588,6 +857,7 @@ where
 /// * `rhss_node` - the `SpecSymbol::RightHandSides` node of the parse tree to traverse.
 /// * `lhs` - the left-hand-side symbol common to each production in this definition.
 /// * `def_pattern_node` - the default formatter pattern for this production definition.
+/// * `recovery` - whether error recovery is enabled for the specification.
 /// * `grammar_builder` - the grammar builder for the specification.
 /// * `formatter_builder` - the formatter builder for the specification.
 #[allow(clippy::ptr_arg)]
@@ -595,6 +865,7 @@ fn generate_grammar_rhss<Symbol: GrammarSymbol, GrammarType>(
     rhss_node: &Tree<SpecSymbol>,
     lhs: &String,
     def_pattern_node: &Tree<SpecSymbol>,
+    recovery: bool,
     grammar_builder: &mut dyn GrammarBuilder<String, Symbol, GrammarType>,
     formatter_builder: &mut FormatterBuilder<Symbol>,
 ) -> Result<(), spec::GenError>
@@ -605,7 +876,7 @@ where
 
     // Build list of symbols representing the right-hand-side of this production.
     let mut ids: Vec<ProductionSymbol<String>> = Vec::new();
-    generate_grammar_ids(rhs_node.get_child(1), &mut ids, grammar_builder);
+    generate_grammar_ids(rhs_node.get_child(1), &mut ids, recovery, grammar_builder);
 
     // Try to mark this symbol as the start symbol of the grammar.
     // This will only succeed for the first caller.
@@ -614,21 +885,30 @@ where
     let string_production = Production::from(lhs.clone(), ids);
     let production = grammar_builder.add_production(string_production.clone());
 
+    let mut annotations: Vec<&Tree<SpecSymbol>> = Vec::new();
+    generate_grammar_annotations(rhs_node.get_child(2), &mut annotations);
+    if let Some(priority) = annotation_priority(&annotations, &string_production)? {
+        grammar_builder.mark_priority(&string_production, priority);
+    }
+
     // If this production does not have a pattern, use the default one.
-    let mut pattopt_node = rhs_node.get_child(2);
+    let mut pattopt_node = rhs_node.get_child(3);
     if pattopt_node.is_empty() {
         pattopt_node = def_pattern_node
     }
 
     if !pattopt_node.is_empty() {
-        let pattc = &pattopt_node.get_child(0).lhs.lexeme();
+        let pattern_node = pattopt_node.get_child(0);
+        let pattc = &pattern_node.lhs.lexeme();
         let pattern_str = &pattc[..].trim_matches('`');
 
-        formatter_builder.add_pattern(PatternPair {
-            production,
-            string_production,
-            pattern: (*pattern_str).to_string(),
-        })?;
+        formatter_builder
+            .add_pattern(PatternPair {
+                production,
+                string_production,
+                pattern: (*pattern_str).to_string(),
+            })
+            .map_err(|err| spec::GenError::from(err).at(*pattern_node.span()))?;
     }
 
     // Recurse if there are more production right-hand-sides.
@@ -637,6 +917,7 @@ where
             rhss_node.get_child(0),
             lhs,
             def_pattern_node,
+            recovery,
             grammar_builder,
             formatter_builder,
         )?;
@@ -645,6 +926,73 @@ where
     Ok(())
 }
 
+/// Recursively traverse `SpecSymbol::Annotations` nodes to build the list of `TAnnotation` nodes
+/// on a production right-hand-side.
+///
+/// # Parameters
+///
+/// * `annotations_node` - the `SpecSymbol::Annotations` node of the parse tree to traverse.
+/// * `annotations_accumulator` - a vector to store the discovered annotation nodes.
+fn generate_grammar_annotations<'tree>(
+    annotations_node: &'tree Tree<SpecSymbol>,
+    annotations_accumulator: &mut Vec<&'tree Tree<SpecSymbol>>,
+) {
+    if annotations_node.is_empty() {
+        return;
+    }
+
+    generate_grammar_annotations(annotations_node.get_child(0), annotations_accumulator);
+
+    annotations_accumulator.push(annotations_node.get_child(1));
+}
+
+/// Returns the priority declared by the annotations of a production right-hand-side, if any.
+///
+/// `@prefer` declares a priority of 1, `@avoid` a priority of -1, and `@<n>` a priority of the
+/// integer `n`. Returns an error if an annotation is not recognized or if more than one priority
+/// is declared.
+///
+/// # Parameters
+///
+/// * `annotations` - the `TAnnotation` nodes of the production.
+/// * `production` - the production being annotated, used to describe errors.
+fn annotation_priority(
+    annotations: &[&Tree<SpecSymbol>],
+    production: &Production<String>,
+) -> Result<Option<i32>, spec::GenError> {
+    let mut priority: Option<i32> = None;
+
+    for annotation_node in annotations {
+        let annotation = &annotation_node.lhs.lexeme()[1..];
+        let value = match annotation {
+            "prefer" => 1,
+            "avoid" => -1,
+            _ => match annotation.parse::<i32>() {
+                Ok(value) => value,
+                Err(_) => {
+                    return Err(spec::GenError::AnnotationErr(format!(
+                        "Unknown annotation '@{}' on production '{}'",
+                        annotation,
+                        production.to_string()
+                    ))
+                    .at(*annotation_node.span()));
+                }
+            },
+        };
+
+        if priority.is_some() {
+            return Err(spec::GenError::AnnotationErr(format!(
+                "Multiple priorities declared on production '{}'",
+                production.to_string()
+            ))
+            .at(*annotation_node.span()));
+        }
+        priority = Some(value);
+    }
+
+    Ok(priority)
+}
+
 /// Recursively traverse `SpecSymbol::Ids` nodes to build the list of production symbols of a
 /// production right-hand-side.
 ///
@@ -652,10 +1000,12 @@ where
 ///
 /// * `ids_node` - the `SpecSymbol::Ids` node of the parse tree to traverse.
 /// * `ids_accumulator` - a vector to store the discovered production symbols.
+/// * `recovery` - whether error recovery is enabled, reserving the error symbol.
 /// * `grammar_builder` - the grammar builder for the specification.
 fn generate_grammar_ids<Symbol: GrammarSymbol, GrammarType>(
     ids_node: &Tree<SpecSymbol>,
     ids_accumulator: &mut Vec<ProductionSymbol<String>>,
+    recovery: bool,
     grammar_builder: &mut dyn GrammarBuilder<String, Symbol, GrammarType>,
 ) where
     GrammarType: Grammar<Symbol>,
@@ -664,26 +1014,46 @@ fn generate_grammar_ids<Symbol: GrammarSymbol, GrammarType>(
         return;
     }
 
-    generate_grammar_ids(ids_node.get_child(0), ids_accumulator, grammar_builder);
+    generate_grammar_ids(
+        ids_node.get_child(0),
+        ids_accumulator,
+        recovery,
+        grammar_builder,
+    );
 
     let id_node = ids_node.get_child(1);
     let symbol = match id_node.lhs.kind() {
-        SpecSymbol::TId => ProductionSymbol::symbol(id_node.lhs.lexeme().clone()),
+        SpecSymbol::TId => {
+            let id = id_node.lhs.lexeme();
+            if recovery && *id == *spec::ERROR_SYMBOL {
+                // The error symbol matches any non-empty sequence of unparsable tokens.
+                grammar_builder.mark_error(id);
+                ProductionSymbol::symbol_list(id.clone())
+            } else {
+                ProductionSymbol::symbol(id.clone())
+            }
+        }
         SpecSymbol::TOptId => {
             let lex = &id_node.lhs.lexeme()[..];
             let dest = &lex[1..lex.len() - 1].to_string();
 
             // Add (hidden) intermediate optional state to grammar.
-            let opt_state: String = format!("opt#{}", dest);
+            let opt_state: String = format!("{}{}", *spec::OPT_PREFIX, dest);
             grammar_builder.add_optional_state(&opt_state, dest);
 
             ProductionSymbol::symbol(opt_state)
         }
         SpecSymbol::TListId => {
             let lex = &id_node.lhs.lexeme()[..];
-            let target = lex[1..lex.len() - 1].to_string();
-
-            ProductionSymbol::symbol_list(target)
+            let mut parts = lex[1..lex.len() - 1].split('/').map(|part| part.trim());
+            let target = parts.next().unwrap().to_string();
+
+            match parts.next() {
+                Some(separator) => {
+                    ProductionSymbol::symbol_separated_list(target, separator.to_string())
+                }
+                None => ProductionSymbol::symbol_list(target),
+            }
         }
         _ => panic!("Unexpected production identifier type"),
     };
@@ -694,9 +1064,13 @@ fn generate_grammar_ids<Symbol: GrammarSymbol, GrammarType>(
 /// Returns an error if there are any terminal symbols in the grammar which are not tokenized by the
 /// CDFA. Such symbols can never be produced, so any productions involving them are meaningless,
 /// and as such they are an indicator of possible grammar or CDFA specification errors.
+///
+/// The error is located at the first use of the orphaned terminal in the regions under
+/// `regions_node`.
 fn orphan_check<Symbol: GrammarSymbol>(
     ecdfa: &EncodedCDFA<Symbol>,
     grammar: &dyn Grammar<Symbol>,
+    regions_node: &Tree<SpecSymbol>,
 ) -> Result<(), spec::GenError> {
     let mut ecdfa_products: HashSet<&Symbol> = HashSet::new();
     for product in ecdfa.produces() {
@@ -705,12 +1079,106 @@ fn orphan_check<Symbol: GrammarSymbol>(
 
     for symbol in grammar.terminals() {
         if !ecdfa_products.contains(symbol) {
-            return Err(spec::GenError::MappingErr(format!(
+            let symbol_string = grammar.symbol_string(symbol);
+            let err = spec::GenError::MappingErr(format!(
                 "Orphaned terminal '{}' is not tokenized by the ECDFA",
-                grammar.symbol_string(symbol),
-            )));
+                symbol_string,
+            ));
+            return Err(locate_id(
+                err,
+                regions_node,
+                RegionType::Grammar,
+                &symbol_string,
+            ));
         }
     }
 
     Ok(())
 }
+
+/// Returns an error if the reserved error symbol of the grammar is tokenized by the CDFA, since
+/// such tokens would be consumed by error recovery rather than parsed.
+///
+/// The error is located at the first use of the error symbol in the CDFA regions under
+/// `regions_node`.
+fn error_symbol_check<Symbol: GrammarSymbol>(
+    ecdfa: &EncodedCDFA<Symbol>,
+    grammar: &dyn Grammar<Symbol>,
+    regions_node: &Tree<SpecSymbol>,
+) -> Result<(), spec::GenError> {
+    let error = match grammar.error_symbol() {
+        Some(error) => error,
+        None => return Ok(()),
+    };
+
+    if ecdfa.produces().any(|product| product == error) {
+        let symbol_string = grammar.symbol_string(error);
+        let err = spec::GenError::MappingErr(format!(
+            "Reserved error symbol '{}' is tokenized by the ECDFA",
+            symbol_string,
+        ));
+        return Err(locate_id(
+            err,
+            regions_node,
+            RegionType::CDFA,
+            &symbol_string,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns the grammar build error `err`, located at the first reference to the offending symbol
+/// in the region which declares it.
+///
+/// # Parameters
+///
+/// * `err` - the grammar build error.
+/// * `regions_node` - the root `SpecSymbol::Regions` node of the parse tree.
+fn locate_grammar_err(err: grammar::BuildError, regions_node: &Tree<SpecSymbol>) -> spec::GenError {
+    let (region_type, symbol) = match err {
+        grammar::BuildError::NonTerminalIgnoredErr(ref symbol)
+        | grammar::BuildError::IgnoredAndInjectedErr(ref symbol) => {
+            (RegionType::Ignorable, symbol.clone())
+        }
+        grammar::BuildError::NonTerminalInjectedErr(ref symbol) => {
+            (RegionType::Injectable, symbol.clone())
+        }
+        grammar::BuildError::NonTerminalSyncErr(ref symbol) => (RegionType::Sync, symbol.clone()),
+        grammar::BuildError::NonTerminalDirectiveErr(ref symbol) => {
+            (RegionType::Directive, symbol.clone())
+        }
+        grammar::BuildError::NonTerminalErrorErr(ref symbol)
+        | grammar::BuildError::NonTerminalPrecedenceErr(ref symbol) => {
+            (RegionType::Grammar, symbol.clone())
+        }
+    };
+
+    locate_id(
+        spec::GenError::from(err),
+        regions_node,
+        region_type,
+        &symbol,
+    )
+}
+
+/// Returns `err` located at the first reference to identifier `id` within a specification region
+/// of type `region_type`, or `err` itself if there is no such reference.
+///
+/// # Parameters
+///
+/// * `err` - the error to locate.
+/// * `regions_node` - the root `SpecSymbol::Regions` node of the parse tree.
+/// * `region_type` - the type of region to search for `id`.
+/// * `id` - the identifier which caused `err`.
+fn locate_id(
+    err: spec::GenError,
+    regions_node: &Tree<SpecSymbol>,
+    region_type: RegionType,
+    id: &str,
+) -> spec::GenError {
+    match region::find_id(regions_node, &region_type, id) {
+        Some(span) => err.in_region(region_type, span),
+        None => err,
+    }
+}
@@ -3,14 +3,14 @@ use core::{
     lex::{
         self,
         ecdfa::{EncodedCDFA, EncodedCDFABuilder},
-        CDFABuilder, Transit,
+        CDFABuilder, Position, Span, Transit,
     },
     parse::{
         self,
         grammar::{self, GrammarBuilder, GrammarSymbol, SimpleGrammar, SimpleGrammarBuilder},
         Tree,
     },
-    spec,
+    spec::{self, region, SpecLocation},
 };
 
 // S: An enum whose elements are the states of the CDFA for lexing a specification.
@@ -26,6 +26,15 @@ enum S {
     Ignorable,
     IgnorableTag,
     IgnorableId,
+    Sync,
+    SyncTag,
+    SyncId,
+    Directive,
+    DirectiveTag,
+    DirectiveId,
+    Width,
+    WidthTag,
+    WidthValue,
     Alphabet,
     AlphabetTag,
     AlphabetString,
@@ -39,6 +48,20 @@ enum S {
     GrammarTag,
     GrammarEntryBrace,
     GrammarBody,
+    Test,
+    TestTag,
+    TestNamePartial,
+    TestNameEscaped,
+    TestName,
+    TestPreBody,
+    TestEntryBrace,
+    TestBody,
+    TestInput,
+    TestExpect,
+    TextOpenTick,
+    TextPartial,
+    TextCloseTick,
+    Text,
     RegionExitBrace,
     Or,
     Hat,
@@ -58,7 +81,15 @@ enum S {
     OptIdPartial,
     OptId,
     ListIdPartial,
+    ListIdSpace,
+    ListIdSlash,
+    ListIdSeparator,
+    ListIdSeparatorSpace,
     ListId,
+    AnnotationPartial,
+    Annotation,
+    AssociativityPartial,
+    Associativity,
     Fail,
 }
 
@@ -83,9 +114,13 @@ fn build_spec_ecdfa() -> Result<EncodedCDFA<SpecSymbol>, lex::CDFAError> {
         .state(&S::Start)
         .mark_chain(Transit::to(S::InjectableTag), "inject")?
         .mark_chain(Transit::to(S::IgnorableTag), "ignore")?
+        .mark_chain(Transit::to(S::SyncTag), "sync")?
+        .mark_chain(Transit::to(S::DirectiveTag), "directive")?
+        .mark_chain(Transit::to(S::WidthTag), "width")?
         .mark_chain(Transit::to(S::AlphabetTag), "alphabet")?
         .mark_chain(Transit::to(S::CDFATag), "cdfa")?
         .mark_chain(Transit::to(S::GrammarTag), "grammar")?
+        .mark_chain(Transit::to(S::TestTag), "test")?
         .mark_trans(Transit::to(S::Comment), '#')?
         .mark_trans(Transit::to(S::Whitespace), ' ')?
         .mark_trans(Transit::to(S::Whitespace), '\t')?
@@ -94,9 +129,13 @@ fn build_spec_ecdfa() -> Result<EncodedCDFA<SpecSymbol>, lex::CDFAError> {
 
     build_injectable_region(&mut builder)?;
     build_ignorable_region(&mut builder)?;
+    build_sync_region(&mut builder)?;
+    build_directive_region(&mut builder)?;
+    build_width_region(&mut builder)?;
     build_alphabet_region(&mut builder)?;
     build_cdfa_region(&mut builder)?;
     build_grammar_region(&mut builder)?;
+    build_test_region(&mut builder)?;
 
     builder.state(&S::Whitespace).accept();
 
@@ -241,6 +280,97 @@ fn build_ignorable_region(
     Ok(())
 }
 
+/// Augments `builder` to lex sync specification regions, or an error if it cannot be built.
+fn build_sync_region(
+    builder: &mut EncodedCDFABuilder<S, SpecSymbol>,
+) -> Result<(), lex::CDFAError> {
+    builder
+        .state(&S::SyncTag)
+        .accept_to(&S::Sync)
+        .tokenize(&SpecSymbol::TSync);
+
+    builder
+        .state(&S::Sync)
+        .mark_range(Transit::to(S::SyncId), 'a', 'z')?
+        .mark_range(Transit::to(S::SyncId), 'A', 'Z')?
+        .mark_range(Transit::to(S::SyncId), '0', '9')?
+        .mark_trans(Transit::to(S::Comment), '#')?
+        .mark_trans(Transit::to(S::Whitespace), ' ')?
+        .mark_trans(Transit::to(S::Whitespace), '\t')?
+        .mark_trans(Transit::to(S::Whitespace), '\r')?
+        .mark_trans(Transit::to(S::Whitespace), '\n')?;
+
+    builder
+        .state(&S::SyncId)
+        .mark_range(Transit::to(S::SyncId), 'a', 'z')?
+        .mark_range(Transit::to(S::SyncId), 'A', 'Z')?
+        .mark_range(Transit::to(S::SyncId), '0', '9')?
+        .mark_trans(Transit::to(S::SyncId), '_')?
+        .accept_to(&S::Start)
+        .tokenize(&SpecSymbol::TId);
+
+    Ok(())
+}
+
+/// Augments `builder` to lex directive specification regions, or an error if it cannot be built.
+fn build_directive_region(
+    builder: &mut EncodedCDFABuilder<S, SpecSymbol>,
+) -> Result<(), lex::CDFAError> {
+    builder
+        .state(&S::DirectiveTag)
+        .accept_to(&S::Directive)
+        .tokenize(&SpecSymbol::TDirective);
+
+    builder
+        .state(&S::Directive)
+        .mark_range(Transit::to(S::DirectiveId), 'a', 'z')?
+        .mark_range(Transit::to(S::DirectiveId), 'A', 'Z')?
+        .mark_range(Transit::to(S::DirectiveId), '0', '9')?
+        .mark_trans(Transit::to(S::Comment), '#')?
+        .mark_trans(Transit::to(S::Whitespace), ' ')?
+        .mark_trans(Transit::to(S::Whitespace), '\t')?
+        .mark_trans(Transit::to(S::Whitespace), '\r')?
+        .mark_trans(Transit::to(S::Whitespace), '\n')?;
+
+    builder
+        .state(&S::DirectiveId)
+        .mark_range(Transit::to(S::DirectiveId), 'a', 'z')?
+        .mark_range(Transit::to(S::DirectiveId), 'A', 'Z')?
+        .mark_range(Transit::to(S::DirectiveId), '0', '9')?
+        .mark_trans(Transit::to(S::DirectiveId), '_')?
+        .accept_to(&S::Start)
+        .tokenize(&SpecSymbol::TId);
+
+    Ok(())
+}
+
+/// Augments `builder` to lex width specification regions, or an error if it cannot be built.
+fn build_width_region(
+    builder: &mut EncodedCDFABuilder<S, SpecSymbol>,
+) -> Result<(), lex::CDFAError> {
+    builder
+        .state(&S::WidthTag)
+        .accept_to(&S::Width)
+        .tokenize(&SpecSymbol::TWidth);
+
+    builder
+        .state(&S::Width)
+        .mark_range(Transit::to(S::WidthValue), '0', '9')?
+        .mark_trans(Transit::to(S::Comment), '#')?
+        .mark_trans(Transit::to(S::Whitespace), ' ')?
+        .mark_trans(Transit::to(S::Whitespace), '\t')?
+        .mark_trans(Transit::to(S::Whitespace), '\r')?
+        .mark_trans(Transit::to(S::Whitespace), '\n')?;
+
+    builder
+        .state(&S::WidthValue)
+        .mark_range(Transit::to(S::WidthValue), '0', '9')?
+        .accept_to(&S::Start)
+        .tokenize(&SpecSymbol::TNumber);
+
+    Ok(())
+}
+
 /// Augments `builder` to lex alphabet specification regions, or an error if it cannot be built.
 fn build_alphabet_region(
     builder: &mut EncodedCDFABuilder<S, SpecSymbol>,
@@ -375,6 +505,8 @@ fn build_grammar_region(
         .mark_trans(Transit::to(S::OptIdPartial), '[')?
         .mark_trans(Transit::to(S::ListIdPartial), '{')?
         .mark_trans(Transit::to(S::PatternPartial), '`')?
+        .mark_trans(Transit::to(S::AnnotationPartial), '@')?
+        .mark_trans(Transit::to(S::AssociativityPartial), '%')?
         .mark_trans(Transit::to(S::RegionExitBrace), '}')?
         .mark_trans(Transit::to(S::Comment), '#')?
         .mark_trans(Transit::to(S::Whitespace), ' ')?
@@ -401,13 +533,163 @@ fn build_grammar_region(
         .mark_range(Transit::to(S::ListIdPartial), 'a', 'z')?
         .mark_range(Transit::to(S::ListIdPartial), 'A', 'Z')?
         .mark_range(Transit::to(S::ListIdPartial), '0', '9')?
-        .mark_trans(Transit::to(S::ListIdPartial), '_')?;
+        .mark_trans(Transit::to(S::ListIdPartial), '_')?
+        .mark_trans(Transit::to(S::ListIdSpace), ' ')?
+        .mark_trans(Transit::to(S::ListIdSlash), '/')?;
+
+    builder
+        .state(&S::ListIdSpace)
+        .mark_trans(Transit::to(S::ListIdSpace), ' ')?
+        .mark_trans(Transit::to(S::ListIdSlash), '/')?;
+
+    builder
+        .state(&S::ListIdSlash)
+        .mark_trans(Transit::to(S::ListIdSlash), ' ')?
+        .mark_range(Transit::to(S::ListIdSeparator), 'a', 'z')?
+        .mark_range(Transit::to(S::ListIdSeparator), 'A', 'Z')?
+        .mark_range(Transit::to(S::ListIdSeparator), '0', '9')?
+        .mark_trans(Transit::to(S::ListIdSeparator), '_')?;
+
+    builder
+        .state(&S::ListIdSeparator)
+        .mark_trans(Transit::to(S::ListId), '}')?
+        .mark_range(Transit::to(S::ListIdSeparator), 'a', 'z')?
+        .mark_range(Transit::to(S::ListIdSeparator), 'A', 'Z')?
+        .mark_range(Transit::to(S::ListIdSeparator), '0', '9')?
+        .mark_trans(Transit::to(S::ListIdSeparator), '_')?
+        .mark_trans(Transit::to(S::ListIdSeparatorSpace), ' ')?;
+
+    builder
+        .state(&S::ListIdSeparatorSpace)
+        .mark_trans(Transit::to(S::ListIdSeparatorSpace), ' ')?
+        .mark_trans(Transit::to(S::ListId), '}')?;
 
     builder
         .state(&S::ListId)
         .accept()
         .tokenize(&SpecSymbol::TListId);
 
+    builder
+        .state(&S::AnnotationPartial)
+        .mark_range(Transit::to(S::Annotation), 'a', 'z')?
+        .mark_range(Transit::to(S::Annotation), 'A', 'Z')?
+        .mark_range(Transit::to(S::Annotation), '0', '9')?
+        .mark_trans(Transit::to(S::Annotation), '_')?
+        .mark_trans(Transit::to(S::Annotation), '-')?;
+
+    builder
+        .state(&S::Annotation)
+        .mark_range(Transit::to(S::Annotation), 'a', 'z')?
+        .mark_range(Transit::to(S::Annotation), 'A', 'Z')?
+        .mark_range(Transit::to(S::Annotation), '0', '9')?
+        .mark_trans(Transit::to(S::Annotation), '_')?
+        .mark_trans(Transit::to(S::Annotation), '-')?
+        .accept()
+        .tokenize(&SpecSymbol::TAnnotation);
+
+    builder
+        .state(&S::AssociativityPartial)
+        .mark_chain(Transit::to(S::Associativity), "left")?
+        .mark_chain(Transit::to(S::Associativity), "right")?
+        .mark_chain(Transit::to(S::Associativity), "nonassoc")?;
+
+    builder
+        .state(&S::Associativity)
+        .accept()
+        .tokenize(&SpecSymbol::TAssociativity);
+
+    Ok(())
+}
+
+/// Augments `builder` to lex test specification regions, or an error if it cannot be built.
+fn build_test_region(
+    builder: &mut EncodedCDFABuilder<S, SpecSymbol>,
+) -> Result<(), lex::CDFAError> {
+    builder
+        .state(&S::TestTag)
+        .accept_to(&S::Test)
+        .tokenize(&SpecSymbol::TTest);
+
+    builder
+        .state(&S::Test)
+        .mark_trans(Transit::to(S::TestNamePartial), '"')?
+        .mark_trans(Transit::to(S::Comment), '#')?
+        .mark_trans(Transit::to(S::Whitespace), ' ')?
+        .mark_trans(Transit::to(S::Whitespace), '\t')?
+        .mark_trans(Transit::to(S::Whitespace), '\r')?
+        .mark_trans(Transit::to(S::Whitespace), '\n')?;
+
+    builder
+        .state(&S::TestNamePartial)
+        .mark_trans(Transit::to(S::TestName), '"')?
+        .mark_trans(Transit::to(S::TestNameEscaped), '\\')?
+        .default_to(Transit::to(S::TestNamePartial))?;
+
+    builder
+        .state(&S::TestNameEscaped)
+        .default_to(Transit::to(S::TestNamePartial))?;
+
+    builder
+        .state(&S::TestName)
+        .accept_to(&S::TestPreBody)
+        .tokenize(&SpecSymbol::TString);
+
+    builder
+        .state(&S::TestPreBody)
+        .mark_trans(Transit::to(S::TestEntryBrace), '{')?
+        .mark_trans(Transit::to(S::Comment), '#')?
+        .mark_trans(Transit::to(S::Whitespace), ' ')?
+        .mark_trans(Transit::to(S::Whitespace), '\t')?
+        .mark_trans(Transit::to(S::Whitespace), '\r')?
+        .mark_trans(Transit::to(S::Whitespace), '\n')?;
+
+    builder
+        .state(&S::TestEntryBrace)
+        .accept_to(&S::TestBody)
+        .tokenize(&SpecSymbol::TLeftBrace);
+
+    builder
+        .state(&S::TestBody)
+        .mark_chain(Transit::to(S::TestInput), "input")?
+        .mark_chain(Transit::to(S::TestExpect), "expect")?
+        .mark_trans(Transit::to(S::TextOpenTick), '`')?
+        .mark_trans(Transit::to(S::RegionExitBrace), '}')?
+        .mark_trans(Transit::to(S::Comment), '#')?
+        .mark_trans(Transit::to(S::Whitespace), ' ')?
+        .mark_trans(Transit::to(S::Whitespace), '\t')?
+        .mark_trans(Transit::to(S::Whitespace), '\r')?
+        .mark_trans(Transit::to(S::Whitespace), '\n')?;
+
+    builder
+        .state(&S::TestInput)
+        .accept()
+        .tokenize(&SpecSymbol::TInput);
+
+    builder
+        .state(&S::TestExpect)
+        .accept()
+        .tokenize(&SpecSymbol::TExpect);
+
+    // Text is delimited by double backticks, so that it can contain single backticks.
+    builder
+        .state(&S::TextOpenTick)
+        .mark_trans(Transit::to(S::TextPartial), '`')?;
+
+    builder
+        .state(&S::TextPartial)
+        .mark_trans(Transit::to(S::TextCloseTick), '`')?
+        .default_to(Transit::to(S::TextPartial))?;
+
+    builder
+        .state(&S::TextCloseTick)
+        .mark_trans(Transit::to(S::Text), '`')?
+        .default_to(Transit::to(S::TextPartial))?;
+
+    builder
+        .state(&S::Text)
+        .accept()
+        .tokenize(&SpecSymbol::TText);
+
     Ok(())
 }
 
@@ -420,6 +702,9 @@ pub enum SpecSymbol {
     Region,
     Injectable,
     Ignorable,
+    Sync,
+    Directive,
+    Width,
     Alphabet,
     CDFA,
     States,
@@ -438,12 +723,17 @@ pub enum SpecSymbol {
     IdOrDef,
     AcceptorDestinationOpt,
     Grammar,
+    Test,
+    Precedences,
+    Precedence,
+    Operators,
     Productions,
     Production,
     PatternOpt,
     RightHandSides,
     RightHandSide,
     Ids,
+    Annotations,
     TAlphabet,
     TCil,
     TCDFA,
@@ -460,10 +750,21 @@ pub enum SpecSymbol {
     TPattern,
     TOptId,
     TListId,
+    TAnnotation,
+    TAssociativity,
     TDef,
     TIgnorable,
+    TSync,
+    TDirective,
+    TWidth,
+    TNumber,
     TInjectable,
     TInjectionAffinity,
+    TTest,
+    TString,
+    TInput,
+    TExpect,
+    TText,
 }
 
 impl Default for SpecSymbol {
@@ -501,9 +802,13 @@ fn build_spec_grammar() -> Result<SimpleGrammar<SpecSymbol>, grammar::BuildError
         .from(SpecSymbol::Region)
         .to(vec![SpecSymbol::Injectable])
         .to(vec![SpecSymbol::Ignorable])
+        .to(vec![SpecSymbol::Sync])
+        .to(vec![SpecSymbol::Directive])
+        .to(vec![SpecSymbol::Width])
         .to(vec![SpecSymbol::Alphabet])
         .to(vec![SpecSymbol::CDFA])
-        .to(vec![SpecSymbol::Grammar]);
+        .to(vec![SpecSymbol::Grammar])
+        .to(vec![SpecSymbol::Test]);
 
     builder.from(SpecSymbol::Injectable).to(vec![
         SpecSymbol::TInjectable,
@@ -516,10 +821,33 @@ fn build_spec_grammar() -> Result<SimpleGrammar<SpecSymbol>, grammar::BuildError
         .from(SpecSymbol::Ignorable)
         .to(vec![SpecSymbol::TIgnorable, SpecSymbol::TId]);
 
+    builder
+        .from(SpecSymbol::Sync)
+        .to(vec![SpecSymbol::TSync, SpecSymbol::TId]);
+
+    builder
+        .from(SpecSymbol::Directive)
+        .to(vec![SpecSymbol::TDirective, SpecSymbol::TId]);
+
+    builder
+        .from(SpecSymbol::Width)
+        .to(vec![SpecSymbol::TWidth, SpecSymbol::TNumber]);
+
     builder
         .from(SpecSymbol::Alphabet)
         .to(vec![SpecSymbol::TAlphabet, SpecSymbol::TCil]);
 
+    builder.from(SpecSymbol::Test).to(vec![
+        SpecSymbol::TTest,
+        SpecSymbol::TString,
+        SpecSymbol::TLeftBrace,
+        SpecSymbol::TInput,
+        SpecSymbol::TText,
+        SpecSymbol::TExpect,
+        SpecSymbol::TText,
+        SpecSymbol::TRightBrace,
+    ]);
+
     builder.from(SpecSymbol::CDFA).to(vec![
         SpecSymbol::TCDFA,
         SpecSymbol::TLeftBrace,
@@ -607,10 +935,27 @@ fn build_spec_grammar() -> Result<SimpleGrammar<SpecSymbol>, grammar::BuildError
     builder.from(SpecSymbol::Grammar).to(vec![
         SpecSymbol::TGrammar,
         SpecSymbol::TLeftBrace,
+        SpecSymbol::Precedences,
         SpecSymbol::Productions,
         SpecSymbol::TRightBrace,
     ]);
 
+    builder
+        .from(SpecSymbol::Precedences)
+        .to(vec![SpecSymbol::Precedences, SpecSymbol::Precedence])
+        .epsilon();
+
+    builder.from(SpecSymbol::Precedence).to(vec![
+        SpecSymbol::TAssociativity,
+        SpecSymbol::Operators,
+        SpecSymbol::TSemi,
+    ]);
+
+    builder
+        .from(SpecSymbol::Operators)
+        .to(vec![SpecSymbol::Operators, SpecSymbol::TId])
+        .to(vec![SpecSymbol::TId]);
+
     builder
         .from(SpecSymbol::Productions)
         .to(vec![SpecSymbol::Productions, SpecSymbol::Production])
@@ -631,6 +976,7 @@ fn build_spec_grammar() -> Result<SimpleGrammar<SpecSymbol>, grammar::BuildError
     builder.from(SpecSymbol::RightHandSide).to(vec![
         SpecSymbol::TOr,
         SpecSymbol::Ids,
+        SpecSymbol::Annotations,
         SpecSymbol::PatternOpt,
     ]);
 
@@ -646,6 +992,11 @@ fn build_spec_grammar() -> Result<SimpleGrammar<SpecSymbol>, grammar::BuildError
         .to(vec![SpecSymbol::Ids, SpecSymbol::TListId])
         .epsilon();
 
+    builder
+        .from(SpecSymbol::Annotations)
+        .to(vec![SpecSymbol::Annotations, SpecSymbol::TAnnotation])
+        .epsilon();
+
     builder
         .from(SpecSymbol::IdOrDef)
         .to(vec![SpecSymbol::TId])
@@ -660,8 +1011,50 @@ fn build_spec_grammar() -> Result<SimpleGrammar<SpecSymbol>, grammar::BuildError
 /// from the input.
 pub fn parse_spec(input: &str) -> Result<Tree<SpecSymbol>, spec::ParseError> {
     SPEC_ECDFA.with(|cdfa| -> Result<Tree<SpecSymbol>, spec::ParseError> {
-        let tokens = lex::def_lexer().lex(input, cdfa)?;
-        let parse = parse::def_parser().parse(tokens, &*SPEC_GRAMMAR)?;
-        Ok(parse)
+        let tokens = match lex::def_lexer().lex(input, cdfa) {
+            Ok(tokens) => tokens,
+            Err(err) => {
+                let position = err.position();
+                return Err(locate(spec::ParseError::from(err), position, input, cdfa));
+            }
+        };
+
+        match parse::def_parser().parse(tokens, &*SPEC_GRAMMAR) {
+            Ok(parse) => Ok(parse),
+            Err(err) => {
+                let position = err.position;
+                Err(locate(spec::ParseError::from(err), position, input, cdfa))
+            }
+        }
     })
 }
+
+/// Returns the specification parse error `err` located at `position` of the specification
+/// `input`, within the region enclosing that position, or `err` itself if `position` is unknown.
+///
+/// The region is found by re-lexing the input preceding `position`, which lexes successfully
+/// since both lexing and parsing errors occur at token boundaries.
+fn locate(
+    err: spec::ParseError,
+    position: Option<Position>,
+    input: &str,
+    cdfa: &EncodedCDFA<SpecSymbol>,
+) -> spec::ParseError {
+    match position {
+        Some(position) => {
+            let region = lex::def_lexer()
+                .lex(&input[..position.offset], cdfa)
+                .ok()
+                .and_then(|tokens| region::enclosing(&tokens));
+
+            spec::ParseError::LocatedErr(
+                Box::new(err),
+                SpecLocation {
+                    span: Span::new(position, position),
+                    region,
+                },
+            )
+        }
+        None => err,
+    }
+}
@@ -0,0 +1,431 @@
+use {
+    core::{
+        lex::{ecdfa::EncodedCDFA, Span},
+        parse::{
+            grammar::{Grammar, GrammarSymbol},
+            Tree,
+        },
+        spec::{
+            lang::SpecSymbol,
+            region::{self, RegionType},
+        },
+        util::encoder::Encoder,
+    },
+    std::{
+        collections::{HashMap, HashSet},
+        fmt,
+    },
+};
+
+/// Spec Lint: A likely problem in a specification, found by analysing its grammar and CDFA.
+///
+/// # Fields
+///
+/// * `kind` - the kind of problem found.
+/// * `name` - the name of the symbol or state with the problem.
+/// * `span` - the region of the specification where `name` is declared.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SpecLint {
+    pub kind: SpecLintKind,
+    pub name: String,
+    pub span: Span,
+}
+
+impl fmt::Display for SpecLint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            SpecLintKind::UnreachableNonTerminal => write!(
+                f,
+                "Non-terminal '{}' is unreachable from the start symbol",
+                self.name
+            ),
+            SpecLintKind::UnproductiveNonTerminal => write!(
+                f,
+                "Non-terminal '{}' cannot derive any terminal string",
+                self.name
+            ),
+            SpecLintKind::UnusedToken => {
+                write!(f, "Token '{}' is never used by the grammar", self.name)
+            }
+            SpecLintKind::OrphanedTerminal => {
+                write!(f, "Terminal '{}' is never produced by the CDFA", self.name)
+            }
+            SpecLintKind::UnreachableState => write!(
+                f,
+                "CDFA state '{}' is unreachable from the start state",
+                self.name
+            ),
+        }
+    }
+}
+
+/// Spec Lint Kind: The kinds of problems found when linting a specification.
+///
+/// # Types
+///
+/// * `UnreachableNonTerminal` - indicates a non-terminal which cannot be derived from the start
+///   symbol of the grammar.
+/// * `UnproductiveNonTerminal` - indicates a non-terminal which cannot derive any string of
+///   terminals, so can never appear in a parse.
+/// * `UnusedToken` - indicates a token produced by the CDFA which is never used by the grammar,
+///   either as a terminal or as an ignorable, injectable or directive symbol.
+/// * `OrphanedTerminal` - indicates a terminal used by the grammar which is never produced by the
+///   CDFA, so productions involving it can never be parsed.
+/// * `UnreachableState` - indicates a CDFA state which cannot be reached from the start state.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum SpecLintKind {
+    UnreachableNonTerminal,
+    UnproductiveNonTerminal,
+    UnusedToken,
+    OrphanedTerminal,
+    UnreachableState,
+}
+
+/// Returns the problems found in the grammar and CDFA generated from the specification `parse`,
+/// ordered by their location in the specification.
+///
+/// # Parameters
+///
+/// * `parse` - the specification parse tree.
+/// * `ecdfa` - the CDFA generated from `parse`.
+/// * `states` - the encoder of the states of `ecdfa`, which maps state names to CDFA states.
+/// * `grammar` - the grammar generated from `parse`.
+pub fn lint<Symbol: GrammarSymbol>(
+    parse: &Tree<SpecSymbol>,
+    ecdfa: &EncodedCDFA<Symbol>,
+    states: &Encoder<String>,
+    grammar: &dyn Grammar<Symbol>,
+) -> Vec<SpecLint> {
+    let declarations = Declarations::from(parse);
+    let mut lints: Vec<SpecLint> = Vec::new();
+
+    let reachable = reachable_non_terminals(grammar);
+    let productive = productive_non_terminals(grammar);
+
+    for non_terminal in grammar.non_terminals() {
+        let name = grammar.symbol_string(non_terminal);
+        if !reachable.contains(non_terminal) {
+            lints.extend(declarations.lint(
+                &declarations.non_terminals,
+                SpecLintKind::UnreachableNonTerminal,
+                name,
+            ));
+        } else if !productive.contains(non_terminal) {
+            lints.extend(declarations.lint(
+                &declarations.non_terminals,
+                SpecLintKind::UnproductiveNonTerminal,
+                name,
+            ));
+        }
+    }
+
+    let tokens: HashSet<&Symbol> = ecdfa.produces().collect();
+    for token in &tokens {
+        let used = grammar.terminals().contains(token)
+            || grammar.is_ignorable(token)
+            || grammar.is_injectable(token)
+            || grammar.is_directive(token);
+
+        if !used {
+            lints.extend(declarations.lint(
+                &declarations.tokens,
+                SpecLintKind::UnusedToken,
+                grammar.symbol_string(token),
+            ));
+        }
+    }
+
+    for terminal in grammar.terminals() {
+        if !tokens.contains(terminal) {
+            let name = grammar.symbol_string(terminal);
+            if let Some(span) = region::find_id(parse.get_child(0), &RegionType::Grammar, &name) {
+                lints.push(SpecLint {
+                    kind: SpecLintKind::OrphanedTerminal,
+                    name,
+                    span,
+                });
+            }
+        }
+    }
+
+    let reachable_states: HashSet<&String> = ecdfa
+        .reachable_states()
+        .into_iter()
+        .filter_map(|state| states.decode(state))
+        .collect();
+    for state in declarations.states.keys() {
+        if !reachable_states.contains(state) {
+            lints.extend(declarations.lint(
+                &declarations.states,
+                SpecLintKind::UnreachableState,
+                state.clone(),
+            ));
+        }
+    }
+
+    lints.sort_by_key(|lint| lint.span.start.offset);
+    lints
+}
+
+/// Returns the non-terminals of `grammar` which can be derived from its start symbol.
+fn reachable_non_terminals<Symbol: GrammarSymbol>(
+    grammar: &dyn Grammar<Symbol>,
+) -> HashSet<&Symbol> {
+    let mut reachable: HashSet<&Symbol> = HashSet::new();
+    let mut stack: Vec<&Symbol> = vec![grammar.start()];
+    reachable.insert(grammar.start());
+
+    while let Some(lhs) = stack.pop() {
+        for production in grammar.productions_for_lhs(lhs).into_iter().flatten() {
+            for sym in &production.rhs {
+                for symbol in Some(&sym.symbol).into_iter().chain(&sym.separator) {
+                    if grammar.is_non_terminal(symbol) && reachable.insert(symbol) {
+                        stack.push(symbol);
+                    }
+                }
+            }
+        }
+    }
+
+    reachable
+}
+
+/// Returns the non-terminals of `grammar` which can derive a (possibly empty) string of
+/// terminals.
+fn productive_non_terminals<Symbol: GrammarSymbol>(
+    grammar: &dyn Grammar<Symbol>,
+) -> HashSet<&Symbol> {
+    let mut productive: HashSet<&Symbol> = HashSet::new();
+
+    loop {
+        let mut changed = false;
+
+        for lhs in grammar.non_terminals() {
+            if productive.contains(lhs) {
+                continue;
+            }
+
+            let derives =
+                grammar
+                    .productions_for_lhs(lhs)
+                    .into_iter()
+                    .flatten()
+                    .any(|production| {
+                        production.rhs.iter().all(|sym| {
+                            Some(&sym.symbol)
+                                .into_iter()
+                                .chain(&sym.separator)
+                                .all(|symbol| {
+                                    !grammar.is_non_terminal(symbol) || productive.contains(symbol)
+                                })
+                        })
+                    });
+
+            if derives {
+                productive.insert(lhs);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return productive;
+        }
+    }
+}
+
+/// Declarations: The locations of the first declaration of each name in a specification.
+///
+/// # Fields
+///
+/// * `non_terminals` - the left-hand-side symbols of production definitions.
+/// * `tokens` - the token kinds of CDFA acceptors.
+/// * `states` - the declared CDFA states.
+struct Declarations {
+    non_terminals: HashMap<String, Span>,
+    tokens: HashMap<String, Span>,
+    states: HashMap<String, Span>,
+}
+
+impl Declarations {
+    /// Returns the declarations found in the specification `parse`.
+    fn from(parse: &Tree<SpecSymbol>) -> Self {
+        let mut declarations = Self {
+            non_terminals: HashMap::new(),
+            tokens: HashMap::new(),
+            states: HashMap::new(),
+        };
+
+        let mut stack: Vec<&Tree<SpecSymbol>> = vec![parse];
+        while let Some(node) = stack.pop() {
+            if node.is_leaf() {
+                continue;
+            }
+
+            let names = match node.lhs.kind() {
+                SpecSymbol::Production => Some(&mut declarations.non_terminals),
+                SpecSymbol::IdOrDef => Some(&mut declarations.tokens),
+                SpecSymbol::Targets => Some(&mut declarations.states),
+                _ => None,
+            };
+
+            if let Some(names) = names {
+                node.children
+                    .iter()
+                    .filter(|child| *child.lhs.kind() == SpecSymbol::TId)
+                    .for_each(|child| {
+                        let name = child.lhs.lexeme().clone();
+                        let span = *child.span();
+                        let first = names.entry(name).or_insert(span);
+                        if span.start.offset < first.start.offset {
+                            *first = span;
+                        }
+                    });
+            }
+
+            stack.extend(node.children.iter());
+        }
+
+        declarations
+    }
+
+    /// Returns a lint of kind `kind` for `name`, located at its declaration in `names`, or `None`
+    /// if `name` is not declared (e.g. if it is an internal symbol).
+    fn lint(
+        &self,
+        names: &HashMap<String, Span>,
+        kind: SpecLintKind,
+        name: String,
+    ) -> Option<SpecLint> {
+        names.get(&name).map(|span| SpecLint {
+            kind,
+            name,
+            span: *span,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{parse::grammar::EncodedGrammarBuilder, spec};
+
+    #[test]
+    fn lint_clean_spec() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        ' ' -> ^_;
+}
+
+grammar {
+    s
+        | s A
+        | A;
+}
+        ";
+
+        let parse = spec::parse_spec(spec).unwrap();
+
+        //exercise
+        let lints = spec::lint_spec(&parse, EncodedGrammarBuilder::new()).unwrap();
+
+        //verify
+        assert!(lints.is_empty());
+    }
+
+    #[test]
+    fn lint_problems() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^B
+        'c' -> comment;
+
+    comment ^COMMENT;
+
+    orphan
+        'd' -> ^D;
+}
+
+ignore COMMENT
+
+grammar {
+    s
+        | A
+        | s loop;
+
+    loop
+        | A loop;
+
+    unused
+        | B;
+}
+        ";
+
+        let parse = spec::parse_spec(spec).unwrap();
+
+        //exercise
+        let lints = spec::lint_spec(&parse, EncodedGrammarBuilder::new()).unwrap();
+
+        //verify
+        let lint_strings: Vec<String> = lints
+            .iter()
+            .map(|lint| {
+                format!(
+                    "{}:{} {}",
+                    lint.span.start.line, lint.span.start.character, lint
+                )
+            })
+            .collect();
+        assert_eq!(
+            lint_strings,
+            vec![
+                "10:5 CDFA state 'orphan' is unreachable from the start state",
+                "11:17 Token 'D' is never used by the grammar",
+                "21:5 Non-terminal 'loop' cannot derive any terminal string",
+                "24:5 Non-terminal 'unused' is unreachable from the start symbol",
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_orphaned_terminal() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' -> ^A;
+}
+
+grammar {
+    s
+        | A
+        | B;
+}
+        ";
+
+        let parse = spec::parse_spec(spec).unwrap();
+
+        //exercise
+        let lints = spec::lint_spec(&parse, EncodedGrammarBuilder::new()).unwrap();
+
+        //verify
+        let lint_strings: Vec<String> = lints
+            .iter()
+            .map(|lint| {
+                format!(
+                    "{}:{} {}",
+                    lint.span.start.line, lint.span.start.character, lint
+                )
+            })
+            .collect();
+        assert_eq!(
+            lint_strings,
+            vec!["10:11 Terminal 'B' is never produced by the CDFA"]
+        );
+        assert!(spec::generate_spec(&parse, EncodedGrammarBuilder::new()).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 use {
     core::{
         fmt::{self, Formatter},
-        lex::{self, CDFA},
+        lex::{self, ecdfa::EncodedCDFA, Span},
         parse::{
             self,
             grammar::{self, Grammar, GrammarBuilder, GrammarSymbol},
@@ -12,22 +12,30 @@ use {
     std::{self, error},
 };
 
+pub use self::{
+    lint::{SpecLint, SpecLintKind},
+    region::RegionType,
+};
+
 mod gen;
 mod lang;
+mod lint;
 mod region;
 
 lazy_static! {
     /// The default transition matcher for CDFA specifications.
     pub static ref DEF_MATCHER: String = String::from("_");
+
+    /// The reserved grammar symbol which matches unparsable input during error recovery.
+    pub static ref ERROR_SYMBOL: String = String::from("error");
+
+    /// The prefix of the (hidden) intermediate non-terminals which match optional symbols.
+    pub static ref OPT_PREFIX: String = String::from("opt#");
 }
 
 /// Spec Gen Result: Stores the CDFA, grammar, and formatter produced during specification
 /// generation.
-type SpecGenResult<Symbol> = (
-    Box<dyn CDFA<usize, Symbol>>,
-    Box<dyn Grammar<Symbol>>,
-    Formatter<Symbol>,
-);
+type SpecGenResult<Symbol, GrammarType> = (EncodedCDFA<Symbol>, GrammarType, Formatter<Symbol>);
 
 /// Parses a specification from the `input` string, returning the result or an error if `input` is
 /// not a valid specification.
@@ -45,7 +53,7 @@ pub fn parse_spec(input: &str) -> Result<Tree<SpecSymbol>, ParseError> {
 pub fn generate_spec<Symbol: 'static + GrammarSymbol, GrammarType, GrammarBuilderType>(
     parse: &Tree<SpecSymbol>,
     grammar_builder: GrammarBuilderType,
-) -> Result<SpecGenResult<Symbol>, GenError>
+) -> Result<SpecGenResult<Symbol, GrammarType>, GenError>
 where
     GrammarType: 'static + Grammar<Symbol>,
     GrammarBuilderType: GrammarBuilder<String, Symbol, GrammarType>,
@@ -53,6 +61,73 @@ where
     gen::generate_spec(parse, grammar_builder)
 }
 
+/// Generates a specification from a parse tree, returning the problems found in its grammar and
+/// CDFA (ordered by their location in the specification), or an error if `parse` does not
+/// represent a valid specification.
+///
+/// # Parameters
+///
+/// * `parse` - the specification parse tree.
+/// * `grammar_builder` - a builder for the specification grammar.
+pub fn lint_spec<Symbol: 'static + GrammarSymbol, GrammarType, GrammarBuilderType>(
+    parse: &Tree<SpecSymbol>,
+    grammar_builder: GrammarBuilderType,
+) -> Result<Vec<SpecLint>, GenError>
+where
+    GrammarType: 'static + Grammar<Symbol>,
+    GrammarBuilderType: GrammarBuilder<String, Symbol, GrammarType>,
+{
+    gen::lint_spec(parse, grammar_builder)
+}
+
+/// Returns the tests declared in the `test` regions of a specification parse tree, or an error if
+/// `parse` is missing a required region.
+pub fn generate_tests(parse: &Tree<SpecSymbol>) -> Result<Vec<SpecTest>, GenError> {
+    gen::generate_tests(parse)
+}
+
+/// Spec Test: An example declared in a `test` region of a specification, pairing an input with
+/// its expected formatted output.
+///
+/// # Fields
+///
+/// * `name` - the name of the test.
+/// * `input` - the text to format.
+/// * `expect` - the expected result of formatting `input`.
+/// * `span` - the region of the specification where the test is declared.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SpecTest {
+    pub name: String,
+    pub input: String,
+    pub expect: String,
+    pub span: Span,
+}
+
+/// Spec Location: The location of a construct in a specification, used to describe errors.
+///
+/// # Fields
+///
+/// * `span` - the region of the specification text covered by the construct.
+/// * `region` - the type of the specification region containing the construct, or `None` if the
+///   construct is not within a region.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SpecLocation {
+    pub span: Span,
+    pub region: Option<RegionType>,
+}
+
+impl std::fmt::Display for SpecLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}", self.span.start.line, self.span.start.character)?;
+
+        if let Some(ref region) = self.region {
+            write!(f, " in {} region", region)?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Gen Error: Represents and error encountered while generating a specification.
 ///
 /// # Types
@@ -63,6 +138,10 @@ where
 /// * `FormatterErr` - indicates an internal error encountered while building a formatter.
 /// * `GrammarBuildErr` - indicates an internal error encountered while building a grammar.
 /// * `RegionErr` - indicates and error encountered while traversing specification regions.
+/// * `WidthErr` - indicates a declared line width which is too large, storing the declared width.
+/// * `AnnotationErr` - indicates an unknown or conflicting annotation on a production.
+/// * `PrecedenceErr` - indicates a conflicting operator precedence declaration.
+/// * `LocatedErr` - wraps an error with the location of the construct which caused it.
 #[derive(Debug)]
 pub enum GenError {
     MatcherErr(String),
@@ -71,6 +150,46 @@ pub enum GenError {
     FormatterErr(fmt::BuildError),
     GrammarBuildErr(grammar::BuildError),
     RegionErr(region::Error),
+    WidthErr(String),
+    AnnotationErr(String),
+    PrecedenceErr(String),
+    LocatedErr(Box<GenError>, SpecLocation),
+}
+
+impl GenError {
+    /// Returns the location of the construct in the specification which caused this error, or
+    /// `None` if it is not known.
+    pub fn location(&self) -> Option<&SpecLocation> {
+        match *self {
+            Self::LocatedErr(_, ref location) => Some(location),
+            _ => None,
+        }
+    }
+
+    /// Returns this error located at `span` of the specification, unless it is already located.
+    fn at(self, span: Span) -> Self {
+        match self {
+            Self::LocatedErr(..) => self,
+            err => Self::LocatedErr(Box::new(err), SpecLocation { span, region: None }),
+        }
+    }
+
+    /// Returns this error within a specification region of type `region`, located at `span` of
+    /// the specification unless it is already located more precisely.
+    fn in_region(self, region: RegionType, span: Span) -> Self {
+        let (err, span) = match self {
+            Self::LocatedErr(err, location) => (*err, location.span),
+            err => (err, span),
+        };
+
+        Self::LocatedErr(
+            Box::new(err),
+            SpecLocation {
+                span,
+                region: Some(region),
+            },
+        )
+    }
 }
 
 impl std::fmt::Display for GenError {
@@ -82,6 +201,10 @@ impl std::fmt::Display for GenError {
             Self::FormatterErr(ref err) => write!(f, "Formatter build error: {}", err),
             Self::GrammarBuildErr(ref err) => write!(f, "Grammar build error: {}", err),
             Self::RegionErr(ref err) => write!(f, "Region error: {}", err),
+            Self::WidthErr(ref width) => write!(f, "Line width '{}' is too large", width),
+            Self::AnnotationErr(ref err) => write!(f, "Annotation error: {}", err),
+            Self::PrecedenceErr(ref err) => write!(f, "Precedence error: {}", err),
+            Self::LocatedErr(ref err, ref location) => write!(f, "{} at {}", err, location),
         }
     }
 }
@@ -95,6 +218,11 @@ impl error::Error for GenError {
             Self::FormatterErr(ref err) => Some(err),
             Self::GrammarBuildErr(ref err) => Some(err),
             Self::RegionErr(ref err) => Some(err),
+            Self::WidthErr(_) => None,
+            Self::AnnotationErr(_) => None,
+            Self::PrecedenceErr(_) => None,
+            // The location wrapper is transparent, so the wrapped error's source is returned.
+            Self::LocatedErr(ref err, _) => err.source(),
         }
     }
 }
@@ -129,10 +257,24 @@ impl From<region::Error> for GenError {
 ///
 /// * `LexErr` - indicates a syntactic error.
 /// * `ParseErr` - indicates a semantic error.
+/// * `LocatedErr` - wraps an error with the location in the specification where it occurred.
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum ParseError {
     LexErr(lex::Error),
     ParseErr(parse::Error),
+    LocatedErr(Box<ParseError>, SpecLocation),
+}
+
+impl ParseError {
+    /// Returns the location in the specification where this error occurred, or `None` if it is
+    /// not known.
+    pub fn location(&self) -> Option<&SpecLocation> {
+        match *self {
+            Self::LocatedErr(_, ref location) => Some(location),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for ParseError {
@@ -140,6 +282,11 @@ impl std::fmt::Display for ParseError {
         match *self {
             Self::LexErr(ref err) => write!(f, "Lex error: {}", err),
             Self::ParseErr(ref err) => write!(f, "Parse error: {}", err),
+            // The wrapped error already describes the position, so only the region is added.
+            Self::LocatedErr(ref err, ref location) => match location.region {
+                Some(ref region) => write!(f, "{} in {} region", err, region),
+                None => write!(f, "{}", err),
+            },
         }
     }
 }
@@ -149,6 +296,8 @@ impl error::Error for ParseError {
         match *self {
             Self::LexErr(ref err) => Some(err),
             Self::ParseErr(ref err) => Some(err),
+            // The location wrapper is transparent, so the wrapped error's source is returned.
+            Self::LocatedErr(ref err, _) => err.source(),
         }
     }
 }
@@ -207,6 +356,8 @@ mod tests {
             └── Grammar
                 ├── TGrammar <- 'grammar'
                 ├── TLeftBrace <- '{'
+                ├── Precedences
+                │   └──  <- 'NULL'
                 ├── Productions
                 │   └── Production
                 │       ├── TId <- 's'
@@ -221,6 +372,8 @@ mod tests {
                 │       │       │   │   │   └──  <- 'NULL'
                 │       │       │   │   └── TId <- 's'
                 │       │       │   └── TId <- 'b'
+                │       │       ├── Annotations
+                │       │       │   └──  <- 'NULL'
                 │       │       └── PatternOpt
                 │       │           └──  <- 'NULL'
                 │       └── TSemi <- ';'
@@ -414,6 +567,8 @@ grammar {
             └── Grammar
                 ├── TGrammar <- 'grammar'
                 ├── TLeftBrace <- '{'
+                ├── Precedences
+                │   └──  <- 'NULL'
                 ├── Productions
                 │   ├── Productions
                 │   │   ├── Productions
@@ -431,12 +586,16 @@ grammar {
                 │   │   │       │   │       │   │   │   └──  <- 'NULL'
                 │   │   │       │   │       │   │   └── TId <- 's'
                 │   │   │       │   │       │   └── TId <- 'b'
+                │   │   │       │   │       ├── Annotations
+                │   │   │       │   │       │   └──  <- 'NULL'
                 │   │   │       │   │       └── PatternOpt
                 │   │   │       │   │           └──  <- 'NULL'
                 │   │   │       │   └── RightHandSide
                 │   │   │       │       ├── TOr <- '|'
                 │   │   │       │       ├── Ids
                 │   │   │       │       │   └──  <- 'NULL'
+                │   │   │       │       ├── Annotations
+                │   │   │       │       │   └──  <- 'NULL'
                 │   │   │       │       └── PatternOpt
                 │   │   │       │           └──  <- 'NULL'
                 │   │   │       └── TSemi <- ';'
@@ -456,6 +615,8 @@ grammar {
                 │   │       │   │       │   │   │   └── TId <- 'LBRACKET'
                 │   │       │   │       │   │   └── TId <- 's'
                 │   │       │   │       │   └── TId <- 'RBRACKET'
+                │   │       │   │       ├── Annotations
+                │   │       │   │       │   └──  <- 'NULL'
                 │   │       │   │       └── PatternOpt
                 │   │       │   │           └── TPattern <- '``'
                 │   │       │   └── RightHandSide
@@ -464,6 +625,8 @@ grammar {
                 │   │       │       │   ├── Ids
                 │   │       │       │   │   └──  <- 'NULL'
                 │   │       │       │   └── TId <- 'w'
+                │   │       │       ├── Annotations
+                │   │       │       │   └──  <- 'NULL'
                 │   │       │       └── PatternOpt
                 │   │       │           └──  <- 'NULL'
                 │   │       └── TSemi <- ';'
@@ -478,6 +641,8 @@ grammar {
                 │       │       │   ├── Ids
                 │       │       │   │   └──  <- 'NULL'
                 │       │       │   └── TId <- 'WHITESPACE'
+                │       │       ├── Annotations
+                │       │       │   └──  <- 'NULL'
                 │       │       └── PatternOpt
                 │       │           └── TPattern <- '`[prefix]{0}\\n\\n{1;prefix=[prefix]\\t}[prefix]{2}\\n\\n`'
                 │       └── TSemi <- ';'
@@ -676,6 +841,8 @@ grammar {
             └── Grammar
                 ├── TGrammar <- 'grammar'
                 ├── TLeftBrace <- '{'
+                ├── Precedences
+                │   └──  <- 'NULL'
                 ├── Productions
                 │   └── Production
                 │       ├── TId <- 's'
@@ -691,6 +858,8 @@ grammar {
                 │       │   │       │   │   │   └──  <- 'NULL'
                 │       │   │       │   │   └── TId <- 'ID'
                 │       │   │       │   └── TId <- 's'
+                │       │   │       ├── Annotations
+                │       │   │       │   └──  <- 'NULL'
                 │       │   │       └── PatternOpt
                 │       │   │           └──  <- 'NULL'
                 │       │   └── RightHandSide
@@ -699,6 +868,8 @@ grammar {
                 │       │       │   ├── Ids
                 │       │       │   │   └──  <- 'NULL'
                 │       │       │   └── TId <- 'ID'
+                │       │       ├── Annotations
+                │       │       │   └──  <- 'NULL'
                 │       │       └── PatternOpt
                 │       │           └── TPattern <- '`{}`'
                 │       └── TSemi <- ';'
@@ -751,12 +922,12 @@ grammar {
             generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
 
         //input
-        let tokens = lexer.lex(input, &*cdfa);
-        let tree = parser.parse(tokens.unwrap(), &*grammar);
+        let tokens = lexer.lex(input, &cdfa);
+        let tree = parser.parse(tokens.unwrap(), &grammar);
         let parse = tree.unwrap();
 
         //exercise
-        let res = formatter.format(&parse);
+        let res = formatter.format(&parse, input, &[], None);
 
         //verify
         assert_eq!(
@@ -826,7 +997,7 @@ grammar {
         let (cdfa, _, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
 
         //exercise
-        let tokens = lexer.lex(input, &*cdfa).unwrap();
+        let tokens = lexer.lex(input, &cdfa).unwrap();
 
         let mut result = String::new();
         for token in tokens {
@@ -877,7 +1048,7 @@ grammar {
         let (cdfa, _, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
 
         //exercise
-        let tokens = lexer.lex(input, &*cdfa).unwrap();
+        let tokens = lexer.lex(input, &cdfa).unwrap();
 
         //verify
         assert_eq!(
@@ -922,7 +1093,7 @@ grammar {
         let (cdfa, _, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
 
         //exercise
-        let tokens = lexer.lex(input, &*cdfa).unwrap();
+        let tokens = lexer.lex(input, &cdfa).unwrap();
 
         //verify
         assert_eq!(tokens_string(tokens), "\nkind=ID lexeme=a\nkind=ID lexeme=ababab\nkind=ID lexeme=_abab\nkind=ID lexeme=ab_abba_")
@@ -962,7 +1133,7 @@ grammar {
         let lexer = lex::def_lexer();
 
         //exercise
-        let tokens = lexer.lex(input, &*cdfa).unwrap();
+        let tokens = lexer.lex(input, &cdfa).unwrap();
 
         //verify
         assert_eq!(
@@ -1022,8 +1193,8 @@ grammar {
         let parser = parse::def_parser();
 
         //exercise
-        let tokens = lexer.lex(input, &*cdfa).unwrap();
-        let tree = parser.parse(tokens, &*grammar).unwrap();
+        let tokens = lexer.lex(input, &cdfa).unwrap();
+        let tree = parser.parse(tokens, &grammar).unwrap();
 
         //verify
         assert_eq!(
@@ -1088,9 +1259,9 @@ grammar {
         let parser = parse::def_parser();
 
         //exercise
-        let tokens = lexer.lex(input, &*cdfa).unwrap();
-        let tree = parser.parse(tokens, &*grammar).unwrap();
-        let res = formatter.format(&tree);
+        let tokens = lexer.lex(input, &cdfa).unwrap();
+        let tree = parser.parse(tokens, &grammar).unwrap();
+        let res = formatter.format(&tree, input, &[], None);
 
         //verify
         assert_eq!(res, "SEPARATED: a b a a");
@@ -1127,7 +1298,7 @@ grammar {
         let (cdfa, _, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
 
         //exercise
-        let tokens = lexer.lex(input, &*cdfa).unwrap();
+        let tokens = lexer.lex(input, &cdfa).unwrap();
 
         //verify
         assert_eq!(
@@ -1188,7 +1359,7 @@ grammar {
         let (cdfa, _, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
 
         //exercise
-        let tokens = lexer.lex(input, &*cdfa).unwrap();
+        let tokens = lexer.lex(input, &cdfa).unwrap();
 
         //verify
         assert_eq!(
@@ -1289,6 +1460,8 @@ grammar {
             └── Grammar
                 ├── TGrammar <- 'grammar'
                 ├── TLeftBrace <- '{'
+                ├── Precedences
+                │   └──  <- 'NULL'
                 ├── Productions
                 │   └── Production
                 │       ├── TId <- 's'
@@ -1299,6 +1472,8 @@ grammar {
                 │       │       ├── TOr <- '|'
                 │       │       ├── Ids
                 │       │       │   └──  <- 'NULL'
+                │       │       ├── Annotations
+                │       │       │   └──  <- 'NULL'
                 │       │       └── PatternOpt
                 │       │           └──  <- 'NULL'
                 │       └── TSemi <- ';'
@@ -1339,7 +1514,7 @@ grammar {
         let (cdfa, _, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
 
         //exercise
-        let tokens = lexer.lex(input, &*cdfa).unwrap();
+        let tokens = lexer.lex(input, &cdfa).unwrap();
 
         //verify
         assert_eq!(
@@ -1385,8 +1560,8 @@ grammar {
         let (cdfa, grammar, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
 
         //exercise
-        let tokens = lexer.lex(input, &*cdfa).unwrap();
-        let parse = parse::def_parser().parse(tokens, &*grammar).unwrap();
+        let tokens = lexer.lex(input, &cdfa).unwrap();
+        let parse = parse::def_parser().parse(tokens, &grammar).unwrap();
 
         //verify
         assert_eq!(
@@ -1423,8 +1598,8 @@ grammar {
         let (cdfa, grammar, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
 
         //exercise
-        let tokens = lexer.lex(input, &*cdfa).unwrap();
-        let parse = parse::def_parser().parse(tokens, &*grammar).unwrap();
+        let tokens = lexer.lex(input, &cdfa).unwrap();
+        let parse = parse::def_parser().parse(tokens, &grammar).unwrap();
 
         //verify
         assert_eq!(
@@ -1437,6 +1612,710 @@ grammar {
         )
     }
 
+    #[test]
+    fn inline_list_separated() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        ',' -> ^COMMA
+        ';' -> ^SEMI;
+}
+
+grammar {
+    s | {A / COMMA} SEMI;
+}
+        ";
+
+        let input = "a,a,a;";
+
+        let lexer = lex::def_lexer();
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+        let (cdfa, grammar, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
+
+        //exercise
+        let tokens = lexer.lex(input, &cdfa).unwrap();
+        let parse = parse::def_parser().parse(tokens, &grammar).unwrap();
+
+        //verify
+        assert_eq!(
+            parse.to_string(),
+            "└── s
+    ├── ?
+    │   ├── A <- 'a'
+    │   ├── COMMA <- ','
+    │   ├── A <- 'a'
+    │   ├── COMMA <- ','
+    │   └── A <- 'a'
+    └── SEMI <- ';'"
+        )
+    }
+
+    #[test]
+    fn inline_list_separated_trailing_separator() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        ',' -> ^COMMA;
+}
+
+grammar {
+    s | {A / COMMA};
+}
+        ";
+
+        let input = "a,a,";
+
+        let lexer = lex::def_lexer();
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+        let (cdfa, grammar, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
+
+        //exercise
+        let tokens = lexer.lex(input, &cdfa).unwrap();
+        let res = parse::def_parser().parse(tokens, &grammar);
+
+        //verify
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn error_recovery() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^B
+        ';' -> ^SEMI
+        ' ' -> ^_;
+}
+
+sync SEMI
+
+grammar {
+    s
+        | s stmt `{}{}\\n`
+        | ;
+
+    stmt
+        | A B SEMI `{} {}{}`
+        | error SEMI `{}{}`;
+}
+        ";
+
+        let input = "ab;  b  a ;ab;";
+
+        let lexer = lex::def_lexer();
+        let parser = parse::def_parser();
+
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+        let (cdfa, grammar, formatter) =
+            generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
+
+        //exercise
+        let tokens = lexer.lex(input, &cdfa).unwrap();
+        let tree = parser.parse(tokens, &grammar).unwrap();
+        let res = formatter.format(&tree, input, &[], None);
+
+        //verify
+        assert_eq!(res, "a b;\nb  a;\na b;\n");
+    }
+
+    #[test]
+    fn error_recovery_stops_at_sync() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^B
+        ';' -> ^SEMI;
+}
+
+sync SEMI
+
+grammar {
+    s | s stmt | ;
+
+    stmt
+        | A B SEMI
+        | error;
+}
+        ";
+
+        let input = "ab;b;a";
+
+        let lexer = lex::def_lexer();
+        let parser = parse::def_parser();
+
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+        let (cdfa, grammar, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
+
+        //exercise
+        let tokens = lexer.lex(input, &cdfa).unwrap();
+        let res = parser.parse(tokens, &grammar);
+
+        //verify
+        assert!(res.is_err());
+        assert_eq!(
+            format!("{}", res.err().unwrap()),
+            "Largest parse did not consume all tokens: 3 of 6 at 1:4, expected `A` in stmt"
+        );
+    }
+
+    #[test]
+    fn error_symbol_without_sync() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        'e' -> ^error
+        ';' -> ^SEMI;
+}
+
+grammar {
+    s | s stmt `{}{}\\n` | ;
+
+    stmt
+        | A SEMI
+        | error SEMI;
+}
+        ";
+
+        let input = "a;e;";
+
+        let lexer = lex::def_lexer();
+        let parser = parse::def_parser();
+
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+        let (cdfa, grammar, formatter) =
+            generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
+
+        //exercise
+        let tokens = lexer.lex(input, &cdfa).unwrap();
+        let tree = parser.parse(tokens, &grammar).unwrap();
+        let res = formatter.format(&tree, input, &[], None);
+
+        //verify
+        assert!(grammar.error_symbol().is_none());
+        assert_eq!(res, "a;\ne;\n");
+    }
+
+    #[test]
+    fn production_priority_prefer() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'i' -> ^IF
+        'e' -> ^EXPR
+        'x' -> ^X
+        'l' -> ^ELSE;
+}
+
+grammar {
+    stmt
+        | IF EXPR stmt @prefer
+        | IF EXPR stmt ELSE stmt
+        | X;
+}
+        ";
+
+        let input = "ieiexlx";
+
+        let lexer = lex::def_lexer();
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+        let (cdfa, grammar, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
+
+        //exercise
+        let tokens = lexer.lex(input, &cdfa).unwrap();
+        let parse = parse::def_parser().parse(tokens, &grammar).unwrap();
+
+        //verify
+        assert_eq!(
+            parse.to_string(),
+            "└── stmt
+    ├── IF <- 'i'
+    ├── EXPR <- 'e'
+    └── stmt
+        ├── IF <- 'i'
+        ├── EXPR <- 'e'
+        ├── stmt
+        │   └── X <- 'x'
+        ├── ELSE <- 'l'
+        └── stmt
+            └── X <- 'x'"
+        )
+    }
+
+    #[test]
+    fn production_priority_numeric() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'i' -> ^IF
+        'e' -> ^EXPR
+        'x' -> ^X
+        'l' -> ^ELSE;
+}
+
+grammar {
+    stmt
+        | IF EXPR stmt @-1
+        | IF EXPR stmt ELSE stmt @2
+        | X;
+}
+        ";
+
+        let input = "ieiexlx";
+
+        let lexer = lex::def_lexer();
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+        let (cdfa, grammar, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
+
+        //exercise
+        let tokens = lexer.lex(input, &cdfa).unwrap();
+        let parse = parse::def_parser().parse(tokens, &grammar).unwrap();
+
+        //verify
+        assert_eq!(
+            parse.to_string(),
+            "└── stmt
+    ├── IF <- 'i'
+    ├── EXPR <- 'e'
+    ├── stmt
+    │   ├── IF <- 'i'
+    │   ├── EXPR <- 'e'
+    │   └── stmt
+    │       └── X <- 'x'
+    ├── ELSE <- 'l'
+    └── stmt
+        └── X <- 'x'"
+        )
+    }
+
+    #[test]
+    fn production_priority_unknown_annotation() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'x' -> ^X;
+}
+
+grammar {
+    s | X @never;
+}
+        ";
+
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+
+        //exercise
+        let res = generate_spec(&parse, SimpleGrammarBuilder::new());
+
+        //verify
+        assert!(res.is_err());
+        assert_eq!(
+            format!("{}", res.err().unwrap()),
+            "Annotation error: Unknown annotation '@never' on production 's X' at 8:11 in grammar \
+             region"
+        );
+    }
+
+    #[test]
+    fn gen_error_location() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'x' -> ^X
+        'ab' .. 'c' -> ^Y;
+}
+
+grammar {
+    s | X;
+}
+        ";
+
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+
+        //exercise
+        let res = generate_spec(&parse, SimpleGrammarBuilder::new());
+
+        //verify
+        let err = res.err().unwrap();
+        let location = err.location().unwrap();
+        assert_eq!(location.span.start.line, 5);
+        assert_eq!(location.span.start.character, 9);
+        assert_eq!(location.span.end.character, 13);
+        assert_eq!(location.region, Some(RegionType::CDFA));
+        assert_eq!(
+            format!("{}", err),
+            "Matcher definition error: Range start must be one character, but was 'ab' at 5:9 in \
+             cdfa region"
+        );
+    }
+
+    #[test]
+    fn gen_error_location_build_error() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'x' -> ^X;
+}
+
+ignore X
+
+grammar {
+    s | X;
+}
+
+inject left s
+        ";
+
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+
+        //exercise
+        let res = generate_spec(&parse, SimpleGrammarBuilder::new());
+
+        //verify
+        let err = res.err().unwrap();
+        let location = err.location().unwrap();
+        assert_eq!(location.span.start.line, 13);
+        assert_eq!(location.span.start.character, 13);
+        assert_eq!(location.region, Some(RegionType::Injectable));
+    }
+
+    #[test]
+    fn parse_error_location() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'x' -> ^X;
+}
+
+grammar {
+    s | X
+}
+        ";
+
+        //exercise
+        let res = lang::parse_spec(spec);
+
+        //verify
+        let err = res.err().unwrap();
+        let location = err.location().unwrap();
+        assert_eq!(location.span.start.line, 9);
+        assert_eq!(location.span.start.character, 1);
+        assert_eq!(location.region, Some(RegionType::Grammar));
+    }
+
+    #[test]
+    fn lex_error_location() {
+        //setup
+        let spec = "
+ignore X
+
+cdfa {
+    start
+        'x' -> ^X
+        ! 'y' -> ^Y;
+}
+        ";
+
+        //exercise
+        let res = lang::parse_spec(spec);
+
+        //verify
+        let err = res.err().unwrap();
+        let location = err.location().unwrap();
+        assert_eq!(location.span.start.line, 7);
+        assert_eq!(location.span.start.character, 9);
+        assert_eq!(location.region, Some(RegionType::CDFA));
+    }
+
+    #[test]
+    fn lex_error_location_between_regions() {
+        //setup
+        let spec = "
+ignore X
+
+gramar {
+    s | X;
+}
+        ";
+
+        //exercise
+        let res = lang::parse_spec(spec);
+
+        //verify
+        let err = res.err().unwrap();
+        let location = err.location().unwrap();
+        assert_eq!(location.span.start.line, 4);
+        assert_eq!(location.span.start.character, 1);
+        assert_eq!(location.region, None);
+    }
+
+    #[test]
+    fn production_priority_conflicting_annotations() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'x' -> ^X;
+}
+
+grammar {
+    s | X @prefer @avoid;
+}
+        ";
+
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+
+        //exercise
+        let res = generate_spec(&parse, SimpleGrammarBuilder::new());
+
+        //verify
+        assert!(res.is_err());
+        assert_eq!(
+            format!("{}", res.err().unwrap()),
+            "Annotation error: Multiple priorities declared on production 's X' at 8:19 in grammar \
+             region"
+        );
+    }
+
+    #[test]
+    fn operator_precedence() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> ^ID
+        '+' -> ^PLUS
+        '-' -> ^MINUS
+        '*' -> ^STAR
+        '^' -> ^HAT;
+}
+
+grammar {
+    %left PLUS MINUS;
+    %left STAR;
+    %right HAT;
+
+    expr
+        | expr PLUS expr
+        | expr MINUS expr
+        | expr STAR expr
+        | expr HAT expr
+        | MINUS expr
+        | ID;
+}
+        ";
+
+        let input = "a-b+c*-d^e^f";
+
+        let lexer = lex::def_lexer();
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+        let (cdfa, grammar, _) = generate_spec(&parse, SimpleGrammarBuilder::new()).unwrap();
+
+        //exercise
+        let tokens = lexer.lex(input, &cdfa).unwrap();
+        let parse = parse::def_parser().parse(tokens, &grammar).unwrap();
+
+        //verify
+        assert_eq!(
+            parse.to_string(),
+            "└── expr
+    ├── expr
+    │   ├── expr
+    │   │   └── ID <- 'a'
+    │   ├── MINUS <- '-'
+    │   └── expr
+    │       └── ID <- 'b'
+    ├── PLUS <- '+'
+    └── expr
+        ├── expr
+        │   └── ID <- 'c'
+        ├── STAR <- '*'
+        └── expr
+            ├── MINUS <- '-'
+            └── expr
+                ├── expr
+                │   └── ID <- 'd'
+                ├── HAT <- '^'
+                └── expr
+                    ├── expr
+                    │   └── ID <- 'e'
+                    ├── HAT <- '^'
+                    └── expr
+                        └── ID <- 'f'"
+        )
+    }
+
+    #[test]
+    fn operator_precedence_duplicate_operator() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> ^ID
+        '+' -> ^PLUS;
+}
+
+grammar {
+    %left PLUS;
+    %right PLUS;
+
+    expr
+        | expr PLUS expr
+        | ID;
+}
+        ";
+
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+
+        //exercise
+        let res = generate_spec(&parse, SimpleGrammarBuilder::new());
+
+        //verify
+        assert!(res.is_err());
+        assert_eq!(
+            format!("{}", res.err().unwrap()),
+            "Precedence error: Operator 'PLUS' has multiple precedence declarations at 10:12 in \
+             grammar region"
+        );
+    }
+
+    #[test]
+    fn operator_precedence_non_terminal_operator() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> ^ID;
+}
+
+grammar {
+    %left expr;
+
+    expr
+        | ID;
+}
+        ";
+
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+
+        //exercise
+        let res = generate_spec(&parse, SimpleGrammarBuilder::new());
+
+        //verify
+        assert!(res.is_err());
+        assert_eq!(
+            format!("{}", res.err().unwrap()),
+            "Grammar build error: Operator symbol 'expr' is non-terminal at 8:11 in grammar region"
+        );
+    }
+
+    #[test]
+    fn test_regions() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        ' ' -> ^_;
+}
+
+test \"single \\\"a\\\"\" {
+    input ``a``
+    expect ``a``
+}
+
+grammar {
+    s | s A | A;
+}
+
+# Text may span lines and contain single backticks.
+test \"lines\" {
+    input ``a
+`a` a``
+    expect ``
+a a
+``
+}
+        ";
+
+        let tree = lang::parse_spec(spec);
+        let parse = tree.unwrap();
+
+        //exercise
+        let tests = generate_tests(&parse).unwrap();
+        let res = generate_spec(&parse, SimpleGrammarBuilder::new());
+
+        //verify
+        assert!(res.is_ok());
+        assert_eq!(tests.len(), 2);
+
+        assert_eq!(tests[0].name, "single \"a\"");
+        assert_eq!(tests[0].input, "a");
+        assert_eq!(tests[0].expect, "a");
+        assert_eq!(tests[0].span.start.line, 8);
+
+        assert_eq!(tests[1].name, "lines");
+        assert_eq!(tests[1].input, "a\n`a` a");
+        assert_eq!(tests[1].expect, "\na a\n");
+        assert_eq!(tests[1].span.start.line, 18);
+    }
+
+    #[test]
+    fn test_region_missing_expect() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' -> ^A;
+}
+
+test \"missing\" {
+    input ``a``
+}
+
+grammar {
+    s | A;
+}
+        ";
+
+        //exercise
+        let res = lang::parse_spec(spec);
+
+        //verify
+        let err = res.err().unwrap();
+        let location = err.location().unwrap();
+        assert_eq!(location.span.start.line, 9);
+        assert_eq!(location.region, Some(RegionType::Test));
+    }
+
     fn tokens_string(tokens: Vec<Token<String>>) -> String {
         let mut res_string = String::new();
         for token in tokens {
@@ -1,18 +1,53 @@
 use {
     core::{
+        lex::{Span, Token},
         parse::Tree,
         spec::{self, lang::SpecSymbol},
     },
     std::{collections::HashSet, error, fmt},
 };
 
+/// Region Type: The types of regions in a specification.
+///
+/// # Types
+///
+/// * `Injectable` - an `inject` region, declaring an injectable terminal symbol.
+/// * `Ignorable` - an `ignore` region, declaring an ignorable terminal symbol.
+/// * `Sync` - a `sync` region, declaring an error recovery synchronization symbol.
+/// * `Directive` - a `directive` region, declaring a formatting directive symbol.
+/// * `Width` - a `width` region, declaring the maximum line width.
+/// * `Alphabet` - an `alphabet` region, declaring the CDFA alphabet.
+/// * `CDFA` - a `cdfa` region, declaring CDFA states and transitions.
+/// * `Grammar` - a `grammar` region, declaring operator precedences and grammar productions.
+/// * `Test` - a `test` region, declaring an input and its expected formatted output.
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub enum RegionType {
     Injectable,
     Ignorable,
+    Sync,
+    Directive,
+    Width,
     Alphabet,
     CDFA,
     Grammar,
+    Test,
+}
+
+impl fmt::Display for RegionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keyword = match *self {
+            Self::Injectable => "inject",
+            Self::Ignorable => "ignore",
+            Self::Sync => "sync",
+            Self::Directive => "directive",
+            Self::Width => "width",
+            Self::Alphabet => "alphabet",
+            Self::CDFA => "cdfa",
+            Self::Grammar => "grammar",
+            Self::Test => "test",
+        };
+        write!(f, "{}", keyword)
+    }
 }
 
 lazy_static! {
@@ -80,15 +115,109 @@ fn traverse_region_node(
     Ok(())
 }
 
+/// Returns the type of the specification region which is open after the specification tokens
+/// `tokens`, or `None` if every region has been closed.
+///
+/// This is used to find the region containing a syntax error, where no parse tree is available.
+pub fn enclosing(tokens: &[Token<SpecSymbol>]) -> Option<RegionType> {
+    let mut region: Option<RegionType> = None;
+    let mut length: usize = 0;
+
+    for token in tokens {
+        if let Some(region_type) = type_from_keyword(token.kind()) {
+            region = Some(region_type);
+            length = 0;
+            continue;
+        }
+
+        length += 1;
+
+        // Braced regions are closed by their right brace, and inline regions by their last token.
+        let closed = match region {
+            Some(RegionType::CDFA) | Some(RegionType::Grammar) | Some(RegionType::Test) => {
+                *token.kind() == SpecSymbol::TRightBrace
+            }
+            Some(RegionType::Injectable) => length == 3,
+            Some(_) => length == 1,
+            None => false,
+        };
+
+        if closed {
+            region = None;
+        }
+    }
+
+    region
+}
+
+/// Returns the span of the first identifier `id` within a specification region of type
+/// `region_type` under `regions_node`, or `None` if there is no such identifier.
+pub fn find_id(
+    regions_node: &Tree<SpecSymbol>,
+    region_type: &RegionType,
+    id: &str,
+) -> Option<Span> {
+    let mut found: Option<Span> = None;
+
+    let mut handler = |inner_node: &Tree<SpecSymbol>, inner_type: &RegionType| {
+        if found.is_none() && inner_type == region_type {
+            found = find_id_in_node(inner_node, id);
+        }
+        Ok(())
+    };
+
+    traverse_regions_node(regions_node, &mut handler, &mut HashSet::new()).ok()?;
+    found
+}
+
+/// Returns the span of the first identifier `id` under `node`, in the order it appears in the
+/// specification, or `None` if there is no such identifier.
+fn find_id_in_node(node: &Tree<SpecSymbol>, id: &str) -> Option<Span> {
+    let mut stack: Vec<&Tree<SpecSymbol>> = vec![node];
+
+    while let Some(node) = stack.pop() {
+        if node.is_leaf() {
+            if *node.lhs.kind_opt() == Some(SpecSymbol::TId) && node.lhs.lexeme() == id {
+                return Some(*node.span());
+            }
+        } else {
+            stack.extend(node.children.iter().rev());
+        }
+    }
+
+    None
+}
+
+/// Returns the region type introduced by the specification token kind `kind`, or `None` if
+/// `kind` is not a region keyword.
+fn type_from_keyword(kind: &SpecSymbol) -> Option<RegionType> {
+    match kind {
+        SpecSymbol::TInjectable => Some(RegionType::Injectable),
+        SpecSymbol::TIgnorable => Some(RegionType::Ignorable),
+        SpecSymbol::TSync => Some(RegionType::Sync),
+        SpecSymbol::TDirective => Some(RegionType::Directive),
+        SpecSymbol::TWidth => Some(RegionType::Width),
+        SpecSymbol::TAlphabet => Some(RegionType::Alphabet),
+        SpecSymbol::TCDFA => Some(RegionType::CDFA),
+        SpecSymbol::TGrammar => Some(RegionType::Grammar),
+        SpecSymbol::TTest => Some(RegionType::Test),
+        _ => None,
+    }
+}
+
 /// Returns the region type associated with the `SpecSymbol::Regions` node `regions_node`.
 fn type_from_node(region_node: &Tree<SpecSymbol>) -> RegionType {
     let region_symbol = &region_node.get_child(0).lhs.kind();
     match region_symbol {
         SpecSymbol::Injectable => RegionType::Injectable,
         SpecSymbol::Ignorable => RegionType::Ignorable,
+        SpecSymbol::Sync => RegionType::Sync,
+        SpecSymbol::Directive => RegionType::Directive,
+        SpecSymbol::Width => RegionType::Width,
         SpecSymbol::Alphabet => RegionType::Alphabet,
         SpecSymbol::CDFA => RegionType::CDFA,
         SpecSymbol::Grammar => RegionType::Grammar,
+        SpecSymbol::Test => RegionType::Test,
         &_ => panic!("Invalid specification region type: '{:?}'", region_symbol),
     }
 }
@@ -0,0 +1,337 @@
+use std::{collections::HashMap, hash::Hash, ops::Range};
+
+/// Difference: A run of elements in the alignment of an original sequence with a changed one.
+///
+/// # Types
+///
+/// * `Same` - indicates that the given number of elements are common to both sequences.
+/// * `Removed` - indicates that the given number of elements of the original sequence have no
+///   counterpart in the changed sequence.
+/// * `Added` - indicates that the given number of elements of the changed sequence have no
+///   counterpart in the original sequence.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Difference {
+    Same(usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// Returns a minimal alignment of the sequences `original` and `changed`, as runs of elements in
+/// sequence order, where elements are identified by their keys.
+///
+/// Within each changed block, removed elements precede added elements. The alignment is found
+/// by Myers' linear space diff algorithm, which takes O((N + M)D) time and O(N + M) memory for
+/// sequences of lengths N and M with D differences.
+pub fn diff<Key: Eq + Hash>(original: &[Key], changed: &[Key]) -> Vec<Difference> {
+    // Elements are compared by integer ids, with equal ids for equal keys.
+    let mut ids: HashMap<&Key, usize> = HashMap::new();
+    let mut id_of = |key| {
+        let next = ids.len();
+        *ids.entry(key).or_insert(next)
+    };
+    let original: Vec<usize> = original.iter().map(&mut id_of).collect();
+    let changed: Vec<usize> = changed.iter().map(&mut id_of).collect();
+
+    let mut diffs: Vec<Difference> = Vec::new();
+
+    let max_d = (original.len() + changed.len()).div_ceil(2) + 1;
+    let mut forward = Frontier::new(max_d);
+    let mut backward = Frontier::new(max_d);
+
+    // The blocks still to be aligned, and the common suffixes to emit after them, with the next
+    // in sequence order on top.
+    let mut tasks: Vec<Task> = vec![Task::Align(0..original.len(), 0..changed.len())];
+
+    while let Some(task) = tasks.pop() {
+        let (mut original_range, mut changed_range) = match task {
+            Task::Align(original_range, changed_range) => (original_range, changed_range),
+            Task::Same(len) => {
+                push(&mut diffs, Difference::Same(len));
+                continue;
+            }
+        };
+
+        let prefix = common_prefix(
+            &original[original_range.clone()],
+            &changed[changed_range.clone()],
+        );
+        push(&mut diffs, Difference::Same(prefix));
+        original_range.start += prefix;
+        changed_range.start += prefix;
+
+        let suffix = common_suffix(
+            &original[original_range.clone()],
+            &changed[changed_range.clone()],
+        );
+        original_range.end -= suffix;
+        changed_range.end -= suffix;
+        tasks.push(Task::Same(suffix));
+
+        if original_range.is_empty() || changed_range.is_empty() {
+            push(&mut diffs, Difference::Removed(original_range.len()));
+            push(&mut diffs, Difference::Added(changed_range.len()));
+            continue;
+        }
+
+        let (x, y) = middle_snake(
+            &original[original_range.clone()],
+            &changed[changed_range.clone()],
+            &mut forward,
+            &mut backward,
+        );
+
+        tasks.push(Task::Align(
+            original_range.start + x..original_range.end,
+            changed_range.start + y..changed_range.end,
+        ));
+        tasks.push(Task::Align(
+            original_range.start..original_range.start + x,
+            changed_range.start..changed_range.start + y,
+        ));
+    }
+
+    diffs
+}
+
+/// Task: A unit of the work remaining to align two sequences.
+///
+/// # Types
+///
+/// * `Align` - indicates that the given ranges of the original and changed sequences are yet to be
+///   aligned.
+/// * `Same` - indicates that the given number of common elements are yet to be emitted.
+enum Task {
+    Align(Range<usize>, Range<usize>),
+    Same(usize),
+}
+
+/// Frontier: The furthest reaching x coordinate found along each diagonal of an edit graph,
+/// indexed by diagonal number k = x - y, which may be negative.
+struct Frontier {
+    offset: isize,
+    xs: Vec<usize>,
+}
+
+impl Frontier {
+    fn new(max_d: usize) -> Self {
+        Self {
+            offset: max_d as isize,
+            xs: vec![0; 2 * max_d + 1],
+        }
+    }
+
+    fn get(&self, k: isize) -> usize {
+        self.xs[(k + self.offset) as usize]
+    }
+
+    fn set(&mut self, k: isize, x: usize) {
+        self.xs[(k + self.offset) as usize] = x;
+    }
+}
+
+/// Returns the start of the middle snake of an optimal path through the edit graph of `original`
+/// and `changed`, which splits the path into two halves with (nearly) equal numbers of
+/// differences.
+///
+/// Both sequences must be non-empty, and differ in their first and last elements, so that the
+/// returned point is neither the start nor the end of the edit graph.
+fn middle_snake(
+    original: &[usize],
+    changed: &[usize],
+    forward: &mut Frontier,
+    backward: &mut Frontier,
+) -> (usize, usize) {
+    let n = original.len();
+    let m = changed.len();
+    let delta = n as isize - m as isize;
+    let odd = delta % 2 != 0;
+
+    forward.set(1, 0);
+    backward.set(1, 0);
+
+    for d in 0..((n + m).div_ceil(2) + 1) as isize {
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && forward.get(k - 1) < forward.get(k + 1)) {
+                forward.get(k + 1)
+            } else {
+                forward.get(k - 1) + 1
+            };
+            let y = (x as isize - k) as usize;
+            let start = (x, y);
+
+            if x < n && y < m {
+                x += common_prefix(&original[x..], &changed[y..]);
+            }
+            forward.set(k, x);
+
+            // The paths can only meet on diagonals which the backward path has reached.
+            if odd && (k - delta).abs() < d && x + backward.get(delta - k) >= n {
+                return start;
+            }
+        }
+
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && backward.get(k - 1) < backward.get(k + 1)) {
+                backward.get(k + 1)
+            } else {
+                backward.get(k - 1) + 1
+            };
+            let mut y = (x as isize - k) as usize;
+
+            if x < n && y < m {
+                let suffix = common_suffix(&original[..n - x], &changed[..m - y]);
+                x += suffix;
+                y += suffix;
+            }
+            backward.set(k, x);
+
+            if !odd && (k - delta).abs() <= d && x + forward.get(delta - k) >= n {
+                return (n - x, m - y);
+            }
+        }
+    }
+
+    panic!("Failed to find the middle snake of an edit graph")
+}
+
+/// Returns the number of elements common to the start of both `original` and `changed`.
+fn common_prefix(original: &[usize], changed: &[usize]) -> usize {
+    original
+        .iter()
+        .zip(changed)
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Returns the number of elements common to the end of both `original` and `changed`.
+fn common_suffix(original: &[usize], changed: &[usize]) -> usize {
+    original
+        .iter()
+        .rev()
+        .zip(changed.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Appends `difference` to `diffs`, merging it into the run of the same type at the end of
+/// `diffs` (if any), so that each changed block has at most one removed run followed by at most
+/// one added run. Empty runs are ignored.
+fn push(diffs: &mut Vec<Difference>, difference: Difference) {
+    let len = diffs.len();
+
+    match (difference, diffs.last_mut()) {
+        (Difference::Same(0), _) | (Difference::Removed(0), _) | (Difference::Added(0), _) => (),
+        (Difference::Same(count), Some(Difference::Same(last)))
+        | (Difference::Removed(count), Some(Difference::Removed(last)))
+        | (Difference::Added(count), Some(Difference::Added(last))) => *last += count,
+        (Difference::Removed(count), Some(Difference::Added(_))) => {
+            match len.checked_sub(2).and_then(|i| diffs.get_mut(i)) {
+                Some(Difference::Removed(last)) => *last += count,
+                _ => diffs.insert(len - 1, Difference::Removed(count)),
+            }
+        }
+        (difference, _) => diffs.push(difference),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_equal() {
+        //setup
+        let keys = vec!["a", "b", "c"];
+
+        //exercise
+        let diffs = diff(&keys, &keys);
+
+        //verify
+        assert_eq!(diffs, vec![Difference::Same(3)]);
+    }
+
+    #[test]
+    fn diff_empty() {
+        //setup
+        let keys = vec!["a", "b"];
+        let empty: Vec<&str> = Vec::new();
+
+        //exercise
+        let removed = diff(&keys, &empty);
+        let added = diff(&empty, &keys);
+        let neither = diff(&empty, &empty);
+
+        //verify
+        assert_eq!(removed, vec![Difference::Removed(2)]);
+        assert_eq!(added, vec![Difference::Added(2)]);
+        assert!(neither.is_empty());
+    }
+
+    #[test]
+    fn diff_minimal() {
+        //setup
+        let original: Vec<char> = "abcabba".chars().collect();
+        let changed: Vec<char> = "cbabac".chars().collect();
+
+        //exercise
+        let diffs = diff(&original, &changed);
+
+        //verify
+        let same: usize = diffs
+            .iter()
+            .map(|difference| match difference {
+                Difference::Same(len) => *len,
+                _ => 0,
+            })
+            .sum();
+        assert_eq!(same, 4);
+        assert_eq!(apply(&original, &changed, &diffs), changed);
+    }
+
+    #[test]
+    fn diff_large() {
+        //setup
+        let original: Vec<usize> = (0..200_000).collect();
+        let mut changed = original.clone();
+        changed.remove(150_000);
+        changed.insert(50_000, 1_000_000);
+
+        //exercise
+        let diffs = diff(&original, &changed);
+
+        //verify
+        assert_eq!(
+            diffs,
+            vec![
+                Difference::Same(50_000),
+                Difference::Added(1),
+                Difference::Same(100_000),
+                Difference::Removed(1),
+                Difference::Same(49_999),
+            ]
+        );
+    }
+
+    fn apply<Key: Clone>(original: &[Key], changed: &[Key], diffs: &[Difference]) -> Vec<Key> {
+        let mut res: Vec<Key> = Vec::new();
+        let mut original_index: usize = 0;
+        let mut changed_index: usize = 0;
+
+        for difference in diffs {
+            match *difference {
+                Difference::Same(len) => {
+                    res.extend_from_slice(&original[original_index..original_index + len]);
+                    original_index += len;
+                    changed_index += len;
+                }
+                Difference::Removed(len) => original_index += len,
+                Difference::Added(len) => {
+                    res.extend_from_slice(&changed[changed_index..changed_index + len]);
+                    changed_index += len;
+                }
+            }
+        }
+
+        res
+    }
+}
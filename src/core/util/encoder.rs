@@ -1,5 +1,8 @@
 use {
-    core::data::Data,
+    core::{
+        data::Data,
+        util::serial::{self, Reader, Serial, Writer},
+    },
     std::{collections::HashMap, fmt},
 };
 
@@ -55,6 +58,21 @@ impl<T: Data> Encoder<T> {
     }
 }
 
+impl<T: Data + Serial> Serial for Encoder<T> {
+    /// Writes the decoded objects in order of their encodings, from which the encoder is rebuilt.
+    fn write(&self, writer: &mut Writer) {
+        self.decoder.write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, serial::Error> {
+        let mut encoder = Self::new();
+        for val in Vec::<T>::read(reader)? {
+            encoder.encode(&val);
+        }
+        Ok(encoder)
+    }
+}
+
 impl<T: Default + Data> fmt::Debug for Encoder<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Encoder")
@@ -1,2 +1,5 @@
+pub mod diff;
 pub mod encoder;
+pub mod random;
+pub mod serial;
 pub mod string_utils;
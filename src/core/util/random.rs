@@ -0,0 +1,68 @@
+/// Random: A small pseudo-random number generator (xorshift64*), used where reproducible
+/// randomness is needed from a seed.
+///
+/// # Fields
+///
+/// * `state` - the current (non-zero) state of the generator.
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    /// Returns a new generator seeded with `seed`, such that generators with the same seed produce
+    /// the same sequence of numbers.
+    pub fn from_seed(seed: u64) -> Self {
+        // The state of a xorshift generator must never be zero.
+        let state = seed ^ 0x9E37_79B9_7F4A_7C15;
+        Self {
+            state: if state == 0 { 1 } else { state },
+        }
+    }
+
+    /// Returns the next pseudo-random number in the sequence.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Returns a pseudo-random number in the range `[0, bound)`, where `bound` must be positive.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        //setup
+        let mut first = Random::from_seed(42);
+        let mut second = Random::from_seed(42);
+
+        //exercise
+        let first_values: Vec<u64> = (0..10).map(|_| first.next_u64()).collect();
+        let second_values: Vec<u64> = (0..10).map(|_| second.next_u64()).collect();
+
+        //verify
+        assert_eq!(first_values, second_values);
+    }
+
+    #[test]
+    fn below_bound() {
+        //setup
+        let mut random = Random::from_seed(0x9E37_79B9_7F4A_7C15);
+
+        //exercise
+        let values: Vec<usize> = (0..1000).map(|_| random.below(7)).collect();
+
+        //verify
+        assert!(values.iter().all(|value| *value < 7));
+        for value in 0..7 {
+            assert!(values.contains(&value));
+        }
+    }
+}
@@ -0,0 +1,368 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error, fmt,
+    hash::Hash,
+};
+
+/// Serial: Trait for types which can be written to and read from a compact binary form, used to
+/// persist compiled specifications.
+pub trait Serial: Sized {
+    /// Writes this value to `writer`.
+    fn write(&self, writer: &mut Writer);
+
+    /// Reads a value from `reader`, or returns an error if the bytes at the head of `reader` do
+    /// not represent a valid value.
+    fn read(reader: &mut Reader) -> Result<Self, Error>;
+}
+
+/// Writer: Accumulates the binary form of serialized values.
+///
+/// # Fields
+///
+/// * `bytes` - the bytes written so far.
+pub struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    /// Returns a new empty writer.
+    pub fn new() -> Self {
+        Self { bytes: Vec::new() }
+    }
+
+    /// Returns the bytes written to this writer, consuming the writer.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn write_u8(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    /// Writes `len` as the length prefix of a sequence.
+    pub fn write_len(&mut self, len: usize) {
+        (len as u64).write(self);
+    }
+}
+
+/// Reader: Reads serialized values from a slice of bytes.
+///
+/// # Fields
+///
+/// * `bytes` - the bytes being read.
+/// * `cursor` - the offset of the next unread byte in `bytes`.
+pub struct Reader<'bytes> {
+    bytes: &'bytes [u8],
+    cursor: usize,
+}
+
+impl<'bytes> Reader<'bytes> {
+    /// Returns a new reader positioned at the start of `bytes`.
+    pub fn new(bytes: &'bytes [u8]) -> Self {
+        Self { bytes, cursor: 0 }
+    }
+
+    /// Returns true if all bytes have been read.
+    pub fn is_exhausted(&self) -> bool {
+        self.cursor == self.bytes.len()
+    }
+
+    /// Returns the next `len` bytes, or an error if fewer than `len` bytes remain.
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'bytes [u8], Error> {
+        if self.bytes.len() - self.cursor < len {
+            return Err(Error::EndOfInputErr);
+        }
+
+        let bytes = &self.bytes[self.cursor..self.cursor + len];
+        self.cursor += len;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    /// Reads the length prefix of a sequence, or returns an error if the length exceeds the
+    /// number of remaining bytes (since every element occupies at least one byte).
+    pub fn read_len(&mut self) -> Result<usize, Error> {
+        let len = u64::read(self)? as usize;
+        if len > self.bytes.len() - self.cursor {
+            return Err(Error::EndOfInputErr);
+        }
+        Ok(len)
+    }
+}
+
+impl Serial for u8 {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u8(*self);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        reader.read_u8()
+    }
+}
+
+impl Serial for u32 {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_bytes(&self.to_le_bytes());
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(reader.read_bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+}
+
+impl Serial for u64 {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_bytes(&self.to_le_bytes());
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(reader.read_bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+impl Serial for i32 {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_bytes(&self.to_le_bytes());
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(reader.read_bytes(4)?);
+        Ok(i32::from_le_bytes(bytes))
+    }
+}
+
+impl Serial for usize {
+    fn write(&self, writer: &mut Writer) {
+        (*self as u64).write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(u64::read(reader)? as usize)
+    }
+}
+
+impl Serial for bool {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_u8(*self as u8);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        match reader.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(Error::InvalidErr(format!("bool {}", byte))),
+        }
+    }
+}
+
+impl Serial for char {
+    fn write(&self, writer: &mut Writer) {
+        (*self as u32).write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let code = u32::read(reader)?;
+        std::char::from_u32(code).ok_or_else(|| Error::InvalidErr(format!("char {}", code)))
+    }
+}
+
+impl Serial for String {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_len(self.len());
+        writer.write_bytes(self.as_bytes());
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let len = reader.read_len()?;
+        match String::from_utf8(reader.read_bytes(len)?.to_vec()) {
+            Ok(string) => Ok(string),
+            Err(_) => Err(Error::InvalidErr("string".to_string())),
+        }
+    }
+}
+
+impl<T: Serial> Serial for Option<T> {
+    fn write(&self, writer: &mut Writer) {
+        match self {
+            None => writer.write_u8(0),
+            Some(value) => {
+                writer.write_u8(1);
+                value.write(writer);
+            }
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        match reader.read_u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(T::read(reader)?)),
+            byte => Err(Error::InvalidErr(format!("option tag {}", byte))),
+        }
+    }
+}
+
+impl<T: Serial> Serial for Box<T> {
+    fn write(&self, writer: &mut Writer) {
+        (**self).write(writer);
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        Ok(Box::new(T::read(reader)?))
+    }
+}
+
+impl<T: Serial> Serial for Vec<T> {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_len(self.len());
+        for value in self {
+            value.write(writer);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let len = reader.read_len()?;
+        let mut vec = Vec::with_capacity(len);
+        for _ in 0..len {
+            vec.push(T::read(reader)?);
+        }
+        Ok(vec)
+    }
+}
+
+impl<K: Serial + Eq + Hash, V: Serial> Serial for HashMap<K, V> {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_len(self.len());
+        for (key, value) in self {
+            key.write(writer);
+            value.write(writer);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let len = reader.read_len()?;
+        let mut map = HashMap::with_capacity(len);
+        for _ in 0..len {
+            let key = K::read(reader)?;
+            map.insert(key, V::read(reader)?);
+        }
+        Ok(map)
+    }
+}
+
+impl<T: Serial + Eq + Hash> Serial for HashSet<T> {
+    fn write(&self, writer: &mut Writer) {
+        writer.write_len(self.len());
+        for value in self {
+            value.write(writer);
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        let len = reader.read_len()?;
+        let mut set = HashSet::with_capacity(len);
+        for _ in 0..len {
+            set.insert(T::read(reader)?);
+        }
+        Ok(set)
+    }
+}
+
+/// Error: Represents an error encountered while reading serialized values.
+///
+/// # Types
+///
+/// * `EndOfInputErr` - indicates that the input ended before a value was fully read.
+/// * `InvalidErr` - indicates that the input contained an invalid value, described by the
+///   stored string.
+#[derive(Debug)]
+pub enum Error {
+    EndOfInputErr,
+    InvalidErr(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::EndOfInputErr => write!(f, "Unexpected end of input"),
+            Self::InvalidErr(ref value) => write!(f, "Invalid {}", value),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        //setup
+        let mut map: HashMap<String, Vec<Option<char>>> = HashMap::new();
+        map.insert("a".to_string(), vec![Some('x'), None, Some('é')]);
+        map.insert(String::new(), Vec::new());
+
+        let mut writer = Writer::new();
+
+        //exercise
+        map.write(&mut writer);
+        true.write(&mut writer);
+        usize::MAX.write(&mut writer);
+        (-7i32).write(&mut writer);
+
+        let bytes = writer.into_bytes();
+        let mut reader = Reader::new(&bytes);
+
+        //verify
+        assert_eq!(HashMap::read(&mut reader).unwrap(), map);
+        assert!(bool::read(&mut reader).unwrap());
+        assert_eq!(usize::read(&mut reader).unwrap(), usize::MAX);
+        assert_eq!(i32::read(&mut reader).unwrap(), -7);
+        assert!(reader.is_exhausted());
+    }
+
+    #[test]
+    fn read_truncated() {
+        //setup
+        let mut writer = Writer::new();
+        "some string".to_string().write(&mut writer);
+
+        let bytes = writer.into_bytes();
+        let mut reader = Reader::new(&bytes[..bytes.len() - 1]);
+
+        //exercise
+        let res = String::read(&mut reader);
+
+        //verify
+        assert_eq!(res.err().unwrap().to_string(), "Unexpected end of input");
+    }
+
+    #[test]
+    fn read_invalid() {
+        //setup
+        let bytes = [2];
+        let mut reader = Reader::new(&bytes);
+
+        //exercise
+        let res = Option::<u8>::read(&mut reader);
+
+        //verify
+        assert_eq!(res.err().unwrap().to_string(), "Invalid option tag 2");
+    }
+}
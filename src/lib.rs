@@ -4,36 +4,649 @@ extern crate stopwatch;
 
 use {
     core::{
-        fmt::Formatter,
-        lex::{self, Lexer, CDFA},
+        fmt::{
+            directive, edit,
+            verify::{self, TokenChange},
+            Formatter,
+        },
+        lex::{
+            self,
+            ecdfa::{EncodedCDFA, SampleLexeme},
+            sample::LexemeSampler,
+            Lexer, CDFA,
+        },
         parse::{
             self,
-            grammar::{EncodedGrammarBuilder, Grammar},
-            Parser,
+            grammar::{EncodedGrammar, EncodedGrammarBuilder, Grammar},
+            sentence::SentenceGenerator,
+            Parser, Tree,
         },
         spec,
+        util::{
+            random::Random,
+            serial::{self, Reader, Serial, Writer},
+        },
+    },
+    std::{
+        collections::{HashSet, VecDeque},
+        error, fmt, mem,
+        ops::{Range, RangeInclusive},
     },
-    std::{error, fmt},
 };
 
 mod core;
 
+pub use core::{
+    fmt::edit::TextEdit,
+    lex::{Position, Span},
+    parse::SymbolParseMethod,
+    spec::{RegionType, SpecLintKind},
+};
+
 pub struct FormatJob {
     text: String,
+    width: Option<usize>,
 }
 
 impl FormatJob {
     pub fn from_text(text: String) -> Self {
-        FormatJob { text }
+        FormatJob { text, width: None }
+    }
+
+    /// Returns this job with the maximum line width set to `width`, overriding any width declared
+    /// by the specification.
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+}
+
+/// Format Range: A range of the input to format.
+///
+/// # Types
+///
+/// * `Bytes` - a range of byte offsets into the input.
+/// * `Lines` - an inclusive range of (1-indexed) line numbers in the input.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum FormatRange {
+    Bytes(Range<usize>),
+    Lines(RangeInclusive<usize>),
+}
+
+impl FormatRange {
+    /// Returns the byte offsets of the start and end of this range in `text`, clamped to the
+    /// length of `text`.
+    fn offsets(&self, text: &str) -> (usize, usize) {
+        match self {
+            FormatRange::Bytes(range) => {
+                let end = range.end.min(text.len());
+                (range.start.min(end), end)
+            }
+            FormatRange::Lines(range) => {
+                let mut start = text.len();
+                let mut end = text.len();
+                let mut line: usize = 1;
+                let mut line_start: usize = 0;
+
+                for (offset, c) in text.char_indices() {
+                    if c != '\n' {
+                        continue;
+                    }
+
+                    if line == *range.start() {
+                        start = line_start;
+                    }
+                    if line == *range.end() {
+                        end = offset;
+                        break;
+                    }
+
+                    line += 1;
+                    line_start = offset + 1;
+                }
+
+                if line == *range.start() && start == text.len() {
+                    start = line_start;
+                }
+
+                (start.min(end), end)
+            }
+        }
+    }
+}
+
+/// Syntax Tree: A read-only parse tree of some input, where grammar symbols are identified by
+/// their names in the specification.
+///
+/// # Fields
+///
+/// * `kind` - the name of the symbol at the root of this tree, or `None` if this tree is an
+///   inline list or an empty (epsilon) leaf.
+/// * `lexeme` - the input text of the token at the root of this tree, or the empty string if this
+///   tree is not a token.
+/// * `span` - the region of the input covered by this tree.
+/// * `children` - the child trees of this tree, in order.
+/// * `production` - the production used to derive the children of this tree, if any.
+/// * `parse_method` - the method by which the parser matched this tree to its parent.
+#[derive(Debug)]
+pub struct SyntaxTree {
+    kind: Option<String>,
+    lexeme: String,
+    span: Span,
+    children: Vec<SyntaxTree>,
+    production: Option<Production>,
+    parse_method: SymbolParseMethod,
+}
+
+impl SyntaxTree {
+    fn from_tree(tree: Tree<String>) -> Self {
+        // Convert nodes iteratively, so that converting a very deep tree cannot overflow the
+        // stack. Each node is converted once its children have been, given their number.
+        let mut stack: Vec<(Tree<String>, Option<usize>)> = vec![(tree, None)];
+        let mut converted: Vec<SyntaxTree> = Vec::new();
+
+        while let Some((mut node, len)) = stack.pop() {
+            match len {
+                None => {
+                    let children = mem::take(&mut node.children);
+                    stack.push((node, Some(children.len())));
+                    stack.extend(children.into_iter().rev().map(|child| (child, None)));
+                }
+                Some(len) => {
+                    let lexeme = if node.is_null() {
+                        String::new()
+                    } else {
+                        node.lhs.lexeme().clone()
+                    };
+
+                    let children = converted.split_off(converted.len() - len);
+                    converted.push(SyntaxTree {
+                        kind: node.lhs.kind_opt().clone(),
+                        lexeme,
+                        span: *node.span(),
+                        children,
+                        production: node.production.take().map(Production::from_production),
+                        parse_method: node.spm.clone(),
+                    });
+                }
+            }
+        }
+
+        converted.pop().unwrap()
+    }
+
+    pub fn kind(&self) -> Option<&str> {
+        self.kind.as_ref().map(|kind| &kind[..])
+    }
+
+    pub fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn children(&self) -> &[SyntaxTree] {
+        &self.children
+    }
+
+    pub fn production(&self) -> Option<&Production> {
+        self.production.as_ref()
+    }
+
+    pub fn parse_method(&self) -> &SymbolParseMethod {
+        &self.parse_method
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Writes the line for this node (but not its children) with the given line prefix, where
+    /// `is_tail` is true if this is the last child of its parent.
+    fn fmt_node(&self, f: &mut fmt::Formatter, prefix: &str, is_tail: bool) -> fmt::Result {
+        write!(f, "{}{}", prefix, if is_tail { "└── " } else { "├── " })?;
+
+        if !self.is_leaf() {
+            return write!(f, "{}", self.kind().unwrap_or("?"));
+        }
+
+        if self.parse_method == SymbolParseMethod::Injected {
+            write!(f, "<< ")?;
+        }
+
+        let lexeme = self
+            .lexeme
+            .replace('\n', "\\n")
+            .replace('\t', "\\t")
+            .replace('\r', "\\r");
+
+        match self.kind {
+            Some(ref kind) => write!(f, "{} <- '{}'", kind, lexeme),
+            None => write!(f, "NULL"),
+        }
+    }
+
+    /// Returns a copy of this node without its children.
+    fn clone_node(&self) -> Self {
+        SyntaxTree {
+            kind: self.kind.clone(),
+            lexeme: self.lexeme.clone(),
+            span: self.span,
+            children: Vec::new(),
+            production: self.production.clone(),
+            parse_method: self.parse_method.clone(),
+        }
+    }
+}
+
+impl Clone for SyntaxTree {
+    fn clone(&self) -> Self {
+        // Clone nodes iteratively, so that cloning a very deep tree cannot overflow the stack.
+        // Each node is cloned once its children have been.
+        let mut stack: Vec<(&SyntaxTree, bool)> = vec![(self, false)];
+        let mut cloned: Vec<SyntaxTree> = Vec::new();
+
+        while let Some((node, expanded)) = stack.pop() {
+            if expanded {
+                let mut copy = node.clone_node();
+                copy.children = cloned.split_off(cloned.len() - node.children.len());
+                cloned.push(copy);
+            } else {
+                stack.push((node, true));
+                stack.extend(node.children.iter().rev().map(|child| (child, false)));
+            }
+        }
+
+        cloned.pop().unwrap()
+    }
+}
+
+impl PartialEq for SyntaxTree {
+    fn eq(&self, other: &SyntaxTree) -> bool {
+        // Compare nodes iteratively, so that comparing very deep trees cannot overflow the stack.
+        let mut stack: Vec<(&SyntaxTree, &SyntaxTree)> = vec![(self, other)];
+
+        while let Some((left, right)) = stack.pop() {
+            if left.kind != right.kind
+                || left.lexeme != right.lexeme
+                || left.span != right.span
+                || left.production != right.production
+                || left.parse_method != right.parse_method
+                || left.children.len() != right.children.len()
+            {
+                return false;
+            }
+
+            stack.extend(left.children.iter().zip(right.children.iter()));
+        }
+
+        true
+    }
+}
+
+impl Eq for SyntaxTree {}
+
+impl Drop for SyntaxTree {
+    fn drop(&mut self) {
+        // Drop descendants iteratively, so that dropping a very deep tree cannot overflow the
+        // stack. Each node is dropped only once its children have been moved onto the stack.
+        let mut stack: Vec<SyntaxTree> = mem::take(&mut self.children);
+        while let Some(mut node) = stack.pop() {
+            stack.append(&mut node.children);
+        }
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Write nodes iteratively in pre-order, so that writing a very deep tree cannot overflow
+        // the stack. The prefix of each line is shared, with one margin for each open ancestor.
+        self.fmt_node(f, "", true)?;
+
+        let mut prefix = String::from("    ");
+        // The open nodes, with the index of their next child and the prefix length of their line.
+        let mut stack: Vec<(&SyntaxTree, usize, usize)> = vec![(self, 0, 0)];
+
+        while let Some(&mut (node, ref mut next, base)) = stack.last_mut() {
+            if *next == node.children.len() {
+                prefix.truncate(base);
+                stack.pop();
+                continue;
+            }
+
+            let child = &node.children[*next];
+            let is_tail = *next == node.children.len() - 1;
+            *next += 1;
+
+            writeln!(f)?;
+            child.fmt_node(f, &prefix, is_tail)?;
+
+            if !child.is_leaf() {
+                let child_base = prefix.len();
+                prefix.push_str(if is_tail { "    " } else { "│   " });
+                stack.push((child, 0, child_base));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Lexed Token: A token of some input, where the token kind is identified by its name in the
+/// specification.
+///
+/// # Fields
+///
+/// * `kind` - the name of the terminal symbol matched by this token.
+/// * `lexeme` - the input text matched by this token.
+/// * `span` - the region of the input covered by this token.
+/// * `ignorable` - true if this token is ignored by the parser.
+/// * `injectable` - true if this token is injected into the parse tree by the parser.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct LexedToken {
+    kind: String,
+    lexeme: String,
+    span: Span,
+    ignorable: bool,
+    injectable: bool,
+}
+
+impl LexedToken {
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn is_ignorable(&self) -> bool {
+        self.ignorable
+    }
+
+    pub fn is_injectable(&self) -> bool {
+        self.injectable
+    }
+}
+
+/// Production: A production of the grammar of a specification, where grammar symbols are
+/// identified by their names in the specification.
+///
+/// # Fields
+///
+/// * `lhs` - the name of the non-terminal derived by this production.
+/// * `rhs` - the symbols of the right-hand-side of this production, in order.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Production {
+    lhs: String,
+    rhs: Vec<ProductionSymbol>,
+}
+
+impl Production {
+    fn from_production(production: parse::Production<String>) -> Self {
+        Production {
+            lhs: production.lhs,
+            rhs: production
+                .rhs
+                .into_iter()
+                .map(|sym| ProductionSymbol {
+                    symbol: sym.symbol,
+                    is_list: sym.is_list,
+                    separator: sym.separator,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn lhs(&self) -> &str {
+        &self.lhs
+    }
+
+    pub fn rhs(&self) -> &[ProductionSymbol] {
+        &self.rhs
+    }
+}
+
+/// Production Symbol: A symbol of the right-hand-side of a production.
+///
+/// # Fields
+///
+/// * `symbol` - the name of the symbol.
+/// * `is_list` - true if the symbol matches an inline list of one or more `symbol`s.
+/// * `separator` - the name of the symbol separating adjacent list elements, if any.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ProductionSymbol {
+    symbol: String,
+    is_list: bool,
+    separator: Option<String>,
+}
+
+impl ProductionSymbol {
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn is_list(&self) -> bool {
+        self.is_list
+    }
+
+    pub fn separator(&self) -> Option<&str> {
+        self.separator.as_ref().map(|separator| &separator[..])
+    }
+}
+
+/// Ambiguity: A region of some input which can be derived from the same symbol in more than one
+/// way, of which the parser chose one.
+///
+/// # Fields
+///
+/// * `span` - the region of the input which is ambiguous.
+/// * `productions` - the distinct productions which can derive the region.
+/// * `chosen` - the production chosen by the parser.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Ambiguity {
+    span: Span,
+    productions: Vec<Production>,
+    chosen: Production,
+}
+
+impl Ambiguity {
+    fn from_ambiguity(ambiguity: parse::Ambiguity<String>) -> Self {
+        Ambiguity {
+            span: ambiguity.span,
+            productions: ambiguity
+                .productions
+                .into_iter()
+                .map(Production::from_production)
+                .collect(),
+            chosen: Production::from_production(ambiguity.chosen),
+        }
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn productions(&self) -> &[Production] {
+        &self.productions
+    }
+
+    pub fn chosen(&self) -> &Production {
+        &self.chosen
+    }
+}
+
+/// Spec Lint: A likely problem in a specification, found by analysing its grammar and CDFA.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SpecLint {
+    lint: spec::SpecLint,
+}
+
+impl SpecLint {
+    pub fn kind(&self) -> &SpecLintKind {
+        &self.lint.kind
+    }
+
+    pub fn name(&self) -> &str {
+        &self.lint.name
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.lint.span
+    }
+}
+
+impl fmt::Display for SpecLint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.lint.fmt(f)
+    }
+}
+
+/// Spec Test: An example declared in a `test` region of a specification, pairing an input with
+/// its expected formatted output.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SpecTest {
+    test: spec::SpecTest,
+}
+
+impl SpecTest {
+    pub fn name(&self) -> &str {
+        &self.test.name
+    }
+
+    pub fn input(&self) -> &str {
+        &self.test.input
+    }
+
+    pub fn expect(&self) -> &str {
+        &self.test.expect
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.test.span
+    }
+}
+
+/// Spec Location: The location of a construct in a specification, used to describe errors.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SpecLocation {
+    location: spec::SpecLocation,
+}
+
+impl SpecLocation {
+    pub fn span(&self) -> &Span {
+        &self.location.span
+    }
+
+    /// Returns the type of the specification region containing the construct, or `None` if the
+    /// construct is not within a region.
+    pub fn region(&self) -> Option<&RegionType> {
+        self.location.region.as_ref()
+    }
+}
+
+impl fmt::Display for SpecLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.location.fmt(f)
+    }
+}
+
+/// Grammar Rule: The productions of a non-terminal in the grammar of a specification, in the
+/// form they are declared in the specification.
+///
+/// # Fields
+///
+/// * `lhs` - the name of the non-terminal.
+/// * `alternatives` - the right-hand-sides of the productions of `lhs`, in declaration order.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct GrammarRule {
+    pub lhs: String,
+    pub alternatives: Vec<Vec<GrammarTerm>>,
+}
+
+/// Grammar Term: A symbol of the right-hand-side of a grammar rule.
+///
+/// # Fields
+///
+/// * `symbol` - the name of the symbol.
+/// * `is_terminal` - true if the symbol is a token kind, rather than a non-terminal.
+/// * `is_optional` - true if the symbol is optional, i.e. declared as `[symbol]`.
+/// * `is_list` - true if the term matches an inline list of one or more `symbol`s.
+/// * `separator` - the name of the symbol separating adjacent list elements, if any.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct GrammarTerm {
+    pub symbol: String,
+    pub is_terminal: bool,
+    pub is_optional: bool,
+    pub is_list: bool,
+    pub separator: Option<String>,
+}
+
+/// Violation: A problem found when verifying that formatting some input preserves its meaning.
+///
+/// # Types
+///
+/// * `NotIdempotent` - indicates that formatting the formatted output changes it, starting at the
+///   given position of the formatted output.
+/// * `ReformatFailed` - indicates that the formatted output could not be formatted again, because
+///   it does not lex or parse, with a description of the error.
+/// * `TokenRemoved` - indicates a token of the input which is missing from the formatted output.
+/// * `TokenAdded` - indicates a token of the formatted output which is not in the input.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Violation {
+    NotIdempotent(Position),
+    ReformatFailed(String),
+    TokenRemoved(LexedToken),
+    TokenAdded(LexedToken),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::NotIdempotent(ref position) => write!(
+                f,
+                "Formatted output changes when formatted again, starting at {}:{} of the output",
+                position.line, position.character
+            ),
+            Violation::ReformatFailed(ref err) => {
+                write!(f, "Formatted output could not be formatted again: {}", err)
+            }
+            Violation::TokenRemoved(ref token) => write!(
+                f,
+                "Token {} '{}' at {}:{} of the input is missing from the output",
+                token.kind, token.lexeme, token.span.start.line, token.span.start.character
+            ),
+            Violation::TokenAdded(ref token) => write!(
+                f,
+                "Token {} '{}' at {}:{} of the output is not in the input",
+                token.kind, token.lexeme, token.span.start.line, token.span.start.character
+            ),
+        }
     }
 }
 
 type StateType = usize;
 type SymbolType = usize;
 
+/// The number of attempts made to generate each random input, before giving up.
+const GENERATE_ATTEMPTS: usize = 10;
+
+/// The version of the serialization format of format job runners. This must be incremented
+/// whenever the serialized form of a runner changes, which the `serial_version` test checks.
+pub const SERIAL_VERSION: u32 = 8;
+
+/// The header of serialized format job runners, which precedes the serialization format version.
+const SERIAL_HEADER: &[u8] = b"padd-fjr";
+
 pub struct FormatJobRunner {
-    cdfa: Box<dyn CDFA<StateType, SymbolType>>,
-    grammar: Box<dyn Grammar<SymbolType>>,
+    cdfa: EncodedCDFA<SymbolType>,
+    grammar: EncodedGrammar<String>,
     formatter: Formatter<SymbolType>,
     lexer: Box<dyn Lexer<StateType, SymbolType>>,
     parser: Box<dyn Parser<SymbolType>>,
@@ -42,8 +655,74 @@ pub struct FormatJobRunner {
 impl FormatJobRunner {
     pub fn build(spec: &str) -> Result<FormatJobRunner, BuildError> {
         let parse = spec::parse_spec(spec)?;
-        let grammar_builder = EncodedGrammarBuilder::new();
-        let (cdfa, grammar, formatter) = spec::generate_spec(&parse, grammar_builder)?;
+        let (cdfa, grammar, formatter) = spec::generate_spec(&parse, EncodedGrammarBuilder::new())?;
+        Ok(FormatJobRunner {
+            cdfa,
+            grammar,
+            formatter,
+            lexer: lex::def_lexer(),
+            parser: parse::def_parser(),
+        })
+    }
+
+    /// Returns the likely problems in the grammar and CDFA of the specification `spec`, such as
+    /// unreachable or unproductive non-terminals, unused tokens and unreachable CDFA states.
+    pub fn lint(spec: &str) -> Result<Vec<SpecLint>, BuildError> {
+        let parse = spec::parse_spec(spec)?;
+        Ok(spec::lint_spec(&parse, EncodedGrammarBuilder::new())?
+            .into_iter()
+            .map(|lint| SpecLint { lint })
+            .collect())
+    }
+
+    /// Returns the tests declared in the `test` regions of the specification `spec`, in the order
+    /// they are declared.
+    pub fn tests(spec: &str) -> Result<Vec<SpecTest>, BuildError> {
+        let parse = spec::parse_spec(spec)?;
+        Ok(spec::generate_tests(&parse)?
+            .into_iter()
+            .map(|test| SpecTest { test })
+            .collect())
+    }
+
+    /// Returns the compiled specification of this runner in a binary form, which can be loaded
+    /// with `from_bytes` without regenerating the specification.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.write_bytes(SERIAL_HEADER);
+        SERIAL_VERSION.write(&mut writer);
+        self.cdfa.write(&mut writer);
+        self.grammar.write(&mut writer);
+        self.formatter.write(&mut writer);
+        writer.into_bytes()
+    }
+
+    /// Returns the runner whose compiled specification was serialized to `bytes` by `to_bytes`,
+    /// or an error if `bytes` is not a compiled specification of this version of padd.
+    pub fn from_bytes(bytes: &[u8]) -> Result<FormatJobRunner, BuildError> {
+        let mut reader = Reader::new(bytes);
+        if reader.read_bytes(SERIAL_HEADER.len())? != SERIAL_HEADER {
+            return Err(BuildError::DecodeErr(serial::Error::InvalidErr(
+                "header".to_string(),
+            )));
+        }
+
+        if u32::read(&mut reader)? != SERIAL_VERSION {
+            return Err(BuildError::DecodeErr(serial::Error::InvalidErr(
+                "version".to_string(),
+            )));
+        }
+
+        let cdfa = EncodedCDFA::read(&mut reader)?;
+        let grammar = EncodedGrammar::read(&mut reader)?;
+        let formatter = Formatter::read(&mut reader)?;
+
+        if !reader.is_exhausted() {
+            return Err(BuildError::DecodeErr(serial::Error::InvalidErr(
+                "trailing bytes".to_string(),
+            )));
+        }
+
         Ok(FormatJobRunner {
             cdfa,
             grammar,
@@ -53,10 +732,301 @@ impl FormatJobRunner {
         })
     }
 
+    /// Returns the tokens lexed from the input of `job` by the CDFA of this runner, in input
+    /// order, including tokens which the grammar ignores or injects.
+    pub fn lex(&self, job: FormatJob) -> Result<Vec<LexedToken>, FormatError> {
+        let tokens = self.lexer.lex(&job.text[..], &self.cdfa)?;
+        Ok(tokens
+            .iter()
+            .map(|token| LexedToken {
+                kind: self.grammar.symbol_string(token.kind()),
+                lexeme: token.lexeme().clone(),
+                span: *token.span(),
+                ignorable: self.grammar.is_ignorable(token.kind()),
+                injectable: self.grammar.is_injectable(token.kind()),
+            })
+            .collect())
+    }
+
+    /// Returns the parse tree of the input of `job`, where leaves are the lexed tokens and
+    /// interior nodes are the non-terminals of the grammar of this runner.
+    pub fn parse(&self, job: FormatJob) -> Result<SyntaxTree, FormatError> {
+        let tokens = self.lexer.lex(&job.text[..], &self.cdfa)?;
+        let parse = self.parser.parse(tokens, &self.grammar)?;
+        Ok(SyntaxTree::from_tree(parse.decode(&self.grammar)))
+    }
+
+    /// Returns the ambiguities resolved while parsing the input of `job`, in pre-order of the
+    /// nodes of the parse tree, where each ambiguity is a region of the input which the grammar
+    /// can derive in more than one way.
+    pub fn ambiguities(&self, job: FormatJob) -> Result<Vec<Ambiguity>, FormatError> {
+        let tokens = self.lexer.lex(&job.text[..], &self.cdfa)?;
+        let (_, ambiguities) = self.parser.parse_with_ambiguities(tokens, &self.grammar)?;
+        Ok(ambiguities
+            .iter()
+            .map(|ambiguity| Ambiguity::from_ambiguity(ambiguity.decode(&self.grammar)))
+            .collect())
+    }
+
+    /// Returns `count` random inputs which lex and parse with the specification of this runner,
+    /// where `seed` determines the inputs generated. Fewer inputs are returned if valid inputs
+    /// cannot be found after repeated attempts (e.g. if the CDFA lexes the input differently).
+    ///
+    /// Each input is generated by randomly deriving a sentence of token kinds from the grammar,
+    /// where derivations are cut short after `max_depth` nested non-terminals, and replacing each
+    /// token kind with a sample lexeme found in the CDFA. Tokens are separated by a sample
+    /// whitespace lexeme which is ignored by the parser, if there is one.
+    pub fn generate(&self, count: usize, max_depth: usize, seed: u64) -> Vec<String> {
+        let generator = SentenceGenerator::new(&self.grammar);
+        let mut sampler = LexemeSampler::new(&self.cdfa);
+        let mut random = Random::from_seed(seed);
+
+        let mut inputs: Vec<String> = Vec::new();
+
+        for _ in 0..count * GENERATE_ATTEMPTS {
+            if inputs.len() == count {
+                break;
+            }
+
+            let sentence = match generator.generate(max_depth, &mut random) {
+                None => break,
+                Some(sentence) => sentence,
+            };
+
+            if let Some(input) = self.sample_input(&sentence, &mut sampler) {
+                if self.parse(FormatJob::from_text(input.clone())).is_ok() {
+                    inputs.push(input);
+                }
+            }
+        }
+
+        inputs
+    }
+
+    /// Returns sample input which lexes as the token kinds of `sentence`, or `None` if there is no
+    /// sample lexeme for some token kind.
+    fn sample_input(
+        &self,
+        sentence: &[SymbolType],
+        sampler: &mut LexemeSampler<SymbolType>,
+    ) -> Option<String> {
+        let is_separator = |sample: &SampleLexeme<SymbolType>| {
+            let ignored = match sample.kind {
+                None => true,
+                Some(ref kind) => self.grammar.is_ignorable(kind),
+            };
+            ignored && sample.lexeme.chars().all(char::is_whitespace)
+        };
+
+        let mut input = String::new();
+        let mut start = self.cdfa.start();
+
+        for (i, kind) in sentence.iter().enumerate() {
+            if i > 0 {
+                if let Some((separator, next_start)) = sampler.separator(start, &is_separator) {
+                    input.push_str(&separator);
+                    start = next_start;
+                }
+            }
+
+            let (lexeme, next_start) = sampler.token(start, kind)?;
+            input.push_str(&lexeme);
+            start = next_start;
+        }
+
+        Some(input)
+    }
+
     pub fn format(&self, job: FormatJob) -> Result<String, FormatError> {
-        let tokens = self.lexer.lex(&job.text[..], &*self.cdfa)?;
-        let parse = self.parser.parse(tokens, &*self.grammar)?;
-        Ok(self.formatter.format(&parse))
+        let tokens = self.lexer.lex(&job.text[..], &self.cdfa)?;
+        let disabled = directive::disabled_ranges(&tokens, &self.grammar);
+        let parse = self.parser.parse(tokens, &self.grammar)?;
+        Ok(self
+            .formatter
+            .format(&parse, &job.text[..], &disabled, job.width))
+    }
+
+    /// Returns the edits which transform the input of `job` into its formatted form, ordered by
+    /// position in the input, or no edits if the input is already formatted.
+    pub fn format_edits(&self, job: FormatJob) -> Result<Vec<TextEdit>, FormatError> {
+        let tokens = self.lexer.lex(&job.text[..], &self.cdfa)?;
+        let disabled = directive::disabled_ranges(&tokens, &self.grammar);
+        let parse = self.parser.parse(tokens, &self.grammar)?;
+        let formatted = self
+            .formatter
+            .format(&parse, &job.text[..], &disabled, job.width);
+        Ok(edit::diff(&job.text[..], &formatted))
+    }
+
+    /// Returns the input of `job` with only the smallest subtrees covering `range` formatted,
+    /// and all other input left unchanged. Ranges which cover no tokens leave the input
+    /// unchanged.
+    pub fn format_range(&self, job: FormatJob, range: FormatRange) -> Result<String, FormatError> {
+        let tokens = self.lexer.lex(&job.text[..], &self.cdfa)?;
+        let disabled = directive::disabled_ranges(&tokens, &self.grammar);
+        let parse = self.parser.parse(tokens, &self.grammar)?;
+        let (start, end) = range.offsets(&job.text[..]);
+        Ok(self
+            .formatter
+            .format_range(&parse, &job.text[..], &disabled, job.width, start, end))
+    }
+
+    /// Returns the rules of the grammar of this runner, starting with the start symbol, followed
+    /// by the other non-terminals in the (breadth-first) order they are used, and then any
+    /// unreachable non-terminals sorted by name.
+    ///
+    /// Optional symbols are represented by optional terms, rather than by the intermediate
+    /// non-terminals used to parse them.
+    pub fn grammar_rules(&self) -> Vec<GrammarRule> {
+        let mut order: Vec<&SymbolType> = Vec::new();
+        let mut seen: HashSet<&SymbolType> = HashSet::new();
+        let mut queue: VecDeque<&SymbolType> = VecDeque::new();
+
+        seen.insert(self.grammar.start());
+        queue.push_back(self.grammar.start());
+
+        while let Some(lhs) = queue.pop_front() {
+            order.push(lhs);
+
+            for production in self.grammar.productions_for_lhs(lhs).into_iter().flatten() {
+                for sym in &production.rhs {
+                    for symbol in Some(&sym.symbol).into_iter().chain(&sym.separator) {
+                        let symbol = self.optional_target(symbol).unwrap_or(symbol);
+                        if self.grammar.is_non_terminal(symbol) && seen.insert(symbol) {
+                            queue.push_back(symbol);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut unreachable: Vec<&SymbolType> = self
+            .grammar
+            .non_terminals()
+            .iter()
+            .filter(|symbol| !seen.contains(symbol) && self.optional_target(symbol).is_none())
+            .collect();
+        unreachable.sort_by_key(|symbol| self.grammar.symbol_string(symbol));
+        order.extend(unreachable);
+
+        // Specification productions are added to the grammar in reverse declaration order.
+        order
+            .into_iter()
+            .map(|lhs| GrammarRule {
+                lhs: self.grammar.symbol_string(lhs),
+                alternatives: self
+                    .grammar
+                    .productions_for_lhs(lhs)
+                    .into_iter()
+                    .flatten()
+                    .rev()
+                    .map(|production| {
+                        production
+                            .rhs
+                            .iter()
+                            .map(|sym| self.grammar_term(sym))
+                            .collect()
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Returns the grammar term of the production symbol `sym`.
+    fn grammar_term(&self, sym: &parse::ProductionSymbol<SymbolType>) -> GrammarTerm {
+        let (symbol, is_optional) = match self.optional_target(&sym.symbol) {
+            Some(target) => (target, true),
+            None => (&sym.symbol, false),
+        };
+
+        // The error symbol is parsed as a list, but is declared as a single symbol.
+        let is_error = self.grammar.error_symbol() == Some(symbol);
+
+        GrammarTerm {
+            symbol: self.grammar.symbol_string(symbol),
+            is_terminal: !self.grammar.is_non_terminal(symbol),
+            is_optional,
+            is_list: sym.is_list && !is_error,
+            separator: sym
+                .separator
+                .as_ref()
+                .map(|separator| self.grammar.symbol_string(separator)),
+        }
+    }
+
+    /// Returns the symbol made optional by `symbol`, or `None` if `symbol` is not an intermediate
+    /// non-terminal of an optional symbol.
+    fn optional_target(&self, symbol: &SymbolType) -> Option<&SymbolType> {
+        if !self.grammar.is_non_terminal(symbol)
+            || !self
+                .grammar
+                .symbol_string(symbol)
+                .starts_with(&*spec::OPT_PREFIX)
+        {
+            return None;
+        }
+
+        self.grammar
+            .productions_for_lhs(symbol)
+            .into_iter()
+            .flatten()
+            .find_map(|production| production.rhs.first())
+            .map(|sym| &sym.symbol)
+    }
+
+    /// Returns the violations found when formatting the input of `job`, or an error if the input
+    /// cannot be formatted.
+    ///
+    /// Formatting is verified to be idempotent, i.e. formatting the formatted output must not
+    /// change it, and to preserve the tokens of the input, i.e. the formatted output must contain
+    /// the same tokens as the input, ignoring ignorable tokens and tokens which are entirely
+    /// whitespace.
+    pub fn verify(&self, job: FormatJob) -> Result<Vec<Violation>, FormatError> {
+        let width = job.width;
+        let input_tokens = self.lex(FormatJob {
+            text: job.text.clone(),
+            width,
+        })?;
+        let formatted = self.format(job)?;
+
+        let mut violations: Vec<Violation> = Vec::new();
+
+        let reformat_job = FormatJob {
+            text: formatted.clone(),
+            width,
+        };
+        match self.format(reformat_job) {
+            Ok(reformatted) => {
+                if let Some(position) = verify::first_difference(&formatted, &reformatted) {
+                    violations.push(Violation::NotIdempotent(position));
+                }
+            }
+            Err(err) => violations.push(Violation::ReformatFailed(err.to_string())),
+        }
+
+        // If the formatted output does not lex, the failure has already been reported above.
+        if let Ok(output_tokens) = self.lex(FormatJob::from_text(formatted)) {
+            let is_verified = |token: &LexedToken| {
+                !token.ignorable && !token.lexeme.chars().all(char::is_whitespace)
+            };
+            let input_tokens: Vec<LexedToken> =
+                input_tokens.into_iter().filter(is_verified).collect();
+            let output_tokens: Vec<LexedToken> =
+                output_tokens.into_iter().filter(is_verified).collect();
+
+            let key = |token: &LexedToken| (token.kind.clone(), token.lexeme.clone());
+            let input_keys: Vec<(String, String)> = input_tokens.iter().map(key).collect();
+            let output_keys: Vec<(String, String)> = output_tokens.iter().map(key).collect();
+
+            for change in verify::token_changes(&input_keys, &output_keys) {
+                violations.push(match change {
+                    TokenChange::Removed(i) => Violation::TokenRemoved(input_tokens[i].clone()),
+                    TokenChange::Added(i) => Violation::TokenAdded(output_tokens[i].clone()),
+                });
+            }
+        }
+
+        Ok(violations)
     }
 }
 
@@ -64,6 +1034,22 @@ impl FormatJobRunner {
 pub enum BuildError {
     SpecParseErr(spec::ParseError),
     SpecGenErr(spec::GenError),
+    DecodeErr(serial::Error),
+}
+
+impl BuildError {
+    /// Returns the location of the construct in the specification which caused this error, or
+    /// `None` if it is not known (e.g. for compiled specifications).
+    pub fn location(&self) -> Option<SpecLocation> {
+        let location = match *self {
+            BuildError::SpecParseErr(ref err) => err.location(),
+            BuildError::SpecGenErr(ref err) => err.location(),
+            BuildError::DecodeErr(_) => None,
+        };
+        location.map(|location| SpecLocation {
+            location: location.clone(),
+        })
+    }
 }
 
 impl fmt::Display for BuildError {
@@ -75,6 +1061,9 @@ impl fmt::Display for BuildError {
             BuildError::SpecGenErr(ref err) => {
                 write!(f, "Failed to generate specification: {}", err)
             }
+            BuildError::DecodeErr(ref err) => {
+                write!(f, "Failed to decode compiled specification: {}", err)
+            }
         }
     }
 }
@@ -84,6 +1073,7 @@ impl error::Error for BuildError {
         match *self {
             BuildError::SpecParseErr(ref err) => Some(err),
             BuildError::SpecGenErr(ref err) => Some(err),
+            BuildError::DecodeErr(ref err) => Some(err),
         }
     }
 }
@@ -100,6 +1090,12 @@ impl From<spec::GenError> for BuildError {
     }
 }
 
+impl From<serial::Error> for BuildError {
+    fn from(err: serial::Error) -> BuildError {
+        BuildError::DecodeErr(err)
+    }
+}
+
 #[derive(Debug)]
 pub enum FormatError {
     LexErr(lex::Error),
@@ -138,10 +1134,102 @@ impl From<parse::Error> for FormatError {
 
 #[cfg(test)]
 mod tests {
-    use {core::parse::grammar::SimpleGrammarBuilder, std::error::Error};
+    use {
+        core::parse::grammar::SimpleGrammarBuilder,
+        std::{error::Error, fs},
+    };
 
     use super::*;
 
+    #[test]
+    fn serialized_runner_round_trip() {
+        //setup
+        let spec = fs::read_to_string("tests/spec/java8").unwrap();
+        let input = fs::read_to_string("tests/input/java8_complex_guice").unwrap();
+
+        let fjr = FormatJobRunner::build(&spec).unwrap();
+        let expected = fjr.format(FormatJob::from_text(input.clone())).unwrap();
+
+        //exercise
+        let bytes = fjr.to_bytes();
+        let loaded = FormatJobRunner::from_bytes(&bytes).unwrap();
+
+        //verify
+        assert_eq!(
+            loaded.format(FormatJob::from_text(input)).unwrap(),
+            expected
+        );
+        assert_eq!(loaded.to_bytes().len(), bytes.len());
+    }
+
+    #[test]
+    fn serial_version() {
+        //setup
+        let spec = fs::read_to_string("tests/spec/java8").unwrap();
+        let fjr = FormatJobRunner::build(&spec).unwrap();
+
+        //exercise
+        let bytes = fjr.to_bytes();
+
+        //verify
+        // If this fails then the serialized form of runners has changed, so increment
+        // SERIAL_VERSION (which invalidates cached specifications) and update the expected length.
+        assert_eq!((SERIAL_VERSION, bytes.len()), (8, 64312));
+    }
+
+    #[test]
+    fn failed_serialized_runner() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'a' -> ^A;
+}
+
+grammar {
+    s | A;
+}
+        ";
+
+        let bytes = FormatJobRunner::build(spec).unwrap().to_bytes();
+
+        //exercise
+        let truncated = FormatJobRunner::from_bytes(&bytes[..bytes.len() - 1]);
+        let invalid = FormatJobRunner::from_bytes(b"something else entirely");
+
+        //verify
+        let err: &dyn Error = &truncated.err().unwrap();
+        assert_eq!(
+            format!("{}", err),
+            "Failed to decode compiled specification: Unexpected end of input"
+        );
+        assert_eq!(
+            format!("{}", err.source().unwrap()),
+            "Unexpected end of input"
+        );
+
+        assert_eq!(
+            format!("{}", invalid.err().unwrap()),
+            "Failed to decode compiled specification: Invalid header"
+        );
+    }
+
+    #[test]
+    fn format_range_offsets() {
+        //setup
+        let text = "ab\ncd\n\nef";
+
+        //exercise/verify
+        assert_eq!(FormatRange::Bytes(1..4).offsets(text), (1, 4));
+        assert_eq!(FormatRange::Bytes(5..100).offsets(text), (5, 9));
+        assert_eq!(FormatRange::Bytes(20..30).offsets(text), (9, 9));
+        assert_eq!(FormatRange::Lines(1..=1).offsets(text), (0, 2));
+        assert_eq!(FormatRange::Lines(2..=3).offsets(text), (3, 6));
+        assert_eq!(FormatRange::Lines(4..=4).offsets(text), (7, 9));
+        assert_eq!(FormatRange::Lines(3..=10).offsets(text), (6, 9));
+        assert_eq!(FormatRange::Lines(10..=12).offsets(text), (9, 9));
+    }
+
     #[test]
     fn failed_lex_input() {
         //setup
@@ -246,13 +1334,14 @@ grammar {
         let mut err: &dyn Error = &res.err().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Failed to parse input: Recognition failed at token 1: ACC <- 'a'"
+            "Failed to parse input: Recognition failed at token 1: ACC <- 'a' at 1:1, \
+             expected `B` in s"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Recognition failed at token 1: ACC <- 'a'"
+            "Recognition failed at token 1: ACC <- 'a' at 1:1, expected `B` in s"
         );
 
         assert!(err.source().is_none());
@@ -327,20 +1416,23 @@ grammar {
         let mut err: &dyn Error = &res.err().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Failed to parse specification: Parse error: Recognition failed at token 10: \
-             TId <- 'SOMETHING'"
+            "Failed to parse specification: Parse error: Recognition failed at token 10: TId <- \
+             'SOMETHING' at 5:23, expected `TArrow` or `TCil` or `TDef` or `TSemi` in State in \
+             cdfa region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Parse error: Recognition failed at token 10: TId <- 'SOMETHING'"
+            "Parse error: Recognition failed at token 10: TId <- 'SOMETHING' at 5:23, expected \
+             `TArrow` or `TCil` or `TDef` or `TSemi` in State in cdfa region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Recognition failed at token 10: TId <- 'SOMETHING'"
+            "Recognition failed at token 10: TId <- 'SOMETHING' at 5:23, \
+             expected `TArrow` or `TCil` or `TDef` or `TSemi` in State"
         );
 
         assert!(err.source().is_none());
@@ -400,13 +1492,14 @@ grammar {
         assert_eq!(
             format!("{}", err),
             "Failed to generate specification: ECDFA generation error: Failed to build CDFA: \
-             Default matcher used twice"
+             Default matcher used twice at 6:9 in cdfa region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "ECDFA generation error: Failed to build CDFA: Default matcher used twice"
+            "ECDFA generation error: Failed to build CDFA: Default matcher used twice at 6:9 in \
+             cdfa region"
         );
 
         err = err.source().unwrap();
@@ -446,14 +1539,14 @@ grammar {
         assert_eq!(
             format!("{}", err),
             "Failed to generate specification: ECDFA generation error: Failed to build CDFA: \
-             Transition trie contains duplicate matchers"
+             Transition trie contains duplicate matchers at 6:9 in cdfa region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "ECDFA generation error: Failed to build CDFA: \
-             Transition trie contains duplicate matchers"
+            "ECDFA generation error: Failed to build CDFA: Transition trie contains duplicate \
+             matchers at 6:9 in cdfa region"
         );
 
         err = err.source().unwrap();
@@ -492,14 +1585,15 @@ grammar {
         let mut err: &dyn Error = &res.err().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Failed to generate specification: ECDFA generation error: Failed to build CDFA: \
-             Range matcher error: Intervals overlap"
+            "Failed to generate specification: ECDFA generation error: Failed to build CDFA: Range \
+             matcher error: Intervals overlap at 6:9 in cdfa region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "ECDFA generation error: Failed to build CDFA: Range matcher error: Intervals overlap"
+            "ECDFA generation error: Failed to build CDFA: Range matcher error: Intervals overlap \
+             at 6:9 in cdfa region"
         );
 
         err = err.source().unwrap();
@@ -537,14 +1631,15 @@ grammar {
         let mut err: &dyn Error = &res.err().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Failed to generate specification: Matcher definition error: \
-             Range start must be one character, but was 'aa'"
+            "Failed to generate specification: Matcher definition error: Range start must be one \
+             character, but was 'aa' at 6:9 in cdfa region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Matcher definition error: Range start must be one character, but was 'aa'"
+            "Matcher definition error: Range start must be one character, but was 'aa' at 6:9 in \
+             cdfa region"
         );
 
         assert!(err.source().is_none());
@@ -576,14 +1671,15 @@ grammar {
         let mut err: &dyn Error = &res.err().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Failed to generate specification: Matcher definition error: \
-             Range end must be one character, but was 'cd'"
+            "Failed to generate specification: Matcher definition error: Range end must be one \
+             character, but was 'cd' at 6:14 in cdfa region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Matcher definition error: Range end must be one character, but was 'cd'"
+            "Matcher definition error: Range end must be one character, but was 'cd' at 6:14 in \
+             cdfa region"
         );
 
         assert!(err.source().is_none());
@@ -616,15 +1712,15 @@ grammar {
         let mut err: &dyn Error = &res.err().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Failed to generate specification: ECDFA to grammar mapping error: \
-             Orphaned terminal 'ORPHANED' is not tokenized by the ECDFA"
+            "Failed to generate specification: ECDFA to grammar mapping error: Orphaned terminal \
+             'ORPHANED' is not tokenized by the ECDFA at 11:9 in grammar region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "ECDFA to grammar mapping error: \
-             Orphaned terminal 'ORPHANED' is not tokenized by the ECDFA"
+            "ECDFA to grammar mapping error: Orphaned terminal 'ORPHANED' is not tokenized by the \
+             ECDFA at 11:9 in grammar region"
         );
 
         assert!(err.source().is_none());
@@ -691,15 +1787,15 @@ grammar {
         let mut err: &dyn Error = &res.err().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Failed to generate specification: Formatter build error: Pattern build error: \
-             Pattern lex error: No accepting tokens after (1,1): \\..."
+            "Failed to generate specification: Formatter build error: Pattern build error: Pattern \
+             lex error: No accepting tokens after (1,1): \\... at 9:9 in grammar region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Formatter build error: Pattern build error: \
-             Pattern lex error: No accepting tokens after (1,1): \\..."
+            "Formatter build error: Pattern build error: Pattern lex error: No accepting tokens \
+             after (1,1): \\... at 9:9 in grammar region"
         );
 
         err = err.source().unwrap();
@@ -745,33 +1841,39 @@ grammar {
         let mut err: &dyn Error = &res.err().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Failed to generate specification: Formatter build error: Pattern build error: \
-             Pattern parse error: Recognition failed after consuming all tokens"
+            "Failed to generate specification: Formatter build error: Pattern build error: Pattern \
+             parse error: Recognition failed after consuming all tokens at 1:2, expected `TNumber` \
+             or `TRightBrace` or `TSemi` in Capture, CaptureDescriptor at 9:9 in grammar region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Formatter build error: Pattern build error: Pattern parse error: \
-             Recognition failed after consuming all tokens"
+            "Formatter build error: Pattern build error: Pattern parse error: Recognition failed \
+             after consuming all tokens at 1:2, expected `TNumber` or `TRightBrace` or `TSemi` in \
+             Capture, CaptureDescriptor at 9:9 in grammar region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Pattern build error: Pattern parse error: Recognition failed after consuming all tokens"
+            "Pattern build error: Pattern parse error: Recognition failed after consuming all \
+             tokens at 1:2, \
+             expected `TNumber` or `TRightBrace` or `TSemi` in Capture, CaptureDescriptor"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Pattern parse error: Recognition failed after consuming all tokens"
+            "Pattern parse error: Recognition failed after consuming all tokens at 1:2, \
+             expected `TNumber` or `TRightBrace` or `TSemi` in Capture, CaptureDescriptor"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Recognition failed after consuming all tokens"
+            "Recognition failed after consuming all tokens at 1:2, \
+             expected `TNumber` or `TRightBrace` or `TSemi` in Capture, CaptureDescriptor"
         );
 
         assert!(err.source().is_none());
@@ -802,16 +1904,16 @@ grammar {
         let mut err: &dyn Error = &res.err().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Failed to generate specification: Formatter build error: Pattern build error: \
-             Pattern capture error: Capture index 4 out of bounds for production \'s\' with 0 \
-             children"
+            "Failed to generate specification: Formatter build error: Pattern build error: Pattern \
+             capture error: Capture index 4 out of bounds for production 's' with 0 children at \
+             9:9 in grammar region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Formatter build error: Pattern build error: Pattern capture error: \
-             Capture index 4 out of bounds for production \'s\' with 0 children"
+            "Formatter build error: Pattern build error: Pattern capture error: Capture index 4 \
+             out of bounds for production 's' with 0 children at 9:9 in grammar region"
         );
 
         err = err.source().unwrap();
@@ -860,13 +1962,13 @@ grammar {
         assert_eq!(
             format!("{}", err),
             "Failed to generate specification: Grammar build error: Ignored symbol 's' is \
-             non-terminal"
+             non-terminal at 9:8 in ignore region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Grammar build error: Ignored symbol 's' is non-terminal"
+            "Grammar build error: Ignored symbol 's' is non-terminal at 9:8 in ignore region"
         );
 
         err = err.source().unwrap();
@@ -904,13 +2006,13 @@ grammar {
         assert_eq!(
             format!("{}", err),
             "Failed to generate specification: Grammar build error: Injected symbol 's' is \
-             non-terminal"
+             non-terminal at 9:13 in inject region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Grammar build error: Injected symbol 's' is non-terminal"
+            "Grammar build error: Injected symbol 's' is non-terminal at 9:13 in inject region"
         );
 
         err = err.source().unwrap();
@@ -919,6 +2021,176 @@ grammar {
         assert!(err.source().is_none());
     }
 
+    #[test]
+    fn failed_sync_non_terminal_error() {
+        //setup
+        let spec = "
+alphabet 's'
+
+cdfa {
+    start
+        's' -> S;
+}
+
+sync s
+
+grammar {
+    s | S;
+}
+        "
+        .to_string();
+
+        //exercise
+        let res = FormatJobRunner::build(&spec);
+
+        //verify
+        assert!(res.is_err());
+
+        let mut err: &dyn Error = &res.err().unwrap();
+        assert_eq!(
+            format!("{}", err),
+            "Failed to generate specification: Grammar build error: Sync symbol 's' is \
+             non-terminal at 9:6 in sync region"
+        );
+
+        err = err.source().unwrap();
+        assert_eq!(
+            format!("{}", err),
+            "Grammar build error: Sync symbol 's' is non-terminal at 9:6 in sync region"
+        );
+
+        err = err.source().unwrap();
+        assert_eq!(format!("{}", err), "Sync symbol 's' is non-terminal");
+
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn failed_reserved_error_symbol() {
+        //setup
+        let spec = "
+cdfa {
+    start
+        'e' -> ^error
+        ';' -> ^SEMI;
+}
+
+sync SEMI
+
+grammar {
+    s | error SEMI;
+}
+        "
+        .to_string();
+
+        //exercise
+        let res = FormatJobRunner::build(&spec);
+
+        //verify
+        assert!(res.is_err());
+
+        let mut err: &dyn Error = &res.err().unwrap();
+        assert_eq!(
+            format!("{}", err),
+            "Failed to generate specification: ECDFA to grammar mapping error: Reserved error \
+             symbol 'error' is tokenized by the ECDFA at 4:17 in cdfa region"
+        );
+
+        err = err.source().unwrap();
+        assert_eq!(
+            format!("{}", err),
+            "ECDFA to grammar mapping error: Reserved error symbol 'error' is tokenized by the \
+             ECDFA at 4:17 in cdfa region"
+        );
+
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn failed_directive_non_terminal_error() {
+        //setup
+        let spec = "
+alphabet 's'
+
+cdfa {
+    start
+        's' -> S;
+}
+
+directive s
+
+grammar {
+    s | S;
+}
+        "
+        .to_string();
+
+        //exercise
+        let res = FormatJobRunner::build(&spec);
+
+        //verify
+        assert!(res.is_err());
+
+        let mut err: &dyn Error = &res.err().unwrap();
+        assert_eq!(
+            format!("{}", err),
+            "Failed to generate specification: Grammar build error: Directive symbol 's' is \
+             non-terminal at 9:11 in directive region"
+        );
+
+        err = err.source().unwrap();
+        assert_eq!(
+            format!("{}", err),
+            "Grammar build error: Directive symbol 's' is non-terminal at 9:11 in directive region"
+        );
+
+        err = err.source().unwrap();
+        assert_eq!(format!("{}", err), "Directive symbol 's' is non-terminal");
+
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn failed_width_error() {
+        //setup
+        let spec = "
+alphabet 's'
+
+cdfa {
+    start
+        's' -> ^S;
+}
+
+width 100000000000000000000000
+
+grammar {
+    s | S;
+}
+        "
+        .to_string();
+
+        //exercise
+        let res = FormatJobRunner::build(&spec);
+
+        //verify
+        assert!(res.is_err());
+
+        let mut err: &dyn Error = &res.err().unwrap();
+        assert_eq!(
+            format!("{}", err),
+            "Failed to generate specification: Line width '100000000000000000000000' is too large \
+             at 9:7 in width region"
+        );
+
+        err = err.source().unwrap();
+        assert_eq!(
+            format!("{}", err),
+            "Line width '100000000000000000000000' is too large at 9:7 in width region"
+        );
+
+        assert!(err.source().is_none());
+    }
+
     #[test]
     fn failed_duplicate_injection() {
         //setup
@@ -949,7 +2221,8 @@ grammar {
         let mut err: &dyn Error = &res.err().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Formatter build error: Injection specified multiple times for symbol \'S\'"
+            "Formatter build error: Injection specified multiple times for symbol 'S' at 10:1 in \
+             inject region"
         );
 
         err = err.source().unwrap();
@@ -990,14 +2263,14 @@ grammar {
         let mut err: &dyn Error = &res.err().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Failed to generate specification: Grammar build error: \
-             Symbol 'S' is both ignored and injected"
+            "Failed to generate specification: Grammar build error: Symbol 'S' is both ignored and \
+             injected at 9:8 in ignore region"
         );
 
         err = err.source().unwrap();
         assert_eq!(
             format!("{}", err),
-            "Grammar build error: Symbol 'S' is both ignored and injected"
+            "Grammar build error: Symbol 'S' is both ignored and injected at 9:8 in ignore region"
         );
 
         err = err.source().unwrap();
@@ -42,8 +42,9 @@ mod tests {
         std::{
             fs::{self, File, OpenOptions},
             io::{prelude::*, Read},
+            ops::{Deref, DerefMut},
             path::{Path, PathBuf},
-            process::Command,
+            process::{Command, Stdio},
             sync::RwLock,
             thread,
             time::Duration,
@@ -52,6 +53,21 @@ mod tests {
 
     static EXECUTABLE: &'static str = "target/debug/padd";
 
+    static SNAPSHOT_SPEC: &str = "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^A
+        ' ' | '\\n' -> ^_;
+}
+
+grammar {
+    s
+        | s A `{} {}`
+        | A;
+}
+";
+
     lazy_static! {
         static ref TEST_DIR: PathBuf = fs::canonicalize(Path::new("tests")).unwrap();
         static ref INPUT_DIR: PathBuf = fs::canonicalize(Path::new("tests/input")).unwrap();
@@ -140,6 +156,25 @@ mod tests {
         }
     }
 
+    struct PaddCommand {
+        command: Command,
+        _cache_dir: TestDir,
+    }
+
+    impl Deref for PaddCommand {
+        type Target = Command;
+
+        fn deref(&self) -> &Command {
+            &self.command
+        }
+    }
+
+    impl DerefMut for PaddCommand {
+        fn deref_mut(&mut self) -> &mut Command {
+            &mut self.command
+        }
+    }
+
     struct TestableFile<'scope> {
         file_name: String,
         path_str: String,
@@ -304,6 +339,33 @@ mod tests {
         test_dir.release();
     }
 
+    #[test]
+    fn test_fmt_all_json_separated() {
+        //setup
+        let mut test_dir = TestDir::new();
+
+        for file_name in files_with_prefix("json") {
+            let file = TestableFile::new(file_name, &test_dir);
+
+            //exercise
+            parallel!({
+                cli::run(vec![
+                    EXECUTABLE,
+                    "fmt",
+                    "tests/spec/json_separated",
+                    "-t",
+                    file.path_str(),
+                ]);
+            });
+
+            //verify
+            file.assert_matches_output();
+        }
+
+        //teardown
+        test_dir.release();
+    }
+
     #[test]
     fn test_format_directory() {
         //setup
@@ -340,8 +402,8 @@ mod tests {
     #[test]
     fn test_missing_spec() {
         //exercise
-        let output = Command::new(EXECUTABLE)
-            .args(&["fmt", "-t", "tests/output"])
+        let output = padd_command()
+            .args(["fmt", "-t", "tests/output"])
             .output()
             .unwrap();
 
@@ -356,8 +418,8 @@ mod tests {
     #[test]
     fn test_missing_target() {
         //exercise
-        let output = Command::new(EXECUTABLE)
-            .args(&["fmt", "test/spec/java8"])
+        let output = padd_command()
+            .args(["fmt", "test/spec/java8"])
             .output()
             .unwrap();
 
@@ -550,6 +612,57 @@ mod tests {
         test_dir.release();
     }
 
+    #[test]
+    fn test_diff_tracking_width_modified() {
+        //setup
+        let mut test_dir = TestDir::new();
+        let file = TestableFile::new("json_simple".to_string(), &test_dir);
+
+        parallel!({
+            cli::run(vec![
+                EXECUTABLE,
+                "fmt",
+                "tests/spec/json",
+                "-t",
+                file.path_str(),
+            ]);
+        });
+
+        //exercise/verify
+        file.assert_modified_by(&|| {
+            parallel!({
+                cli::run(vec![
+                    EXECUTABLE,
+                    "fmt",
+                    "tests/spec/json",
+                    "-t",
+                    file.path_str(),
+                    "--width",
+                    "40",
+                ]);
+            });
+        });
+
+        file.assert_not_modified_by(&|| {
+            parallel!({
+                cli::run(vec![
+                    EXECUTABLE,
+                    "fmt",
+                    "tests/spec/json",
+                    "-t",
+                    file.path_str(),
+                    "--width",
+                    "40",
+                ]);
+            });
+        });
+
+        file.assert_matches_output();
+
+        //teardown
+        test_dir.release();
+    }
+
     #[test]
     fn test_diff_tracking_file_modified() {
         //setup
@@ -724,7 +837,7 @@ mod tests {
     #[test]
     fn test_clear_tracking_without_target() {
         //exercise
-        let output = Command::new(EXECUTABLE).args(&["forget"]).output().unwrap();
+        let output = padd_command().args(["forget"]).output().unwrap();
 
         //verify
         let code = output.status.code().unwrap();
@@ -734,6 +847,995 @@ mod tests {
         assert!(stderr.contains(&not_provided_matcher("<PATH>")));
     }
 
+    #[test]
+    fn test_lex_table() {
+        //exercise
+        let output = padd_command()
+            .args(["lex", "tests/spec/java8", "tests/input/java8_line_comments"])
+            .output()
+            .unwrap();
+
+        //verify
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert_eq!(lines[0], "POS     KIND         FLAGS  LEXEME");
+        assert_eq!(
+            lines[1],
+            "1:1     PREFIX_LCOM  inj    '// Here is where the license goes\\n'"
+        );
+        assert_eq!(lines[2], "2:1     PREFIX       inj    ''");
+    }
+
+    #[test]
+    fn test_lex_json() {
+        //exercise
+        let output = padd_command()
+            .args([
+                "lex",
+                "--format",
+                "json",
+                "tests/spec/json",
+                "tests/input/json_simple",
+            ])
+            .output()
+            .unwrap();
+
+        //verify
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.starts_with(
+            "[{\"kind\":\"LBRACKET\",\"lexeme\":\"{\",\
+             \"start\":{\"offset\":0,\"line\":1,\"character\":1},\
+             \"end\":{\"offset\":1,\"line\":1,\"character\":2},\
+             \"ignorable\":false,\"injectable\":false},\
+             {\"kind\":\"STRING\",\"lexeme\":\"\\\"glossary\\\"\","
+        ));
+        assert!(stdout.ends_with("}]\n"));
+    }
+
+    #[test]
+    fn test_lex_invalid_input() {
+        //exercise
+        let output = padd_command()
+            .args(["lex", "tests/spec/json", "tests/spec/json"])
+            .output()
+            .unwrap();
+
+        //verify
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 1);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("Error lexing tests/spec/json: Failed to lex input"));
+    }
+
+    #[test]
+    fn test_parse_tree() {
+        //exercise
+        let output = padd_command()
+            .args(["parse", "tests/spec/json", "tests/input/json_simple"])
+            .output()
+            .unwrap();
+
+        //verify
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.starts_with(
+            "└── json [json -> element]
+    └── element [element -> value]
+        └── value [value -> object]
+            └── object [object -> LBRACKET members RBRACKET]
+                ├── LBRACKET <- '{'
+                ├── members [members -> member]
+                │   └── member [member -> STRING COLON element]
+                │       ├── STRING <- '\"glossary\"'
+"
+        ));
+    }
+
+    #[test]
+    fn test_parse_tree_injected() {
+        //exercise
+        let output = padd_command()
+            .args([
+                "parse",
+                "tests/spec/java8",
+                "tests/input/java8_line_comments",
+            ])
+            .output()
+            .unwrap();
+
+        //verify
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout
+            .contains("<< PREFIX_LCOM <- '// Here is where the license goes\\n' (Injected)\n"));
+    }
+
+    #[test]
+    fn test_parse_json() {
+        //exercise
+        let output = padd_command()
+            .args([
+                "parse",
+                "--format",
+                "json",
+                "tests/spec/json",
+                "tests/input/json_simple",
+            ])
+            .output()
+            .unwrap();
+
+        //verify
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.starts_with(
+            "{\"kind\":\"json\",\"lexeme\":\"\",\
+             \"start\":{\"offset\":0,\"line\":1,\"character\":1},\
+             \"end\":{\"offset\":407,\"line\":14,\"character\":6},\
+             \"method\":\"Standard\",\
+             \"production\":{\"lhs\":\"json\",\"rhs\":[{\"symbol\":\"element\",\"list\":false}]},\
+             \"children\":[{\"kind\":\"element\","
+        ));
+        assert!(stdout.ends_with("]}\n"));
+    }
+
+    #[test]
+    fn test_parse_sexp() {
+        //exercise
+        let output = padd_command()
+            .args([
+                "parse",
+                "--format",
+                "sexp",
+                "tests/spec/json",
+                "tests/input/json_simple",
+            ])
+            .output()
+            .unwrap();
+
+        //verify
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.starts_with(
+            "(json (element (value (object (LBRACKET \"{\") (members (member \
+             (STRING \"\\\"glossary\\\"\") (COLON \":\") (element"
+        ));
+    }
+
+    #[test]
+    fn test_parse_long_input() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        fs::write(
+            &spec_path,
+            "
+cdfa {
+    start
+        'a' .. 'z' -> word
+        ' ' | '\\t' -> ^_
+        '\\n' -> ^NEWLINE;
+
+    word ^WORD
+        'a' .. 'z' -> word;
+}
+
+grammar {
+    lines
+        | lines line `{}{}`
+        | line;
+
+    line
+        | WORD NEWLINE `{}\\n`;
+}
+",
+        )
+        .unwrap();
+
+        let lines = 100_000;
+        let input_path = format!("{}/input", test_dir.path_str());
+        fs::write(&input_path, "  word \n".repeat(lines)).unwrap();
+
+        let parse = |format: &str| {
+            let output = padd_command()
+                .args(["parse", "--format", format, &spec_path, &input_path])
+                .output()
+                .unwrap();
+
+            assert!(output.status.success());
+            String::from_utf8(output.stdout).unwrap()
+        };
+
+        //exercise
+        let sexp = parse("sexp");
+        let json = parse("json");
+
+        //verify
+        assert_eq!(sexp.matches("(WORD \"word\")").count(), lines);
+        assert_eq!(json.matches("\"kind\":\"WORD\"").count(), lines);
+    }
+
+    #[test]
+    fn test_parse_ambiguities() {
+        //exercise
+        let output = padd_command()
+            .args([
+                "parse",
+                "--ambiguities",
+                "tests/spec/java8",
+                "tests/input/java8_annotation",
+            ])
+            .output()
+            .unwrap();
+
+        //verify
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "2:1-2:11: ambiguous annotation_member_dec, \
+             chose `annotation_member_dec -> annotation_element_dec` \
+             over `annotation_member_dec -> class_member_dec`"
+        );
+    }
+
+    #[test]
+    fn test_lint_corpus_ambiguous() {
+        //exercise
+        let output = padd_command()
+            .args([
+                "lint",
+                "tests/spec/java8",
+                "--corpus",
+                "tests/input",
+                "--matching",
+                "java8_annotation",
+            ])
+            .output()
+            .unwrap();
+
+        //verify
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 1);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("java8_annotation:5:1-5:21: ambiguous annotation_member_dec"));
+        assert!(stdout.contains("Found 3 problems in tests/spec/java8"));
+    }
+
+    #[test]
+    fn test_lint_corpus_unambiguous() {
+        //exercise
+        let output = padd_command()
+            .args([
+                "lint",
+                "tests/spec/json",
+                "-c",
+                "tests/input",
+                "-m",
+                "^json",
+            ])
+            .output()
+            .unwrap();
+
+        //verify
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(stdout, "No problems found in tests/spec/json\n");
+    }
+
+    #[test]
+    fn test_lint_corpus_tracker_dir() {
+        //setup
+        let test_dir = TestDir::new();
+        let corpus_path = format!("{}/corpus", test_dir.path_str());
+        fs::create_dir_all(format!("{}/.padd", corpus_path)).unwrap();
+        fs::copy(
+            "tests/input/json_simple",
+            format!("{}/json_simple", corpus_path),
+        )
+        .unwrap();
+        fs::write(format!("{}/.padd/json_simple", corpus_path), "not json").unwrap();
+
+        //exercise
+        let output = padd_command()
+            .args(["lint", "tests/spec/json", "-c", &corpus_path, "-m", "^json"])
+            .output()
+            .unwrap();
+
+        //verify
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(stdout, "No problems found in tests/spec/json\n");
+    }
+
+    #[test]
+    fn test_lint_spec_problems() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        fs::write(
+            &spec_path,
+            "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^B;
+}
+
+grammar {
+    s
+        | A;
+
+    unused
+        | A;
+}
+",
+        )
+        .unwrap();
+
+        //exercise
+        let output = padd_command().args(["lint", &spec_path]).output().unwrap();
+
+        //verify
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 1);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert_eq!(
+            lines[0],
+            format!(
+                "{}:5:17-5:18: Token 'B' is never used by the grammar",
+                spec_path
+            )
+        );
+        assert_eq!(
+            lines[1],
+            format!(
+                "{}:12:5-12:11: Non-terminal 'unused' is unreachable from the start symbol",
+                spec_path
+            )
+        );
+        assert!(stdout.contains(&format!("Found 2 problems in {}", spec_path)));
+    }
+
+    #[test]
+    fn test_lint_spec_orphaned_terminal() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        fs::write(
+            &spec_path,
+            "
+cdfa {
+    start
+        'a' -> ^A;
+}
+
+grammar {
+    s
+        | A
+        | B;
+}
+",
+        )
+        .unwrap();
+
+        //exercise
+        let output = padd_command().args(["lint", &spec_path]).output().unwrap();
+
+        //verify
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 1);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(
+            stdout.lines().next().unwrap(),
+            format!(
+                "{}:10:11-10:12: Terminal 'B' is never produced by the CDFA",
+                spec_path
+            )
+        );
+        assert!(stdout.contains(&format!("Found 1 problems in {}", spec_path)));
+    }
+
+    #[test]
+    fn test_spec_tests_pass() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        fs::write(
+            &spec_path,
+            "
+cdfa {
+    start
+        'a' -> ^A
+        ' ' -> ^_;
+}
+
+grammar {
+    s
+        | s A `{} {}`
+        | A;
+}
+
+test \"spaced\" {
+    input ``a   a  a``
+    expect ``a a a``
+}
+",
+        )
+        .unwrap();
+
+        //exercise
+        let output = padd_command().args(["test", &spec_path]).output().unwrap();
+
+        //verify
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 0);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("test spaced ... "));
+        assert!(stdout.contains("ok"));
+        assert!(stdout.contains(&format!("1 tests passed in {}", spec_path)));
+    }
+
+    #[test]
+    fn test_spec_tests_fail() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        fs::write(
+            &spec_path,
+            "
+cdfa {
+    start
+        'a' -> ^A
+        ' ' -> ^_
+        '\\n' -> ^_;
+}
+
+grammar {
+    s
+        | s A `{}\\n{}`
+        | A;
+}
+
+test \"passing\" {
+    input ``a a``
+    expect ``a
+a``
+}
+
+test \"failing\" {
+    input ``a a a``
+    expect ``a
+b
+a``
+}
+",
+        )
+        .unwrap();
+
+        //exercise
+        let output = padd_command()
+            .args(["test", &spec_path])
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap();
+
+        //verify
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 1);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("test passing ... "));
+        assert!(stdout.contains("test failing ... "));
+        assert!(stdout.contains("FAILED"));
+        assert!(stdout.contains("\n a\n-b\n"));
+        assert!(stdout.contains("\n+a\n"));
+        assert!(stdout.contains(&format!("1 of 2 tests failed in {}", spec_path)));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        let dir_path = format!("{}/snapshots", test_dir.path_str());
+        fs::create_dir(&dir_path).unwrap();
+        fs::write(&spec_path, SNAPSHOT_SPEC).unwrap();
+        fs::write(format!("{}/matching", dir_path), "a   a").unwrap();
+        fs::write(format!("{}/matching.expected", dir_path), "a a").unwrap();
+        fs::write(format!("{}/different", dir_path), "a a\na").unwrap();
+        fs::write(format!("{}/different.expected", dir_path), "a\nb a").unwrap();
+        fs::write(format!("{}/missing", dir_path), "a").unwrap();
+
+        //exercise
+        let output = padd_command()
+            .args(["snapshot", &spec_path, &dir_path])
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap();
+
+        //verify
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 1);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(&format!("{}/matching ... ok", dir_path)));
+        assert!(stdout.contains(&format!("{}/different ... FAILED", dir_path)));
+        assert!(stdout.contains("\n-a\n-b a\n+a a a\n"));
+        assert!(stdout.contains(&format!("{}/missing ... FAILED", dir_path)));
+        assert!(stdout.contains(&format!(
+            "Missing expected output {}/missing.expected",
+            dir_path
+        )));
+        assert!(stdout.contains(&format!("2 of 3 snapshots failed in {}", dir_path)));
+    }
+
+    #[test]
+    fn test_snapshot_update() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        let dir_path = format!("{}/snapshots", test_dir.path_str());
+        fs::create_dir(&dir_path).unwrap();
+        fs::write(&spec_path, SNAPSHOT_SPEC).unwrap();
+        fs::write(format!("{}/matching", dir_path), "a   a").unwrap();
+        fs::write(format!("{}/matching.expected", dir_path), "a a").unwrap();
+        fs::write(format!("{}/different", dir_path), "a a\na").unwrap();
+        fs::write(format!("{}/different.expected", dir_path), "a\nb a").unwrap();
+        fs::write(format!("{}/missing", dir_path), "a").unwrap();
+
+        //exercise
+        let output = padd_command()
+            .args(["snapshot", &spec_path, &dir_path, "--update"])
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap();
+
+        //verify
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 0);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(&format!("{}/matching ... ok", dir_path)));
+        assert!(stdout.contains(&format!("{}/different ... updated", dir_path)));
+        assert!(stdout.contains(&format!("{}/missing ... updated", dir_path)));
+        assert!(stdout.contains(&format!("Updated 2 of 3 snapshots in {}", dir_path)));
+
+        let different = fs::read_to_string(format!("{}/different.expected", dir_path)).unwrap();
+        assert_eq!(different, "a a a");
+        let missing = fs::read_to_string(format!("{}/missing.expected", dir_path)).unwrap();
+        assert_eq!(missing, "a");
+
+        let output = padd_command()
+            .args(["snapshot", &spec_path, &dir_path])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_snapshot_tracker_dir() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        let dir_path = format!("{}/snapshots", test_dir.path_str());
+        fs::create_dir_all(format!("{}/.padd", dir_path)).unwrap();
+        fs::write(&spec_path, SNAPSHOT_SPEC).unwrap();
+        fs::write(format!("{}/matching", dir_path), "a   a").unwrap();
+        fs::write(format!("{}/matching.expected", dir_path), "a a").unwrap();
+        fs::write(format!("{}/.padd/matching", dir_path), "1234").unwrap();
+
+        //exercise
+        let output = padd_command()
+            .args(["snapshot", &spec_path, &dir_path])
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap();
+
+        //verify
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 0);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(!stdout.contains(".padd"));
+        assert!(stdout.contains(&format!("1 snapshots passed in {}", dir_path)));
+
+        let output = padd_command()
+            .args(["snapshot", &spec_path, &dir_path, "--update"])
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code().unwrap(), 0);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(!stdout.contains(".padd"));
+        assert!(!Path::new(&format!("{}/.padd/matching.expected", dir_path)).exists());
+    }
+
+    #[test]
+    fn test_generate() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        fs::write(&spec_path, SNAPSHOT_SPEC).unwrap();
+
+        //exercise
+        let output = padd_command()
+            .args(["generate", &spec_path, "-n", "3", "-s", "7"])
+            .output()
+            .unwrap();
+
+        //verify
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 0);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let inputs: Vec<&str> = stdout.lines().collect();
+        assert_eq!(inputs.len(), 3);
+        for input in inputs {
+            assert!(!input.is_empty());
+            assert!(input
+                .split(' ')
+                .all(|lexeme| lexeme == "a" || lexeme == "b"));
+        }
+
+        let output = padd_command()
+            .args(["generate", &spec_path, "-n", "3", "-s", "7"])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), stdout);
+    }
+
+    #[test]
+    fn test_generate_output_dir() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        let dir_path = format!("{}/generated", test_dir.path_str());
+        fs::write(&spec_path, SNAPSHOT_SPEC).unwrap();
+
+        //exercise
+        let output = padd_command()
+            .args([
+                "generate",
+                &spec_path,
+                "--count",
+                "2",
+                "--max-depth",
+                "3",
+                "--seed",
+                "11",
+                "-o",
+                &dir_path,
+            ])
+            .output()
+            .unwrap();
+
+        //verify
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 0);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(&format!("Generated 2 inputs in {} with seed 11", dir_path)));
+
+        let mut file_names: Vec<String> = fs::read_dir(&dir_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        file_names.sort();
+        assert_eq!(file_names, vec!["1", "2"]);
+    }
+
+    #[test]
+    fn test_verify() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        let dir_path = format!("{}/files", test_dir.path_str());
+        fs::create_dir(&dir_path).unwrap();
+        fs::write(
+            &spec_path,
+            "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^B
+        ' ' -> ^_;
+}
+
+grammar {
+    s
+        | s A `{} {}`
+        | A
+        | A B `{}`;
+}
+",
+        )
+        .unwrap();
+        fs::write(format!("{}/good", dir_path), "a  a").unwrap();
+        fs::write(format!("{}/bad", dir_path), "a b").unwrap();
+
+        //exercise
+        let output = padd_command()
+            .args(["verify", &spec_path, "-t", &dir_path])
+            .output()
+            .unwrap();
+
+        //verify
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 1);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(&format!(
+            "{}/bad: Token B 'b' at 1:3 of the input is missing from the output",
+            dir_path
+        )));
+        assert!(!stdout.contains(&format!("{}/good", dir_path)));
+        assert!(stdout.contains(&format!(
+            "1 of 2 files failed verification under {}, with 1 violations",
+            dir_path
+        )));
+
+        let output = padd_command()
+            .args(["verify", &spec_path, "-t", &dir_path, "-m", "good"])
+            .output()
+            .unwrap();
+
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 0);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(&format!("Verified 1 files under {}", dir_path)));
+    }
+
+    #[test]
+    fn test_verify_tracker_dir() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        let dir_path = format!("{}/files", test_dir.path_str());
+        fs::create_dir_all(format!("{}/.padd", dir_path)).unwrap();
+        fs::write(&spec_path, SNAPSHOT_SPEC).unwrap();
+        fs::write(format!("{}/good", dir_path), "a  b").unwrap();
+        fs::write(format!("{}/.padd/good", dir_path), "1234").unwrap();
+
+        //exercise
+        let output = padd_command()
+            .args(["verify", &spec_path, "-t", &dir_path])
+            .output()
+            .unwrap();
+
+        //verify
+        let code = output.status.code().unwrap();
+        assert_eq!(code, 0);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(&format!("Verified 1 files under {}", dir_path)));
+    }
+
+    #[test]
+    fn test_export_grammar() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        fs::write(
+            &spec_path,
+            "
+cdfa {
+    start
+        'a' -> ^A
+        ',' -> ^COMMA
+        ';' -> ^SEMI
+        ' ' -> ^_;
+}
+
+grammar {
+    s
+        | [items] SEMI;
+
+    items
+        | {A / COMMA} {item}
+        | ;
+
+    item
+        | A;
+}
+",
+        )
+        .unwrap();
+
+        let export = |format: &str| {
+            let output = padd_command()
+                .args(["export-grammar", &spec_path, "--format", format])
+                .output()
+                .unwrap();
+            assert_eq!(output.status.code().unwrap(), 0);
+            String::from_utf8(output.stdout).unwrap()
+        };
+
+        //exercise
+        let ebnf = export("ebnf");
+        let w3c = export("w3c");
+        let dot = export("dot");
+
+        //verify
+        assert_eq!(
+            ebnf,
+            "s = [items], SEMI ;
+
+items = A, {COMMA, A}, item, {item}
+      | (* empty *) ;
+
+item = A ;
+"
+        );
+        assert_eq!(
+            w3c,
+            "s ::= items? SEMI
+
+items ::= A (COMMA A)* item+
+        | /* empty */
+
+item ::= A
+"
+        );
+        assert_eq!(
+            dot,
+            "digraph grammar {
+    \"s\" [shape=box];
+    \"items\" [shape=box];
+    \"item\" [shape=box];
+    \"SEMI\" [shape=ellipse];
+    \"A\" [shape=ellipse];
+    \"COMMA\" [shape=ellipse];
+
+    \"s\" -> \"items\" [style=dashed];
+    \"s\" -> \"SEMI\";
+    \"items\" -> \"A\" [label=\"+\"];
+    \"items\" -> \"COMMA\" [style=dotted];
+    \"items\" -> \"item\" [label=\"+\"];
+    \"item\" -> \"A\";
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_invalid_specification_snippet() {
+        //setup
+        let test_dir = TestDir::new();
+        let spec_path = format!("{}/spec", test_dir.path_str());
+        fs::write(
+            &spec_path,
+            "
+cdfa {
+    start
+        'a' -> ^A;
+}
+
+grammar {
+    s
+        | A
+        | B;
+}
+",
+        )
+        .unwrap();
+
+        //exercise
+        let output = padd_command()
+            .args(["lex", &spec_path, "tests/input/json_simple"])
+            .output()
+            .unwrap();
+
+        //verify
+        assert!(!output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(
+            "Orphaned terminal 'B' is not tokenized by the ECDFA at 10:11 in grammar region"
+        ));
+        assert!(stdout.contains(&format!(
+            "  --> {}:10:11 in grammar region\n   |\n10 |         | B;\n   |           ^\n",
+            spec_path
+        )));
+    }
+
+    #[test]
+    fn test_compiled_spec_cache() {
+        //setup
+        let test_dir = TestDir::new();
+        let lex = || {
+            let output = padd_command()
+                .env("PADD_CACHE_DIR", test_dir.path_str())
+                .args(["lex", "tests/spec/json", "tests/input/json_simple"])
+                .output()
+                .unwrap();
+
+            assert!(output.status.success());
+            String::from_utf8(output.stdout).unwrap()
+        };
+
+        //exercise
+        let uncached = lex();
+
+        //verify
+        let cache_files: Vec<PathBuf> = fs::read_dir(test_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(cache_files.len(), 1);
+
+        let cache_file = &cache_files[0];
+        assert!(cache_file.to_string_lossy().ends_with(&format!(
+            "-v{}-{}",
+            env!("CARGO_PKG_VERSION"),
+            padd::SERIAL_VERSION
+        )));
+
+        // Replace the cached specification, to check that it is used instead of the original.
+        let other_spec = "
+cdfa {
+    start
+        _ -> ^CHAR;
+}
+
+grammar {
+    s
+        | s CHAR
+        | ;
+}
+        ";
+        let other_fjr = padd::FormatJobRunner::build(other_spec).unwrap();
+        fs::write(cache_file, other_fjr.to_bytes()).unwrap();
+
+        //exercise
+        let cached = lex();
+
+        //verify
+        assert!(cached.lines().nth(1).unwrap().contains("CHAR"));
+
+        // Invalid cache files are ignored and replaced.
+        fs::write(cache_file, "invalid").unwrap();
+
+        //exercise
+        let invalidated = lex();
+
+        //verify
+        assert_eq!(invalidated, uncached);
+        assert!(fs::read(cache_file).unwrap().len() > "invalid".len());
+
+        // Cache files written by an older version of the serial format are also replaced.
+        let mut old_bytes = other_fjr.to_bytes();
+        old_bytes[..10].copy_from_slice(b"padd-fjr-6");
+        fs::write(cache_file, &old_bytes).unwrap();
+
+        //exercise
+        let outdated = lex();
+
+        //verify
+        assert_eq!(outdated, uncached);
+        assert_ne!(fs::read(cache_file).unwrap(), old_bytes);
+    }
+
     #[test]
     fn test_no_skip() {
         //setup
@@ -1006,8 +2108,8 @@ mod tests {
     #[test]
     fn test_invalid_log_level() {
         //exercise
-        let output = Command::new(EXECUTABLE)
-            .args(&[
+        let output = padd_command()
+            .args([
                 "--log",
                 &&*LOG_PATH,
                 "--level",
@@ -1606,7 +2708,13 @@ mod tests {
             server::kill();
             assert!(!server::running());
 
-            cli::run(vec![EXECUTABLE, "daemon", "start"]);
+            // The daemon is started by the executable, so that it uses the command's cache.
+            let mut daemon = padd_command();
+            daemon
+                .args(["daemon", "start"])
+                .stdout(Stdio::null())
+                .status()
+                .unwrap();
 
             // Allow time for the server to start
             thread::sleep(Duration::from_millis(20));
@@ -1720,6 +2828,19 @@ mod tests {
         test_dir2.release();
     }
 
+    /// Returns a command which runs the padd executable with its own compiled specification cache,
+    /// rather than the user's cache directory, which is removed when the command is dropped.
+    fn padd_command() -> PaddCommand {
+        let cache_dir = TestDir::new();
+        let mut command = Command::new(EXECUTABLE);
+        command.env("PADD_CACHE_DIR", cache_dir.path());
+
+        PaddCommand {
+            command,
+            _cache_dir: cache_dir,
+        }
+    }
+
     fn read_to_string(path: &Path) -> String {
         let mut contents = String::new();
 
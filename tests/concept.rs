@@ -1,6 +1,9 @@
 extern crate padd;
 
-use padd::{FormatJob, FormatJobRunner};
+use padd::{
+    FormatJob, FormatJobRunner, FormatRange, GrammarRule, GrammarTerm, Position, RegionType,
+    SpecLintKind, SymbolParseMethod, TextEdit, Violation,
+};
 
 #[test]
 fn test_def_input_matcher() {
@@ -935,6 +938,153 @@ grammar {
     assert_eq!(res, "{aa} [a] {b} b {_c, c, c d {ee}__c, c d {e}_}");
 }
 
+#[test]
+fn test_inline_lists_separated() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        ',' -> ^COMMA
+        '(' -> ^LPAREN
+        ')' -> ^RPAREN
+        ' ' -> ^_;
+}
+
+grammar {
+    s
+        | LPAREN {A / COMMA} RPAREN
+        | LPAREN {s / COMMA} RPAREN `{}{;separator=\\, }{}`;
+}
+    "
+    .to_string();
+
+    let input = "((a , a,a),(a),( a,a ))".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(res, "((a,a,a), (a), (a,a))");
+}
+
+#[test]
+fn test_inline_lists_separated_ignored() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        ',' -> ^COMMA
+        '(' -> ^LPAREN
+        ')' -> ^RPAREN
+        ' ' -> ^WS;
+}
+
+ignore WS
+
+grammar {
+    s
+        | LPAREN {A / COMMA} RPAREN `{}{;separator=\\, }{}`;
+}
+    "
+    .to_string();
+
+    let input = "(a , a ,a)".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(res, "(a, a, a)");
+}
+
+#[test]
+fn test_inline_lists_first_last() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> ^ID
+        ' ' | '\n' -> ^_;
+}
+
+grammar {
+    s | {item} `{;separator=\n}`;
+
+    item | ID `{?first:<}{}{?last:>}`;
+}
+    "
+    .to_string();
+
+    let input = "a b c".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(res, "<a\nb\nc>");
+}
+
+#[test]
+fn test_long_input() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> word
+        ' ' | '\\t' -> ^_
+        '\\n' -> ^NEWLINE;
+
+    word ^WORD
+        'a' .. 'z' -> word;
+}
+
+grammar {
+    lines
+        | lines line `{}{}`
+        | line;
+
+    line
+        | WORD NEWLINE `{}\\n`;
+}
+    "
+    .to_string();
+
+    let lines = 100_000;
+    let input = "  word \n".repeat(lines);
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input.clone())).unwrap();
+    let tree = fjr.parse(FormatJob::from_text(input.clone())).unwrap();
+    let ambiguities = fjr.ambiguities(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(res, "word\n".repeat(lines));
+
+    let mut words: usize = 0;
+    let mut stack = vec![&tree];
+    while let Some(node) = stack.pop() {
+        if node.kind() == Some("WORD") {
+            words += 1;
+        }
+        stack.extend(node.children());
+    }
+    assert_eq!(words, lines);
+
+    assert!(tree.clone() == tree);
+
+    assert!(ambiguities.is_empty());
+}
+
 #[test]
 fn test_non_prefix_free_cdfa_transitions() {
     //setup
@@ -971,3 +1121,1326 @@ grammar {
     //verify
     assert_eq!(res, "int o interface i i int o");
 }
+
+#[test]
+fn test_error_recovery() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^B
+        '=' -> ^EQ
+        ';' -> ^SEMI
+        ' ' | '\n' -> ^_;
+}
+
+sync SEMI
+
+grammar {
+    s
+        | s stmt `{}{}\\n`
+        | ;
+
+    stmt
+        | A EQ B SEMI `{} {} {}{}`
+        | error SEMI `{}{}`;
+}
+    "
+    .to_string();
+
+    let input = "a=b;\n b =  = a ;a   =b;".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(res, "a = b;\nb =  = a;\na = b;\n");
+}
+
+#[test]
+fn test_format_range_bytes() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^B
+        '=' -> ^EQ
+        ';' -> ^SEMI
+        ' ' | '\n' -> ^_;
+}
+
+grammar {
+    s
+        | s stmt `{}{}\\n`
+        | ;
+
+    stmt
+        | A EQ B SEMI `{} {} {}{}`;
+}
+    "
+    .to_string();
+
+    let input = "a=b;\n a =b ;\na  = b;".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr
+        .format_range(FormatJob::from_text(input), FormatRange::Bytes(8..9))
+        .unwrap();
+
+    //verify
+    assert_eq!(res, "a=b;\n a = b;\na  = b;");
+}
+
+#[test]
+fn test_format_range_multiple_subtrees() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^B
+        '=' -> ^EQ
+        ';' -> ^SEMI
+        ' ' | '\n' -> ^_;
+}
+
+grammar {
+    s
+        | {stmt};
+
+    stmt
+        | A EQ B SEMI `{} {} {}{}`;
+}
+    "
+    .to_string();
+
+    let input = "a=b;  a =b ;a  = b;a=b;".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr
+        .format_range(FormatJob::from_text(input), FormatRange::Bytes(9..14))
+        .unwrap();
+
+    //verify
+    assert_eq!(res, "a=b;  a = b;a = b;a=b;");
+}
+
+#[test]
+fn test_format_range_lines_preserves_scope() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        '{' -> ^LBRACE
+        '}' -> ^RBRACE
+        'a' -> ^A
+        ' ' | '\t' | '\n' -> ^_;
+}
+
+grammar {
+    s
+        | s elem `{}{}`
+        | ;
+
+    elem
+        | LBRACE s RBRACE `[prefix]{0}\\n{1;prefix=[prefix]\\t}[prefix]{2}\\n`
+        | A `[prefix]{0}\\n`;
+}
+    "
+    .to_string();
+
+    let input = "{\n{\n  a\n   {a}\n}\n}\n".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr
+        .format_range(FormatJob::from_text(input), FormatRange::Lines(4..=4))
+        .unwrap();
+
+    //verify
+    assert_eq!(res, "{\n{\n  a\n\t\t{\n\t\t\ta\n\t\t}\n}\n}\n");
+}
+
+#[test]
+fn test_format_range_lines_replaces_whitespace() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        '{' -> ^LBRACE
+        '}' -> ^RBRACE
+        'a' -> ^A
+        ' ' | '\t' | '\n' -> ^_;
+}
+
+grammar {
+    s
+        | s elem `{}{}`
+        | ;
+
+    elem
+        | LBRACE s RBRACE `[prefix]{0}\\n{1;prefix=[prefix]\\t}[prefix]{2}\\n`
+        | A `[prefix]{0}\\n`;
+}
+    "
+    .to_string();
+
+    let input = "{\n{\n  a\n\n   {a}   \n}\n}\n".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr
+        .format_range(FormatJob::from_text(input), FormatRange::Lines(3..=5))
+        .unwrap();
+
+    //verify
+    assert_eq!(res, "{\n{\n\t\ta\n\t\t{\n\t\t\ta\n\t\t}\n}\n}\n");
+}
+
+#[test]
+fn test_format_edits() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^B
+        '=' -> ^EQ
+        ';' -> ^SEMI
+        ' ' | '\n' -> ^_;
+}
+
+grammar {
+    s
+        | s stmt `{}{}\\n`
+        | ;
+
+    stmt
+        | A EQ B SEMI `{} {} {}{}`;
+}
+    "
+    .to_string();
+
+    let input = "a = b;\na=b;\na = b;\na = b ;\n".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr
+        .format_edits(FormatJob::from_text(input.clone()))
+        .unwrap();
+
+    //verify
+    assert_eq!(
+        res,
+        vec![
+            TextEdit {
+                range: 8..9,
+                replacement: " = ".to_string(),
+            },
+            TextEdit {
+                range: 24..25,
+                replacement: String::new(),
+            },
+        ]
+    );
+    assert_eq!(
+        TextEdit::apply(&input, &res),
+        fjr.format(FormatJob::from_text(input.clone())).unwrap()
+    );
+}
+
+#[test]
+fn test_parse_syntax_tree() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^B
+        ';' -> ^SEMI
+        ' ' -> ^_;
+}
+
+grammar {
+    s
+        | s stmt
+        | ;
+
+    stmt
+        | {A} B SEMI;
+}
+    "
+    .to_string();
+
+    let input = "a a b;".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let tree = fjr.parse(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(
+        tree.to_string(),
+        "└── s
+    ├── s
+    │   └── NULL
+    └── stmt
+        ├── ?
+        │   ├── A <- 'a'
+        │   └── A <- 'a'
+        ├── B <- 'b'
+        └── SEMI <- ';'"
+    );
+
+    assert_eq!(tree.kind(), Some("s"));
+    assert_eq!(tree.parse_method(), &SymbolParseMethod::Standard);
+    assert_eq!(tree.children().len(), 2);
+
+    let stmt = &tree.children()[1];
+    let production = stmt.production().unwrap();
+    assert_eq!(production.lhs(), "stmt");
+    assert_eq!(
+        production
+            .rhs()
+            .iter()
+            .map(|symbol| (symbol.symbol(), symbol.is_list()))
+            .collect::<Vec<(&str, bool)>>(),
+        vec![("A", true), ("B", false), ("SEMI", false)]
+    );
+    assert_eq!(stmt.span().start.offset, 0);
+    assert_eq!(stmt.span().end.offset, 6);
+
+    let list = &stmt.children()[0];
+    assert_eq!(list.kind(), None);
+    assert_eq!(list.parse_method(), &SymbolParseMethod::Repeated);
+    assert_eq!(list.children()[1].lexeme(), "a");
+    assert_eq!(list.children()[1].span().start.character, 3);
+    assert!(list.children()[1].is_leaf());
+}
+
+#[test]
+fn test_lex_tokens() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^B
+        '#' -> ^C
+        ' ' | '\n' -> ^WS;
+}
+
+ignore WS
+
+inject left C
+
+grammar {
+    s
+        | s A
+        | s B
+        | ;
+}
+    "
+    .to_string();
+
+    let input = "a #\nb".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let tokens = fjr.lex(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(
+        tokens
+            .iter()
+            .map(|token| (
+                token.kind(),
+                token.lexeme(),
+                token.is_ignorable(),
+                token.is_injectable()
+            ))
+            .collect::<Vec<(&str, &str, bool, bool)>>(),
+        vec![
+            ("A", "a", false, false),
+            ("WS", " ", true, false),
+            ("C", "#", false, true),
+            ("WS", "\n", true, false),
+            ("B", "b", false, false),
+        ]
+    );
+
+    assert_eq!(tokens[2].span().start.offset, 2);
+    assert_eq!(tokens[2].span().end.offset, 3);
+    assert_eq!(tokens[4].span().start.line, 2);
+    assert_eq!(tokens[4].span().start.character, 1);
+}
+
+#[test]
+fn test_format_disabled_regions() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> ^ID
+        '=' -> ^EQ
+        ';' -> ^SEMI
+        '#' -> comment
+        ' ' | '\\n' -> ^_;
+
+    comment ^COMMENT
+        '\\n' -> fail
+        _ -> comment;
+}
+
+inject right COMMENT `{}\\n`
+
+directive COMMENT
+
+grammar {
+    s
+        | s stmt `{}{}\\n`
+        | ;
+
+    stmt
+        | ID EQ ID SEMI `{} {} {}{}`;
+}
+    "
+    .to_string();
+
+    let input = "a=b;\n# padd:off\nc  =   d;\ne=f;\n# padd:on\ng  =h;\n# padd:off\ni=j;\n";
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input.to_string())).unwrap();
+
+    //verify
+    assert_eq!(
+        res,
+        "a = b;\n# padd:off\nc  =   d;\ne=f;\n# padd:on\ng = h;\n# padd:off\ni=j;\n"
+    );
+}
+
+#[test]
+fn test_format_disabled_regions_marker_input() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> ^ID
+        '=' -> ^EQ
+        ';' -> ^SEMI
+        '#' -> comment
+        ' ' | '\\n' -> ^_;
+
+    comment ^COMMENT
+        '\\n' -> fail
+        _ -> comment;
+}
+
+inject right COMMENT `{}\\n`
+
+directive COMMENT
+
+grammar {
+    s
+        | s stmt `{}{}\\n`
+        | ;
+
+    stmt
+        | ID EQ ID SEMI `{} {} {}{}`;
+}
+    "
+    .to_string();
+
+    let input = "a=b;\n# \u{FDD0}0\u{FDD2}\n# padd:off\nc  =   d;\n# padd:on\ng  =h;\n";
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input.to_string())).unwrap();
+
+    //verify
+    assert_eq!(
+        res,
+        "a = b;\n# \u{FDD0}0\u{FDD2}\n# padd:off\nc  =   d;\n# padd:on\ng = h;\n"
+    );
+}
+
+#[test]
+fn test_format_disabled_regions_ignored() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> ^ID
+        '=' -> ^EQ
+        ';' -> ^SEMI
+        '#' -> comment
+        ' ' | '\\n' -> ^_;
+
+    comment ^COMMENT
+        '\\n' -> fail
+        _ -> comment;
+}
+
+ignore COMMENT
+
+directive COMMENT
+
+grammar {
+    s
+        | s stmt `{}{}\\n`
+        | ;
+
+    stmt
+        | ID EQ ID SEMI `{} {} {}{}`;
+}
+    "
+    .to_string();
+
+    let input = "a=b;# padd:off\nc  =   d;# padd:on\ne=f; # other comment\ng  =h;";
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input.to_string())).unwrap();
+
+    //verify
+    assert_eq!(
+        res,
+        "a = b;\n# padd:off\nc  =   d;# padd:on\ne = f;\ng = h;\n"
+    );
+}
+
+#[test]
+fn test_format_groups() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> id
+        '(' -> ^LPAREN
+        ')' -> ^RPAREN
+        ',' -> ^COMMA
+        ';' -> ^SEMI
+        ' ' | '\\n' -> ^_;
+
+    id ^ID
+        'a' .. 'z' -> id;
+}
+
+width 16
+
+grammar {
+    s
+        | s stmt `{}{}\\n`
+        | ;
+
+    stmt
+        | call SEMI;
+
+    call
+        | ID LPAREN args RPAREN `{}{}{^{/;\\n[prefix]    }{;prefix=[prefix]    }{/;\\n[prefix]}}{}`;
+
+    args
+        | args COMMA arg `{}{}{/ ;\\n[prefix]}{}`
+        | arg;
+
+    arg
+        | ID
+        | call;
+}
+    "
+    .to_string();
+
+    let input = "f(a,b);\nfunc(alpha, g(beta,c), delta);\n".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(
+        res,
+        "f(a, b);\nfunc(\n    alpha,\n    g(beta, c),\n    delta\n);\n"
+    );
+}
+
+#[test]
+fn test_format_groups_width_override() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> id
+        '(' -> ^LPAREN
+        ')' -> ^RPAREN
+        ',' -> ^COMMA
+        ' ' | '\\n' -> ^_;
+
+    id ^ID
+        'a' .. 'z' -> id;
+}
+
+grammar {
+    call
+        | ID LPAREN args RPAREN `{}{}{^{/;\\n    }{}{/;\\n}}{}`;
+
+    args
+        | args COMMA ID `{}{}{/ ;\\n    }{}`
+        | ID;
+}
+    "
+    .to_string();
+
+    let input = "f(alpha,beta,gamma)".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let wide = fjr.format(FormatJob::from_text(input.clone())).unwrap();
+    let narrow = fjr
+        .format(FormatJob::from_text(input).with_width(10))
+        .unwrap();
+
+    //verify
+    assert_eq!(wide, "f(alpha, beta, gamma)");
+    assert_eq!(narrow, "f(\n    alpha,\n    beta,\n    gamma\n)");
+}
+
+#[test]
+fn test_format_groups_marker_input() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> id
+        '(' -> ^LPAREN
+        ')' -> ^RPAREN
+        ',' -> ^COMMA
+        '#' -> comment
+        ' ' | '\\n' -> ^_;
+
+    id ^ID
+        'a' .. 'z' -> id;
+
+    comment ^COMMENT
+        '\\n' -> fail
+        _ -> comment;
+}
+
+inject right COMMENT ` {}`
+
+grammar {
+    call
+        | ID LPAREN args RPAREN `{}{}{^{/;\\n    }{}{/;\\n}}{}`;
+
+    args
+        | args COMMA ID `{}{}{/ ;\\n    }{}`
+        | ID;
+}
+    "
+    .to_string();
+
+    let plain = "f(a,b)#x".to_string();
+    let marked = "f(a,b)#\u{FDD5}b\u{FDD6}c\u{FDD7}d".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let plain_res = fjr.format(FormatJob::from_text(plain)).unwrap();
+    let marked_res = fjr.format(FormatJob::from_text(marked)).unwrap();
+
+    //verify
+    assert_eq!(plain_res, "f(a, b) #x");
+    assert_eq!(marked_res, "f(a, b) #\u{FDD5}b\u{FDD6}c\u{FDD7}d");
+}
+
+#[test]
+fn test_format_groups_disabled_regions() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> id
+        '(' -> ^LPAREN
+        ')' -> ^RPAREN
+        ',' -> ^COMMA
+        ';' -> ^SEMI
+        '#' -> comment
+        ' ' | '\\n' -> ^_;
+
+    id ^ID
+        'a' .. 'z' -> id;
+
+    comment ^COMMENT
+        '\\n' -> fail
+        _ -> comment;
+}
+
+inject left COMMENT
+
+directive COMMENT
+
+width 25
+
+grammar {
+    s
+        | s stmt `{}{}\\n`
+        | ;
+
+    stmt
+        | call SEMI;
+
+    call
+        | ID LPAREN args RPAREN `{}{}{^{/;\\n    }{}{/;\\n}}{}`;
+
+    args
+        | args COMMA ID `{}{}{/ ;\\n    }{}`
+        | ID;
+}
+    "
+    .to_string();
+
+    let input = "f(alpha,beta);# padd:off\ng(x ,  y);# padd:on\nh(x,y);\n".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(
+        res,
+        "f(alpha, beta);# padd:off\ng(x ,  y);# padd:on\nh(x, y);\n"
+    );
+}
+
+#[test]
+fn test_format_conditional_capture() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> id
+        '=' -> ^EQ
+        ';' -> ^SEMI
+        ' ' | '\\n' -> ^_;
+
+    id ^ID
+        'a' .. 'z' -> id;
+}
+
+grammar {
+    s
+        | s decl `{}{}\\n`
+        | ;
+
+    decl
+        | ID ID [init] SEMI `{} {}{?2: {}}{}`;
+
+    init
+        | EQ ID `{} {}`;
+}
+    "
+    .to_string();
+
+    let input = "int x=y;int   z ;".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(res, "int x = y;\nint z;\n");
+}
+
+#[test]
+fn test_format_conditional_variable() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> id
+        '[' -> ^LBRACKET
+        ']' -> ^RBRACKET
+        '(' -> ^LPAREN
+        ')' -> ^RPAREN
+        '<' -> ^LANGLE
+        '>' -> ^RANGLE
+        ',' -> ^COMMA
+        ' ' | '\\n' -> ^_;
+
+    id ^ID
+        'a' .. 'z' -> id;
+}
+
+grammar {
+    s
+        | s list `{}{}\\n`
+        | ;
+
+    list
+        | LBRACKET items RBRACKET `{}{;mode=tight}{}`
+        | LPAREN items RPAREN `{}{;mode=loose}{}`
+        | LANGLE items RANGLE;
+
+    items
+        | items COMMA ID `{0}{?mode=tight:,|{?mode:, |\\;}}{2}`
+        | ID;
+}
+    "
+    .to_string();
+
+    let input = "[a, b,c] (a,b ,c) <a,b,c>".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(res, "[a,b,c]\n(a, b, c)\n<a;b;c>\n");
+}
+
+#[test]
+fn test_ambiguities() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> id
+        '+' -> ^PLUS
+        ';' -> ^SEMI
+        ' ' -> ^_;
+
+    id ^ID
+        'a' .. 'z' -> id;
+}
+
+grammar {
+    s
+        | s stmt
+        | ;
+
+    stmt
+        | expr SEMI;
+
+    expr
+        | expr PLUS expr
+        | ID;
+}
+    "
+    .to_string();
+
+    let input = "a + b; a + b + c;".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let ambiguities = fjr.ambiguities(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(ambiguities.len(), 1);
+
+    let ambiguity = &ambiguities[0];
+    assert_eq!(ambiguity.span().start.offset, 7);
+    assert_eq!(ambiguity.span().end.offset, 16);
+    assert_eq!(ambiguity.chosen().lhs(), "expr");
+    assert_eq!(ambiguity.chosen().rhs().len(), 3);
+    assert_eq!(ambiguity.productions(), &[ambiguity.chosen().clone()]);
+}
+
+#[test]
+fn test_lint_spec() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^B
+        ' ' -> ^_;
+
+    dead
+        'c' -> ^A;
+}
+
+grammar {
+    s
+        | s A
+        | A
+        | B never;
+
+    never
+        | B never;
+}
+    "
+    .to_string();
+
+    //exercise
+    let lints = FormatJobRunner::lint(&spec).unwrap();
+
+    //verify
+    let kinds: Vec<(SpecLintKind, &str)> = lints
+        .iter()
+        .map(|lint| (lint.kind().clone(), lint.name()))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (SpecLintKind::UnreachableState, "dead"),
+            (SpecLintKind::UnproductiveNonTerminal, "never"),
+        ]
+    );
+    assert_eq!(lints[0].span().start.line, 8);
+    assert_eq!(lints[1].span().start.line, 18);
+}
+
+#[test]
+fn test_spec_error_location() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A;
+}
+
+width 100000000000000000000000
+
+grammar {
+    s | A;
+}
+    "
+    .to_string();
+
+    //exercise
+    let res = FormatJobRunner::build(&spec);
+
+    //verify
+    let err = res.err().unwrap();
+    let location = err.location().unwrap();
+    assert_eq!(location.span().start.line, 7);
+    assert_eq!(location.span().start.character, 7);
+    assert_eq!(location.region(), Some(&RegionType::Width));
+    assert_eq!(location.to_string(), "7:7 in width region");
+}
+
+#[test]
+fn test_production_priorities() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'if' -> ^IF
+        'else' -> ^ELSE
+        'c' -> ^COND
+        'x' -> ^X
+        '#' -> ^COMMENT
+        ' ' -> ^_;
+}
+
+inject right COMMENT
+
+grammar {
+    stmt
+        | IF COND stmt @prefer `{} {} ({})`
+        | IF COND stmt ELSE stmt `{} {} ({}) {} ({})`
+        | X;
+}
+    "
+    .to_string();
+
+    let input = "if c if c x else x".to_string();
+    let commented = "if c # if c x else x".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input.clone())).unwrap();
+    let res_commented = fjr.format(FormatJob::from_text(commented)).unwrap();
+    let ambiguities = fjr.ambiguities(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(res, "if c (if c (x) else (x))");
+    assert_eq!(res_commented, "if c (#if c (x) else (x))");
+    assert!(ambiguities.is_empty());
+}
+
+#[test]
+fn test_operator_precedence() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' .. 'z' -> ^ID
+        '+' -> ^PLUS
+        '*' -> ^STAR
+        '<' -> ^LT
+        '=' -> ^ASSIGN
+        ' ' -> ^_;
+}
+
+grammar {
+    %right ASSIGN;
+    %nonassoc LT;
+    %left PLUS;
+    %left STAR;
+
+    expr
+        | expr ASSIGN expr `({} {} {})`
+        | expr LT expr `({} {} {})`
+        | expr PLUS expr `({} {} {})`
+        | expr STAR expr `({} {} {})`
+        | ID;
+}
+    "
+    .to_string();
+
+    let input = "a = b = c + d * e + f < g".to_string();
+    let chained = "a < b < c".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let res = fjr.format(FormatJob::from_text(input.clone())).unwrap();
+    let ambiguities = fjr.ambiguities(FormatJob::from_text(input)).unwrap();
+    let chained_ambiguities = fjr.ambiguities(FormatJob::from_text(chained)).unwrap();
+
+    //verify
+    assert_eq!(res, "(a = (b = (((c + (d * e)) + f) < g)))");
+    assert!(ambiguities.is_empty());
+    assert_eq!(chained_ambiguities.len(), 1);
+    assert_eq!(chained_ambiguities[0].span().start.offset, 0);
+    assert_eq!(chained_ambiguities[0].span().end.offset, 9);
+}
+
+#[test]
+fn test_spec_tests() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        ' ' -> ^_;
+}
+
+grammar {
+    s
+        | s A `{} {}`
+        | A;
+}
+
+test \"spaced\" {
+    input ``a   a``
+    expect ``a a``
+}
+    "
+    .to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let tests = FormatJobRunner::tests(&spec).unwrap();
+
+    //verify
+    assert_eq!(tests.len(), 1);
+
+    let test = &tests[0];
+    assert_eq!(test.name(), "spaced");
+    assert_eq!(
+        fjr.format(FormatJob::from_text(test.input().to_string()))
+            .unwrap(),
+        test.expect()
+    );
+}
+
+#[test]
+fn test_generate() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        '[' -> ^LBR
+        ']' -> ^RBR
+        ',' -> ^COMMA
+        '0' .. '9' -> num
+        ' ' -> ws;
+
+    num ^NUM
+        '0' .. '9' -> num;
+
+    ws ^WS
+        ' ' -> ws;
+}
+
+ignore WS
+
+grammar {
+    value
+        | LBR [items] RBR
+        | NUM;
+
+    items
+        | {value / COMMA};
+}
+    "
+    .to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let inputs = fjr.generate(20, 5, 42);
+
+    //verify
+    assert_eq!(inputs.len(), 20);
+    for input in &inputs {
+        assert!(fjr.parse(FormatJob::from_text(input.clone())).is_ok());
+    }
+    assert!(inputs.iter().any(|input| input.contains(',')));
+    assert_eq!(fjr.generate(20, 5, 42), inputs);
+}
+
+#[test]
+fn test_verify_clean() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        ' ' -> ^WS
+        '\\n' -> ^_;
+}
+
+ignore WS
+
+grammar {
+    s
+        | s A `{} {}`
+        | A;
+}
+    "
+    .to_string();
+
+    let input = "a   a\na  a".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let violations = fjr.verify(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(violations, vec![]);
+}
+
+#[test]
+fn test_verify_dropped_capture() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        'b' -> ^B
+        ' ' -> ^_;
+}
+
+grammar {
+    s
+        | A B `{}`;
+}
+    "
+    .to_string();
+
+    let input = "a b".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let violations = fjr.verify(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    assert_eq!(violations.len(), 2);
+
+    match violations[0] {
+        Violation::ReformatFailed(_) => {}
+        ref violation => panic!("Unexpected violation: {:?}", violation),
+    }
+
+    match violations[1] {
+        Violation::TokenRemoved(ref token) => {
+            assert_eq!(token.kind(), "B");
+            assert_eq!(token.lexeme(), "b");
+            assert_eq!(token.span().start.line, 1);
+            assert_eq!(token.span().start.character, 3);
+        }
+        ref violation => panic!("Unexpected violation: {:?}", violation),
+    }
+    assert_eq!(
+        violations[1].to_string(),
+        "Token B 'b' at 1:3 of the input is missing from the output"
+    );
+}
+
+#[test]
+fn test_verify_not_idempotent() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> a
+        'b' -> ^B
+        ' ' -> ^_
+        'x' -> ^_;
+
+    a ^A
+        'b' -> ^AB;
+}
+
+grammar {
+    s
+        | A B `{}{}`
+        | AB `x{}`;
+}
+    "
+    .to_string();
+
+    let input = "a b".to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    //exercise
+    let violations = fjr.verify(FormatJob::from_text(input)).unwrap();
+
+    //verify
+    let kinds: Vec<String> = violations
+        .iter()
+        .map(|violation| match violation {
+            Violation::TokenRemoved(token) => format!("-{}", token.kind()),
+            Violation::TokenAdded(token) => format!("+{}", token.kind()),
+            violation => violation.to_string(),
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            "Formatted output changes when formatted again, starting at 1:1 of the output",
+            "-A",
+            "-B",
+            "+AB",
+        ]
+    );
+    assert_eq!(
+        violations[0],
+        Violation::NotIdempotent(Position {
+            offset: 0,
+            line: 1,
+            character: 1,
+        })
+    );
+}
+
+#[test]
+fn test_grammar_rules() {
+    //setup
+    let spec = "
+cdfa {
+    start
+        'a' -> ^A
+        ',' -> ^COMMA
+        ';' -> ^SEMI
+        ' ' -> ^_;
+}
+
+sync SEMI
+
+grammar {
+    s
+        | [items] SEMI
+        | error SEMI;
+
+    items
+        | {A / COMMA} {item}
+        | ;
+
+    item
+        | A;
+
+    unused
+        | A;
+}
+    "
+    .to_string();
+
+    let fjr = FormatJobRunner::build(&spec).unwrap();
+
+    let term = |symbol: &str, is_terminal: bool| GrammarTerm {
+        symbol: symbol.to_string(),
+        is_terminal,
+        is_optional: false,
+        is_list: false,
+        separator: None,
+    };
+
+    //exercise
+    let rules = fjr.grammar_rules();
+
+    //verify
+    assert_eq!(
+        rules,
+        vec![
+            GrammarRule {
+                lhs: "s".to_string(),
+                alternatives: vec![
+                    vec![
+                        GrammarTerm {
+                            is_optional: true,
+                            ..term("items", false)
+                        },
+                        term("SEMI", true),
+                    ],
+                    vec![term("error", true), term("SEMI", true)],
+                ],
+            },
+            GrammarRule {
+                lhs: "items".to_string(),
+                alternatives: vec![
+                    vec![
+                        GrammarTerm {
+                            is_list: true,
+                            separator: Some("COMMA".to_string()),
+                            ..term("A", true)
+                        },
+                        GrammarTerm {
+                            is_list: true,
+                            ..term("item", false)
+                        },
+                    ],
+                    vec![],
+                ],
+            },
+            GrammarRule {
+                lhs: "item".to_string(),
+                alternatives: vec![vec![term("A", true)]],
+            },
+            GrammarRule {
+                lhs: "unused".to_string(),
+                alternatives: vec![vec![term("A", true)]],
+            },
+        ]
+    );
+}